@@ -13,6 +13,48 @@ mod m20250816_000004_convert_sct_transaction_type;
 mod m20250821_000005_add_stripe_transactions;
 mod m20250821_000006_add_monthly_cards;
 mod m20250821_000007_add_lucky_draw;
+mod m20251015_000001_add_login_lockout;
+mod m20251015_000002_add_recharge_refunded_status;
+mod m20251015_000003_add_recharge_tiers;
+mod m20251015_000004_add_pending_member_type;
+mod m20251015_000005_add_membership_gift_recipient;
+mod m20251015_000006_add_membership_auto_renew;
+mod m20251015_000007_add_stamp_rules;
+mod m20251015_000008_add_sync_state;
+mod m20251015_000009_add_percent_off_code_type;
+mod m20251015_000010_make_discount_code_user_nullable;
+mod m20251015_000011_add_discount_code_is_expired;
+mod m20251015_000012_add_discount_code_status;
+mod m20251015_000013_add_discount_code_transfers;
+mod m20251015_000014_add_lucky_draw_pity_counter;
+mod m20251015_000015_add_lucky_draw_free_spin_date;
+mod m20251015_000016_add_user_role;
+mod m20251015_000017_add_processed_events;
+mod m20251015_000018_add_referral_rewards;
+mod m20251015_000019_normalize_user_phones;
+mod m20251015_000020_add_stamp_redemption_tiers;
+mod m20251015_000021_add_payment_confirmations;
+mod m20251015_000022_add_monthly_card_cancel_at_period_end;
+mod m20251015_000023_add_audit_log;
+mod m20251015_000024_add_login_history;
+mod m20251015_000025_add_user_search_indexes;
+mod m20251015_000026_add_notifications;
+mod m20251015_000027_add_user_token_version;
+mod m20251015_000028_add_updated_at_triggers;
+mod m20251015_000029_add_monthly_card_and_birthday_code_types;
+mod m20251015_000030_create_sync_runs;
+mod m20251015_000031_create_sync_failures;
+mod m20251015_000032_add_stripe_transactions_user_index;
+mod m20251015_000033_add_recharge_currency;
+mod m20251015_000034_add_recharge_refund_pending;
+mod m20251015_000035_add_order_spins_earned;
+mod m20251015_000036_create_membership_reward_grants;
+mod m20251015_000037_add_recharge_refunded_amount;
+mod m20251015_000038_add_recharge_exchange_rate;
+mod m20251015_000039_add_processed_event_completed_at;
+mod m20251015_000040_drop_percent_off_columns;
+mod m20251015_000041_add_recharge_request_key;
+mod m20251015_000042_create_expiring_keys;
 
 pub struct Migrator;
 
@@ -33,6 +75,48 @@ impl MigratorTrait for Migrator {
             Box::new(m20250821_000005_add_stripe_transactions::Migration),
             Box::new(m20250821_000006_add_monthly_cards::Migration),
             Box::new(m20250821_000007_add_lucky_draw::Migration),
+            Box::new(m20251015_000001_add_login_lockout::Migration),
+            Box::new(m20251015_000002_add_recharge_refunded_status::Migration),
+            Box::new(m20251015_000003_add_recharge_tiers::Migration),
+            Box::new(m20251015_000004_add_pending_member_type::Migration),
+            Box::new(m20251015_000005_add_membership_gift_recipient::Migration),
+            Box::new(m20251015_000006_add_membership_auto_renew::Migration),
+            Box::new(m20251015_000007_add_stamp_rules::Migration),
+            Box::new(m20251015_000008_add_sync_state::Migration),
+            Box::new(m20251015_000009_add_percent_off_code_type::Migration),
+            Box::new(m20251015_000010_make_discount_code_user_nullable::Migration),
+            Box::new(m20251015_000011_add_discount_code_is_expired::Migration),
+            Box::new(m20251015_000012_add_discount_code_status::Migration),
+            Box::new(m20251015_000013_add_discount_code_transfers::Migration),
+            Box::new(m20251015_000014_add_lucky_draw_pity_counter::Migration),
+            Box::new(m20251015_000015_add_lucky_draw_free_spin_date::Migration),
+            Box::new(m20251015_000016_add_user_role::Migration),
+            Box::new(m20251015_000017_add_processed_events::Migration),
+            Box::new(m20251015_000018_add_referral_rewards::Migration),
+            Box::new(m20251015_000019_normalize_user_phones::Migration),
+            Box::new(m20251015_000020_add_stamp_redemption_tiers::Migration),
+            Box::new(m20251015_000021_add_payment_confirmations::Migration),
+            Box::new(m20251015_000022_add_monthly_card_cancel_at_period_end::Migration),
+            Box::new(m20251015_000023_add_audit_log::Migration),
+            Box::new(m20251015_000024_add_login_history::Migration),
+            Box::new(m20251015_000025_add_user_search_indexes::Migration),
+            Box::new(m20251015_000026_add_notifications::Migration),
+            Box::new(m20251015_000027_add_user_token_version::Migration),
+            Box::new(m20251015_000028_add_updated_at_triggers::Migration),
+            Box::new(m20251015_000029_add_monthly_card_and_birthday_code_types::Migration),
+            Box::new(m20251015_000030_create_sync_runs::Migration),
+            Box::new(m20251015_000031_create_sync_failures::Migration),
+            Box::new(m20251015_000032_add_stripe_transactions_user_index::Migration),
+            Box::new(m20251015_000033_add_recharge_currency::Migration),
+            Box::new(m20251015_000034_add_recharge_refund_pending::Migration),
+            Box::new(m20251015_000035_add_order_spins_earned::Migration),
+            Box::new(m20251015_000036_create_membership_reward_grants::Migration),
+            Box::new(m20251015_000037_add_recharge_refunded_amount::Migration),
+            Box::new(m20251015_000038_add_recharge_exchange_rate::Migration),
+            Box::new(m20251015_000039_add_processed_event_completed_at::Migration),
+            Box::new(m20251015_000040_drop_percent_off_columns::Migration),
+            Box::new(m20251015_000041_add_recharge_request_key::Migration),
+            Box::new(m20251015_000042_create_expiring_keys::Migration),
         ]
     }
 }
@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+/// Recharge Tiers (充值档位与赠送金额配置)
+#[derive(DeriveIden)]
+enum RechargeTiers {
+    Table,
+    Id,
+    AmountCents,
+    BonusCents,
+    Active,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// 表为空时服务端回退到内置默认档位（$5/$10/$20/$100），因此这里不预置数据
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RechargeTiers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RechargeTiers::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RechargeTiers::AmountCents)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RechargeTiers::BonusCents)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(RechargeTiers::Active)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(RechargeTiers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(RechargeTiers::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RechargeTiers::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
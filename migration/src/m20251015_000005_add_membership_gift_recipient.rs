@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum MembershipPurchases {
+    Table,
+    RecipientUserId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 赠送会员的受赠人（为空表示购买人自己）
+        if !manager
+            .has_column("membership_purchases", "recipient_user_id")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MembershipPurchases::Table)
+                        .add_column(
+                            ColumnDef::new(MembershipPurchases::RecipientUserId)
+                                .big_integer()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MembershipPurchases::Table)
+                    .drop_column(MembershipPurchases::RecipientUserId)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
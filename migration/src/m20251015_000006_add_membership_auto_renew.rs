@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    StripeCustomerId,
+    MembershipAutoRenew,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Stripe Customer（用于保存支付方式与离线扣款）
+        if !manager.has_column("users", "stripe_customer_id").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(ColumnDef::new(Users::StripeCustomerId).string().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // 会员到期自动续费开关（默认关闭，用户主动开启）
+        if !manager.has_column("users", "membership_auto_renew").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(
+                            ColumnDef::new(Users::MembershipAutoRenew)
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::StripeCustomerId)
+                    .drop_column(Users::MembershipAutoRenew)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
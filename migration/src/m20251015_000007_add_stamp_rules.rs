@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+/// Stamp Rules (订单印花奖励规则)
+#[derive(DeriveIden)]
+enum StampRules {
+    Table,
+    Id,
+    ProductNo,
+    MinPriceCents,
+    Stamps,
+    Active,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// product_no 非空为单品规则；product_no 为空时按 min_price_cents 作为价格档规则。
+/// 无匹配规则时服务端默认奖励 1 个印花，因此这里不预置数据
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(StampRules::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(StampRules::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRules::ProductNo)
+                            .string()
+                            .null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRules::MinPriceCents)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRules::Stamps)
+                            .big_integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(
+                        ColumnDef::new(StampRules::Active)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(StampRules::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRules::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StampRules::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+/// Sync State (外部数据同步游标)
+#[derive(DeriveIden)]
+enum SyncState {
+    Table,
+    Key,
+    LastSyncedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SyncState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SyncState::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SyncState::LastSyncedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(SyncState::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SyncState::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
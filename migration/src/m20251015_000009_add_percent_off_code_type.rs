@@ -0,0 +1,68 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum DiscountCodes {
+    Table,
+    PercentOff,
+    MaxDiscountCents,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Append new enum value 'percent_off' to code_type
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TYPE code_type ADD VALUE IF NOT EXISTS 'percent_off'".to_string(),
+        );
+        manager.get_connection().execute(stmt).await?;
+
+        // 百分比折扣码：折扣百分比与封顶金额（美分），固定金额码为空
+        if !manager.has_column("discount_codes", "percent_off").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .add_column(ColumnDef::new(DiscountCodes::PercentOff).integer().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        if !manager
+            .has_column("discount_codes", "max_discount_cents")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .add_column(
+                            ColumnDef::new(DiscountCodes::MaxDiscountCents)
+                                .big_integer()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No easy way to drop enum value in PostgreSQL; only drop the columns
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DiscountCodes::Table)
+                    .drop_column(DiscountCodes::PercentOff)
+                    .drop_column(DiscountCodes::MaxDiscountCents)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
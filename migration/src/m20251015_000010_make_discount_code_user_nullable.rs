@@ -0,0 +1,28 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 活动批量生成的优惠码不绑定用户
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE discount_codes ALTER COLUMN user_id DROP NOT NULL".to_string(),
+        );
+        manager.get_connection().execute(stmt).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 回滚前需先清理未绑定用户的优惠码
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE discount_codes ALTER COLUMN user_id SET NOT NULL".to_string(),
+        );
+        manager.get_connection().execute(stmt).await?;
+        Ok(())
+    }
+}
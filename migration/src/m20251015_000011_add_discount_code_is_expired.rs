@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum DiscountCodes {
+    Table,
+    IsExpired,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 由后台任务标记的过期状态（未使用且已过 expires_at）
+        if !manager.has_column("discount_codes", "is_expired").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .add_column(
+                            ColumnDef::new(DiscountCodes::IsExpired)
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DiscountCodes::Table)
+                    .drop_column(DiscountCodes::IsExpired)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
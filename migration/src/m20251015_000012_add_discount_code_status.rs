@@ -0,0 +1,80 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum DiscountCodes {
+    Table,
+    Status,
+    StampsSpent,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "DO $$ BEGIN \n  CREATE TYPE discount_code_status AS ENUM ('pending','active','failed');\nEXCEPTION WHEN duplicate_object THEN NULL; END $$;".to_string(),
+            ))
+            .await?;
+
+        // 兑换流程：先以 pending 落库并扣减，七云注册成功后置为 active，失败则 failed 并退还
+        if !manager.has_column("discount_codes", "status").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .add_column(
+                            ColumnDef::new(DiscountCodes::Status)
+                                .custom(Alias::new("discount_code_status"))
+                                .not_null()
+                                .default(Expr::cust("'active'::discount_code_status")),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // 印花兑换时扣除的印花数，用于失败退还
+        if !manager.has_column("discount_codes", "stamps_spent").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .add_column(
+                            ColumnDef::new(DiscountCodes::StampsSpent)
+                                .big_integer()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DiscountCodes::Table)
+                    .drop_column(DiscountCodes::Status)
+                    .drop_column(DiscountCodes::StampsSpent)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_type(
+                Type::drop()
+                    .name(Alias::new("discount_code_status"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use sea_orm_migration::prelude::*;
+
+/// Discount Code Transfers (优惠码转赠记录)
+#[derive(DeriveIden)]
+enum DiscountCodeTransfers {
+    Table,
+    Id,
+    DiscountCodeId,
+    FromUserId,
+    ToUserId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum DiscountCodes {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DiscountCodeTransfers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DiscountCodeTransfers::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DiscountCodeTransfers::DiscountCodeId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DiscountCodeTransfers::FromUserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DiscountCodeTransfers::ToUserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DiscountCodeTransfers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                DiscountCodeTransfers::Table,
+                                DiscountCodeTransfers::DiscountCodeId,
+                            )
+                            .to(DiscountCodes::Table, DiscountCodes::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                DiscountCodeTransfers::Table,
+                                DiscountCodeTransfers::FromUserId,
+                            )
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                DiscountCodeTransfers::Table,
+                                DiscountCodeTransfers::ToUserId,
+                            )
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_discount_code_transfers_code")
+                    .table(DiscountCodeTransfers::Table)
+                    .col(DiscountCodeTransfers::DiscountCodeId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DiscountCodeTransfers::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
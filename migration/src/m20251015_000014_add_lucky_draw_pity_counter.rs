@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum LuckyDrawChances {
+    Table,
+    LossesSinceWin,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 连续未中奖（Thank You）次数，达到阈值后下一次必中
+        if !manager
+            .has_column("lucky_draw_chances", "losses_since_win")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(LuckyDrawChances::Table)
+                        .add_column(
+                            ColumnDef::new(LuckyDrawChances::LossesSinceWin)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LuckyDrawChances::Table)
+                    .drop_column(LuckyDrawChances::LossesSinceWin)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum LuckyDrawChances {
+    Table,
+    LastFreeSpinOn,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 最近一次发放每日免费抽奖的日期，用于保证同一天只发一次
+        if !manager
+            .has_column("lucky_draw_chances", "last_free_spin_on")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(LuckyDrawChances::Table)
+                        .add_column(
+                            ColumnDef::new(LuckyDrawChances::LastFreeSpinOn)
+                                .date()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LuckyDrawChances::Table)
+                    .drop_column(LuckyDrawChances::LastFreeSpinOn)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Role,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "DO $$ BEGIN \n  CREATE TYPE user_role AS ENUM ('user','admin');\nEXCEPTION WHEN duplicate_object THEN NULL; END $$;".to_string(),
+            ))
+            .await?;
+
+        // 用户角色：/admin 下的接口仅允许 admin 访问
+        if !manager.has_column("users", "role").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(
+                            ColumnDef::new(Users::Role)
+                                .custom(Alias::new("user_role"))
+                                .not_null()
+                                .default(Expr::cust("'user'::user_role")),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::Role)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_type(Type::drop().name(Alias::new("user_role")).to_owned())
+            .await?;
+        Ok(())
+    }
+}
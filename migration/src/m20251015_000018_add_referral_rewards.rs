@@ -0,0 +1,114 @@
+use sea_orm_migration::prelude::*;
+
+/// Referral Rewards (推荐奖励发放记录，每个被推荐人仅发放一次)
+#[derive(DeriveIden)]
+enum ReferralRewards {
+    Table,
+    Id,
+    ReferrerId,
+    RefereeId,
+    DiscountCodeId,
+    AmountCents,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum DiscountCodes {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReferralRewards::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ReferralRewards::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ReferralRewards::ReferrerId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReferralRewards::RefereeId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ReferralRewards::DiscountCodeId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReferralRewards::AmountCents)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReferralRewards::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ReferralRewards::Table, ReferralRewards::ReferrerId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ReferralRewards::Table, ReferralRewards::RefereeId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ReferralRewards::Table, ReferralRewards::DiscountCodeId)
+                            .to(DiscountCodes::Table, DiscountCodes::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_referral_rewards_referrer")
+                    .table(ReferralRewards::Table)
+                    .col(ReferralRewards::ReferrerId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReferralRewards::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
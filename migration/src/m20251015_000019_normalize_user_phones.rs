@@ -0,0 +1,47 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 一次性将历史手机号规范为 E.164 (+1XXXXXXXXXX)；
+        // 规范化后与已有号码冲突的记录保持原样，需人工处理
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                WITH candidates AS (
+                    SELECT id, '+1' || RIGHT(digits, 10) AS normalized
+                    FROM (
+                        SELECT id, phone, regexp_replace(phone, '\D', '', 'g') AS digits
+                        FROM "users"
+                    ) d
+                    WHERE phone !~ '^\+1\d{10}$'
+                      AND (LENGTH(digits) = 10 OR (LENGTH(digits) = 11 AND digits LIKE '1%'))
+                ),
+                unique_candidates AS (
+                    SELECT c.id, c.normalized
+                    FROM candidates c
+                    WHERE NOT EXISTS (SELECT 1 FROM "users" u WHERE u.phone = c.normalized)
+                      AND (SELECT COUNT(*) FROM candidates c2 WHERE c2.normalized = c.normalized) = 1
+                )
+                UPDATE "users" u
+                SET phone = uc.normalized
+                FROM unique_candidates uc
+                WHERE u.id = uc.id
+                "#
+                .to_string(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // 数据规范化不可逆
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+/// Stamp Redemption Tiers (印花兑换优惠码档位配置)
+#[derive(DeriveIden)]
+enum StampRedemptionTiers {
+    Table,
+    Id,
+    ValueCents,
+    StampsRequired,
+    Active,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// 表为空时服务端回退到内置默认档位（10 stamps 兑换 $5.5），因此这里不预置数据
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(StampRedemptionTiers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(StampRedemptionTiers::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRedemptionTiers::ValueCents)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRedemptionTiers::StampsRequired)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRedemptionTiers::Active)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(StampRedemptionTiers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(StampRedemptionTiers::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StampRedemptionTiers::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
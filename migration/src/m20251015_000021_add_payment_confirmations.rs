@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+/// Payment Confirmations (统一确认接口的首次成功结果，按 PaymentIntent 幂等返回)
+#[derive(DeriveIden)]
+enum PaymentConfirmations {
+    Table,
+    PaymentIntentId,
+    UserId,
+    Category,
+    Response,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentConfirmations::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PaymentConfirmations::PaymentIntentId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentConfirmations::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentConfirmations::Category)
+                            .custom(Alias::new("stripe_transaction_category"))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentConfirmations::Response)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentConfirmations::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentConfirmations::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
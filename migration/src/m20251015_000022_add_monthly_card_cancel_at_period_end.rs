@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum MonthlyCards {
+    Table,
+    CancelAtPeriodEnd,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 订阅已在 Stripe 侧设置为到期取消（当期结束后不再续费）
+        if !manager
+            .has_column("monthly_cards", "cancel_at_period_end")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MonthlyCards::Table)
+                        .add_column(
+                            ColumnDef::new(MonthlyCards::CancelAtPeriodEnd)
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MonthlyCards::Table)
+                    .drop_column(MonthlyCards::CancelAtPeriodEnd)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
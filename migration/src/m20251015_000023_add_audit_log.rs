@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+/// Audit Log (余额、会员、优惠码及后台操作的审计记录)
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    ActorId,
+    Action,
+    Entity,
+    EntityId,
+    Before,
+    After,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // 操作人用户 ID；webhook/定时任务等系统操作为空
+                    .col(ColumnDef::new(AuditLog::ActorId).big_integer().null())
+                    .col(ColumnDef::new(AuditLog::Action).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Entity).string().not_null())
+                    .col(ColumnDef::new(AuditLog::EntityId).big_integer().null())
+                    .col(ColumnDef::new(AuditLog::Before).json_binary().null())
+                    .col(ColumnDef::new(AuditLog::After).json_binary().null())
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_entity")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Entity)
+                    .col(AuditLog::EntityId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
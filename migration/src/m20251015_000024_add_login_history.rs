@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    LastLoginAt,
+}
+
+/// Login History (最近登录记录：IP 与 User-Agent)
+#[derive(DeriveIden)]
+enum LoginHistory {
+    Table,
+    Id,
+    UserId,
+    Ip,
+    UserAgent,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 最近一次成功登录时间，用于识别沉睡账号
+        if !manager.has_column("users", "last_login_at").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .add_column(
+                            ColumnDef::new(Users::LastLoginAt)
+                                .timestamp_with_time_zone()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LoginHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LoginHistory::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LoginHistory::Ip).string().null())
+                    .col(ColumnDef::new(LoginHistory::UserAgent).string().null())
+                    .col(
+                        ColumnDef::new(LoginHistory::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .default(Expr::cust("NOW()"))
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(LoginHistory::Table, LoginHistory::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_login_history_user")
+                    .table(LoginHistory::Table)
+                    .col(LoginHistory::UserId)
+                    .col(LoginHistory::Id)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoginHistory::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::LastLoginAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
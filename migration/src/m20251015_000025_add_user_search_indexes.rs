@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 管理端用户搜索：用户名不区分大小写前缀匹配、手机号后缀匹配（按反转后的前缀匹配）
+        // 表达式索引 SeaQuery 不支持，使用原生语句
+        for sql in [
+            "CREATE INDEX IF NOT EXISTS idx_users_username_lower ON users (LOWER(username) text_pattern_ops)",
+            "CREATE INDEX IF NOT EXISTS idx_users_phone_reverse ON users (REVERSE(phone) text_pattern_ops)",
+        ] {
+            let stmt =
+                sea_orm::Statement::from_string(manager.get_database_backend(), sql.to_owned());
+            manager.get_connection().execute(stmt).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for sql in [
+            "DROP INDEX IF EXISTS idx_users_username_lower",
+            "DROP INDEX IF EXISTS idx_users_phone_reverse",
+        ] {
+            let stmt =
+                sea_orm::Statement::from_string(manager.get_database_backend(), sql.to_owned());
+            manager.get_connection().execute(stmt).await?;
+        }
+        Ok(())
+    }
+}
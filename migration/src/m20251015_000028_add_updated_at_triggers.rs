@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+/// 由触发器统一维护 updated_at 的表
+const TABLES: [&str; 6] = [
+    "users",
+    "orders",
+    "discount_codes",
+    "recharge_records",
+    "membership_purchases",
+    "monthly_cards",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 每次 UPDATE 都刷新 updated_at，业务代码不再需要逐处手动设置
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            CREATE OR REPLACE FUNCTION set_updated_at() RETURNS trigger AS $$
+            BEGIN
+                NEW.updated_at = NOW();
+                RETURN NEW;
+            END;
+            $$ LANGUAGE plpgsql
+            "#,
+        )
+        .await?;
+        for table in TABLES {
+            db.execute_unprepared(&format!(
+                "DROP TRIGGER IF EXISTS trg_{table}_updated_at ON {table}"
+            ))
+            .await?;
+            db.execute_unprepared(&format!(
+                "CREATE TRIGGER trg_{table}_updated_at BEFORE UPDATE ON {table} \
+                 FOR EACH ROW EXECUTE FUNCTION set_updated_at()"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for table in TABLES {
+            db.execute_unprepared(&format!(
+                "DROP TRIGGER IF EXISTS trg_{table}_updated_at ON {table}"
+            ))
+            .await?;
+        }
+        db.execute_unprepared("DROP FUNCTION IF EXISTS set_updated_at()")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 月卡每日券与生日券使用独立类型，便于统计；已有记录保持原类型
+        for value in ["monthly_card_daily", "birthday_reward"] {
+            let stmt = Statement::from_string(
+                manager.get_database_backend(),
+                format!("ALTER TYPE code_type ADD VALUE IF NOT EXISTS '{value}'"),
+            );
+            manager.get_connection().execute(stmt).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // No easy way to drop enum value in PostgreSQL
+        Ok(())
+    }
+}
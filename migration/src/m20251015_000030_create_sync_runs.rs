@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+/// SyncRuns (每次七云同步的运行记录，用于观察同步健康状况)
+#[derive(DeriveIden)]
+enum SyncRuns {
+    Table,
+    Id,
+    Kind,
+    StartedAt,
+    FinishedAt,
+    Processed,
+    Errors,
+    Status,
+    ErrorMessage,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SyncRuns::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SyncRuns::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SyncRuns::Kind).string().not_null())
+                    .col(
+                        ColumnDef::new(SyncRuns::StartedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(SyncRuns::FinishedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(SyncRuns::Processed)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(SyncRuns::Errors)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    // running / succeeded / partial / failed
+                    .col(ColumnDef::new(SyncRuns::Status).string().not_null())
+                    .col(ColumnDef::new(SyncRuns::ErrorMessage).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sync_runs_kind")
+                    .table(SyncRuns::Table)
+                    .col(SyncRuns::Kind)
+                    .col(SyncRuns::Id)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SyncRuns::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
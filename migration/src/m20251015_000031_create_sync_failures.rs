@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+
+/// SyncFailures (同步处理失败的七云订单，达到重试上限后标记为待人工处理)
+#[derive(DeriveIden)]
+enum SyncFailures {
+    Table,
+    Id,
+    OrderId,
+    OrderCreatedAt,
+    Attempts,
+    LastError,
+    Flagged,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SyncFailures::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SyncFailures::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // 七云订单 ID
+                    .col(
+                        ColumnDef::new(SyncFailures::OrderId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // 订单创建时间，重试时据此补同步当天订单
+                    .col(
+                        ColumnDef::new(SyncFailures::OrderCreatedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(SyncFailures::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .col(ColumnDef::new(SyncFailures::LastError).text().not_null())
+                    .col(
+                        ColumnDef::new(SyncFailures::Flagged)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(SyncFailures::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(SyncFailures::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SyncFailures::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+/// StripeTransactions (用户交易记录列表按时间倒序分页)
+#[derive(DeriveIden)]
+enum StripeTransactions {
+    Table,
+    UserId,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_stripe_transactions_user_created_at")
+                    .table(StripeTransactions::Table)
+                    .col(StripeTransactions::UserId)
+                    .col(StripeTransactions::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .if_exists()
+                    .name("idx_stripe_transactions_user_created_at")
+                    .table(StripeTransactions::Table)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum RechargeRecords {
+    Table,
+    Currency,
+}
+
+#[derive(DeriveIden)]
+enum RechargeTiers {
+    Table,
+    Currency,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 充值记录的支付货币（小写 ISO 代码），已有记录均为 USD
+        if !manager.has_column("recharge_records", "currency").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RechargeRecords::Table)
+                        .add_column(
+                            ColumnDef::new(RechargeRecords::Currency)
+                                .string_len(3)
+                                .not_null()
+                                .default("usd"),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        // 充值档位按货币区分，同一货币内金额唯一
+        if !manager.has_column("recharge_tiers", "currency").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RechargeTiers::Table)
+                        .add_column(
+                            ColumnDef::new(RechargeTiers::Currency)
+                                .string_len(3)
+                                .not_null()
+                                .default("usd"),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        for sql in [
+            "ALTER TABLE recharge_tiers DROP CONSTRAINT IF EXISTS recharge_tiers_amount_cents_key",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_recharge_tiers_currency_amount ON recharge_tiers (currency, amount_cents)",
+        ] {
+            let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
+            manager.get_connection().execute(stmt).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for sql in [
+            "DROP INDEX IF EXISTS idx_recharge_tiers_currency_amount",
+            "ALTER TABLE recharge_tiers DROP COLUMN IF EXISTS currency",
+            "ALTER TABLE recharge_tiers ADD CONSTRAINT recharge_tiers_amount_cents_key UNIQUE (amount_cents)",
+            "ALTER TABLE recharge_records DROP COLUMN IF EXISTS currency",
+        ] {
+            let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
+            manager.get_connection().execute(stmt).await?;
+        }
+        Ok(())
+    }
+}
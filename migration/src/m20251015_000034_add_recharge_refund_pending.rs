@@ -0,0 +1,63 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum RechargeRecords {
+    Table,
+    PendingRefundAmount,
+    PendingRefundDebit,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已扣回余额、等待 Stripe 退款完成的中间状态
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TYPE recharge_status ADD VALUE IF NOT EXISTS 'refund_pending'".to_string(),
+        );
+        manager.get_connection().execute(stmt).await?;
+
+        // 进行中的退款：Stripe 退款金额与已扣回的余额，Stripe 失败时据此退回
+        if !manager
+            .has_column("recharge_records", "pending_refund_amount")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RechargeRecords::Table)
+                        .add_column(
+                            ColumnDef::new(RechargeRecords::PendingRefundAmount)
+                                .big_integer()
+                                .null(),
+                        )
+                        .add_column(
+                            ColumnDef::new(RechargeRecords::PendingRefundDebit)
+                                .big_integer()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 枚举值无法直接删除，只移除列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RechargeRecords::Table)
+                    .drop_column(RechargeRecords::PendingRefundAmount)
+                    .drop_column(RechargeRecords::PendingRefundDebit)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
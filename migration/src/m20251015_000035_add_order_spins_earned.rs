@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    SpinsEarned,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 订单发放的抽奖次数，取消/退款时据此回收；已有订单为 NULL，回收时按金额推算
+        if !manager.has_column("orders", "spins_earned").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Orders::Table)
+                        .add_column(ColumnDef::new(Orders::SpinsEarned).big_integer().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .drop_column(Orders::SpinsEarned)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use sea_orm_migration::prelude::*;
+
+/// MembershipRewardGrants (会员升级奖励发放记录，每条购买记录一行，未发放完的由定时任务重试)
+#[derive(DeriveIden)]
+enum MembershipRewardGrants {
+    Table,
+    Id,
+    PurchaseId,
+    UserId,
+    MemberType,
+    CodesTotal,
+    CodesGranted,
+    Granted,
+    ClaimedAt,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum MembershipPurchases {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MembershipRewardGrants::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::PurchaseId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // 获得奖励的用户（赠送订单为受赠人）
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::MemberType)
+                            .custom(Alias::new("member_type"))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::CodesTotal)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::CodesGranted)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::Granted)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    // 正在发放的认领时间，超时后可被重试任务重新认领
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::ClaimedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::LastError)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(MembershipRewardGrants::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                MembershipRewardGrants::Table,
+                                MembershipRewardGrants::PurchaseId,
+                            )
+                            .to(MembershipPurchases::Table, MembershipPurchases::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                MembershipRewardGrants::Table,
+                                MembershipRewardGrants::UserId,
+                            )
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_membership_reward_grants_pending")
+                    .table(MembershipRewardGrants::Table)
+                    .col(MembershipRewardGrants::Granted)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(MembershipRewardGrants::Table)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum RechargeRecords {
+    Table,
+    RefundedAmount,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 部分退款后仍可继续退款，累计退款达到实付金额时才置为 refunded
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TYPE recharge_status ADD VALUE IF NOT EXISTS 'partially_refunded'".to_string(),
+        );
+        manager.get_connection().execute(stmt).await?;
+
+        // 累计已退款金额（美分）
+        if !manager
+            .has_column("recharge_records", "refunded_amount")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RechargeRecords::Table)
+                        .add_column(
+                            ColumnDef::new(RechargeRecords::RefundedAmount)
+                                .big_integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            // 历史退款记录的实际退款金额未知，视为已全额退款，不再允许继续退款。
+            // 'refunded' 由同一批迁移（000002）以 ADD VALUE 添加，事务内不能直接当枚举值比较，按文本比较
+            let stmt = Statement::from_string(
+                manager.get_database_backend(),
+                "UPDATE recharge_records SET refunded_amount = amount WHERE status::text = 'refunded'"
+                    .to_string(),
+            );
+            manager.get_connection().execute(stmt).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 枚举值无法直接删除，只移除列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RechargeRecords::Table)
+                    .drop_column(RechargeRecords::RefundedAmount)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
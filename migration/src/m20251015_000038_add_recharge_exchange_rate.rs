@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum RechargeRecords {
+    Table,
+    ExchangeRateBps,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建充值时 1 单位支付货币折合的 USD（基点），余额按此折算为美分；
+        // 已有记录均按 1:1 入账，默认 10000 与之一致
+        if !manager
+            .has_column("recharge_records", "exchange_rate_bps")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RechargeRecords::Table)
+                        .add_column(
+                            ColumnDef::new(RechargeRecords::ExchangeRateBps)
+                                .big_integer()
+                                .not_null()
+                                .default(10000),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RechargeRecords::Table)
+                    .drop_column(RechargeRecords::ExchangeRateBps)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ProcessedEvents {
+    Table,
+    CompletedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 事件处理完成时间；为空表示仅被认领（processed_at 为认领时间，作为租约），
+        // 处理中途崩溃的事件在租约过期后可被 Stripe 重试重新认领
+        if !manager
+            .has_column("processed_events", "completed_at")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(ProcessedEvents::Table)
+                        .add_column(
+                            ColumnDef::new(ProcessedEvents::CompletedAt)
+                                .timestamp_with_time_zone()
+                                .null(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            // 已有记录均视为处理完成
+            manager
+                .get_connection()
+                .execute_unprepared(
+                    "UPDATE processed_events SET completed_at = COALESCE(processed_at, NOW())",
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ProcessedEvents::Table)
+                    .drop_column(ProcessedEvents::CompletedAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum DiscountCodes {
+    Table,
+    PercentOff,
+    MaxDiscountCents,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 百分比折扣码已下线（七云无法按封顶核销，此前一律校验为不可用），
+        // 清理遗留的码后删除相关列；枚举值 'percent_off' 在 PostgreSQL 中无法直接删除，保留不用
+        let stmt = Statement::from_string(
+            manager.get_database_backend(),
+            "DELETE FROM discount_codes WHERE code_type::text = 'percent_off'".to_string(),
+        );
+        manager.get_connection().execute(stmt).await?;
+
+        if manager.has_column("discount_codes", "percent_off").await? {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .drop_column(DiscountCodes::PercentOff)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        if manager
+            .has_column("discount_codes", "max_discount_cents")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(DiscountCodes::Table)
+                        .drop_column(DiscountCodes::MaxDiscountCents)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DiscountCodes::Table)
+                    .add_column(ColumnDef::new(DiscountCodes::PercentOff).integer().null())
+                    .add_column(
+                        ColumnDef::new(DiscountCodes::MaxDiscountCents)
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum RechargeRecords {
+    Table,
+    RequestKey,
+    CheckoutUrl,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 客户端 request_id 派生的幂等 key 与 Checkout 地址：同一 request_id 重复创建时直接返回已有记录
+        if !manager
+            .has_column("recharge_records", "request_key")
+            .await?
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(RechargeRecords::Table)
+                        .add_column(ColumnDef::new(RechargeRecords::RequestKey).string().null())
+                        .add_column(ColumnDef::new(RechargeRecords::CheckoutUrl).text().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_recharge_records_request_key")
+                    .table(RechargeRecords::Table)
+                    .col(RechargeRecords::RequestKey)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_recharge_records_request_key")
+                    .table(RechargeRecords::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RechargeRecords::Table)
+                    .drop_column(RechargeRecords::RequestKey)
+                    .drop_column(RechargeRecords::CheckoutUrl)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
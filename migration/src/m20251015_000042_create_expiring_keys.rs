@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+/// ExpiringKeys (带过期时间的唯一键：重设密码锁、验证码防重放等跨实例的短时标记)
+#[derive(DeriveIden)]
+enum ExpiringKeys {
+    Table,
+    Key,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExpiringKeys::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ExpiringKeys::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ExpiringKeys::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ExpiringKeys::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_expiring_keys_expires_at")
+                    .table(ExpiringKeys::Table)
+                    .col(ExpiringKeys::ExpiresAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExpiringKeys::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
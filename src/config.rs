@@ -1,3 +1,6 @@
+use crate::entities::{CodeType, MonthlyCardPlanType};
+use crate::error::{AppError, AppResult};
+use crate::utils::TrustedProxies;
 use serde::{Deserialize, Serialize};
 use std::env;
 
@@ -11,12 +14,30 @@ pub struct Config {
     pub sevencloud: SevenCloudConfig,
     #[serde(default)]
     pub turnstile: TurnstileConfig,
+    #[serde(default)]
+    pub cashback: CashbackConfig,
+    #[serde(default)]
+    pub recharge: RechargeConfig,
+    #[serde(default)]
+    pub lucky_draw: LuckyDrawConfig,
+    #[serde(default)]
+    pub birthday_reward: BirthdayRewardConfig,
+    #[serde(default)]
+    pub monthly_card: MonthlyCardConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub tasks: TasksConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// 受信任的反向代理（IP 或 CIDR），仅来自这些地址的请求才采信
+    /// CF-Connecting-IP / X-Forwarded-For；为空则一律使用连接对端地址
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +51,16 @@ pub struct JwtConfig {
     pub secret: String,
     pub access_token_expires_in: i64,  // seconds
     pub refresh_token_expires_in: i64, // seconds
+    /// 甜心股东令牌有效期（秒），未配置时使用上面的默认值
+    #[serde(default)]
+    pub sweet_shareholder_access_token_expires_in: Option<i64>,
+    #[serde(default)]
+    pub sweet_shareholder_refresh_token_expires_in: Option<i64>,
+    /// 超级股东令牌有效期（秒），未配置时使用上面的默认值
+    #[serde(default)]
+    pub super_shareholder_access_token_expires_in: Option<i64>,
+    #[serde(default)]
+    pub super_shareholder_refresh_token_expires_in: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +87,23 @@ pub struct StripeConfig {
     pub monthly_card_one_time_price_id: Option<String>,
     #[serde(default)]
     pub monthly_card_subscription_price_id: Option<String>,
+    /// 未配置月卡 price id 时使用的金额（美分），便于本地/开发环境无需创建 Stripe Product
+    #[serde(default = "default_monthly_card_fallback_amount_cents")]
+    pub monthly_card_fallback_amount_cents: i64,
+    /// Webhook 签名时间戳允许的偏差（秒），超出视为重放
+    #[serde(default = "default_webhook_tolerance_secs")]
+    pub webhook_tolerance_secs: i64,
+    /// Webhook 来源 IP 白名单（Stripe 公布的 webhook IP），为空则不校验
+    #[serde(default)]
+    pub webhook_ip_allowlist: Vec<String>,
+}
+
+fn default_webhook_tolerance_secs() -> i64 {
+    300
+}
+
+fn default_monthly_card_fallback_amount_cents() -> i64 {
+    550
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +111,13 @@ pub struct SevenCloudConfig {
     pub username: String,
     pub password: String,
     pub base_url: String,
+    /// 拉取订单时的分页大小
+    #[serde(default = "default_sevencloud_page_size")]
+    pub page_size: u32,
+}
+
+fn default_sevencloud_page_size() -> u32 {
+    1000
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -74,6 +129,389 @@ pub struct TurnstileConfig {
     pub expected_action: Option<String>,
 }
 
+/// 订单返利比例（基点，100 = 1%），买家与推荐人共用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashbackConfig {
+    #[serde(default = "default_sweet_cashback_bps")]
+    pub sweet_bps: i64,
+    #[serde(default = "default_super_cashback_bps")]
+    pub super_bps: i64,
+}
+
+fn default_sweet_cashback_bps() -> i64 {
+    500
+}
+
+fn default_super_cashback_bps() -> i64 {
+    1000
+}
+
+impl Default for CashbackConfig {
+    fn default() -> Self {
+        Self {
+            sweet_bps: default_sweet_cashback_bps(),
+            super_bps: default_super_cashback_bps(),
+        }
+    }
+}
+
+/// 非 USD 充值入账余额时的汇率（基点，1 单位外币折合的 USD，10000 = 1:1）；
+/// 余额以美分计，创建充值时的汇率随记录保存，入账与退款扣回都按该汇率折算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RechargeConfig {
+    #[serde(default = "default_eur_usd_bps")]
+    pub eur_usd_bps: i64,
+    #[serde(default = "default_gbp_usd_bps")]
+    pub gbp_usd_bps: i64,
+}
+
+fn default_eur_usd_bps() -> i64 {
+    10800
+}
+
+fn default_gbp_usd_bps() -> i64 {
+    12700
+}
+
+impl Default for RechargeConfig {
+    fn default() -> Self {
+        Self {
+            eur_usd_bps: default_eur_usd_bps(),
+            gbp_usd_bps: default_gbp_usd_bps(),
+        }
+    }
+}
+
+impl RechargeConfig {
+    /// 指定货币（小写 ISO 代码）折合 USD 的汇率，不支持的货币返回 None
+    pub fn usd_rate_bps(&self, currency: &str) -> Option<i64> {
+        match currency {
+            "usd" => Some(10000),
+            "eur" => Some(self.eur_usd_bps),
+            "gbp" => Some(self.gbp_usd_bps),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LuckyDrawConfig {
+    /// 连续 N 次未中奖后下一次必中（0 表示关闭保底）
+    #[serde(default = "default_pity_threshold")]
+    pub pity_threshold: i32,
+    /// 每日免费抽奖仅发给近 30 天有订单的活跃用户
+    #[serde(default)]
+    pub free_spin_active_only: bool,
+}
+
+fn default_pity_threshold() -> i32 {
+    10
+}
+
+impl Default for LuckyDrawConfig {
+    fn default() -> Self {
+        Self {
+            pity_threshold: default_pity_threshold(),
+            free_spin_active_only: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BirthdayRewardConfig {
+    /// 各会员等级的生日福利金额（美分）
+    #[serde(default = "default_birthday_fan_cents")]
+    pub fan_cents: i64,
+    #[serde(default = "default_birthday_sweet_cents")]
+    pub sweet_cents: i64,
+    #[serde(default = "default_birthday_super_cents")]
+    pub super_cents: i64,
+    /// 是否以优惠码形式发放；否则直接计入余额
+    #[serde(default)]
+    pub issue_code: bool,
+    /// 以优惠码发放时的类型，默认 `birthday_reward` 便于单独统计
+    #[serde(default = "default_birthday_code_type")]
+    pub code_type: CodeType,
+    /// 优惠码有效期（月，1-3）
+    #[serde(default = "default_birthday_expire_months")]
+    pub expire_months: u32,
+}
+
+fn default_birthday_fan_cents() -> i64 {
+    50
+}
+
+fn default_birthday_sweet_cents() -> i64 {
+    550
+}
+
+fn default_birthday_super_cents() -> i64 {
+    800
+}
+
+fn default_birthday_code_type() -> CodeType {
+    CodeType::BirthdayReward
+}
+
+fn default_birthday_expire_months() -> u32 {
+    1
+}
+
+impl Default for BirthdayRewardConfig {
+    fn default() -> Self {
+        Self {
+            fan_cents: default_birthday_fan_cents(),
+            sweet_cents: default_birthday_sweet_cents(),
+            super_cents: default_birthday_super_cents(),
+            issue_code: false,
+            code_type: default_birthday_code_type(),
+            expire_months: default_birthday_expire_months(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyCardConfig {
+    /// 月卡每日优惠码面值（美分）
+    #[serde(default = "default_monthly_card_daily_coupon_cents")]
+    pub daily_coupon_cents: i64,
+    /// 订阅月卡的每日优惠码面值（美分），未配置时与 `daily_coupon_cents` 相同
+    #[serde(default)]
+    pub subscription_daily_coupon_cents: Option<i64>,
+    /// 每日优惠码类型
+    #[serde(default = "default_monthly_card_daily_coupon_code_type")]
+    pub daily_coupon_code_type: CodeType,
+}
+
+fn default_monthly_card_daily_coupon_cents() -> i64 {
+    550
+}
+
+fn default_monthly_card_daily_coupon_code_type() -> CodeType {
+    CodeType::MonthlyCardDaily
+}
+
+impl Default for MonthlyCardConfig {
+    fn default() -> Self {
+        Self {
+            daily_coupon_cents: default_monthly_card_daily_coupon_cents(),
+            subscription_daily_coupon_cents: None,
+            daily_coupon_code_type: default_monthly_card_daily_coupon_code_type(),
+        }
+    }
+}
+
+impl MonthlyCardConfig {
+    /// 指定月卡类型的每日优惠码面值
+    pub fn daily_coupon_cents_for(&self, plan_type: &MonthlyCardPlanType) -> i64 {
+        match plan_type {
+            MonthlyCardPlanType::OneTime => self.daily_coupon_cents,
+            MonthlyCardPlanType::Subscription => self
+                .subscription_daily_coupon_cents
+                .unwrap_or(self.daily_coupon_cents),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_rate_limit_enabled")]
+    pub enabled: bool,
+    /// 认证接口（登录/注册/验证码）每个 IP 每分钟请求数，0 表示不限
+    #[serde(default = "default_auth_per_minute")]
+    pub auth_per_minute: u32,
+    /// 其余 /api/v1 接口每个 IP 每分钟请求数，0 表示不限
+    #[serde(default = "default_api_per_minute")]
+    pub api_per_minute: u32,
+}
+
+fn default_rate_limit_enabled() -> bool {
+    true
+}
+
+fn default_auth_per_minute() -> u32 {
+    10
+}
+
+fn default_api_per_minute() -> u32 {
+    120
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_rate_limit_enabled(),
+            auth_per_minute: default_auth_per_minute(),
+            api_per_minute: default_api_per_minute(),
+        }
+    }
+}
+
+/// 后台定时任务的执行间隔（秒）与开关
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TasksConfig {
+    /// 订单与优惠码同步
+    #[serde(default = "default_orders_sync_secs")]
+    pub orders_sync_secs: u64,
+    /// 会员过期检查
+    #[serde(default = "default_membership_expiry_secs")]
+    pub membership_expiry_secs: u64,
+    /// 会员自动续费
+    #[serde(default = "default_membership_renewal_secs")]
+    pub membership_renewal_secs: u64,
+    /// 会员升级奖励补发
+    #[serde(default = "default_membership_reward_retry_secs")]
+    pub membership_reward_retry_secs: u64,
+    /// 优惠码过期标记
+    #[serde(default = "default_discount_code_expiry_secs")]
+    pub discount_code_expiry_secs: u64,
+    /// pending 优惠码对账
+    #[serde(default = "default_discount_code_reconcile_secs")]
+    pub discount_code_reconcile_secs: u64,
+    /// 停留在 refund_pending 的充值退款对账
+    #[serde(default = "default_recharge_refund_reconcile_secs")]
+    pub recharge_refund_reconcile_secs: u64,
+    /// 每日免费抽奖次数发放检查
+    #[serde(default = "default_free_spin_secs")]
+    pub free_spin_secs: u64,
+    /// 生日福利发放
+    #[serde(default = "default_birthday_reward_secs")]
+    pub birthday_reward_secs: u64,
+    /// 月卡过期检查
+    #[serde(default = "default_monthly_card_expiry_secs")]
+    pub monthly_card_expiry_secs: u64,
+    /// 月卡每日优惠券发放
+    #[serde(default = "default_monthly_card_coupon_secs")]
+    pub monthly_card_coupon_secs: u64,
+    /// 关闭的任务，名称为对应间隔字段去掉 `_secs`（如 `orders_sync`）
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+fn default_orders_sync_secs() -> u64 {
+    60
+}
+
+fn default_membership_expiry_secs() -> u64 {
+    6 * 3600
+}
+
+fn default_membership_renewal_secs() -> u64 {
+    24 * 3600
+}
+
+fn default_membership_reward_retry_secs() -> u64 {
+    600
+}
+
+fn default_discount_code_expiry_secs() -> u64 {
+    3600
+}
+
+fn default_discount_code_reconcile_secs() -> u64 {
+    600
+}
+
+fn default_recharge_refund_reconcile_secs() -> u64 {
+    900
+}
+
+fn default_free_spin_secs() -> u64 {
+    3600
+}
+
+fn default_birthday_reward_secs() -> u64 {
+    3600
+}
+
+fn default_monthly_card_expiry_secs() -> u64 {
+    6 * 3600
+}
+
+fn default_monthly_card_coupon_secs() -> u64 {
+    24 * 3600
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            orders_sync_secs: default_orders_sync_secs(),
+            membership_expiry_secs: default_membership_expiry_secs(),
+            membership_renewal_secs: default_membership_renewal_secs(),
+            membership_reward_retry_secs: default_membership_reward_retry_secs(),
+            discount_code_expiry_secs: default_discount_code_expiry_secs(),
+            discount_code_reconcile_secs: default_discount_code_reconcile_secs(),
+            recharge_refund_reconcile_secs: default_recharge_refund_reconcile_secs(),
+            free_spin_secs: default_free_spin_secs(),
+            birthday_reward_secs: default_birthday_reward_secs(),
+            monthly_card_expiry_secs: default_monthly_card_expiry_secs(),
+            monthly_card_coupon_secs: default_monthly_card_coupon_secs(),
+            disabled: Vec::new(),
+        }
+    }
+}
+
+impl TasksConfig {
+    /// 全部任务名
+    pub const NAMES: [&'static str; 11] = [
+        "orders_sync",
+        "membership_expiry",
+        "membership_renewal",
+        "membership_reward_retry",
+        "discount_code_expiry",
+        "discount_code_reconcile",
+        "recharge_refund_reconcile",
+        "free_spin",
+        "birthday_reward",
+        "monthly_card_expiry",
+        "monthly_card_coupon",
+    ];
+
+    /// 任务是否启用
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.iter().any(|d| d == name)
+    }
+
+    fn interval_mut(&mut self, name: &str) -> Option<&mut u64> {
+        match name {
+            "orders_sync" => Some(&mut self.orders_sync_secs),
+            "membership_expiry" => Some(&mut self.membership_expiry_secs),
+            "membership_renewal" => Some(&mut self.membership_renewal_secs),
+            "membership_reward_retry" => Some(&mut self.membership_reward_retry_secs),
+            "discount_code_expiry" => Some(&mut self.discount_code_expiry_secs),
+            "discount_code_reconcile" => Some(&mut self.discount_code_reconcile_secs),
+            "recharge_refund_reconcile" => Some(&mut self.recharge_refund_reconcile_secs),
+            "free_spin" => Some(&mut self.free_spin_secs),
+            "birthday_reward" => Some(&mut self.birthday_reward_secs),
+            "monthly_card_expiry" => Some(&mut self.monthly_card_expiry_secs),
+            "monthly_card_coupon" => Some(&mut self.monthly_card_coupon_secs),
+            _ => None,
+        }
+    }
+}
+
+/// 无配置文件且未设置 JWT_SECRET 时使用的占位密钥
+const DEFAULT_JWT_SECRET: &str = "change-me-in-production";
+
+/// 视为未配置的 JWT 密钥（内置默认值与 config.toml.example 中的示例值）
+const PLACEHOLDER_JWT_SECRETS: [&str; 2] = [
+    DEFAULT_JWT_SECRET,
+    "your-super-secret-jwt-key-change-this-in-production",
+];
+
+/// 解析逗号分隔的列表，忽略空白项
+fn parse_list(v: &str) -> Vec<String> {
+    v.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// 解析 snake_case 的优惠码类型（如 free_topping）
+fn parse_code_type(v: &str) -> Option<CodeType> {
+    serde_json::from_value(serde_json::Value::String(v.to_string())).ok()
+}
+
 impl Config {
     pub fn from_toml() -> Result<Self, Box<dyn std::error::Error>> {
         let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
@@ -107,6 +545,9 @@ impl Config {
                     server: ServerConfig {
                         host: get_env("SERVER_HOST").unwrap_or_else(|| "0.0.0.0".to_string()),
                         port: get_env_parse("SERVER_PORT", 8080u16),
+                        trusted_proxies: get_env("SERVER_TRUSTED_PROXIES")
+                            .map(|v| parse_list(&v))
+                            .unwrap_or_default(),
                     },
                     database: DatabaseConfig {
                         url: database_url,
@@ -114,12 +555,28 @@ impl Config {
                     },
                     jwt: JwtConfig {
                         secret: get_env("JWT_SECRET")
-                            .unwrap_or_else(|| "change-me-in-production".to_string()),
+                            .unwrap_or_else(|| DEFAULT_JWT_SECRET.to_string()),
                         access_token_expires_in: get_env_parse("JWT_ACCESS_EXPIRES_IN", 7200i64),
                         refresh_token_expires_in: get_env_parse(
                             "JWT_REFRESH_EXPIRES_IN",
                             2_592_000i64,
                         ),
+                        sweet_shareholder_access_token_expires_in: get_env(
+                            "JWT_SWEET_SHAREHOLDER_ACCESS_EXPIRES_IN",
+                        )
+                        .and_then(|v| v.parse().ok()),
+                        sweet_shareholder_refresh_token_expires_in: get_env(
+                            "JWT_SWEET_SHAREHOLDER_REFRESH_EXPIRES_IN",
+                        )
+                        .and_then(|v| v.parse().ok()),
+                        super_shareholder_access_token_expires_in: get_env(
+                            "JWT_SUPER_SHAREHOLDER_ACCESS_EXPIRES_IN",
+                        )
+                        .and_then(|v| v.parse().ok()),
+                        super_shareholder_refresh_token_expires_in: get_env(
+                            "JWT_SUPER_SHAREHOLDER_REFRESH_EXPIRES_IN",
+                        )
+                        .and_then(|v| v.parse().ok()),
                     },
                     twilio: TwilioConfig {
                         account_sid: get_env("TWILIO_ACCOUNT_SID").unwrap_or_default(),
@@ -140,18 +597,105 @@ impl Config {
                         monthly_card_subscription_price_id: get_env(
                             "STRIPE_MONTHLY_CARD_SUBSCRIPTION_PRICE_ID",
                         ),
+                        monthly_card_fallback_amount_cents: get_env_parse(
+                            "STRIPE_MONTHLY_CARD_FALLBACK_AMOUNT_CENTS",
+                            default_monthly_card_fallback_amount_cents(),
+                        ),
+                        webhook_tolerance_secs: get_env_parse(
+                            "STRIPE_WEBHOOK_TOLERANCE_SECS",
+                            default_webhook_tolerance_secs(),
+                        ),
+                        webhook_ip_allowlist: get_env("STRIPE_WEBHOOK_IP_ALLOWLIST")
+                            .map(|v| parse_list(&v))
+                            .unwrap_or_default(),
                     },
                     sevencloud: SevenCloudConfig {
                         username: get_env("SEVENCLOUD_USERNAME").unwrap_or_default(),
                         password: get_env("SEVENCLOUD_PASSWORD").unwrap_or_default(),
                         base_url: get_env("SEVENCLOUD_BASE_URL")
                             .unwrap_or_else(|| "https://sz.sunzee.com.cn".to_string()),
+                        page_size: get_env_parse(
+                            "SEVENCLOUD_PAGE_SIZE",
+                            default_sevencloud_page_size(),
+                        ),
                     },
                     turnstile: TurnstileConfig {
                         secret_key: get_env("TURNSTILE_SECRET_KEY").unwrap_or_default(),
                         expected_hostname: get_env("TURNSTILE_EXPECTED_HOSTNAME"),
                         expected_action: get_env("TURNSTILE_EXPECTED_ACTION"),
                     },
+                    cashback: CashbackConfig {
+                        sweet_bps: get_env_parse(
+                            "CASHBACK_SWEET_BPS",
+                            default_sweet_cashback_bps(),
+                        ),
+                        super_bps: get_env_parse(
+                            "CASHBACK_SUPER_BPS",
+                            default_super_cashback_bps(),
+                        ),
+                    },
+                    recharge: RechargeConfig {
+                        eur_usd_bps: get_env_parse("RECHARGE_EUR_USD_BPS", default_eur_usd_bps()),
+                        gbp_usd_bps: get_env_parse("RECHARGE_GBP_USD_BPS", default_gbp_usd_bps()),
+                    },
+                    lucky_draw: LuckyDrawConfig {
+                        pity_threshold: get_env_parse(
+                            "LUCKY_DRAW_PITY_THRESHOLD",
+                            default_pity_threshold(),
+                        ),
+                        free_spin_active_only: get_env_parse(
+                            "LUCKY_DRAW_FREE_SPIN_ACTIVE_ONLY",
+                            false,
+                        ),
+                    },
+                    birthday_reward: BirthdayRewardConfig {
+                        fan_cents: get_env_parse(
+                            "BIRTHDAY_REWARD_FAN_CENTS",
+                            default_birthday_fan_cents(),
+                        ),
+                        sweet_cents: get_env_parse(
+                            "BIRTHDAY_REWARD_SWEET_CENTS",
+                            default_birthday_sweet_cents(),
+                        ),
+                        super_cents: get_env_parse(
+                            "BIRTHDAY_REWARD_SUPER_CENTS",
+                            default_birthday_super_cents(),
+                        ),
+                        issue_code: get_env_parse("BIRTHDAY_REWARD_ISSUE_CODE", false),
+                        code_type: get_env("BIRTHDAY_REWARD_CODE_TYPE")
+                            .and_then(|v| parse_code_type(&v))
+                            .unwrap_or_else(default_birthday_code_type),
+                        expire_months: get_env_parse(
+                            "BIRTHDAY_REWARD_EXPIRE_MONTHS",
+                            default_birthday_expire_months(),
+                        ),
+                    },
+                    monthly_card: MonthlyCardConfig {
+                        daily_coupon_cents: get_env_parse(
+                            "MONTHLY_CARD_DAILY_COUPON_CENTS",
+                            default_monthly_card_daily_coupon_cents(),
+                        ),
+                        subscription_daily_coupon_cents: get_env(
+                            "MONTHLY_CARD_SUBSCRIPTION_DAILY_COUPON_CENTS",
+                        )
+                        .and_then(|v| v.parse().ok()),
+                        daily_coupon_code_type: get_env("MONTHLY_CARD_DAILY_COUPON_CODE_TYPE")
+                            .and_then(|v| parse_code_type(&v))
+                            .unwrap_or_else(default_monthly_card_daily_coupon_code_type),
+                    },
+                    rate_limit: RateLimitConfig {
+                        enabled: get_env_parse("RATE_LIMIT_ENABLED", default_rate_limit_enabled()),
+                        auth_per_minute: get_env_parse(
+                            "RATE_LIMIT_AUTH_PER_MINUTE",
+                            default_auth_per_minute(),
+                        ),
+                        api_per_minute: get_env_parse(
+                            "RATE_LIMIT_API_PER_MINUTE",
+                            default_api_per_minute(),
+                        ),
+                    },
+                    // 定时任务配置统一由下方的环境变量覆盖处理
+                    tasks: TasksConfig::default(),
                 }
             }
             Err(e) => {
@@ -168,6 +712,9 @@ impl Config {
         {
             config.server.port = p;
         }
+        if let Ok(v) = env::var("SERVER_TRUSTED_PROXIES") {
+            config.server.trusted_proxies = parse_list(&v);
+        }
         if let Ok(v) = env::var("DATABASE_URL") {
             config.database.url = v;
         }
@@ -189,6 +736,26 @@ impl Config {
         {
             config.jwt.refresh_token_expires_in = n;
         }
+        if let Ok(v) = env::var("JWT_SWEET_SHAREHOLDER_ACCESS_EXPIRES_IN")
+            && let Ok(n) = v.parse()
+        {
+            config.jwt.sweet_shareholder_access_token_expires_in = Some(n);
+        }
+        if let Ok(v) = env::var("JWT_SWEET_SHAREHOLDER_REFRESH_EXPIRES_IN")
+            && let Ok(n) = v.parse()
+        {
+            config.jwt.sweet_shareholder_refresh_token_expires_in = Some(n);
+        }
+        if let Ok(v) = env::var("JWT_SUPER_SHAREHOLDER_ACCESS_EXPIRES_IN")
+            && let Ok(n) = v.parse()
+        {
+            config.jwt.super_shareholder_access_token_expires_in = Some(n);
+        }
+        if let Ok(v) = env::var("JWT_SUPER_SHAREHOLDER_REFRESH_EXPIRES_IN")
+            && let Ok(n) = v.parse()
+        {
+            config.jwt.super_shareholder_refresh_token_expires_in = Some(n);
+        }
         if let Ok(v) = env::var("TWILIO_ACCOUNT_SID") {
             config.twilio.account_sid = v;
         }
@@ -222,6 +789,19 @@ impl Config {
         if let Ok(v) = env::var("STRIPE_MONTHLY_CARD_SUBSCRIPTION_PRICE_ID") {
             config.stripe.monthly_card_subscription_price_id = Some(v);
         }
+        if let Ok(v) = env::var("STRIPE_MONTHLY_CARD_FALLBACK_AMOUNT_CENTS")
+            && let Ok(n) = v.parse()
+        {
+            config.stripe.monthly_card_fallback_amount_cents = n;
+        }
+        if let Ok(v) = env::var("STRIPE_WEBHOOK_TOLERANCE_SECS")
+            && let Ok(n) = v.parse()
+        {
+            config.stripe.webhook_tolerance_secs = n;
+        }
+        if let Ok(v) = env::var("STRIPE_WEBHOOK_IP_ALLOWLIST") {
+            config.stripe.webhook_ip_allowlist = parse_list(&v);
+        }
         if let Ok(v) = env::var("SEVENCLOUD_USERNAME") {
             config.sevencloud.username = v;
         }
@@ -231,6 +811,11 @@ impl Config {
         if let Ok(v) = env::var("SEVENCLOUD_BASE_URL") {
             config.sevencloud.base_url = v;
         }
+        if let Ok(v) = env::var("SEVENCLOUD_PAGE_SIZE")
+            && let Ok(n) = v.parse()
+        {
+            config.sevencloud.page_size = n;
+        }
 
         // Turnstile
         if let Ok(v) = env::var("TURNSTILE_SECRET_KEY") {
@@ -243,6 +828,215 @@ impl Config {
             config.turnstile.expected_action = Some(v);
         }
 
+        // 订单返利
+        if let Ok(v) = env::var("CASHBACK_SWEET_BPS")
+            && let Ok(n) = v.parse()
+        {
+            config.cashback.sweet_bps = n;
+        }
+        if let Ok(v) = env::var("CASHBACK_SUPER_BPS")
+            && let Ok(n) = v.parse()
+        {
+            config.cashback.super_bps = n;
+        }
+
+        // 充值汇率
+        if let Ok(v) = env::var("RECHARGE_EUR_USD_BPS")
+            && let Ok(n) = v.parse()
+        {
+            config.recharge.eur_usd_bps = n;
+        }
+        if let Ok(v) = env::var("RECHARGE_GBP_USD_BPS")
+            && let Ok(n) = v.parse()
+        {
+            config.recharge.gbp_usd_bps = n;
+        }
+
+        // 抽奖保底
+        if let Ok(v) = env::var("LUCKY_DRAW_PITY_THRESHOLD")
+            && let Ok(n) = v.parse()
+        {
+            config.lucky_draw.pity_threshold = n;
+        }
+        if let Ok(v) = env::var("LUCKY_DRAW_FREE_SPIN_ACTIVE_ONLY")
+            && let Ok(b) = v.parse()
+        {
+            config.lucky_draw.free_spin_active_only = b;
+        }
+        if let Ok(v) = env::var("BIRTHDAY_REWARD_FAN_CENTS")
+            && let Ok(n) = v.parse()
+        {
+            config.birthday_reward.fan_cents = n;
+        }
+        if let Ok(v) = env::var("BIRTHDAY_REWARD_SWEET_CENTS")
+            && let Ok(n) = v.parse()
+        {
+            config.birthday_reward.sweet_cents = n;
+        }
+        if let Ok(v) = env::var("BIRTHDAY_REWARD_SUPER_CENTS")
+            && let Ok(n) = v.parse()
+        {
+            config.birthday_reward.super_cents = n;
+        }
+        if let Ok(v) = env::var("BIRTHDAY_REWARD_ISSUE_CODE")
+            && let Ok(b) = v.parse()
+        {
+            config.birthday_reward.issue_code = b;
+        }
+        if let Ok(v) = env::var("BIRTHDAY_REWARD_CODE_TYPE")
+            && let Some(t) = parse_code_type(&v)
+        {
+            config.birthday_reward.code_type = t;
+        }
+        if let Ok(v) = env::var("BIRTHDAY_REWARD_EXPIRE_MONTHS")
+            && let Ok(n) = v.parse()
+        {
+            config.birthday_reward.expire_months = n;
+        }
+        if let Ok(v) = env::var("MONTHLY_CARD_DAILY_COUPON_CENTS")
+            && let Ok(n) = v.parse()
+        {
+            config.monthly_card.daily_coupon_cents = n;
+        }
+        if let Ok(v) = env::var("MONTHLY_CARD_SUBSCRIPTION_DAILY_COUPON_CENTS")
+            && let Ok(n) = v.parse()
+        {
+            config.monthly_card.subscription_daily_coupon_cents = Some(n);
+        }
+        if let Ok(v) = env::var("MONTHLY_CARD_DAILY_COUPON_CODE_TYPE")
+            && let Some(t) = parse_code_type(&v)
+        {
+            config.monthly_card.daily_coupon_code_type = t;
+        }
+        if let Ok(v) = env::var("RATE_LIMIT_ENABLED")
+            && let Ok(b) = v.parse()
+        {
+            config.rate_limit.enabled = b;
+        }
+        if let Ok(v) = env::var("RATE_LIMIT_AUTH_PER_MINUTE")
+            && let Ok(n) = v.parse()
+        {
+            config.rate_limit.auth_per_minute = n;
+        }
+        if let Ok(v) = env::var("RATE_LIMIT_API_PER_MINUTE")
+            && let Ok(n) = v.parse()
+        {
+            config.rate_limit.api_per_minute = n;
+        }
+        for name in TasksConfig::NAMES {
+            if let Ok(v) = env::var(format!("TASKS_{}_SECS", name.to_uppercase()))
+                && let Ok(n) = v.parse()
+                && let Some(secs) = config.tasks.interval_mut(name)
+            {
+                *secs = n;
+            }
+        }
+        if let Ok(v) = env::var("TASKS_DISABLED") {
+            config.tasks.disabled = parse_list(&v);
+        }
+
         Ok(config)
     }
+
+    /// 启动时校验关键配置，缺失或仍为占位值时返回 `AppError::ConfigError` 并列出全部问题。
+    /// `KKSS_ENV=dev` 时仅记录警告，便于本地开发
+    pub fn validate(&self) -> AppResult<()> {
+        let mut problems = Vec::new();
+        if self.jwt.secret.trim().is_empty()
+            || PLACEHOLDER_JWT_SECRETS.contains(&self.jwt.secret.as_str())
+        {
+            problems.push("jwt.secret (JWT_SECRET) is empty or a placeholder");
+        }
+        let token_lifetimes = [
+            self.jwt.access_token_expires_in,
+            self.jwt.refresh_token_expires_in,
+        ];
+        if token_lifetimes
+            .into_iter()
+            .chain(
+                [
+                    self.jwt.sweet_shareholder_access_token_expires_in,
+                    self.jwt.sweet_shareholder_refresh_token_expires_in,
+                    self.jwt.super_shareholder_access_token_expires_in,
+                    self.jwt.super_shareholder_refresh_token_expires_in,
+                ]
+                .into_iter()
+                .flatten(),
+            )
+            .any(|n| n <= 0)
+        {
+            problems.push("jwt token lifetimes (JWT_*_EXPIRES_IN) must be positive");
+        }
+        if TrustedProxies::parse(&self.server.trusted_proxies).is_err() {
+            problems.push(
+                "server.trusted_proxies (SERVER_TRUSTED_PROXIES) contains an invalid IP or CIDR",
+            );
+        }
+        if self.stripe.secret_key.trim().is_empty() {
+            problems.push("stripe.secret_key (STRIPE_SECRET_KEY) is empty");
+        }
+        if self.stripe.webhook_secret.trim().is_empty() {
+            problems.push("stripe.webhook_secret (STRIPE_WEBHOOK_SECRET) is empty");
+        }
+        if self.stripe.monthly_card_fallback_amount_cents <= 0 {
+            problems.push(
+                "stripe.monthly_card_fallback_amount_cents (STRIPE_MONTHLY_CARD_FALLBACK_AMOUNT_CENTS) must be positive",
+            );
+        }
+        if self.monthly_card.daily_coupon_cents <= 0
+            || self
+                .monthly_card
+                .subscription_daily_coupon_cents
+                .is_some_and(|n| n <= 0)
+        {
+            problems.push(
+                "monthly_card daily coupon cents (MONTHLY_CARD_*DAILY_COUPON_CENTS) must be positive",
+            );
+        }
+        if self.recharge.eur_usd_bps <= 0 || self.recharge.gbp_usd_bps <= 0 {
+            problems.push("recharge exchange rates (RECHARGE_*_USD_BPS) must be positive");
+        }
+        if self.sevencloud.page_size == 0 {
+            problems.push("sevencloud.page_size (SEVENCLOUD_PAGE_SIZE) must be positive");
+        }
+        let tasks = &self.tasks;
+        if [
+            tasks.orders_sync_secs,
+            tasks.membership_expiry_secs,
+            tasks.membership_renewal_secs,
+            tasks.membership_reward_retry_secs,
+            tasks.discount_code_expiry_secs,
+            tasks.discount_code_reconcile_secs,
+            tasks.recharge_refund_reconcile_secs,
+            tasks.free_spin_secs,
+            tasks.birthday_reward_secs,
+            tasks.monthly_card_expiry_secs,
+            tasks.monthly_card_coupon_secs,
+        ]
+        .contains(&0)
+        {
+            problems.push("tasks intervals (TASKS_*_SECS) must be positive");
+        }
+        if tasks
+            .disabled
+            .iter()
+            .any(|name| !TasksConfig::NAMES.contains(&name.as_str()))
+        {
+            problems.push("tasks.disabled (TASKS_DISABLED) contains an unknown task name");
+        }
+        if self.twilio.account_sid.trim().is_empty() {
+            problems.push("twilio.account_sid (TWILIO_ACCOUNT_SID) is empty");
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        if env::var("KKSS_ENV").is_ok_and(|v| v == "dev") {
+            for p in &problems {
+                log::warn!("Config check skipped in dev: {p}");
+            }
+            return Ok(());
+        }
+        Err(AppError::ConfigError(problems.join("; ")))
+    }
 }
@@ -0,0 +1,89 @@
+use crate::entities::expiring_key_entity as keys;
+use crate::error::AppResult;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+
+/// 唯一键的认领结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyClaim {
+    /// 本次认领成功
+    Claimed,
+    /// 键已被认领，到期时间之前不可再次认领
+    Held { expires_at: DateTime<Utc> },
+}
+
+/// 认领一个带有效期的唯一键。以主键原子插入，多实例并发认领同一键时只有一个成功；
+/// 认领前顺带清理已过期的键，过期后同一键可被重新认领
+pub async fn claim_key<C: ConnectionTrait>(
+    db: &C,
+    key: &str,
+    ttl: Duration,
+) -> AppResult<KeyClaim> {
+    let now = Utc::now();
+    keys::Entity::delete_many()
+        .filter(keys::Column::ExpiresAt.lte(now))
+        .exec(db)
+        .await?;
+
+    let inserted = keys::Entity::insert(keys::ActiveModel {
+        key: Set(key.to_string()),
+        expires_at: Set(now + ttl),
+        created_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::column(keys::Column::Key)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    if inserted > 0 {
+        return Ok(KeyClaim::Claimed);
+    }
+
+    let expires_at = keys::Entity::find_by_id(key.to_string())
+        .one(db)
+        .await?
+        .map_or(now, |k| k.expires_at);
+    Ok(KeyClaim::Held { expires_at })
+}
+
+/// 提前释放已认领的键
+pub async fn release_key<C: ConnectionTrait>(db: &C, key: &str) -> AppResult<()> {
+    keys::Entity::delete_by_id(key.to_string()).exec(db).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_db;
+
+    #[tokio::test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    async fn test_claim_key_until_expired_or_released() {
+        let pool = test_db::setup_db().await;
+        let key = format!("test:{}", Utc::now().timestamp_micros());
+
+        assert_eq!(
+            claim_key(&pool, &key, Duration::minutes(1)).await.unwrap(),
+            KeyClaim::Claimed
+        );
+        assert!(matches!(
+            claim_key(&pool, &key, Duration::minutes(1)).await.unwrap(),
+            KeyClaim::Held { expires_at } if expires_at > Utc::now()
+        ));
+
+        release_key(&pool, &key).await.unwrap();
+        assert_eq!(
+            claim_key(&pool, &key, Duration::zero()).await.unwrap(),
+            KeyClaim::Claimed
+        );
+        // 有效期为零的键立即过期，可被再次认领
+        assert_eq!(
+            claim_key(&pool, &key, Duration::minutes(1)).await.unwrap(),
+            KeyClaim::Claimed
+        );
+    }
+}
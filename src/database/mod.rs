@@ -1,4 +1,7 @@
 pub mod connection;
+pub mod expiring_keys;
 pub mod migrations;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_db;
 
 pub use connection::*;
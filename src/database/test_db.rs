@@ -0,0 +1,46 @@
+//! 数据库测试公共工具（库内测试与 tests/ 集成测试共用，集成测试经 `test-util` feature 引入）：
+//! 连接 TEST_DATABASE_URL（缺省回退 DATABASE_URL）指向的 Postgres，首次使用时执行全部迁移。
+//!
+//! TEST_DATABASE_URL=postgres://postgres@localhost:55432/postgres cargo test --workspace -- --ignored
+
+use super::{DbConn, run_migrations};
+use crate::entities::{MemberType, user_entity as users};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, Database, Set};
+use tokio::sync::OnceCell;
+
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+/// 连接测试库；同一测试进程内只执行一次迁移
+pub async fn setup_db() -> DbConn {
+    let url = std::env::var("TEST_DATABASE_URL")
+        .or_else(|_| std::env::var("DATABASE_URL"))
+        .expect("TEST_DATABASE_URL or DATABASE_URL must be set");
+    let pool = Database::connect(url).await.expect("connect test database");
+    MIGRATED
+        .get_or_init(|| async {
+            run_migrations(&pool).await.expect("run migrations");
+        })
+        .await;
+    pool
+}
+
+/// 创建一个唯一的测试用户（Fan，余额为 0）
+pub async fn create_user(pool: &DbConn, prefix: &str) -> users::Model {
+    let suffix = Utc::now().timestamp_micros().to_string();
+    users::ActiveModel {
+        member_code: Set(format!("{prefix}{suffix}")),
+        phone: Set(format!("+1{suffix}")),
+        username: Set(format!("{prefix}_{suffix}")),
+        password_hash: Set(String::new()),
+        birthday: Set(chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+        birthday_month: Set(1),
+        birthday_day: Set(1),
+        member_type: Set(MemberType::Fan),
+        balance: Set(Some(0)),
+        ..Default::default()
+    }
+    .insert(pool)
+    .await
+    .expect("insert test user")
+}
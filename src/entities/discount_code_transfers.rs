@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "discount_code_transfers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub discount_code_id: i64,
+    pub from_user_id: i64,
+    pub to_user_id: i64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
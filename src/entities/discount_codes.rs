@@ -18,6 +18,12 @@ pub enum CodeType {
     SweetsCreditsReward,
     #[sea_orm(string_value = "free_topping")]
     FreeTopping,
+    /// 月卡每日优惠码
+    #[sea_orm(string_value = "monthly_card_daily")]
+    MonthlyCardDaily,
+    /// 生日福利优惠码
+    #[sea_orm(string_value = "birthday_reward")]
+    BirthdayReward,
 }
 
 impl std::fmt::Display for CodeType {
@@ -27,16 +33,36 @@ impl std::fmt::Display for CodeType {
             CodeType::SuperShareholderReward => write!(f, "super_shareholder_reward"),
             CodeType::SweetsCreditsReward => write!(f, "sweets_credits_reward"),
             CodeType::FreeTopping => write!(f, "free_topping"),
+            CodeType::MonthlyCardDaily => write!(f, "monthly_card_daily"),
+            CodeType::BirthdayReward => write!(f, "birthday_reward"),
         }
     }
 }
 
+#[derive(
+    Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema, DeriveActiveEnum, EnumIter,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "discount_code_status"
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountCodeStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "active")]
+    Active,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "discount_codes")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
-    pub user_id: i64,
+    pub user_id: Option<i64>,
     pub code: String,
     pub discount_amount: i64,
     pub code_type: CodeType,
@@ -44,6 +70,9 @@ pub struct Model {
     pub used_at: Option<DateTime<Utc>>,
     pub expires_at: DateTime<Utc>,
     pub external_id: Option<i64>,
+    pub is_expired: bool,
+    pub status: DiscountCodeStatus,
+    pub stamps_spent: Option<i64>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 /// - total_awarded: 累计发放的抽奖次数
 /// - total_used: 已使用的抽奖次数
 /// - 剩余次数 = total_awarded - total_used
+/// - losses_since_win: 连续抽中 "Thank You" 的次数（保底计数）
+/// - last_free_spin_on: 最近一次发放每日免费抽奖的日期
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "lucky_draw_chances")]
 pub struct Model {
@@ -15,6 +17,8 @@ pub struct Model {
     pub user_id: i64,
     pub total_awarded: i64,
     pub total_used: i64,
+    pub losses_since_win: i32,
+    pub last_free_spin_on: Option<NaiveDate>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
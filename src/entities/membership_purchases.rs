@@ -47,6 +47,7 @@ pub struct Model {
     pub amount: i64,
     pub status: MembershipPurchaseStatus,
     pub stripe_status: Option<String>,
+    pub recipient_user_id: Option<i64>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,29 @@
+use super::users::MemberType;
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+/// 会员升级奖励发放记录：purchase_id 唯一，与升级在同一事务内写入；
+/// granted 为 false 的记录由定时任务补发剩余的奖励码
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "membership_reward_grants")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub purchase_id: i64,
+    pub user_id: i64,
+    pub member_type: MemberType,
+    pub codes_total: i32,
+    pub codes_granted: i32,
+    pub granted: bool,
+    /// 正在发放的认领时间，超时未释放视为发放中断
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,34 +1,64 @@
+pub mod audit_log;
 pub mod birthday_rewards;
+pub mod discount_code_transfers;
 pub mod discount_codes;
+pub mod expiring_keys;
+pub mod login_history;
 pub mod lucky_draw_chances;
 pub mod lucky_draw_prizes;
 pub mod lucky_draw_records;
 pub mod membership_purchases;
+pub mod membership_reward_grants;
 pub mod monthly_cards;
+pub mod notifications;
 pub mod orders;
+pub mod payment_confirmations;
+pub mod processed_events;
 pub mod recharge_records;
+pub mod recharge_tiers;
+pub mod referral_rewards;
+pub mod stamp_redemption_tiers;
+pub mod stamp_rules;
 pub mod stripe_transactions;
 pub mod sweet_cash_transactions;
+pub mod sync_failures;
+pub mod sync_runs;
+pub mod sync_state;
 pub mod users;
 
+pub use audit_log as audit_log_entity;
 pub use birthday_rewards as birthday_reward_entity;
+pub use discount_code_transfers as discount_code_transfer_entity;
 pub use discount_codes as discount_code_entity;
+pub use expiring_keys as expiring_key_entity;
+pub use login_history as login_history_entity;
 pub use lucky_draw_chances as lucky_draw_chance_entity;
 pub use lucky_draw_prizes as lucky_draw_prize_entity;
 pub use lucky_draw_records as lucky_draw_record_entity;
 pub use membership_purchases as membership_purchase_entity;
+pub use membership_reward_grants as membership_reward_grant_entity;
 pub use monthly_cards as monthly_card_entity;
+pub use notifications as notification_entity;
 pub use orders as order_entity;
+pub use payment_confirmations as payment_confirmation_entity;
+pub use processed_events as processed_event_entity;
 pub use recharge_records as recharge_record_entity;
+pub use recharge_tiers as recharge_tier_entity;
+pub use referral_rewards as referral_reward_entity;
+pub use stamp_redemption_tiers as stamp_redemption_tier_entity;
+pub use stamp_rules as stamp_rule_entity;
 pub use stripe_transactions as stripe_transaction_entity;
 pub use sweet_cash_transactions as sweet_cash_transaction_entity;
+pub use sync_failures as sync_failure_entity;
+pub use sync_runs as sync_run_entity;
+pub use sync_state as sync_state_entity;
 pub use users as user_entity;
 
 // Re-export enums/types that are shared
-pub use discount_codes::CodeType;
+pub use discount_codes::{CodeType, DiscountCodeStatus};
 pub use membership_purchases::MembershipPurchaseStatus;
 pub use monthly_cards::{MonthlyCardPlanType, MonthlyCardStatus};
 pub use recharge_records::RechargeStatus;
 pub use stripe_transactions::StripeTransactionCategory;
 pub use sweet_cash_transactions::TransactionType;
-pub use users::MemberType;
+pub use users::{MemberType, UserRole};
@@ -72,6 +72,7 @@ pub struct Model {
     pub starts_at: Option<DateTime<Utc>>,
     pub ends_at: Option<DateTime<Utc>>,
     pub last_coupon_granted_on: Option<NaiveDate>,
+    pub cancel_at_period_end: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
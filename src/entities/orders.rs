@@ -14,6 +14,7 @@ pub struct Model {
     pub order_status: i32,
     pub pay_type: Option<i32>,
     pub stamps_earned: Option<i64>,
+    pub spins_earned: Option<i64>,
     pub external_created_at: DateTime<Utc>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
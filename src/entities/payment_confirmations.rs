@@ -0,0 +1,19 @@
+use super::stripe_transactions::StripeTransactionCategory;
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "payment_confirmations")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub payment_intent_id: String,
+    pub user_id: i64,
+    pub category: StripeTransactionCategory,
+    pub response: Json,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -18,6 +18,12 @@ pub enum RechargeStatus {
     Failed,
     #[sea_orm(string_value = "canceled")]
     Canceled,
+    #[sea_orm(string_value = "refunded")]
+    Refunded,
+    #[sea_orm(string_value = "refund_pending")]
+    RefundPending,
+    #[sea_orm(string_value = "partially_refunded")]
+    PartiallyRefunded,
 }
 
 impl std::fmt::Display for RechargeStatus {
@@ -27,6 +33,9 @@ impl std::fmt::Display for RechargeStatus {
             RechargeStatus::Succeeded => write!(f, "succeeded"),
             RechargeStatus::Failed => write!(f, "failed"),
             RechargeStatus::Canceled => write!(f, "canceled"),
+            RechargeStatus::Refunded => write!(f, "refunded"),
+            RechargeStatus::RefundPending => write!(f, "refund_pending"),
+            RechargeStatus::PartiallyRefunded => write!(f, "partially_refunded"),
         }
     }
 }
@@ -41,12 +50,37 @@ pub struct Model {
     pub amount: i64,
     pub bonus_amount: i64,
     pub total_amount: i64,
+    pub currency: String,
+    /// 创建时 1 单位支付货币折合的 USD（基点，USD 为 10000），入账余额按此折算为美分
+    pub exchange_rate_bps: i64,
     pub status: RechargeStatus,
     pub stripe_status: Option<String>,
+    /// 累计已退款金额（美分），不含进行中的退款
+    pub refunded_amount: i64,
+    /// 进行中的退款：Stripe 退款金额（美分）
+    pub pending_refund_amount: Option<i64>,
+    /// 进行中的退款：已扣回的余额（美分）
+    pub pending_refund_debit: Option<i64>,
+    /// 客户端 request_id 派生的幂等 key（含用户、金额与货币），同一 key 只对应一条记录
+    pub request_key: Option<String>,
+    /// 创建时的 Stripe Checkout 地址，重复请求时原样返回
+    pub checkout_url: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+impl Model {
+    /// 按记录保存的汇率把支付货币金额折算为 USD 美分（向下取整）
+    pub fn to_usd_cents(&self, cents: i64) -> i64 {
+        ((cents as i128 * self.exchange_rate_bps as i128) / 10000) as i64
+    }
+
+    /// 入账余额的美分数（含赠送）
+    pub fn credited_amount(&self) -> i64 {
+        self.to_usd_cents(self.total_amount)
+    }
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {}
 
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+/// 推荐奖励发放记录：被推荐人首次购买会员时给推荐人发放奖励券，referee_id 唯一保证只发一次
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "referral_rewards")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub referrer_id: i64,
+    #[sea_orm(unique)]
+    pub referee_id: i64,
+    /// 发放的优惠码ID（发放失败时为空）
+    pub discount_code_id: Option<i64>,
+    pub amount_cents: i64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
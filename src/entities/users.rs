@@ -28,6 +28,27 @@ impl std::fmt::Display for MemberType {
     }
 }
 
+#[derive(
+    Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema, DeriveActiveEnum, EnumIter,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "user_role")]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[sea_orm(string_value = "user")]
+    User,
+    #[sea_orm(string_value = "admin")]
+    Admin,
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserRole::User => write!(f, "user"),
+            UserRole::Admin => write!(f, "admin"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "users")]
 pub struct Model {
@@ -42,10 +63,19 @@ pub struct Model {
     pub birthday_day: i16,
     pub member_type: MemberType,
     pub membership_expires_at: Option<DateTime<Utc>>,
+    pub pending_member_type: Option<MemberType>,
+    pub membership_auto_renew: bool,
+    pub stripe_customer_id: Option<String>,
     pub balance: Option<i64>,
     pub stamps: Option<i64>,
     pub referrer_id: Option<i64>,
     pub referral_code: Option<String>,
+    pub failed_login_attempts: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub notification_prefs: Option<Json>,
+    pub token_version: i32,
+    pub role: UserRole,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
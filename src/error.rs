@@ -1,3 +1,4 @@
+use crate::middlewares::current_request_id;
 use actix_web::{HttpResponse, ResponseError};
 use serde_json::json;
 use thiserror::Error;
@@ -12,6 +13,10 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    /// 字段级校验错误，(字段名, 错误信息)
+    #[error("Field validation error: {0:?}")]
+    FieldValidation(Vec<(String, String)>),
+
     #[error("Auth error: {0}")]
     AuthError(String),
 
@@ -24,9 +29,19 @@ pub enum AppError {
     #[error("Permission denied")]
     PermissionDenied,
 
+    /// 与正在进行的操作冲突（如同步任务已在运行）
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Rate limited, retry after {retry_after}s")]
+    RateLimited { retry_after: u64 },
+
     #[error("External API error: {0}")]
     ExternalApiError(String),
 
+    #[error("Payment client secret missing: {0}")]
+    MissingClientSecret(String),
+
     #[error("Config error: {0}")]
     ConfigError(String),
 
@@ -45,6 +60,23 @@ pub enum AppError {
 
 impl ResponseError for AppError {
     fn error_response(&self) -> HttpResponse {
+        if let AppError::FieldValidation(errors) = self {
+            log::warn!("Field validation error: {errors:?}");
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|(field, message)| json!({ "field": field, "message": message }))
+                .collect();
+            return HttpResponse::UnprocessableEntity().json(json!({
+                "success": false,
+                "error": {
+                    "code": "FIELD_VALIDATION_ERROR",
+                    "message": "Validation failed",
+                    "request_id": current_request_id()
+                },
+                "errors": errors
+            }));
+        }
+
         let (status_code, error_code, message) = match self {
             AppError::ValidationError(msg) => {
                 log::warn!("Validation error: {msg}");
@@ -75,6 +107,18 @@ impl ResponseError for AppError {
                     &"Permission denied".to_string(),
                 )
             }
+            AppError::Conflict(msg) => {
+                log::warn!("Conflict: {msg}");
+                (actix_web::http::StatusCode::CONFLICT, "CONFLICT", msg)
+            }
+            AppError::RateLimited { retry_after } => {
+                log::warn!("Rate limited, retry after {retry_after}s");
+                (
+                    actix_web::http::StatusCode::TOO_MANY_REQUESTS,
+                    "RATE_LIMITED",
+                    &"Too many requests".to_string(),
+                )
+            }
             AppError::ExternalApiError(msg) => {
                 log::error!("External API error: {msg}");
                 (
@@ -83,6 +127,14 @@ impl ResponseError for AppError {
                     msg,
                 )
             }
+            AppError::MissingClientSecret(payment_intent_id) => {
+                log::error!("Stripe returned no client secret for {payment_intent_id}");
+                (
+                    actix_web::http::StatusCode::BAD_GATEWAY,
+                    "PAYMENT_CLIENT_SECRET_MISSING",
+                    &"Payment could not be initialized, please try again".to_string(),
+                )
+            }
             AppError::DatabaseError(err) => {
                 log::error!("Database error: {err}");
                 (
@@ -101,12 +153,40 @@ impl ResponseError for AppError {
             }
         };
 
-        HttpResponse::build(status_code).json(json!({
+        let mut builder = HttpResponse::build(status_code);
+        if let AppError::RateLimited { retry_after } = self {
+            builder.insert_header(("Retry-After", retry_after.to_string()));
+        }
+        builder.json(json!({
             "success": false,
             "error": {
                 "code": error_code,
-                "message": message
+                "message": message,
+                "request_id": current_request_id()
             }
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_field_validation_response() {
+        let err = AppError::FieldValidation(vec![
+            ("phone".to_string(), "bad phone".to_string()),
+            ("password".to_string(), "too short".to_string()),
+        ]);
+        let resp = err.error_response();
+        assert_eq!(
+            resp.status(),
+            actix_web::http::StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["errors"][0]["field"], "phone");
+        assert_eq!(value["errors"][1]["message"], "too short");
+    }
+}
@@ -1,8 +1,20 @@
 use crate::config::SevenCloudConfig;
 use crate::error::{AppError, AppResult};
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 单次请求的最大尝试次数（网络错误指数退避；鉴权失败最多重登一次）
+const MAX_REQUEST_ATTEMPTS: u32 = 3;
+/// 退避基准时长，第 n 次重试等待 base * 2^(n-1)
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// 七云订单状态：已支付
+pub const ORDER_STATUS_PAID: i32 = 1;
 
 fn deserialize_flexible_date<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
 where
@@ -69,7 +81,7 @@ pub struct OrdersData {
     pub pages: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderRecord {
     pub id: i64,
     #[serde(rename = "createDate")]
@@ -95,7 +107,7 @@ pub struct CouponsData {
     pub pages: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CouponRecord {
     pub id: i64,
     #[serde(rename = "adminId")]
@@ -192,11 +204,101 @@ impl SevenCloudAPI {
         Ok(())
     }
 
+    /// 是否持有有效的登录态（token 与 admin_id）
+    pub fn is_logged_in(&self) -> bool {
+        self.token.is_some() && self.admin_id.is_some()
+    }
+
+    /// 尚未登录（无 token）时先登录
+    pub async fn ensure_logged_in(&mut self) -> AppResult<()> {
+        if !self.is_logged_in() {
+            self.login().await?;
+        }
+        Ok(())
+    }
+
+    /// 发送请求并解析响应：网络错误按指数退避重试，业务失败（通常为 token 失效）时重新登录后重试一次。
+    /// 重试耗尽后返回 `AppError::ExternalApiError`；成功时返回响应中的 data。
+    ///
+    /// `idempotent` 为 false 的写请求（如 tPromoCode/add）网络错误时不重发：请求可能已被七云处理，
+    /// 原样返回 `AppError::ReqwestError`，由调用方确认结果后再决定是否重试
+    async fn send_with_retry<T, F>(
+        &mut self,
+        action: &str,
+        idempotent: bool,
+        build: F,
+    ) -> AppResult<Option<T>>
+    where
+        T: DeserializeOwned,
+        F: Fn(&Client, &str) -> RequestBuilder,
+    {
+        let mut relogged = false;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.ensure_logged_in().await?;
+            let token = self.token.clone().unwrap_or_default();
+
+            let result = match build(&self.client, &token).send().await {
+                Ok(resp) => resp.json::<ApiResponse<T>>().await,
+                Err(e) => Err(e),
+            };
+            let result = match result {
+                Ok(r) => r,
+                Err(e) if !idempotent => return Err(e.into()),
+                Err(e) => {
+                    if attempt >= MAX_REQUEST_ATTEMPTS {
+                        return Err(AppError::ExternalApiError(format!(
+                            "Failed to {action} after {attempt} attempts: {e}"
+                        )));
+                    }
+                    let delay = RETRY_BASE_DELAY_MS * 2u64.pow(attempt - 1);
+                    log::warn!(
+                        "Sevencloud request failed when trying to {action}, retry in {delay}ms: {e}"
+                    );
+                    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                    continue;
+                }
+            };
+
+            if !result.success {
+                if !relogged && attempt < MAX_REQUEST_ATTEMPTS {
+                    log::warn!(
+                        "Sevencloud token maybe expired when trying to {action}, relogin and retry...: {}",
+                        result.message
+                    );
+                    relogged = true;
+                    self.login().await?;
+                    continue;
+                }
+                return Err(AppError::ExternalApiError(format!(
+                    "Failed to {action}: {}",
+                    result.message
+                )));
+            }
+
+            return Ok(result.data);
+        }
+    }
+
+    /// 拉取时间范围内已支付（status=1）的订单
     pub async fn get_orders(
         &mut self,
         start_date: &str,
         end_date: &str,
     ) -> AppResult<Vec<OrderRecord>> {
+        self.get_orders_with_status(start_date, end_date, Some(ORDER_STATUS_PAID))
+            .await
+    }
+
+    /// 拉取时间范围内指定状态的订单（如 1 为已支付），`status` 为 None 时不过滤状态
+    pub async fn get_orders_with_status(
+        &mut self,
+        start_date: &str,
+        end_date: &str,
+        status: Option<i32>,
+    ) -> AppResult<Vec<OrderRecord>> {
+        self.ensure_logged_in().await?;
         let url = format!("{}/ORDER-SERVER/tOrder/pageOrder", self.config.base_url);
         let mut all_orders = Vec::new();
         let mut current_page = 1;
@@ -214,47 +316,22 @@ impl SevenCloudAPI {
             params.insert("startDate", start_date.to_string());
             params.insert("endDate", end_date.to_string());
             params.insert("current", current_page.to_string());
-            params.insert("size", "1000".to_string());
-            params.insert("status", "1".to_string());
+            params.insert("size", self.config.page_size.to_string());
+            params.insert("status", status.map(|s| s.to_string()).unwrap_or_default());
             params.insert("companyType", "".to_string());
             params.insert("machineType", "".to_string());
             params.insert("ifForeign", "".to_string());
             params.insert("chartType", "day".to_string());
 
-            // 最多尝试 2 次 (第一次失败且判定为 token 失效时自动重登重试)
-            let mut attempt = 0;
-            let page_data = loop {
-                attempt += 1;
-                let response = self
-                    .client
-                    .get(&url)
-                    .query(&params)
-                    .header("Authorization", self.token.as_ref().unwrap())
-                    .send()
-                    .await?;
-
-                let result: ApiResponse<OrdersData> = response.json().await?;
-
-                if !result.success {
-                    if attempt == 1 {
-                        log::warn!(
-                            "Sevencloud token maybe expired when fetching orders, relogin and retry...: {}",
-                            result.message
-                        );
-                        self.login().await?; // 重新登录并重试
-                        continue;
-                    }
-                    return Err(AppError::ExternalApiError(format!(
-                        "Failed to retrieve orders: {}",
-                        result.message
-                    )));
-                }
-
-                let data = result.data.ok_or_else(|| {
-                    AppError::ExternalApiError("Orders data is empty".to_string())
-                })?;
-                break data;
-            };
+            let page_data: OrdersData = self
+                .send_with_retry("retrieve orders", true, |client, token| {
+                    client
+                        .get(&url)
+                        .query(&params)
+                        .header("Authorization", token)
+                })
+                .await?
+                .ok_or_else(|| AppError::ExternalApiError("Orders data is empty".to_string()))?;
 
             all_orders.extend(page_data.records);
 
@@ -272,6 +349,7 @@ impl SevenCloudAPI {
         &mut self,
         is_use: Option<bool>,
     ) -> AppResult<Vec<CouponRecord>> {
+        self.ensure_logged_in().await?;
         let url = format!("{}/SZWL-SERVER/tPromoCode/list", self.config.base_url);
         let mut all_coupons = Vec::new();
         let mut current_page = 1;
@@ -288,37 +366,14 @@ impl SevenCloudAPI {
                     serde_json::Value::String(if is_use { "1" } else { "0" }.to_string());
             }
 
-            let mut attempt = 0;
-            let page_data = loop {
-                attempt += 1;
-                let response = self
-                    .client
-                    .post(&url)
-                    .json(&data)
-                    .header("Authorization", self.token.as_ref().unwrap())
-                    .send()
-                    .await?;
-
-                let result: ApiResponse<CouponsData> = response.json().await?;
-                if !result.success {
-                    if attempt == 1 {
-                        log::warn!(
-                            "Sevencloud token maybe expired when fetching discount codes, relogin and retry...: {}",
-                            result.message
-                        );
-                        self.login().await?;
-                        continue;
-                    }
-                    return Err(AppError::ExternalApiError(format!(
-                        "Failed to retrieve discount codes: {}",
-                        result.message
-                    )));
-                }
-                let data = result.data.ok_or_else(|| {
+            let page_data: CouponsData = self
+                .send_with_retry("retrieve discount codes", true, |client, token| {
+                    client.post(&url).json(&data).header("Authorization", token)
+                })
+                .await?
+                .ok_or_else(|| {
                     AppError::ExternalApiError("Discount codes data is empty".to_string())
                 })?;
-                break data;
-            };
 
             all_coupons.extend(page_data.records);
             if current_page >= page_data.pages {
@@ -330,6 +385,18 @@ impl SevenCloudAPI {
         Ok(all_coupons)
     }
 
+    /// 七云中是否已存在该优惠码
+    async fn discount_code_exists(&mut self, code: &str) -> AppResult<bool> {
+        let Ok(code) = code.parse::<i64>() else {
+            return Ok(false);
+        };
+        Ok(self
+            .get_discount_codes(None)
+            .await?
+            .iter()
+            .any(|c| c.code == code))
+    }
+
     /// 生成优惠码
     ///
     /// # 参数
@@ -339,6 +406,7 @@ impl SevenCloudAPI {
     ///
     /// # 返回值
     /// 返回一个布尔值，表示优惠码是否生成成功。
+    /// 添加接口不幂等，网络错误时先确认码是否已存在再重发，不会重复添加。
     pub async fn generate_discount_code(
         &mut self,
         code: &str,
@@ -363,6 +431,7 @@ impl SevenCloudAPI {
             ));
         }
 
+        self.ensure_logged_in().await?;
         let url = format!("{}/SZWL-SERVER/tPromoCode/add", self.config.base_url);
 
         let mut params = HashMap::new();
@@ -376,32 +445,37 @@ impl SevenCloudAPI {
         params.insert("adminId", self.admin_id.unwrap().to_string());
 
         let mut attempt = 0;
-        let _result = loop {
+        loop {
             attempt += 1;
-            let response = self
-                .client
-                .get(&url)
-                .query(&params)
-                .header("Authorization", self.token.as_ref().unwrap())
-                .send()
-                .await?;
-            let result: ApiResponse<String> = response.json().await?;
-            if !result.success {
-                if attempt == 1 {
+            let sent = self
+                .send_with_retry::<String, _>("generate discount code", false, |client, token| {
+                    client
+                        .get(&url)
+                        .query(&params)
+                        .header("Authorization", token)
+                })
+                .await;
+            match sent {
+                Ok(_) => break,
+                Err(AppError::ReqwestError(e)) => {
+                    // 添加请求不幂等：结果未知时先确认码是否已存在，存在视为成功，不存在才重发
+                    let delay = RETRY_BASE_DELAY_MS * 2u64.pow(attempt - 1);
                     log::warn!(
-                        "Sevencloud token maybe expired when generating discount code, relogin and retry...: {}",
-                        result.message
+                        "Sevencloud request failed when trying to generate discount code {code}, verify in {delay}ms: {e}"
                     );
-                    self.login().await?;
-                    continue;
+                    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                    if self.discount_code_exists(code).await? {
+                        break;
+                    }
+                    if attempt >= MAX_REQUEST_ATTEMPTS {
+                        return Err(AppError::ExternalApiError(format!(
+                            "Failed to generate discount code after {attempt} attempts: {e}"
+                        )));
+                    }
                 }
-                return Err(AppError::ExternalApiError(format!(
-                    "Failed to generate discount code: {}",
-                    result.message
-                )));
+                Err(e) => return Err(e),
             }
-            break result;
-        };
+        }
 
         log::info!(
             "Successfully generated discount code: {code}, Amount: {discount}, Expiration: {expire_months} months"
@@ -410,3 +484,215 @@ impl SevenCloudAPI {
         Ok(true)
     }
 }
+
+/// POS 后端抽象：优惠码与订单同步依赖该 trait，便于测试注入假实现或切换其他 POS 厂商
+#[async_trait]
+pub trait PosBackend: Send + Sync {
+    async fn login(&mut self) -> AppResult<()>;
+
+    /// 是否持有有效登录态（就绪探针使用）
+    fn is_logged_in(&self) -> bool;
+
+    /// 拉取时间范围内指定状态的订单，`status` 为 None 时返回全部状态
+    async fn get_orders_with_status(
+        &mut self,
+        start_date: &str,
+        end_date: &str,
+        status: Option<i32>,
+    ) -> AppResult<Vec<OrderRecord>>;
+
+    /// 拉取时间范围内已支付（status=1）的订单
+    async fn get_orders(
+        &mut self,
+        start_date: &str,
+        end_date: &str,
+    ) -> AppResult<Vec<OrderRecord>> {
+        self.get_orders_with_status(start_date, end_date, Some(ORDER_STATUS_PAID))
+            .await
+    }
+
+    async fn get_discount_codes(&mut self, is_use: Option<bool>) -> AppResult<Vec<CouponRecord>>;
+
+    /// 生成固定金额优惠码，`discount` 单位为美元
+    async fn generate_discount_code(
+        &mut self,
+        code: &str,
+        discount: f64,
+        expire_months: u32,
+    ) -> AppResult<bool>;
+}
+
+/// 服务间共享的 POS 后端
+pub type SharedPosBackend = Arc<Mutex<dyn PosBackend>>;
+
+#[async_trait]
+impl PosBackend for SevenCloudAPI {
+    async fn login(&mut self) -> AppResult<()> {
+        SevenCloudAPI::login(self).await
+    }
+
+    fn is_logged_in(&self) -> bool {
+        SevenCloudAPI::is_logged_in(self)
+    }
+
+    async fn get_orders_with_status(
+        &mut self,
+        start_date: &str,
+        end_date: &str,
+        status: Option<i32>,
+    ) -> AppResult<Vec<OrderRecord>> {
+        SevenCloudAPI::get_orders_with_status(self, start_date, end_date, status).await
+    }
+
+    async fn get_discount_codes(&mut self, is_use: Option<bool>) -> AppResult<Vec<CouponRecord>> {
+        SevenCloudAPI::get_discount_codes(self, is_use).await
+    }
+
+    async fn generate_discount_code(
+        &mut self,
+        code: &str,
+        discount: f64,
+        expire_months: u32,
+    ) -> AppResult<bool> {
+        SevenCloudAPI::generate_discount_code(self, code, discount, expire_months).await
+    }
+}
+
+/// 内存中的 POS 后端，供测试使用：返回预置的订单与优惠码，并记录生成过的优惠码
+#[derive(Debug, Default)]
+pub struct MockPosBackend {
+    pub orders: Vec<OrderRecord>,
+    pub coupons: Vec<CouponRecord>,
+    /// 已生成的优惠码
+    pub generated: Vec<String>,
+    /// 为 true 时生成优惠码返回错误，模拟 POS 侧失败
+    pub fail_generate: bool,
+}
+
+impl MockPosBackend {
+    fn generate(&mut self, code: &str) -> AppResult<bool> {
+        if self.fail_generate {
+            return Err(AppError::ExternalApiError(
+                "Mock POS backend rejected discount code".to_string(),
+            ));
+        }
+        self.generated.push(code.to_string());
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl PosBackend for MockPosBackend {
+    async fn login(&mut self) -> AppResult<()> {
+        Ok(())
+    }
+
+    fn is_logged_in(&self) -> bool {
+        true
+    }
+
+    async fn get_orders_with_status(
+        &mut self,
+        _start_date: &str,
+        _end_date: &str,
+        status: Option<i32>,
+    ) -> AppResult<Vec<OrderRecord>> {
+        Ok(self
+            .orders
+            .iter()
+            .filter(|o| status.is_none_or(|s| o.status == s))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_discount_codes(&mut self, is_use: Option<bool>) -> AppResult<Vec<CouponRecord>> {
+        let wanted = is_use.map(|u| if u { "1" } else { "0" });
+        Ok(self
+            .coupons
+            .iter()
+            .filter(|c| wanted.is_none_or(|w| c.is_use == w))
+            .cloned()
+            .collect())
+    }
+
+    async fn generate_discount_code(
+        &mut self,
+        code: &str,
+        _discount: f64,
+        _expire_months: u32,
+    ) -> AppResult<bool> {
+        self.generate(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 最简七云服务：第一次添加请求处理后直接断开连接（客户端拿不到响应）
+    async fn flaky_sevencloud(adds: Arc<AtomicU32>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let data = if path.starts_with("/SZWL-SERVER/tAdmin/loginSys") {
+                    serde_json::json!({"id": 1, "name": "admin", "currentToken": "token"})
+                } else if path.starts_with("/SZWL-SERVER/tPromoCode/add") {
+                    if adds.fetch_add(1, Ordering::SeqCst) == 0 {
+                        continue;
+                    }
+                    serde_json::Value::Null
+                } else {
+                    let records = if adds.load(Ordering::SeqCst) > 0 {
+                        serde_json::json!([{
+                            "id": 1,
+                            "createDate": 0,
+                            "code": 123456,
+                            "isUse": "0",
+                            "discount": 5.0,
+                        }])
+                    } else {
+                        serde_json::json!([])
+                    };
+                    serde_json::json!({
+                        "records": records, "total": 1, "size": 1000, "current": 1, "pages": 1
+                    })
+                };
+                let body = serde_json::json!({
+                    "code": "200", "message": "ok", "data": data, "success": true
+                })
+                .to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn test_generate_discount_code_does_not_resend_applied_add() {
+        let adds = Arc::new(AtomicU32::new(0));
+        let base_url = flaky_sevencloud(adds.clone()).await;
+        let mut api = SevenCloudAPI::new(SevenCloudConfig {
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+            base_url,
+            page_size: 1000,
+        });
+
+        assert!(api.generate_discount_code("123456", 5.0, 1).await.unwrap());
+        // 第一次添加已生效，确认码存在后不再重发
+        assert_eq!(adds.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,13 +1,20 @@
 use crate::config::StripeConfig;
+use crate::entities::user_entity as users;
 use crate::error::{AppError, AppResult};
+use async_trait::async_trait;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::str::FromStr;
 use stripe::{
     CheckoutSession, CheckoutSessionMode, Client, CreateCheckoutSession,
     CreateCheckoutSessionLineItems, CreateCheckoutSessionLineItemsPriceData,
     CreateCheckoutSessionLineItemsPriceDataProductData, CreateCheckoutSessionPaymentIntentData,
-    CreatePaymentIntent, CreatePaymentIntentAutomaticPaymentMethods, Currency, Event, Expandable,
-    PaymentIntent, PaymentIntentId, Price as StripePrice, PriceId,
+    CreateCustomer, CreatePaymentIntent, CreatePaymentIntentAutomaticPaymentMethods, CreateRefund,
+    Currency, Customer, CustomerId, Event, Expandable, ListPaymentMethods, ListRefunds,
+    PaymentIntent, PaymentIntentId, PaymentIntentOffSession, PaymentMethod,
+    PaymentMethodTypeFilter, Price as StripePrice, PriceId, Refund, RequestStrategy,
 };
 
 /// Stripe服务，用于处理支付意图和webhook验证
@@ -97,7 +104,7 @@ impl StripeService {
         let client_ref = user_id.to_string();
         create.client_reference_id = Some(&client_ref);
         create.payment_intent_data = Some(CreateCheckoutSessionPaymentIntentData {
-            description: description,
+            description,
             metadata: Some(meta),
             ..Default::default()
         });
@@ -135,7 +142,9 @@ impl StripeService {
         })
     }
 
-    /// 创建 Stripe Checkout Session（基于自定义金额，底层仍走 PaymentIntent）并返回 URL
+    /// 创建 Stripe Checkout Session（基于自定义金额，底层仍走 PaymentIntent）并返回 URL；
+    /// `idempotency_key` 不为空时相同 key 的重试只会得到同一个 Session
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_checkout_session_for_amount(
         &self,
         amount: i64,
@@ -144,6 +153,7 @@ impl StripeService {
         category: &str,
         description: Option<String>,
         extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
     ) -> AppResult<CheckoutInit> {
         // 金额校验
         if amount < 50 {
@@ -208,7 +218,14 @@ impl StripeService {
             metadata: Some(meta),
             ..Default::default()
         });
-        let session = CheckoutSession::create(&self.client, create)
+        let client = match idempotency_key {
+            Some(key) => self
+                .client
+                .clone()
+                .with_strategy(RequestStrategy::Idempotent(key)),
+            None => self.client.clone(),
+        };
+        let session = CheckoutSession::create(&client, create)
             .await
             .map_err(|e| {
                 AppError::ExternalApiError(format!("Failed to create checkout session: {e}"))
@@ -270,6 +287,8 @@ impl StripeService {
             currency,
             description,
             None,
+            None,
+            None,
         )
         .await
     }
@@ -298,7 +317,70 @@ impl StripeService {
         )
     }
 
+    /// 未配置月卡 price id 时使用的金额（美分）
+    pub fn monthly_card_fallback_amount_cents(&self) -> i64 {
+        self.config.monthly_card_fallback_amount_cents
+    }
+
+    /// 获取用户的 Stripe Customer ID，不存在时创建并写回 `users.stripe_customer_id`
+    ///
+    /// 仅在用户首次发起支付时调用（懒创建），从未付款的用户不会产生 Customer。
+    /// 并发创建时以先写入数据库的 ID 为准。
+    pub async fn get_or_create_customer(
+        &self,
+        db: &DatabaseConnection,
+        user_id: i64,
+    ) -> AppResult<String> {
+        let user = users::Entity::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        if let Some(id) = user.stripe_customer_id {
+            return Ok(id);
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("user_id".to_string(), user_id.to_string());
+        let mut params = CreateCustomer::new();
+        params.phone = Some(&user.phone);
+        params.name = Some(&user.username);
+        params.metadata = Some(metadata);
+        let client = self
+            .client
+            .clone()
+            .with_strategy(RequestStrategy::Idempotent(format!("customer-{user_id}")));
+        let customer = Customer::create(&client, params)
+            .await
+            .map_err(|e| AppError::ExternalApiError(format!("Failed to create customer: {e}")))?;
+        let customer_id = customer.id.to_string();
+
+        let res = users::Entity::update_many()
+            .col_expr(
+                users::Column::StripeCustomerId,
+                Expr::value(customer_id.clone()),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .filter(users::Column::StripeCustomerId.is_null())
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            // 其它请求已写入，使用已保存的 ID
+            if let Some(id) = users::Entity::find_by_id(user_id)
+                .one(db)
+                .await?
+                .and_then(|u| u.stripe_customer_id)
+            {
+                return Ok(id);
+            }
+        }
+        Ok(customer_id)
+    }
+
     /// 创建带有业务类别与自定义 metadata 的支付意图
+    ///
+    /// `idempotency_key` 不为空时作为 Stripe Idempotency-Key 发送，相同 key 的重试只会得到同一个 PaymentIntent；
+    /// `customer_id` 不为空时关联到该 Stripe Customer，以便保存支付方式用于后续自动续费
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_payment_intent_with_category(
         &self,
         amount: i64,
@@ -307,6 +389,8 @@ impl StripeService {
         currency: Option<String>,
         description: Option<String>,
         extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+        customer_id: Option<String>,
     ) -> AppResult<PaymentIntent> {
         // 验证最小金额 (50美分 = $0.50)
         if amount < 50 {
@@ -345,6 +429,11 @@ impl StripeService {
         let mut create_payment_intent = CreatePaymentIntent::new(amount, currency);
         create_payment_intent.description = Some(&description);
         create_payment_intent.metadata = Some(metadata);
+        if let Some(customer_id) = customer_id {
+            let customer_id = CustomerId::from_str(&customer_id)
+                .map_err(|e| AppError::ValidationError(format!("Invalid customer ID: {e}")))?;
+            create_payment_intent.customer = Some(customer_id);
+        }
 
         // 启用自动支付方式
         create_payment_intent.automatic_payment_methods =
@@ -353,8 +442,15 @@ impl StripeService {
                 allow_redirects: None,
             });
 
-        // 发送请求
-        let payment_intent = PaymentIntent::create(&self.client, create_payment_intent)
+        // 发送请求（带幂等 key 时使用独立的请求策略）
+        let client = match idempotency_key {
+            Some(key) => self
+                .client
+                .clone()
+                .with_strategy(RequestStrategy::Idempotent(key)),
+            None => self.client.clone(),
+        };
+        let payment_intent = PaymentIntent::create(&client, create_payment_intent)
             .await
             .map_err(|e| {
                 AppError::ExternalApiError(format!("Failed to create payment intent: {e}"))
@@ -363,6 +459,73 @@ impl StripeService {
         Ok(payment_intent)
     }
 
+    /// 使用客户已保存的支付方式发起离线（off-session）扣款，用于自动续费
+    ///
+    /// 优先使用 Customer 的默认支付方式，没有则取其第一张已保存的卡。
+    /// `idempotency_key` 保证同一续费周期重复执行不会重复扣款。
+    pub async fn create_offsession_payment_intent(
+        &self,
+        customer_id: &str,
+        amount: i64,
+        metadata: HashMap<String, String>,
+        description: String,
+        idempotency_key: String,
+    ) -> AppResult<PaymentIntent> {
+        let customer_id = CustomerId::from_str(customer_id)
+            .map_err(|e| AppError::ValidationError(format!("Invalid customer ID: {e}")))?;
+
+        let customer = Customer::retrieve(&self.client, &customer_id, &[])
+            .await
+            .map_err(|e| AppError::ExternalApiError(format!("Failed to retrieve customer: {e}")))?;
+        let default_pm = customer
+            .invoice_settings
+            .and_then(|s| s.default_payment_method)
+            .map(|pm| pm.id());
+        let payment_method = match default_pm {
+            Some(id) => id,
+            None => {
+                let mut params = ListPaymentMethods::new();
+                params.customer = Some(customer_id.clone());
+                params.type_ = Some(PaymentMethodTypeFilter::Card);
+                params.limit = Some(1);
+                let list = PaymentMethod::list(&self.client, &params)
+                    .await
+                    .map_err(|e| {
+                        AppError::ExternalApiError(format!("Failed to list payment methods: {e}"))
+                    })?;
+                list.data
+                    .into_iter()
+                    .next()
+                    .map(|pm| pm.id)
+                    .ok_or_else(|| {
+                        AppError::ValidationError(
+                            "No saved payment method for customer".to_string(),
+                        )
+                    })?
+            }
+        };
+
+        let mut create_payment_intent = CreatePaymentIntent::new(amount, Currency::USD);
+        create_payment_intent.customer = Some(customer_id);
+        create_payment_intent.payment_method = Some(payment_method);
+        create_payment_intent.off_session = Some(PaymentIntentOffSession::exists(true));
+        create_payment_intent.confirm = Some(true);
+        create_payment_intent.description = Some(&description);
+        create_payment_intent.metadata = Some(metadata);
+
+        let client = self
+            .client
+            .clone()
+            .with_strategy(RequestStrategy::Idempotent(idempotency_key));
+        let payment_intent = PaymentIntent::create(&client, create_payment_intent)
+            .await
+            .map_err(|e| {
+                AppError::ExternalApiError(format!("Failed to create off-session payment: {e}"))
+            })?;
+
+        Ok(payment_intent)
+    }
+
     /// 检索已存在的支付意图
     ///
     /// # 参数
@@ -388,13 +551,78 @@ impl StripeService {
         Ok(payment_intent)
     }
 
+    /// 对某个支付意图发起退款
+    ///
+    /// # 参数
+    ///
+    /// * `payment_intent_id` - Stripe支付意图ID
+    /// * `amount_cents` - 退款金额（美分），None 表示全额退款
+    ///
+    /// # 返回
+    ///
+    /// 返回Stripe退款ID
+    pub async fn create_refund(
+        &self,
+        payment_intent_id: &str,
+        amount_cents: Option<i64>,
+    ) -> AppResult<String> {
+        let payment_intent_id = PaymentIntentId::from_str(payment_intent_id)
+            .map_err(|e| AppError::ValidationError(format!("Invalid payment intent ID: {e}")))?;
+
+        let mut create_refund = CreateRefund::new();
+        create_refund.payment_intent = Some(payment_intent_id);
+        create_refund.amount = amount_cents;
+
+        let refund = Refund::create(&self.client, create_refund)
+            .await
+            .map_err(|e| AppError::ExternalApiError(format!("Failed to create refund: {e}")))?;
+
+        Ok(refund.id.to_string())
+    }
+
+    /// 查询某个支付意图在 Stripe 上已生效（含处理中）的累计退款金额（美分），
+    /// 失败与已取消的退款不计入
+    pub async fn refunded_amount(&self, payment_intent_id: &str) -> AppResult<i64> {
+        let payment_intent_id = PaymentIntentId::from_str(payment_intent_id)
+            .map_err(|e| AppError::ValidationError(format!("Invalid payment intent ID: {e}")))?;
+
+        let mut params = ListRefunds::new();
+        params.payment_intent = Some(payment_intent_id);
+        params.limit = Some(100);
+        let refunds = Refund::list(&self.client, &params)
+            .await
+            .map_err(|e| AppError::ExternalApiError(format!("Failed to list refunds: {e}")))?;
+
+        Ok(refunds
+            .data
+            .iter()
+            .filter(|r| !matches!(r.status.as_deref(), Some("failed" | "canceled")))
+            .map(|r| r.amount)
+            .sum())
+    }
+
+    /// Webhook 来源 IP 是否允许；白名单为空时不校验。
+    /// `ip` 应为 `client_ip` 的结果（非受信任代理来源时即连接对端地址），按解析后的地址比较
+    pub fn is_webhook_ip_allowed(&self, ip: Option<&str>) -> bool {
+        let allowlist = &self.config.webhook_ip_allowlist;
+        if allowlist.is_empty() {
+            return true;
+        }
+        let Some(ip) = ip.and_then(|ip| ip.trim().parse::<IpAddr>().ok()) else {
+            return false;
+        };
+        allowlist
+            .iter()
+            .any(|a| a.trim().parse::<IpAddr>().is_ok_and(|a| a == ip))
+    }
+
     /// 验证Stripe Webhook签名
     ///
     /// # 参数
     ///
     /// * `payload` - webhook请求体
     /// * `signature` - Stripe-Signature头
-    /// * `timestamp` - 时间戳（当前未使用）
+    /// * `now` - 当前 Unix 时间戳（秒），用于校验签名时间是否在容忍窗口内
     ///
     /// # 返回
     ///
@@ -403,23 +631,57 @@ impl StripeService {
         &self,
         payload: &str,
         signature: &str,
-        _timestamp: i64,
+        now: i64,
     ) -> AppResult<Event> {
         // 验证webhook签名头是否存在
         if signature.is_empty() {
             return Err(AppError::AuthError("Invalid webhook signature".to_string()));
         }
 
-        // 使用async-stripe的webhook验证
-        let event =
-            stripe::Webhook::construct_event(payload, signature, &self.config.webhook_secret)
-                .map_err(|e| {
-                    AppError::AuthError(format!("Webhook signature verification failed: {e}"))
-                })?;
+        // 解析签名头中的 t= 时间戳，并按配置的容忍窗口拒绝过期/超前事件
+        let signed_at = Self::parse_signature_timestamp(signature)?;
+        if (now - signed_at).abs() > self.config.webhook_tolerance_secs {
+            return Err(AppError::AuthError(
+                "Webhook timestamp outside the tolerance window".to_string(),
+            ));
+        }
+
+        // 使用async-stripe的webhook验证（时间窗口已在上面校验，这里以签名时间为基准只校验 HMAC）
+        let event = stripe::Webhook::construct_event_with_timestamp(
+            payload,
+            signature,
+            &self.config.webhook_secret,
+            signed_at,
+        )
+        .map_err(|e| AppError::AuthError(format!("Webhook signature verification failed: {e}")))?;
 
         Ok(event)
     }
 
+    /// 从 Stripe-Signature 头（形如 `t=1492774577,v1=...`）中取出时间戳
+    fn parse_signature_timestamp(signature: &str) -> AppResult<i64> {
+        signature
+            .split(',')
+            .filter_map(|part| part.trim().split_once('='))
+            .find(|(k, _)| *k == "t")
+            .and_then(|(_, v)| v.parse::<i64>().ok())
+            .ok_or_else(|| AppError::AuthError("Invalid webhook signature".to_string()))
+    }
+
+    /// 取出可用的 client_secret（优先 Checkout Session，其次 PaymentIntent）
+    ///
+    /// 两者都缺失或为空时返回 `MissingClientSecret`，避免把空字符串返回给客户端
+    pub fn require_client_secret(
+        payment_intent_id: &str,
+        primary: Option<String>,
+        fallback: Option<String>,
+    ) -> AppResult<String> {
+        primary
+            .filter(|s| !s.is_empty())
+            .or_else(|| fallback.filter(|s| !s.is_empty()))
+            .ok_or_else(|| AppError::MissingClientSecret(payment_intent_id.to_string()))
+    }
+
     /// 将美元金额转换为美分
     ///
     /// # 示例
@@ -491,6 +753,190 @@ impl StripeService {
     }
 }
 
+/// 支付网关抽象：业务服务通过该 trait 调用 Stripe，测试中可注入假实现
+///
+/// 方法签名与 `StripeService` 上的同名方法一致，`StripeService` 的公开 API 不受影响。
+#[async_trait]
+pub trait StripeGateway: Send + Sync {
+    #[allow(clippy::too_many_arguments)]
+    async fn create_payment_intent_with_category(
+        &self,
+        amount: i64,
+        user_id: i64,
+        category: &str,
+        currency: Option<String>,
+        description: Option<String>,
+        extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+        customer_id: Option<String>,
+    ) -> AppResult<PaymentIntent>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_checkout_session_for_amount(
+        &self,
+        amount: i64,
+        currency: Option<String>,
+        user_id: i64,
+        category: &str,
+        description: Option<String>,
+        extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+    ) -> AppResult<CheckoutInit>;
+
+    async fn create_offsession_payment_intent(
+        &self,
+        customer_id: &str,
+        amount: i64,
+        metadata: HashMap<String, String>,
+        description: String,
+        idempotency_key: String,
+    ) -> AppResult<PaymentIntent>;
+
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> AppResult<PaymentIntent>;
+
+    async fn create_refund(
+        &self,
+        payment_intent_id: &str,
+        amount_cents: Option<i64>,
+    ) -> AppResult<String>;
+
+    async fn refunded_amount(&self, payment_intent_id: &str) -> AppResult<i64>;
+
+    async fn get_price_unit_amount(&self, price_id: &str) -> AppResult<i64>;
+
+    async fn get_or_create_customer(
+        &self,
+        db: &DatabaseConnection,
+        user_id: i64,
+    ) -> AppResult<String>;
+
+    fn monthly_card_ids(&self) -> (Option<String>, Option<String>, Option<String>);
+
+    fn monthly_card_fallback_amount_cents(&self) -> i64;
+
+    fn verify_webhook_signature(
+        &self,
+        payload: &str,
+        signature: &str,
+        now: i64,
+    ) -> AppResult<Event>;
+}
+
+#[async_trait]
+impl StripeGateway for StripeService {
+    async fn create_payment_intent_with_category(
+        &self,
+        amount: i64,
+        user_id: i64,
+        category: &str,
+        currency: Option<String>,
+        description: Option<String>,
+        extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+        customer_id: Option<String>,
+    ) -> AppResult<PaymentIntent> {
+        StripeService::create_payment_intent_with_category(
+            self,
+            amount,
+            user_id,
+            category,
+            currency,
+            description,
+            extra_metadata,
+            idempotency_key,
+            customer_id,
+        )
+        .await
+    }
+
+    async fn create_checkout_session_for_amount(
+        &self,
+        amount: i64,
+        currency: Option<String>,
+        user_id: i64,
+        category: &str,
+        description: Option<String>,
+        extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+    ) -> AppResult<CheckoutInit> {
+        StripeService::create_checkout_session_for_amount(
+            self,
+            amount,
+            currency,
+            user_id,
+            category,
+            description,
+            extra_metadata,
+            idempotency_key,
+        )
+        .await
+    }
+
+    async fn create_offsession_payment_intent(
+        &self,
+        customer_id: &str,
+        amount: i64,
+        metadata: HashMap<String, String>,
+        description: String,
+        idempotency_key: String,
+    ) -> AppResult<PaymentIntent> {
+        StripeService::create_offsession_payment_intent(
+            self,
+            customer_id,
+            amount,
+            metadata,
+            description,
+            idempotency_key,
+        )
+        .await
+    }
+
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> AppResult<PaymentIntent> {
+        StripeService::retrieve_payment_intent(self, payment_intent_id).await
+    }
+
+    async fn create_refund(
+        &self,
+        payment_intent_id: &str,
+        amount_cents: Option<i64>,
+    ) -> AppResult<String> {
+        StripeService::create_refund(self, payment_intent_id, amount_cents).await
+    }
+
+    async fn refunded_amount(&self, payment_intent_id: &str) -> AppResult<i64> {
+        StripeService::refunded_amount(self, payment_intent_id).await
+    }
+
+    async fn get_price_unit_amount(&self, price_id: &str) -> AppResult<i64> {
+        StripeService::get_price_unit_amount(self, price_id).await
+    }
+
+    async fn get_or_create_customer(
+        &self,
+        db: &DatabaseConnection,
+        user_id: i64,
+    ) -> AppResult<String> {
+        StripeService::get_or_create_customer(self, db, user_id).await
+    }
+
+    fn monthly_card_ids(&self) -> (Option<String>, Option<String>, Option<String>) {
+        StripeService::monthly_card_ids(self)
+    }
+
+    fn monthly_card_fallback_amount_cents(&self) -> i64 {
+        StripeService::monthly_card_fallback_amount_cents(self)
+    }
+
+    fn verify_webhook_signature(
+        &self,
+        payload: &str,
+        signature: &str,
+        now: i64,
+    ) -> AppResult<Event> {
+        StripeService::verify_webhook_signature(self, payload, signature, now)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -528,4 +974,91 @@ mod tests {
         assert!(StripeService::validate_amount(50, "jpy").is_ok());
         assert!(StripeService::validate_amount(49, "jpy").is_err());
     }
+
+    fn test_service(tolerance: i64) -> StripeService {
+        StripeService::new(StripeConfig {
+            secret_key: "sk_test_dummy".to_string(),
+            webhook_secret: "whsec_test".to_string(),
+            checkout_success_url: None,
+            checkout_cancel_url: None,
+            monthly_card_product_id: None,
+            monthly_card_one_time_price_id: None,
+            monthly_card_subscription_price_id: None,
+            monthly_card_fallback_amount_cents: 550,
+            webhook_tolerance_secs: tolerance,
+            webhook_ip_allowlist: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_webhook_ip_allowlist() {
+        let mut service = test_service(300);
+        // 白名单为空时不校验
+        assert!(service.is_webhook_ip_allowed(None));
+        assert!(service.is_webhook_ip_allowed(Some("1.2.3.4")));
+
+        service.config.webhook_ip_allowlist = vec!["3.18.12.63".to_string()];
+        assert!(service.is_webhook_ip_allowed(Some("3.18.12.63")));
+        assert!(!service.is_webhook_ip_allowed(Some("1.2.3.4")));
+        assert!(!service.is_webhook_ip_allowed(None));
+        assert!(!service.is_webhook_ip_allowed(Some("3.18.12.63, 1.2.3.4")));
+    }
+
+    #[test]
+    fn test_webhook_stale_signature_rejected() {
+        let service = test_service(300);
+        let signed_at = 1_700_000_000;
+        let header = format!("t={signed_at},v1=deadbeef");
+
+        // 超出容忍窗口（签名已过去 301 秒）
+        let err = service
+            .verify_webhook_signature("{}", &header, signed_at + 301)
+            .unwrap_err();
+        assert!(
+            matches!(err, AppError::AuthError(ref msg) if msg.contains("tolerance")),
+            "unexpected error: {err:?}"
+        );
+
+        // 窗口内则进入 HMAC 校验（伪造签名仍应失败，但不是时间窗口错误）
+        let err = service
+            .verify_webhook_signature("{}", &header, signed_at + 10)
+            .unwrap_err();
+        assert!(matches!(err, AppError::AuthError(ref msg) if !msg.contains("tolerance")));
+
+        // 缺少 t= 的签名头直接拒绝
+        assert!(
+            service
+                .verify_webhook_signature("{}", "v1=deadbeef", signed_at)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_missing_client_secret() {
+        use actix_web::ResponseError;
+
+        // 优先使用 Checkout Session 的 client_secret
+        assert_eq!(
+            StripeService::require_client_secret(
+                "pi_1",
+                Some("cs_secret".to_string()),
+                Some("pi_secret".to_string())
+            )
+            .unwrap(),
+            "cs_secret"
+        );
+        // 回退到 PaymentIntent
+        assert_eq!(
+            StripeService::require_client_secret("pi_1", None, Some("pi_secret".to_string()))
+                .unwrap(),
+            "pi_secret"
+        );
+
+        // 两者均缺失（或为空）时返回明确错误
+        let err =
+            StripeService::require_client_secret("pi_1", Some(String::new()), None).unwrap_err();
+        assert!(matches!(err, AppError::MissingClientSecret(ref id) if id == "pi_1"));
+        let resp = err.error_response();
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_GATEWAY);
+    }
 }
@@ -1,12 +1,43 @@
 use crate::config::TwilioConfig;
+use crate::database::expiring_keys::{KeyClaim, claim_key};
 use crate::error::{AppError, AppResult};
+use chrono::Duration;
 use reqwest::Client;
-use serde::Deserialize;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Twilio Verify 验证码有效期（秒），已通过的验证码在此期间内记录，防止被重放
+const VERIFICATION_TTL_SECS: i64 = 600;
 
 #[derive(Clone)]
 pub struct TwilioService {
     client: Client,
     config: TwilioConfig,
+    /// 已通过校验的验证码记录在 expiring_keys 表中，多实例共享
+    pool: DatabaseConnection,
+}
+
+/// Twilio Verify 验证码下发渠道
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationChannel {
+    #[default]
+    Sms,
+    #[serde(rename = "whatsapp")]
+    WhatsApp,
+    Call,
+}
+
+impl VerificationChannel {
+    /// Twilio `Channel` 参数取值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationChannel::Sms => "sms",
+            VerificationChannel::WhatsApp => "whatsapp",
+            VerificationChannel::Call => "call",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,16 +52,21 @@ struct VerifyCheckResponse {
 }
 
 impl TwilioService {
-    pub fn new(config: TwilioConfig) -> Self {
+    pub fn new(config: TwilioConfig, pool: DatabaseConnection) -> Self {
         Self {
             client: Client::new(),
             config,
+            pool,
         }
     }
 
-    /// Start a Verify verification via SMS.
+    /// Start a Verify verification via the given channel (SMS / WhatsApp / voice call).
     /// Docs: POST https://verify.twilio.com/v2/Services/{ServiceSid}/Verifications
-    pub async fn start_verification_sms(&self, phone: &str) -> AppResult<()> {
+    pub async fn start_verification(
+        &self,
+        phone: &str,
+        channel: VerificationChannel,
+    ) -> AppResult<()> {
         if self.config.verify_service_sid.is_empty() {
             return Err(AppError::InternalError(
                 "Missing TWILIO_VERIFY_SERVICE_SID config".to_string(),
@@ -43,7 +79,7 @@ impl TwilioService {
         );
 
         // Twilio Verify expects x-www-form-urlencoded with keys To/Channel
-        let params = [("To", phone), ("Channel", "sms")];
+        let params = [("To", phone), ("Channel", channel.as_str())];
 
         let resp = self
             .client
@@ -69,7 +105,12 @@ impl TwilioService {
             .json()
             .await
             .unwrap_or(VerifyStartResponse { status: None });
-        log::info!("Twilio Verify started for {}: {:?}", phone, body.status);
+        log::info!(
+            "Twilio Verify started for {} via {}: {:?}",
+            phone,
+            channel.as_str(),
+            body.status
+        );
         Ok(())
     }
 
@@ -112,6 +153,26 @@ impl TwilioService {
             AppError::ExternalApiError(format!("Failed to parse Twilio response: {e}"))
         })?;
         let approved = body.status.as_deref() == Some("approved") && body.valid.unwrap_or(false);
-        Ok(approved)
+        if !approved {
+            return Ok(false);
+        }
+
+        // 验证码只能使用一次（注册、重设密码、更换手机号共用），重复使用视为无效
+        let first_use = self.consume_code(phone, code).await?;
+        if !first_use {
+            log::warn!("Replayed verification code for {phone}");
+        }
+        Ok(first_use)
+    }
+
+    /// 记录一次验证码使用，同一验证码在有效期内第二次使用时返回 false
+    async fn consume_code(&self, phone: &str, code: &str) -> AppResult<bool> {
+        let claim = claim_key(
+            &self.pool,
+            &format!("verification_code:{phone}:{code}"),
+            Duration::seconds(VERIFICATION_TTL_SECS),
+        )
+        .await?;
+        Ok(claim == KeyClaim::Claimed)
     }
 }
@@ -0,0 +1,669 @@
+use crate::error::AppError;
+use crate::middlewares::require_admin;
+use crate::models::*;
+use crate::services::{
+    AuditEntry, AuditService, DiscountCodeService, LuckyDrawService, RechargeService, SyncService,
+    UserService,
+};
+use actix_web::middleware::from_fn;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError, Result, web};
+use serde_json::json;
+
+fn get_user_id_from_request(req: &HttpRequest) -> Option<i64> {
+    req.extensions().get::<i64>().copied()
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/recharge-tiers",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取充值档位成功", body = [RechargeTierResponse]),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 列出全部充值档位（含未启用的）；表为空时返回内置默认档位
+pub async fn list_recharge_tiers(
+    recharge_service: web::Data<RechargeService>,
+) -> Result<HttpResponse> {
+    match recharge_service.list_tiers().await {
+        Ok(list) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": list }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/stamp-redemption-tiers",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取印花兑换档位成功", body = [StampRedemptionTierResponse]),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 列出全部印花兑换档位（含未启用的）；表为空时返回内置默认档位
+pub async fn list_stamp_redemption_tiers(
+    discount_code_service: web::Data<DiscountCodeService>,
+) -> Result<HttpResponse> {
+    match discount_code_service.list_stamp_redemption_tiers().await {
+        Ok(list) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": list }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/discount-codes/bulk",
+    tag = "admin",
+    request_body = BulkGenerateDiscountCodesRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "批量生成优惠码成功", body = BulkGenerateDiscountCodesResponse),
+        (status = 400, description = "请求参数错误"),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 批量生成活动优惠码（不绑定用户）
+pub async fn bulk_generate_discount_codes(
+    discount_service: web::Data<DiscountCodeService>,
+    audit_service: web::Data<AuditService>,
+    req: HttpRequest,
+    request: web::Json<BulkGenerateDiscountCodesRequest>,
+) -> Result<HttpResponse> {
+    let request = request.into_inner();
+    let code_type = request.code_type.clone();
+    match discount_service
+        .bulk_generate(
+            request.count,
+            request.discount_amount,
+            request.code_type,
+            request.expire_months,
+        )
+        .await
+    {
+        Ok(resp) => {
+            audit_service.record(AuditEntry {
+                actor_id: get_user_id_from_request(&req),
+                action: "admin.discount_codes.bulk_generate",
+                entity: "discount_code",
+                entity_id: None,
+                before: None,
+                after: Some(json!({
+                    "count": resp.codes.len(),
+                    "failed": resp.failed,
+                    "aborted": resp.aborted,
+                    "discount_amount": request.discount_amount,
+                    "code_type": code_type,
+                })),
+            });
+            Ok(HttpResponse::Ok().json(json!({ "success": true, "data": resp })))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/discount-codes/validate/{code}",
+    tag = "admin",
+    params(
+        ("code" = String, Path, description = "优惠码")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "校验优惠码成功", body = DiscountCodeValidation),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 收银核销前校验优惠码是否可用（只读），返回持有人会员号
+pub async fn validate_discount_code(
+    discount_service: web::Data<DiscountCodeService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    match discount_service.validate_code(&path.into_inner()).await {
+        Ok(response) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": response }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/lucky-draw/prizes",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取全部奖品成功", body = [LuckyDrawPrizeResponse]),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 列出全部抽奖奖品（含未启用的）
+pub async fn list_lucky_draw_prizes(
+    lucky_draw_service: web::Data<LuckyDrawService>,
+) -> Result<HttpResponse> {
+    match lucky_draw_service.list_all_prizes().await {
+        Ok(list) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": list }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/lucky-draw/prizes",
+    tag = "admin",
+    request_body = UpsertLuckyDrawPrizeRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "保存奖品成功", body = LuckyDrawPrizeResponse),
+        (status = 400, description = "请求参数错误或概率之和超过 10000bp"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "奖品不存在")
+    )
+)]
+/// 创建或更新抽奖奖品（概率、库存、启用状态）
+pub async fn upsert_lucky_draw_prize(
+    lucky_draw_service: web::Data<LuckyDrawService>,
+    audit_service: web::Data<AuditService>,
+    req: HttpRequest,
+    request: web::Json<UpsertLuckyDrawPrizeRequest>,
+) -> Result<HttpResponse> {
+    match lucky_draw_service.upsert_prize(request.into_inner()).await {
+        Ok(prize) => {
+            audit_prize_change(
+                &audit_service,
+                &req,
+                "admin.lucky_draw.upsert_prize",
+                &prize,
+            );
+            Ok(HttpResponse::Ok().json(json!({ "success": true, "data": prize })))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/lucky-draw/prizes/{id}/refill",
+    tag = "admin",
+    params(
+        ("id" = i64, Path, description = "奖品ID")
+    ),
+    request_body = RefillLuckyDrawPrizeRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "补充库存成功", body = LuckyDrawPrizeResponse),
+        (status = 400, description = "请求参数错误"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "奖品不存在")
+    )
+)]
+/// 补充限量奖品库存
+pub async fn refill_lucky_draw_prize(
+    lucky_draw_service: web::Data<LuckyDrawService>,
+    audit_service: web::Data<AuditService>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    request: web::Json<RefillLuckyDrawPrizeRequest>,
+) -> Result<HttpResponse> {
+    match lucky_draw_service
+        .refill_prize_stock(path.into_inner(), request.into_inner())
+        .await
+    {
+        Ok(prize) => {
+            audit_prize_change(
+                &audit_service,
+                &req,
+                "admin.lucky_draw.refill_prize",
+                &prize,
+            );
+            Ok(HttpResponse::Ok().json(json!({ "success": true, "data": prize })))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/lucky-draw/stats",
+    tag = "admin",
+    params(
+        ("from" = Option<String>, Query, description = "起始时间 (RFC3339，含)"),
+        ("to" = Option<String>, Query, description = "截止时间 (RFC3339，不含)")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取奖品分布统计成功", body = LuckyDrawStatsResponse),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 奖品实际分布与配置概率对比（含总抽奖次数与限量奖品剩余库存）
+pub async fn get_lucky_draw_stats(
+    lucky_draw_service: web::Data<LuckyDrawService>,
+    query: web::Query<LuckyDrawStatsQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    match lucky_draw_service.prize_stats(query.from, query.to).await {
+        Ok(stats) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": stats }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/by-code/{code}",
+    tag = "admin",
+    params(
+        ("code" = String, Path, description = "会员码")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "查询用户成功", body = UserResponse),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "用户不存在")
+    )
+)]
+/// 按会员码查询用户资料与统计（客服电话场景）
+pub async fn get_user_by_member_code(
+    user_service: web::Data<UserService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    match user_service.get_by_member_code(&path.into_inner()).await {
+        Ok((user, statistics)) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": {
+                "user": user,
+                "statistics": statistics
+            }
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{user_id}/discount-codes/{code}",
+    tag = "admin",
+    params(
+        ("user_id" = i64, Path, description = "用户ID"),
+        ("code" = String, Path, description = "优惠码")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "查询优惠码成功", body = DiscountCodeResponse),
+        (status = 401, description = "未授权"),
+        (status = 403, description = "优惠码不属于该用户"),
+        (status = 404, description = "优惠码不存在")
+    )
+)]
+/// 查询指定用户名下的优惠码（客服核对场景，区分不存在与不属于该用户）
+pub async fn get_user_discount_code(
+    discount_service: web::Data<DiscountCodeService>,
+    path: web::Path<(i64, String)>,
+) -> Result<HttpResponse> {
+    let (user_id, code) = path.into_inner();
+    match discount_service.find_for_user_admin(user_id, &code).await {
+        Ok(m) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": DiscountCodeResponse::from(m)
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/search",
+    tag = "admin",
+    params(
+        ("q" = String, Query, description = "用户名前缀（不区分大小写）或手机号末尾数字"),
+        ("page" = Option<u32>, Query, description = "页码"),
+        ("page_size" = Option<u32>, Query, description = "每页数量")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "搜索用户成功", body = PaginatedResponse<UserResponse>),
+        (status = 400, description = "搜索关键字为空"),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 按用户名前缀或手机号后缀搜索用户（客服场景）
+pub async fn search_users(
+    user_service: web::Data<UserService>,
+    query: web::Query<UserSearchQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let params = PaginationParams::new(query.page, query.page_size);
+    match user_service.search(&query.q, &params).await {
+        Ok(response) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": response
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync/orders",
+    tag = "admin",
+    request_body(content = ManualSyncOrdersRequest, description = "可选日期范围；省略时按游标增量同步"),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "同步完成", body = ManualSyncResponse),
+        (status = 400, description = "日期范围无效"),
+        (status = 401, description = "未授权"),
+        (status = 409, description = "订单同步正在进行")
+    )
+)]
+/// 手动触发订单同步；指定日期范围时补同步该范围且不移动游标
+pub async fn sync_orders(
+    sync_service: web::Data<SyncService>,
+    audit_service: web::Data<AuditService>,
+    req: HttpRequest,
+    request: Option<web::Json<ManualSyncOrdersRequest>>,
+) -> Result<HttpResponse> {
+    let request = request.map(|r| r.into_inner()).unwrap_or_default();
+    let result = match (request.start_date, request.end_date) {
+        (None, None) => sync_service.sync_orders().await,
+        (Some(start), end) => {
+            let end = end.unwrap_or_else(|| chrono::Utc::now().date_naive());
+            sync_service.sync_orders_between(start, end).await
+        }
+        (None, Some(_)) => Err(AppError::ValidationError(
+            "start_date is required when end_date is set".to_string(),
+        )),
+    };
+    match result {
+        Ok(processed) => {
+            audit_service.record(AuditEntry {
+                actor_id: get_user_id_from_request(&req),
+                action: "admin.sync.orders",
+                entity: "sync",
+                entity_id: None,
+                before: None,
+                after: Some(json!({
+                    "start_date": request.start_date,
+                    "end_date": request.end_date,
+                    "processed": processed,
+                })),
+            });
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": ManualSyncResponse { processed }
+            })))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync/discount-codes",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "同步完成", body = ManualSyncResponse),
+        (status = 401, description = "未授权"),
+        (status = 409, description = "优惠码同步正在进行")
+    )
+)]
+/// 手动触发七云优惠码同步
+pub async fn sync_discount_codes(
+    sync_service: web::Data<SyncService>,
+    audit_service: web::Data<AuditService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    match sync_service.sync_discount_codes().await {
+        Ok(processed) => {
+            audit_service.record(AuditEntry {
+                actor_id: get_user_id_from_request(&req),
+                action: "admin.sync.discount_codes",
+                entity: "sync",
+                entity_id: None,
+                before: None,
+                after: Some(json!({ "processed": processed })),
+            });
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": ManualSyncResponse { processed }
+            })))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/sync/runs",
+    tag = "admin",
+    params(
+        ("kind" = Option<String>, Query, description = "同步类型：orders / discount_codes"),
+        ("limit" = Option<u64>, Query, description = "返回条数，默认 20，最大 100")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取同步运行记录成功", body = [SyncRunResponse]),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 最近的同步运行记录（最新在前），用于发现七云同步持续失败
+pub async fn list_sync_runs(
+    sync_service: web::Data<SyncService>,
+    query: web::Query<SyncRunQuery>,
+) -> Result<HttpResponse> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    match sync_service.list_runs(query.kind.as_deref(), limit).await {
+        Ok(list) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": list }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/sync/failures",
+    tag = "admin",
+    params(
+        ("flagged" = Option<bool>, Query, description = "仅返回已标记待人工处理的订单")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取订单同步失败记录成功", body = [SyncFailureResponse]),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 同步处理失败的七云订单（最近更新在前）
+pub async fn list_sync_failures(
+    sync_service: web::Data<SyncService>,
+    query: web::Query<SyncFailureQuery>,
+) -> Result<HttpResponse> {
+    match sync_service
+        .list_failures(query.flagged.unwrap_or(false))
+        .await
+    {
+        Ok(list) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": list }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync/failures/{order_id}/retry",
+    tag = "admin",
+    params(
+        ("order_id" = i64, Path, description = "七云订单 ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "已清除失败记录并补同步", body = ManualSyncResponse),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "失败记录不存在"),
+        (status = 409, description = "订单同步正在进行")
+    )
+)]
+/// 清除订单的失败记录并补同步该订单创建当天的订单
+pub async fn retry_sync_failure(
+    sync_service: web::Data<SyncService>,
+    audit_service: web::Data<AuditService>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let order_id = path.into_inner();
+    match sync_service.retry_failure(order_id).await {
+        Ok(processed) => {
+            audit_service.record(AuditEntry {
+                actor_id: get_user_id_from_request(&req),
+                action: "admin.sync.retry_failure",
+                entity: "sync_failure",
+                entity_id: Some(order_id),
+                before: None,
+                after: Some(json!({ "processed": processed })),
+            });
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "data": ManualSyncResponse { processed }
+            })))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/recharges/{payment_intent_id}/refund",
+    tag = "admin",
+    params(
+        ("payment_intent_id" = String, Path, description = "充值的 Stripe PaymentIntent ID")
+    ),
+    request_body = RefundRechargeRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "退款成功", body = RefundRechargeResponse),
+        (status = 400, description = "记录状态或金额不允许退款"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "充值记录不存在"),
+        (status = 409, description = "退款正在进行")
+    )
+)]
+/// 退款一笔已成功的充值并扣回余额（审计记录在服务内写入）
+pub async fn refund_recharge(
+    recharge_service: web::Data<RechargeService>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    request: web::Json<RefundRechargeRequest>,
+) -> Result<HttpResponse> {
+    match recharge_service
+        .refund_recharge(
+            get_user_id_from_request(&req),
+            &path.into_inner(),
+            request.amount_cents,
+        )
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": resp }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// 记录奖品变更审计，after 为变更后的奖品
+fn audit_prize_change(
+    audit_service: &AuditService,
+    req: &HttpRequest,
+    action: &'static str,
+    prize: &LuckyDrawPrizeResponse,
+) {
+    audit_service.record(AuditEntry {
+        actor_id: get_user_id_from_request(req),
+        action,
+        entity: "lucky_draw_prize",
+        entity_id: Some(prize.id),
+        before: None,
+        after: serde_json::to_value(prize).ok(),
+    });
+}
+
+/// 路由配置
+pub fn admin_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .wrap(from_fn(require_admin))
+            .route("/recharge-tiers", web::get().to(list_recharge_tiers))
+            .route(
+                "/stamp-redemption-tiers",
+                web::get().to(list_stamp_redemption_tiers),
+            )
+            .route(
+                "/discount-codes/bulk",
+                web::post().to(bulk_generate_discount_codes),
+            )
+            .route(
+                "/discount-codes/validate/{code}",
+                web::get().to(validate_discount_code),
+            )
+            .route("/lucky-draw/prizes", web::get().to(list_lucky_draw_prizes))
+            .route(
+                "/lucky-draw/prizes",
+                web::post().to(upsert_lucky_draw_prize),
+            )
+            .route(
+                "/lucky-draw/prizes/{id}/refill",
+                web::post().to(refill_lucky_draw_prize),
+            )
+            .route("/lucky-draw/stats", web::get().to(get_lucky_draw_stats))
+            .route("/users/search", web::get().to(search_users))
+            .route("/sync/orders", web::post().to(sync_orders))
+            .route("/sync/discount-codes", web::post().to(sync_discount_codes))
+            .route("/sync/runs", web::get().to(list_sync_runs))
+            .route("/sync/failures", web::get().to(list_sync_failures))
+            .route(
+                "/sync/failures/{order_id}/retry",
+                web::post().to(retry_sync_failure),
+            )
+            .route(
+                "/recharges/{payment_intent_id}/refund",
+                web::post().to(refund_recharge),
+            )
+            .route(
+                "/users/by-code/{code}",
+                web::get().to(get_user_by_member_code),
+            )
+            .route(
+                "/users/{user_id}/discount-codes/{code}",
+                web::get().to(get_user_discount_code),
+            ),
+    );
+}
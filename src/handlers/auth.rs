@@ -1,9 +1,31 @@
+use crate::error::AppError;
 use crate::external::TurnstileService;
 use crate::models::*;
 use crate::services::AuthService;
+use crate::utils::client_ip;
 use actix_web::{HttpRequest, HttpResponse, ResponseError, Result, web};
 use serde_json::json;
 
+/// 若启用 Turnstile，则要求并校验 token（按客户端 IP）；未启用时直接通过
+async fn verify_turnstile(
+    turnstile: &TurnstileService,
+    req: &HttpRequest,
+    token: Option<&str>,
+) -> Result<(), AppError> {
+    if !turnstile.is_enabled() {
+        return Ok(());
+    }
+    let token = token
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| AppError::ValidationError("Missing Turnstile token".into()))?;
+
+    let remote_ip = client_ip(req);
+    log::debug!("Verifying Turnstile token, IP: {remote_ip:?}");
+    turnstile
+        .verify_token(token, remote_ip.as_deref(), None)
+        .await
+}
+
 #[utoipa::path(
     post,
     path = "/auth/send-code",
@@ -21,48 +43,15 @@ pub async fn send_code(
     req: HttpRequest,
     request: web::Json<SendCodeRequest>,
 ) -> Result<HttpResponse> {
-    // 若启用 Turnstile，则要求并校验 token
-    if turnstile.as_ref().is_enabled() {
-        let token = match &request.cf_turnstile_token {
-            Some(t) if !t.is_empty() => t,
-            _ => {
-                return Ok(crate::error::AppError::ValidationError(
-                    "Missing Turnstile token".into(),
-                )
-                .error_response());
-            }
-        };
-
-        // 提取客户端 IP（优先 CF-Connecting-IP, 然后 X-Forwarded-For，再从连接信息）
-        let header_ip = req
-            .headers()
-            .get("CF-Connecting-IP")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string())
-            .or_else(|| {
-                req.headers()
-                    .get("X-Forwarded-For")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-            });
-        let remote_ip = if let Some(ip) = header_ip {
-            Some(ip)
-        } else {
-            req.connection_info()
-                .realip_remote_addr()
-                .map(|s| s.to_string())
-        };
-        let remote_ip_ref = remote_ip.as_deref();
-
-        log::info!("Verifying Turnstile token: {token}, IP: {remote_ip_ref:?}");
-
-        // 调用 Turnstile 服务验证
-        if let Err(e) = turnstile.verify_token(token, remote_ip_ref, None).await {
-            return Ok(e.error_response());
-        }
+    if let Err(e) = verify_turnstile(&turnstile, &req, request.cf_turnstile_token.as_deref()).await
+    {
+        return Ok(e.error_response());
     }
 
-    match auth_service.send_verification_code(&request.phone).await {
+    match auth_service
+        .send_verification_code(&request.phone, request.channel)
+        .await
+    {
         Ok(response) => Ok(HttpResponse::Ok().json(json!({
             "success": true,
             "data": response,
@@ -79,13 +68,20 @@ pub async fn send_code(
     responses(
         (status = 200, description = "注册成功", body = AuthResponse),
         (status = 400, description = "请求参数错误"),
+        (status = 422, description = "字段校验失败，errors 中列出各字段错误"),
         (status = 500, description = "服务器内部错误")
     )
 )]
 pub async fn register(
     auth_service: web::Data<AuthService>,
+    turnstile: web::Data<TurnstileService>,
+    req: HttpRequest,
     request: web::Json<CreateUserRequest>,
 ) -> Result<HttpResponse> {
+    if let Err(e) = verify_turnstile(&turnstile, &req, request.cf_turnstile_token.as_deref()).await
+    {
+        return Ok(e.error_response());
+    }
     match auth_service.register(request.into_inner()).await {
         Ok(response) => Ok(HttpResponse::Ok().json(json!({
             "success": true,
@@ -108,9 +104,24 @@ pub async fn register(
 )]
 pub async fn login(
     auth_service: web::Data<AuthService>,
+    turnstile: web::Data<TurnstileService>,
+    req: HttpRequest,
     request: web::Json<LoginRequest>,
 ) -> Result<HttpResponse> {
-    match auth_service.login(request.into_inner()).await {
+    if let Err(e) = verify_turnstile(&turnstile, &req, request.cf_turnstile_token.as_deref()).await
+    {
+        return Ok(e.error_response());
+    }
+    let ip = client_ip(&req);
+    let user_agent = req
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    match auth_service
+        .login(request.into_inner(), ip, user_agent)
+        .await
+    {
         Ok(response) => Ok(HttpResponse::Ok().json(json!({
             "success": true,
             "data": response
@@ -187,6 +198,7 @@ pub async fn refresh(
         (status = 200, description = "重设密码成功"),
         (status = 400, description = "请求参数错误"),
         (status = 404, description = "用户不存在"),
+        (status = 429, description = "该手机号的重设请求过于频繁"),
         (status = 500, description = "服务器内部错误")
     )
 )]
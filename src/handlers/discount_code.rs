@@ -15,7 +15,7 @@ fn get_user_id_from_request(req: &HttpRequest) -> Option<i64> {
         ("page" = Option<u32>, Query, description = "页码"),
         ("per_page" = Option<u32>, Query, description = "每页数量"),
         ("status" = Option<String>, Query, description = "状态: available/used/expired"),
-        ("code_type" = Option<String>, Query, description = "类型: shareholder_reward/super_shareholder_reward/sweets_credits_reward")
+        ("code_type" = Option<String>, Query, description = "类型: shareholder_reward/super_shareholder_reward/sweets_credits_reward/free_topping/monthly_card_daily/birthday_reward")
     ),
     security(
         ("bearer_auth" = [])
@@ -110,6 +110,40 @@ pub async fn redeem_balance_discount_code(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/discount-codes/transfer",
+    tag = "discount",
+    request_body = TransferDiscountCodeRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "转赠优惠码成功", body = TransferDiscountCodeResponse),
+        (status = 400, description = "优惠码已使用或已过期"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "优惠码或受赠人不存在")
+    )
+)]
+pub async fn transfer_discount_code(
+    discount_service: web::Data<DiscountCodeService>,
+    req: HttpRequest,
+    request: web::Json<TransferDiscountCodeRequest>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match discount_service
+        .transfer_code(user_id, &request.code, &request.to_member_code)
+        .await
+    {
+        Ok(response) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": response
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 pub fn discount_code_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/discount-codes")
@@ -118,6 +152,7 @@ pub fn discount_code_config(cfg: &mut web::ServiceConfig) {
             .route(
                 "/redeem-balance",
                 web::post().to(redeem_balance_discount_code),
-            ),
+            )
+            .route("/transfer", web::post().to(transfer_discount_code)),
     );
 }
@@ -0,0 +1,56 @@
+use crate::external::PosBackend;
+use actix_web::{HttpResponse, Result, web};
+use sea_orm::{ConnectionTrait, DatabaseConnection};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+/// 存活探针：进程可响应即返回 200
+pub async fn healthz() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(json!({ "status": "ok" })))
+}
+
+/// 就绪探针：检查数据库连通性与七云登录状态，任一失败返回 503 并列出失败项
+pub async fn readyz(
+    pool: web::Data<DatabaseConnection>,
+    sevencloud_api: web::Data<Mutex<dyn PosBackend>>,
+) -> Result<HttpResponse> {
+    let mut failed = Vec::new();
+
+    let database = match pool.execute_unprepared("SELECT 1").await {
+        Ok(_) => "ok".to_string(),
+        Err(e) => {
+            log::warn!("Readiness check: database unavailable: {e}");
+            failed.push("database");
+            "unavailable".to_string()
+        }
+    };
+
+    // 锁被占用说明有请求正在进行，视为可用，避免探针被长时间同步阻塞
+    let sevencloud = match sevencloud_api.try_lock() {
+        Ok(api) if api.is_logged_in() => "ok",
+        Ok(_) => {
+            failed.push("sevencloud");
+            "not_logged_in"
+        }
+        Err(_) => "busy",
+    };
+
+    let body = json!({
+        "status": if failed.is_empty() { "ok" } else { "unavailable" },
+        "checks": {
+            "database": database,
+            "sevencloud": sevencloud,
+        },
+        "failed": failed,
+    });
+    if failed.is_empty() {
+        Ok(HttpResponse::Ok().json(body))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(body))
+    }
+}
+
+pub fn health_config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/healthz", web::get().to(healthz))
+        .route("/readyz", web::get().to(readyz));
+}
@@ -1,13 +1,17 @@
+pub mod admin;
 pub mod auth;
 pub mod discount_code;
+pub mod health;
 pub mod lucky_draw;
 pub mod order;
 pub mod recharge;
 pub mod user;
 pub mod webhook;
 
+pub use admin::admin_config;
 pub use auth::auth_config;
 pub use discount_code::discount_code_config;
+pub use health::health_config;
 pub use lucky_draw::lucky_draw_config;
 pub use order::order_config;
 pub use recharge::membership_config;
@@ -1,5 +1,6 @@
 use crate::models::*;
 use crate::services::OrderService;
+use actix_web::http::header;
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError, Result, web};
 use serde_json::json;
 
@@ -14,15 +15,20 @@ fn get_user_id_from_request(req: &HttpRequest) -> Option<i64> {
     params(
         ("page" = Option<u32>, Query, description = "页码"),
         ("per_page" = Option<u32>, Query, description = "每页数量"),
+        ("after_id" = Option<i64>, Query, description = "游标分页：传上一页的 next_cursor，返回排在该订单之后的记录；提供时忽略 page 且不返回 total"),
         ("status" = Option<i32>, Query, description = "订单状态"),
         ("start_date" = Option<String>, Query, description = "开始日期"),
-        ("end_date" = Option<String>, Query, description = "结束日期")
+        ("end_date" = Option<String>, Query, description = "结束日期"),
+        ("from" = Option<String>, Query, description = "起始日期 (YYYY-MM-DD，含)"),
+        ("to" = Option<String>, Query, description = "结束日期 (YYYY-MM-DD，含)"),
+        ("product_no" = Option<String>, Query, description = "商品编号")
     ),
     security(
         ("bearer_auth" = [])
     ),
     responses(
         (status = 200, description = "获取订单列表成功", body = OrderListApiResponse),
+        (status = 400, description = "日期范围无效"),
         (status = 401, description = "未授权")
     )
 )]
@@ -42,6 +48,83 @@ pub async fn get_orders(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/orders/export",
+    tag = "order",
+    params(
+        ("status" = Option<i32>, Query, description = "订单状态"),
+        ("start_date" = Option<String>, Query, description = "开始日期"),
+        ("end_date" = Option<String>, Query, description = "结束日期"),
+        ("from" = Option<String>, Query, description = "起始日期 (YYYY-MM-DD，含)"),
+        ("to" = Option<String>, Query, description = "结束日期 (YYYY-MM-DD，含)"),
+        ("product_no" = Option<String>, Query, description = "商品编号")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "导出订单 CSV", content_type = "text/csv", body = String),
+        (status = 400, description = "日期范围无效"),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn export_orders(
+    order_service: web::Data<OrderService>,
+    req: HttpRequest,
+    query: web::Query<OrderQuery>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match order_service.export_orders_csv(user_id, &query).await {
+        Ok(csv) => Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"orders.csv\"",
+            ))
+            .body(csv)),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    tag = "order",
+    params(
+        ("id" = i64, Path, description = "订单ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取订单详情成功", body = OrderDetailResponse),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "订单不存在")
+    )
+)]
+pub async fn get_order(
+    order_service: web::Data<OrderService>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match order_service.get_order(user_id, path.into_inner()).await {
+        Ok(response) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": response
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 pub fn order_config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/orders").route("", web::get().to(get_orders)));
+    cfg.service(
+        web::scope("/orders")
+            .route("", web::get().to(get_orders))
+            .route("/export", web::get().to(export_orders))
+            .route("/{id}", web::get().to(get_order)),
+    );
 }
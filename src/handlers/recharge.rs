@@ -1,5 +1,9 @@
+use crate::entities::StripeTransactionCategory;
+use crate::error::AppError;
 use crate::models::*;
-use crate::services::{MembershipService, MonthlyCardService, RechargeService};
+use crate::services::{
+    MembershipService, MonthlyCardService, RechargeService, StripeTransactionService,
+};
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError, Result, web};
 use serde_json::json;
 
@@ -144,6 +148,30 @@ pub async fn create_membership_payment_intent(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/membership/benefits",
+    tag = "membership",
+    security(
+        (),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取会员权益成功", body = MembershipBenefitsResponse)
+    )
+)]
+/// 各会员等级权益摘要；携带有效 token 时额外返回当前用户等级与到期时间
+pub async fn get_membership_benefits(
+    membership_service: web::Data<MembershipService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req);
+    match membership_service.benefits_summary(user_id).await {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/membership/confirm",
@@ -171,6 +199,92 @@ pub async fn confirm_membership(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/membership/gift/create-payment-intent",
+    tag = "membership",
+    request_body = CreateGiftMembershipIntentRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "创建赠送会员支付意图成功", body = CreateMembershipIntentResponse),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "受赠人不存在"),
+        (status = 400, description = "请求参数错误")
+    )
+)]
+pub async fn create_gift_membership_payment_intent(
+    membership_service: web::Data<MembershipService>,
+    req: HttpRequest,
+    request: web::Json<CreateGiftMembershipIntentRequest>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    let request = request.into_inner();
+    match membership_service
+        .create_gift_membership_intent(
+            user_id,
+            &request.recipient_member_code,
+            request.target_member_type,
+        )
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/membership/schedule-downgrade",
+    tag = "membership",
+    request_body = ScheduleDowngradeRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "预约降级成功，到期自动续费时按新等级生效", body = ScheduleDowngradeResponse),
+        (status = 401, description = "未授权"),
+        (status = 400, description = "请求参数错误，或未开启自动续费")
+    )
+)]
+pub async fn schedule_membership_downgrade(
+    membership_service: web::Data<MembershipService>,
+    req: HttpRequest,
+    request: web::Json<ScheduleDowngradeRequest>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match membership_service
+        .schedule_downgrade(user_id, request.into_inner().target_member_type)
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/membership/auto-renew",
+    tag = "membership",
+    request_body = AutoRenewRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "更新自动续费设置成功", body = AutoRenewResponse),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn set_membership_auto_renew(
+    membership_service: web::Data<MembershipService>,
+    req: HttpRequest,
+    request: web::Json<AutoRenewRequest>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match membership_service
+        .set_auto_renew(user_id, request.into_inner().enabled)
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 pub fn membership_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/membership")
@@ -178,7 +292,17 @@ pub fn membership_config(cfg: &mut web::ServiceConfig) {
                 "/create-payment-intent",
                 web::post().to(create_membership_payment_intent),
             )
-            .route("/confirm", web::post().to(confirm_membership)),
+            .route("/confirm", web::post().to(confirm_membership))
+            .route("/benefits", web::get().to(get_membership_benefits))
+            .route(
+                "/gift/create-payment-intent",
+                web::post().to(create_gift_membership_payment_intent),
+            )
+            .route(
+                "/schedule-downgrade",
+                web::post().to(schedule_membership_downgrade),
+            )
+            .route("/auto-renew", web::put().to(set_membership_auto_renew)),
     );
 }
 
@@ -191,7 +315,7 @@ pub fn membership_config(cfg: &mut web::ServiceConfig) {
     responses(
         (status = 200, description = "创建月卡支付意图成功", body = CreateMonthlyCardIntentResponse),
         (status = 401, description = "未授权"),
-        (status = 400, description = "请求参数错误")
+        (status = 400, description = "请求参数错误或已有生效月卡")
     )
 )]
 pub async fn create_monthly_card_payment_intent(
@@ -236,6 +360,57 @@ pub async fn confirm_monthly_card(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/monthly-card/current",
+    tag = "monthly_card",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "获取当前月卡成功", body = CurrentMonthlyCardResponse),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "没有生效中的月卡")
+    )
+)]
+pub async fn get_current_monthly_card(
+    monthly_service: web::Data<MonthlyCardService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match monthly_service.get_current(user_id).await {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/monthly-card/history",
+    tag = "monthly_card",
+    params(
+        ("page" = Option<u32>, Query, description = "页码"),
+        ("page_size" = Option<u32>, Query, description = "每页数量")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "获取月卡记录成功", body = PaginatedResponse<MonthlyCardRecordResponse>),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn get_monthly_card_history(
+    monthly_service: web::Data<MonthlyCardService>,
+    req: HttpRequest,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match monthly_service
+        .list_history(user_id, &query.into_inner())
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 #[derive(serde::Deserialize, utoipa::ToSchema)]
 pub struct UnifiedConfirmRequest {
     pub category: String,
@@ -249,55 +424,134 @@ pub struct UnifiedConfirmRequest {
     request_body = UnifiedConfirmRequest,
     security(("bearer_auth" = [])),
     responses(
-        (status = 200, description = "统一确认成功"),
-        (status = 400, description = "请求参数错误")
+        (status = 200, description = "统一确认成功；同一 PaymentIntent 重复确认返回首次结果"),
+        (status = 400, description = "请求参数错误或类别与支付记录不一致")
     )
 )]
 pub async fn confirm_unified(
     recharge_service: web::Data<RechargeService>,
     membership_service: web::Data<MembershipService>,
     monthly_service: web::Data<MonthlyCardService>,
+    stx_service: web::Data<StripeTransactionService>,
     req: HttpRequest,
     body: web::Json<UnifiedConfirmRequest>,
 ) -> Result<HttpResponse> {
     let user_id = get_user_id_from_request(&req).unwrap_or(0);
     let payload = body.into_inner();
-    let resp = match payload.category.as_str() {
-        "recharge" => serde_json::to_value(
+    let category = match payload.category.as_str() {
+        "recharge" => StripeTransactionCategory::Recharge,
+        "membership" => StripeTransactionCategory::Membership,
+        "monthly_card" => StripeTransactionCategory::MonthlyCard,
+        _ => return Ok(HttpResponse::BadRequest().json(json!({"error": "invalid category"}))),
+    };
+    let payment_intent_id = payload.payment_intent_id;
+
+    // 幂等：同一 PaymentIntent 已确认成功过时直接返回首次结果
+    if let Some(saved) = stx_service.find_confirmation(&payment_intent_id).await? {
+        if saved.user_id != user_id {
+            return Err(AppError::NotFound("Payment not found".to_string()).into());
+        }
+        if saved.category != category {
+            return Err(category_mismatch(&saved.category).into());
+        }
+        return Ok(HttpResponse::Ok().json(json!({"success": true, "data": saved.response})));
+    }
+
+    // 创建支付时记录的类别必须与请求一致，防止充值的 PI 被当作会员确认
+    if let Some(tx) = stx_service
+        .find_by_payment_intent(&payment_intent_id)
+        .await?
+        && tx.category != category
+    {
+        return Err(category_mismatch(&tx.category).into());
+    }
+
+    let resp = match category {
+        StripeTransactionCategory::Recharge => serde_json::to_value(
             recharge_service
                 .confirm_recharge(
                     user_id,
                     ConfirmRechargeRequest {
-                        payment_intent_id: payload.payment_intent_id,
+                        payment_intent_id: payment_intent_id.clone(),
                     },
                 )
                 .await?,
         )?,
-        "membership" => serde_json::to_value(
+        StripeTransactionCategory::Membership => serde_json::to_value(
             membership_service
                 .confirm_membership(
                     user_id,
                     ConfirmMembershipRequest {
-                        payment_intent_id: payload.payment_intent_id,
+                        payment_intent_id: payment_intent_id.clone(),
                     },
                 )
                 .await?,
         )?,
-        "monthly_card" => serde_json::to_value(
+        StripeTransactionCategory::MonthlyCard => serde_json::to_value(
             monthly_service
                 .confirm_monthly_card(
                     user_id,
                     ConfirmMonthlyCardRequest {
-                        payment_intent_id: payload.payment_intent_id,
+                        payment_intent_id: payment_intent_id.clone(),
                     },
                 )
                 .await?,
         )?,
-        _ => return Ok(HttpResponse::BadRequest().json(json!({"error": "invalid category"}))),
     };
+
+    // 保存失败不影响本次结果，下次重试会重新走确认逻辑（各服务自身幂等）
+    if let Err(e) = stx_service
+        .save_confirmation(&payment_intent_id, user_id, category, resp.clone())
+        .await
+    {
+        log::warn!("Failed to save confirmation for {payment_intent_id}: {e}");
+    }
     Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp})))
 }
 
+#[utoipa::path(
+    get,
+    path = "/payments/transactions",
+    tag = "payments",
+    params(
+        ("page" = Option<i64>, Query, description = "页码，默认 1"),
+        ("page_size" = Option<i64>, Query, description = "每页条数，默认 20"),
+        ("category" = Option<String>, Query, description = "业务类别：recharge / membership / monthly_card")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "获取支付交易记录成功"),
+        (status = 401, description = "未授权")
+    )
+)]
+/// 充值、会员、月卡的统一支付交易时间线（最新在前）
+pub async fn list_transactions(
+    stx_service: web::Data<StripeTransactionService>,
+    req: HttpRequest,
+    query: web::Query<StripeTransactionQuery>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    let query = query.into_inner();
+    let params = PaginationParams {
+        page: query.page,
+        page_size: query.page_size,
+        after_id: None,
+    };
+    match stx_service
+        .list_by_user(user_id, &params, query.category)
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+fn category_mismatch(expected: &StripeTransactionCategory) -> AppError {
+    AppError::ValidationError(format!(
+        "Payment category mismatch, this payment is a {expected} payment"
+    ))
+}
+
 pub fn monthly_card_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/monthly-card")
@@ -305,6 +559,8 @@ pub fn monthly_card_config(cfg: &mut web::ServiceConfig) {
                 "/create-payment-intent",
                 web::post().to(create_monthly_card_payment_intent),
             )
-            .route("/confirm", web::post().to(confirm_monthly_card)),
+            .route("/confirm", web::post().to(confirm_monthly_card))
+            .route("/current", web::get().to(get_current_monthly_card))
+            .route("/history", web::get().to(get_monthly_card_history)),
     );
 }
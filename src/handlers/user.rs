@@ -1,6 +1,6 @@
 use crate::models::pagination::PaginationParams;
 use crate::models::*;
-use crate::services::UserService;
+use crate::services::{BirthdayRewardService, NotificationService, UserService};
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError, Result, web};
 use serde_json::json;
 
@@ -39,6 +39,31 @@ pub async fn get_profile(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/user/me",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取当前用户完整信息成功", body = MeResponse),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "用户不存在")
+    )
+)]
+/// 资料、统计、生效月卡与抽奖次数合并返回，减少 App 启动时的请求数
+pub async fn get_me(
+    user_service: web::Data<UserService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match user_service.get_me(user_id).await {
+        Ok(me) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": me}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 #[utoipa::path(
     put,
     path = "/user/profile",
@@ -74,6 +99,42 @@ pub async fn update_profile(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/change-phone",
+    tag = "user",
+    request_body = ChangePhoneRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "更换手机号成功", body = UserResponse),
+        (status = 401, description = "未授权"),
+        (status = 400, description = "手机号无效、已注册或验证码错误")
+    )
+)]
+pub async fn change_phone(
+    user_service: web::Data<UserService>,
+    req: HttpRequest,
+    request: web::Json<ChangePhoneRequest>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    let request = request.into_inner();
+
+    match user_service
+        .change_phone(user_id, &request.new_phone, &request.verification_code)
+        .await
+    {
+        Ok(user) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": {
+                "user": user
+            }
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/user/referrals",
@@ -115,18 +176,23 @@ pub async fn get_referrals(
     tag = "user",
     params(
         ("page" = Option<u32>, Query, description = "页码"),
-        ("per_page" = Option<u32>, Query, description = "每页数量")
+        ("page_size" = Option<u32>, Query, description = "每页数量"),
+        ("after_id" = Option<i64>, Query, description = "游标分页：传上一页的 next_cursor，返回 id 小于该值的流水；提供时忽略 page 且不返回 total"),
+        ("kind" = Option<String>, Query, description = "流水类型：earn / spend / redeem"),
+        ("from" = Option<String>, Query, description = "起始日期 (YYYY-MM-DD，含)"),
+        ("to" = Option<String>, Query, description = "结束日期 (YYYY-MM-DD，含)")
     ),
     security(("bearer_auth" = [])),
     responses(
         (status = 200, description = "获取钱包流水成功"),
+        (status = 400, description = "参数无效"),
         (status = 401, description = "未授权")
     )
 )]
 pub async fn get_wallet_transactions(
     user_service: web::Data<UserService>,
     req: HttpRequest,
-    query: web::Query<PaginationParams>,
+    query: web::Query<WalletTransactionQuery>,
 ) -> Result<HttpResponse> {
     let user_id = get_user_id_from_request(&req).unwrap_or(0);
     match user_service
@@ -138,15 +204,272 @@ pub async fn get_wallet_transactions(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/transfer",
+    tag = "user",
+    request_body = WalletTransferRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "转赠余额成功", body = WalletTransferResponse),
+        (status = 400, description = "金额无效、余额不足或超出每日转赠上限"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "受赠人不存在")
+    )
+)]
+pub async fn transfer_balance(
+    user_service: web::Data<UserService>,
+    req: HttpRequest,
+    request: web::Json<WalletTransferRequest>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match user_service
+        .transfer_balance(user_id, &request.to_member_code, request.amount)
+        .await
+    {
+        Ok(resp) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": resp}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/birthday-reward",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取生日福利预览成功", body = BirthdayRewardPreview),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "用户不存在")
+    )
+)]
+pub async fn get_birthday_reward_preview(
+    birthday_reward_service: web::Data<BirthdayRewardService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match birthday_reward_service
+        .birthday_reward_preview(user_id)
+        .await
+    {
+        Ok(preview) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": preview}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/referral-tree",
+    tag = "user",
+    params(
+        ("depth" = Option<u32>, Query, description = "展开层级（默认 1，最大 3）")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取推荐树成功", body = ReferralTreeResponse),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn get_referral_tree(
+    user_service: web::Data<UserService>,
+    req: HttpRequest,
+    query: web::Query<ReferralTreeQuery>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match user_service
+        .get_referral_tree(user_id, query.depth.unwrap_or(1))
+        .await
+    {
+        Ok(tree) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": tree}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/spending",
+    tag = "user",
+    params(
+        ("months" = Option<u32>, Query, description = "统计最近 N 个月（默认 12，最大 36）")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取月度消费统计成功", body = [MonthlySpending]),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn get_spending(
+    user_service: web::Data<UserService>,
+    req: HttpRequest,
+    query: web::Query<SpendingQuery>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+    match user_service
+        .get_spending_by_month(user_id, query.months.unwrap_or(12))
+        .await
+    {
+        Ok(series) => Ok(HttpResponse::Ok().json(json!({"success": true, "data": series}))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/notifications",
+    tag = "user",
+    params(
+        ("page" = Option<u32>, Query, description = "页码"),
+        ("page_size" = Option<u32>, Query, description = "每页数量")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取通知列表成功", body = PaginatedResponse<NotificationResponse>),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn get_notifications(
+    notification_service: web::Data<NotificationService>,
+    req: HttpRequest,
+    query: web::Query<PaginationParams>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match notification_service
+        .list(user_id, &query.into_inner())
+        .await
+    {
+        Ok(response) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": response
+        }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/notifications/{id}/read",
+    tag = "user",
+    params(
+        ("id" = i64, Path, description = "通知ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "标记已读成功"),
+        (status = 401, description = "未授权"),
+        (status = 404, description = "通知不存在")
+    )
+)]
+pub async fn mark_notification_read(
+    notification_service: web::Data<NotificationService>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match notification_service
+        .mark_read(user_id, path.into_inner())
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::Ok().json(json!({ "success": true }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/notification-prefs",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "获取通知偏好成功", body = NotificationPrefs),
+        (status = 401, description = "未授权")
+    )
+)]
+pub async fn get_notification_prefs(
+    notification_service: web::Data<NotificationService>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match notification_service.get_prefs(user_id).await {
+        Ok(prefs) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": prefs }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/user/notification-prefs",
+    tag = "user",
+    request_body = NotificationPrefs,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "更新通知偏好成功", body = NotificationPrefs),
+        (status = 401, description = "未授权"),
+        (status = 400, description = "请求参数错误")
+    )
+)]
+pub async fn update_notification_prefs(
+    notification_service: web::Data<NotificationService>,
+    req: HttpRequest,
+    request: web::Json<NotificationPrefs>,
+) -> Result<HttpResponse> {
+    let user_id = get_user_id_from_request(&req).unwrap_or(0);
+
+    match notification_service
+        .update_prefs(user_id, request.into_inner())
+        .await
+    {
+        Ok(prefs) => Ok(HttpResponse::Ok().json(json!({ "success": true, "data": prefs }))),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
 pub fn user_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/user")
+            .route("/me", web::get().to(get_me))
             .route("/profile", web::get().to(get_profile))
             .route("/profile", web::put().to(update_profile))
+            .route("/change-phone", web::post().to(change_phone))
             .route("/referrals", web::get().to(get_referrals))
+            .route("/referral-tree", web::get().to(get_referral_tree))
+            .route("/spending", web::get().to(get_spending))
+            .route("/notifications", web::get().to(get_notifications))
+            .route(
+                "/notifications/{id}/read",
+                web::post().to(mark_notification_read),
+            )
+            .route("/notification-prefs", web::get().to(get_notification_prefs))
+            .route(
+                "/notification-prefs",
+                web::put().to(update_notification_prefs),
+            )
             .route(
                 "/wallet/transactions",
                 web::get().to(get_wallet_transactions),
+            )
+            .route("/transfer", web::post().to(transfer_balance))
+            .route(
+                "/birthday-reward",
+                web::get().to(get_birthday_reward_preview),
             ),
     );
 }
@@ -5,10 +5,19 @@ use crate::models::{ConfirmMembershipRequest, ConfirmMonthlyCardRequest};
 use crate::services::membership_service::MembershipService;
 use crate::services::monthly_card_service::MonthlyCardService;
 use crate::services::recharge_service::RechargeService;
-use crate::services::stripe_transaction_service::StripeTransactionService;
+use crate::services::stripe_transaction_service::{EventClaim, StripeTransactionService};
+use crate::utils::client_ip;
 use actix_web::{HttpRequest, HttpResponse, Result, web};
 use log::{error, info, warn};
-use stripe::{Event, EventObject, EventType, Expandable, PaymentIntent};
+use stripe::{
+    CheckoutSessionPaymentStatus, Event, EventObject, EventType, Expandable, PaymentIntent,
+};
+
+/// 来源 IP 白名单校验：按连接对端地址判断，只有对端是受信任代理时才采信转发头，
+/// 客户端自行伪造的 CF-Connecting-IP / X-Forwarded-For 不会通过
+fn is_allowed_source(req: &HttpRequest, stripe_service: &StripeService) -> bool {
+    stripe_service.is_webhook_ip_allowed(client_ip(req).as_deref())
+}
 
 /// Stripe webhook处理器
 ///
@@ -22,6 +31,17 @@ pub async fn stripe_webhook(
     membership_service: web::Data<MembershipService>,
     stx_service: web::Data<StripeTransactionService>,
 ) -> Result<HttpResponse> {
+    // 配置了来源 IP 白名单时，先于签名校验拒绝非 Stripe 来源
+    if !is_allowed_source(&req, &stripe_service) {
+        warn!(
+            "Rejected Stripe webhook from disallowed IP: {:?}",
+            client_ip(&req)
+        );
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Forbidden"
+        })));
+    }
+
     let signature = match req.headers().get("stripe-signature") {
         Some(sig) => sig.to_str().unwrap_or(""),
         None => {
@@ -38,7 +58,11 @@ pub async fn stripe_webhook(
     })?;
 
     // 验证webhook签名
-    let event = match stripe_service.verify_webhook_signature(payload, signature, 0) {
+    let event = match stripe_service.verify_webhook_signature(
+        payload,
+        signature,
+        chrono::Utc::now().timestamp(),
+    ) {
         Ok(event) => event,
         Err(e) => {
             error!("Webhook signature verification failed: {e}");
@@ -53,6 +77,33 @@ pub async fn stripe_webhook(
         event.type_, event.id
     );
 
+    // 事件级去重：先认领再处理，处理成功后才标记完成；
+    // 处理中途崩溃的事件在租约过期后由 Stripe 重试重新处理
+    let event_id = event.id.to_string();
+    let event_type = event.type_.to_string();
+    let claimed = match stx_service.claim_event(&event_id, &event_type).await {
+        Ok(EventClaim::Claimed) => true,
+        Ok(EventClaim::Completed) => {
+            info!("Stripe event {event_id} already processed, skipping");
+            return Ok(HttpResponse::Ok().json(serde_json::json!({
+                "received": true,
+                "duplicate": true
+            })));
+        }
+        Ok(EventClaim::InFlight) => {
+            // 另一请求正在处理：返回 409 让 Stripe 稍后重试，避免原请求失败时事件被视为已送达
+            info!("Stripe event {event_id} is being processed, asking Stripe to retry");
+            return Ok(HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Event is being processed"
+            })));
+        }
+        Err(e) => {
+            // 认领失败时继续处理，各处理函数本身有状态保护
+            warn!("Failed to claim Stripe event {event_id}: {e}");
+            false
+        }
+    };
+
     // 处理不同类型的事件
     match handle_stripe_event(
         event,
@@ -65,16 +116,22 @@ pub async fn stripe_webhook(
     {
         Ok(_) => {
             info!("Successfully processed webhook event");
+            if claimed && let Err(e) = stx_service.complete_event(&event_id).await {
+                warn!("Failed to mark Stripe event {event_id} as completed: {e}");
+            }
             Ok(HttpResponse::Ok().json(serde_json::json!({
                 "received": true
             })))
         }
         Err(e) => {
             error!("Failed to process webhook event: {e}");
-            // 返回200状态码避免Stripe重试，但记录错误
-            Ok(HttpResponse::Ok().json(serde_json::json!({
-                "received": true,
-                "error": format!("Processing failed: {}", e)
+            if claimed && let Err(e) = stx_service.release_event(&event_id).await {
+                warn!("Failed to release Stripe event {event_id}: {e}");
+            }
+            // 返回 5xx 让 Stripe 重试
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "received": false,
+                "error": "Processing failed"
             })))
         }
     }
@@ -99,6 +156,15 @@ async fn handle_stripe_event(
             )
             .await
         }
+        EventType::CheckoutSessionCompleted => {
+            handle_checkout_session_completed(
+                event,
+                recharge_service,
+                monthly_service,
+                membership_service,
+            )
+            .await
+        }
         EventType::PaymentIntentPaymentFailed => {
             handle_payment_intent_failed(event, recharge_service, stx_service).await
         }
@@ -106,7 +172,6 @@ async fn handle_stripe_event(
             handle_payment_intent_canceled(event, recharge_service, stx_service).await
         }
         EventType::ChargeRefunded => {
-            // record refund only
             if let EventObject::Charge(charge) = event.data.object.clone() {
                 let user_id = charge
                     .metadata
@@ -118,11 +183,8 @@ async fn handle_stripe_event(
                     .get("category")
                     .map(|s| s.as_str())
                     .unwrap_or("recharge");
-                let cat = match category {
-                    "membership" => StripeTransactionCategory::Membership,
-                    "monthly_card" => StripeTransactionCategory::MonthlyCard,
-                    _ => StripeTransactionCategory::Recharge,
-                };
+                let cat = transaction_category(category);
+                let is_recharge = cat == StripeTransactionCategory::Recharge;
                 let _ = stx_service
                     .record_refund(
                         user_id,
@@ -138,8 +200,46 @@ async fn handle_stripe_event(
                         Some(charge.currency.to_string()),
                         Some(format!("{:?}", charge.status)),
                         Some("Charge refunded".to_string()),
+                        serde_json::to_value(&event.data.object).ok(),
                     )
                     .await;
+                // 充值退款：完成停留在 RefundPending 的本地退款
+                if is_recharge && let Some(pi) = charge.payment_intent.as_ref() {
+                    let payment_intent_id = match pi {
+                        Expandable::Id(id) => id.to_string(),
+                        Expandable::Object(obj) => obj.id.to_string(),
+                    };
+                    recharge_service
+                        .handle_refund_webhook(&payment_intent_id, charge.amount_refunded)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+        EventType::ChargeDisputeCreated => handle_charge_dispute_created(event, stx_service).await,
+        EventType::CustomerSubscriptionDeleted => {
+            // 订阅已取消：本地月卡随之取消，不再等待自然过期
+            if let EventObject::Subscription(sub) = event.data.object {
+                let canceled = monthly_service.cancel_by_subscription(&sub.id).await?;
+                info!(
+                    "Subscription {} deleted, canceled {canceled} monthly card(s)",
+                    sub.id
+                );
+            }
+            Ok(())
+        }
+        EventType::CustomerSubscriptionUpdated => {
+            // 同步“当期结束后取消”标记，月卡在当期内保持有效
+            if let EventObject::Subscription(sub) = event.data.object {
+                let updated = monthly_service
+                    .set_cancel_at_period_end(&sub.id, sub.cancel_at_period_end)
+                    .await?;
+                if updated > 0 {
+                    info!(
+                        "Subscription {} cancel_at_period_end set to {}",
+                        sub.id, sub.cancel_at_period_end
+                    );
+                }
             }
             Ok(())
         }
@@ -165,6 +265,63 @@ async fn handle_stripe_event(
     }
 }
 
+/// 处理争议（chargeback）创建事件：通过 PaymentIntent 关联原充值/会员/月卡交易，
+/// 记录一条 disputed 交易供人工审核
+async fn handle_charge_dispute_created(
+    event: Event,
+    stx_service: &StripeTransactionService,
+) -> AppResult<()> {
+    let raw_event = serde_json::to_value(&event.data.object).ok();
+    let EventObject::Dispute(dispute) = event.data.object else {
+        return Err(AppError::ValidationError(
+            "Invalid event object type for dispute".to_string(),
+        ));
+    };
+
+    let payment_intent_id = dispute.payment_intent.as_ref().map(|pi| match pi {
+        Expandable::Id(id) => id.to_string(),
+        Expandable::Object(obj) => obj.id.to_string(),
+    });
+    let charge_id = match &dispute.charge {
+        Expandable::Id(id) => id.to_string(),
+        Expandable::Object(obj) => obj.id.to_string(),
+    };
+
+    let original = match payment_intent_id.as_deref() {
+        Some(pi_id) => stx_service.find_by_payment_intent(pi_id).await?,
+        None => None,
+    };
+    let (user_id, category) = match &original {
+        Some(tx) => (tx.user_id, tx.category.clone()),
+        None => (0, StripeTransactionCategory::Recharge),
+    };
+
+    warn!(
+        "Charge dispute {} created: charge={charge_id}, payment_intent={payment_intent_id:?}, user={user_id}, category={category}, amount={}, reason={}",
+        dispute.id, dispute.amount, dispute.reason
+    );
+    if original.is_none() {
+        warn!("No local transaction found for disputed charge {charge_id}, manual review required");
+    }
+
+    stx_service
+        .record_dispute(
+            user_id,
+            category,
+            payment_intent_id,
+            Some(charge_id),
+            Some(dispute.amount),
+            Some(dispute.currency.to_string()),
+            Some(format!(
+                "Dispute {} created: {}",
+                dispute.id, dispute.reason
+            )),
+            raw_event,
+        )
+        .await?;
+    Ok(())
+}
+
 /// 处理支付成功事件
 async fn handle_payment_intent_succeeded(
     event: Event,
@@ -173,6 +330,7 @@ async fn handle_payment_intent_succeeded(
     membership_service: &MembershipService,
     stx_service: &StripeTransactionService,
 ) -> AppResult<()> {
+    let raw_event = serde_json::to_value(&event.data.object).ok();
     let payment_intent = extract_payment_intent_from_event(event)?;
 
     info!("Payment succeeded for PaymentIntent: {}", payment_intent.id);
@@ -202,24 +360,41 @@ async fn handle_payment_intent_succeeded(
     let _ = stx_service
         .record_payment_intent(
             user_id,
-            match category {
-                "membership" => StripeTransactionCategory::Membership,
-                "monthly_card" => StripeTransactionCategory::MonthlyCard,
-                _ => StripeTransactionCategory::Recharge,
-            },
+            transaction_category(category),
             payment_intent.id.as_ref(),
             Some(payment_intent.amount),
             Some(payment_intent.currency.to_string()),
             Some("succeeded".to_string()),
             payment_intent.description.clone(),
+            raw_event,
         )
         .await;
 
+    dispatch_payment_success(
+        category,
+        user_id,
+        payment_intent.id.as_ref(),
+        recharge_service,
+        monthly_service,
+        membership_service,
+    )
+    .await
+}
+
+/// 按业务类别分发支付成功后的确认逻辑（PaymentIntent 成功与 Checkout 完成共用）
+async fn dispatch_payment_success(
+    category: &str,
+    user_id: i64,
+    payment_intent_id: &str,
+    recharge_service: &RechargeService,
+    monthly_service: &MonthlyCardService,
+    membership_service: &MembershipService,
+) -> AppResult<()> {
     match category {
         "recharge" => {
             // 充值成功
             recharge_service
-                .handle_payment_success_webhook(payment_intent.id.as_ref(), user_id)
+                .handle_payment_success_webhook(payment_intent_id, user_id)
                 .await?;
         }
         "monthly_card" => {
@@ -228,7 +403,7 @@ async fn handle_payment_intent_succeeded(
                 .confirm_monthly_card(
                     user_id,
                     ConfirmMonthlyCardRequest {
-                        payment_intent_id: payment_intent.id.to_string(),
+                        payment_intent_id: payment_intent_id.to_string(),
                     },
                 )
                 .await?;
@@ -239,7 +414,7 @@ async fn handle_payment_intent_succeeded(
                 .confirm_membership(
                     user_id,
                     ConfirmMembershipRequest {
-                        payment_intent_id: payment_intent.id.to_string(),
+                        payment_intent_id: payment_intent_id.to_string(),
                     },
                 )
                 .await?;
@@ -252,12 +427,77 @@ async fn handle_payment_intent_succeeded(
     Ok(())
 }
 
+/// 处理 Checkout Session 完成事件
+///
+/// 从 Session 读取 payment_intent 与 metadata.category 后复用支付成功的确认逻辑；
+/// 各确认函数对已处理的记录是幂等的，与随后到达的 PaymentIntent 成功事件不会重复发放。
+async fn handle_checkout_session_completed(
+    event: Event,
+    recharge_service: &RechargeService,
+    monthly_service: &MonthlyCardService,
+    membership_service: &MembershipService,
+) -> AppResult<()> {
+    let EventObject::CheckoutSession(session) = event.data.object else {
+        return Err(AppError::ValidationError(
+            "Expected CheckoutSession object in event".to_string(),
+        ));
+    };
+
+    // 异步支付方式在 Session 完成时可能尚未到账，等待 PaymentIntent 成功事件
+    if session.payment_status != CheckoutSessionPaymentStatus::Paid {
+        info!(
+            "Checkout session {} completed with payment_status={:?}, waiting for payment",
+            session.id, session.payment_status
+        );
+        return Ok(());
+    }
+
+    let payment_intent_id = match session.payment_intent.as_ref() {
+        Some(Expandable::Id(id)) => id.to_string(),
+        Some(Expandable::Object(pi)) => pi.id.to_string(),
+        None => {
+            return Err(AppError::ValidationError(
+                "Missing payment_intent in checkout session".to_string(),
+            ));
+        }
+    };
+    let metadata = session.metadata.unwrap_or_default();
+    let user_id = metadata
+        .get("user_id")
+        .map(String::as_str)
+        .or(session.client_reference_id.as_deref())
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| {
+            AppError::ValidationError("Missing or invalid user_id in metadata".to_string())
+        })?;
+    let category = metadata
+        .get("category")
+        .map(|s| s.as_str())
+        .unwrap_or("recharge");
+
+    info!(
+        "Dispatching CheckoutSessionCompleted for user_id={}, category={}, payment_intent={}",
+        user_id, category, payment_intent_id
+    );
+
+    dispatch_payment_success(
+        category,
+        user_id,
+        &payment_intent_id,
+        recharge_service,
+        monthly_service,
+        membership_service,
+    )
+    .await
+}
+
 /// 处理支付失败事件
 async fn handle_payment_intent_failed(
     event: Event,
     recharge_service: &RechargeService,
     stx_service: &StripeTransactionService,
 ) -> AppResult<()> {
+    let raw_event = serde_json::to_value(&event.data.object).ok();
     let payment_intent = extract_payment_intent_from_event(event)?;
 
     warn!("Payment failed for PaymentIntent: {}", payment_intent.id);
@@ -282,16 +522,13 @@ async fn handle_payment_intent_failed(
     let _ = stx_service
         .record_payment_intent(
             user_id,
-            match category {
-                "membership" => StripeTransactionCategory::Membership,
-                "monthly_card" => StripeTransactionCategory::MonthlyCard,
-                _ => StripeTransactionCategory::Recharge,
-            },
+            transaction_category(category),
             payment_intent.id.as_ref(),
             Some(payment_intent.amount),
             Some(payment_intent.currency.to_string()),
             Some("failed".to_string()),
             payment_intent.description.clone(),
+            raw_event,
         )
         .await;
 
@@ -311,6 +548,7 @@ async fn handle_payment_intent_canceled(
     recharge_service: &RechargeService,
     stx_service: &StripeTransactionService,
 ) -> AppResult<()> {
+    let raw_event = serde_json::to_value(&event.data.object).ok();
     let payment_intent = extract_payment_intent_from_event(event)?;
 
     info!("Payment canceled for PaymentIntent: {}", payment_intent.id);
@@ -335,16 +573,13 @@ async fn handle_payment_intent_canceled(
     let _ = stx_service
         .record_payment_intent(
             user_id,
-            match category {
-                "membership" => StripeTransactionCategory::Membership,
-                "monthly_card" => StripeTransactionCategory::MonthlyCard,
-                _ => StripeTransactionCategory::Recharge,
-            },
+            transaction_category(category),
             payment_intent.id.as_ref(),
             Some(payment_intent.amount),
             Some(payment_intent.currency.to_string()),
             Some("canceled".to_string()),
             payment_intent.description.clone(),
+            raw_event,
         )
         .await;
 
@@ -358,6 +593,15 @@ async fn handle_payment_intent_canceled(
     Ok(())
 }
 
+/// 将 metadata 中的业务类别映射为统一交易表类别；会员自动续费归入会员
+fn transaction_category(category: &str) -> StripeTransactionCategory {
+    match category {
+        "membership" | "membership_renewal" => StripeTransactionCategory::Membership,
+        "monthly_card" => StripeTransactionCategory::MonthlyCard,
+        _ => StripeTransactionCategory::Recharge,
+    }
+}
+
 /// 从事件中提取PaymentIntent对象
 fn extract_payment_intent_from_event(event: Event) -> AppResult<PaymentIntent> {
     match event.data.object {
@@ -372,3 +616,55 @@ fn extract_payment_intent_from_event(event: Event) -> AppResult<PaymentIntent> {
 pub fn webhook_config(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/webhook").route("/stripe", web::post().to(stripe_webhook)));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StripeConfig;
+    use crate::utils::TrustedProxies;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_allowlist_ignores_spoofed_forwarded_headers() {
+        let stripe_service = StripeService::new(StripeConfig {
+            secret_key: "sk_test_dummy".to_string(),
+            webhook_secret: "whsec_test".to_string(),
+            checkout_success_url: None,
+            checkout_cancel_url: None,
+            monthly_card_product_id: None,
+            monthly_card_one_time_price_id: None,
+            monthly_card_subscription_price_id: None,
+            monthly_card_fallback_amount_cents: 550,
+            webhook_tolerance_secs: 300,
+            webhook_ip_allowlist: vec!["3.18.12.63".to_string()],
+        });
+        let proxies = web::Data::new(TrustedProxies::parse(&["10.0.0.1".to_string()]).unwrap());
+        let request = |peer: &str, forwarded: &str| {
+            TestRequest::post()
+                .peer_addr(peer.parse().unwrap())
+                .app_data(proxies.clone())
+                .insert_header(("CF-Connecting-IP", forwarded))
+                .insert_header(("X-Forwarded-For", forwarded))
+                .to_http_request()
+        };
+
+        // 非代理来源伪造转发头，按对端地址判断
+        assert!(!is_allowed_source(
+            &request("198.51.100.7:443", "3.18.12.63"),
+            &stripe_service
+        ));
+        assert!(is_allowed_source(
+            &request("3.18.12.63:443", "198.51.100.7"),
+            &stripe_service
+        ));
+        // 受信任代理转发的请求按转发头判断
+        assert!(is_allowed_source(
+            &request("10.0.0.1:443", "3.18.12.63"),
+            &stripe_service
+        ));
+        assert!(!is_allowed_source(
+            &request("10.0.0.1:443", "198.51.100.7"),
+            &stripe_service
+        ));
+    }
+}
@@ -9,12 +9,15 @@ use kkss_backend::tasks;
 use kkss_backend::{
     config::Config,
     database::{create_pool, run_migrations},
-    external::{SevenCloudAPI, StripeService, TwilioService},
+    entities::MemberType,
+    external::{SevenCloudAPI, SharedPosBackend, StripeGateway, StripeService, TwilioService},
     handlers,
-    middlewares::{AuthMiddleware, create_cors},
+    middlewares::{
+        AuthMiddleware, RateLimitMiddleware, RequestIdMiddleware, create_cors, current_request_id,
+    },
     services::*,
     swagger::swagger_config,
-    utils::JwtService,
+    utils::{JwtService, TokenLifetimes, TrustedProxies},
 };
 
 #[actix_web::main]
@@ -25,13 +28,19 @@ async fn main() -> std::io::Result<()> {
             let level = record.level().as_str().to_ascii_lowercase();
             let msg_json = serde_json::to_string(&format!("{}", record.args()))
                 .unwrap_or_else(|_| "\"<invalid utf8>\"".to_string());
+            // 处于请求上下文时附带请求 ID，便于与客户端错误响应关联
+            let request_id = current_request_id()
+                .and_then(|id| serde_json::to_string(&id).ok())
+                .map(|id| format!(",\"request_id\":{id}"))
+                .unwrap_or_default();
             writeln!(
                 buf,
-                "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"message\":{},\"target\":\"{}\"}}",
+                "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"message\":{},\"target\":\"{}\"{}}}",
                 ts,
                 level,
                 msg_json,
                 record.target(),
+                request_id,
             )
         })
         .target(Target::Stdout)
@@ -39,6 +48,7 @@ async fn main() -> std::io::Result<()> {
 
     // 加载配置
     let config = Config::from_toml().expect("Failed to load configuration file");
+    config.validate().expect("Invalid configuration");
 
     // 创建数据库连接池
     let pool = create_pool(&config.database)
@@ -51,48 +61,100 @@ async fn main() -> std::io::Result<()> {
         .expect("Failed to run database migrations");
 
     // 创建JWT服务
+    // 各会员等级的令牌有效期，未配置时使用默认值
+    let jwt_config = &config.jwt;
     let jwt_service = JwtService::new(
-        &config.jwt.secret,
-        config.jwt.access_token_expires_in,
-        config.jwt.refresh_token_expires_in,
+        &jwt_config.secret,
+        jwt_config.access_token_expires_in,
+        jwt_config.refresh_token_expires_in,
+    )
+    .with_tier_lifetimes(
+        &MemberType::SweetShareholder,
+        TokenLifetimes {
+            access: jwt_config
+                .sweet_shareholder_access_token_expires_in
+                .unwrap_or(jwt_config.access_token_expires_in),
+            refresh: jwt_config
+                .sweet_shareholder_refresh_token_expires_in
+                .unwrap_or(jwt_config.refresh_token_expires_in),
+        },
+    )
+    .with_tier_lifetimes(
+        &MemberType::SuperShareholder,
+        TokenLifetimes {
+            access: jwt_config
+                .super_shareholder_access_token_expires_in
+                .unwrap_or(jwt_config.access_token_expires_in),
+            refresh: jwt_config
+                .super_shareholder_refresh_token_expires_in
+                .unwrap_or(jwt_config.refresh_token_expires_in),
+        },
     );
 
     // 创建外部服务
-    let twilio_service = TwilioService::new(config.twilio.clone());
+    let twilio_service = TwilioService::new(config.twilio.clone(), pool.clone());
     let turnstile_service = kkss_backend::external::TurnstileService::new(config.turnstile.clone());
     let stripe_service = StripeService::new(config.stripe.clone());
+    let stripe_gateway: Arc<dyn StripeGateway> = Arc::new(stripe_service.clone());
 
     let mut sevencloud_api = SevenCloudAPI::new(config.sevencloud.clone());
     if let Err(e) = sevencloud_api.login().await {
         log::error!("SevenCloud API login failed: {e:?}");
     }
-    let sevencloud_api = Arc::new(Mutex::new(sevencloud_api));
+    let sevencloud_api: SharedPosBackend = Arc::new(Mutex::new(sevencloud_api));
 
     // 创建服务 (注意顺序: 先创建依赖，再注入)
     let discount_code_service = DiscountCodeService::new(pool.clone(), sevencloud_api.clone());
     let auth_service = AuthService::new(
         pool.clone(),
         jwt_service.clone(),
-        twilio_service,
+        twilio_service.clone(),
         discount_code_service.clone(),
     );
-    let user_service = UserService::new(pool.clone());
     let order_service = OrderService::new(pool.clone());
-    let recharge_service = RechargeService::new(pool.clone(), stripe_service.clone());
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service.clone(),
+        config.lucky_draw.clone(),
+    );
+    let recharge_service = RechargeService::new(
+        pool.clone(),
+        stripe_gateway.clone(),
+        lucky_draw_service.clone(),
+        config.recharge.clone(),
+    );
     let membership_service = MembershipService::new(
         pool.clone(),
-        stripe_service.clone(),
+        stripe_gateway.clone(),
         discount_code_service.clone(),
+        config.cashback.clone(),
+        config.monthly_card.clone(),
     );
     let monthly_card_service = MonthlyCardService::new(
         pool.clone(),
-        stripe_service.clone(),
+        stripe_gateway.clone(),
         discount_code_service.clone(),
+        config.monthly_card.clone(),
+    );
+    let user_service = UserService::new(
+        pool.clone(),
+        twilio_service,
+        monthly_card_service.clone(),
+        lucky_draw_service.clone(),
     );
     let stripe_transaction_service = StripeTransactionService::new(pool.clone());
-    let sync_service = SyncService::new(pool.clone(), sevencloud_api.clone());
-    let birthday_reward_service = BirthdayRewardService::new(pool.clone());
-    let lucky_draw_service = LuckyDrawService::new(pool.clone(), discount_code_service.clone());
+    let audit_service = AuditService::new(pool.clone());
+    let notification_service = NotificationService::new(pool.clone());
+    let sync_service = SyncService::new(
+        pool.clone(),
+        sevencloud_api.clone(),
+        config.cashback.clone(),
+    );
+    let birthday_reward_service = BirthdayRewardService::new(
+        pool.clone(),
+        discount_code_service.clone(),
+        config.birthday_reward.clone(),
+    );
 
     // 启动后台定时任务
     tasks::spawn_all(
@@ -100,6 +162,10 @@ async fn main() -> std::io::Result<()> {
         membership_service.clone(),
         birthday_reward_service.clone(),
         monthly_card_service.clone(),
+        discount_code_service.clone(),
+        lucky_draw_service.clone(),
+        recharge_service.clone(),
+        config.tasks.clone(),
     );
 
     // 启动HTTP服务器
@@ -109,11 +175,20 @@ async fn main() -> std::io::Result<()> {
         config.server.port
     );
 
+    let rate_limit = RateLimitMiddleware::new(config.rate_limit.clone());
+    let trusted_proxies =
+        TrustedProxies::parse(&config.server.trusted_proxies).unwrap_or_else(|entry| {
+            log::warn!("Ignoring trusted proxies, invalid entry: {entry}");
+            TrustedProxies::default()
+        });
+
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
             .wrap(create_cors())
             .wrap(AuthMiddleware::new(jwt_service.clone()))
+            .wrap(rate_limit.clone())
+            .wrap(RequestIdMiddleware)
             .app_data(web::Data::new(auth_service.clone()))
             .app_data(web::Data::new(turnstile_service.clone()))
             .app_data(web::Data::new(user_service.clone()))
@@ -124,10 +199,16 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(monthly_card_service.clone()))
             .app_data(web::Data::new(birthday_reward_service.clone()))
             .app_data(web::Data::new(stripe_transaction_service.clone()))
+            .app_data(web::Data::new(audit_service.clone()))
+            .app_data(web::Data::new(notification_service.clone()))
             .app_data(web::Data::new(stripe_service.clone()))
             .app_data(web::Data::new(sync_service.clone()))
             .app_data(web::Data::new(lucky_draw_service.clone()))
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(trusted_proxies.clone()))
+            .app_data(web::Data::from(sevencloud_api.clone()))
             .configure(swagger_config)
+            .configure(handlers::health_config)
             .configure(handlers::webhook_config)
             .service(
                 web::scope("/api/v1")
@@ -138,12 +219,17 @@ async fn main() -> std::io::Result<()> {
                     .configure(handlers::recharge_config)
                     .configure(handlers::membership_config)
                     .configure(handlers::lucky_draw_config)
+                    .configure(handlers::admin_config)
                     .configure(|cfg| {
                         handlers::recharge::monthly_card_config(cfg);
                     })
                     .route(
                         "/payments/confirm",
                         web::post().to(handlers::recharge::confirm_unified),
+                    )
+                    .route(
+                        "/payments/transactions",
+                        web::get().to(handlers::recharge::list_transactions),
                     ),
             )
     })
@@ -1,6 +1,8 @@
 use crate::error::AppError;
-use crate::utils::JwtService;
+use crate::utils::{Claims, JwtService};
+use actix_web::body::MessageBody;
 use actix_web::http::Method;
+use actix_web::middleware::Next;
 use actix_web::{
     Error, HttpMessage,
     dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
@@ -13,20 +15,33 @@ struct PublicPaths {
     exact_paths: Vec<&'static str>,
     prefix_paths: Vec<&'static str>,
     excluded_paths: Vec<&'static str>,
+    optional_auth_paths: Vec<&'static str>,
 }
 
 impl PublicPaths {
     fn new() -> Self {
         Self {
             // 完全匹配的公开路径
-            exact_paths: vec!["/swagger-ui", "/swagger-ui/", "/api-docs/openapi.json"],
+            exact_paths: vec![
+                "/swagger-ui",
+                "/swagger-ui/",
+                "/api-docs/openapi.json",
+                "/healthz",
+                "/readyz",
+            ],
             // 前缀匹配的公开路径
             prefix_paths: vec!["/swagger-ui/", "/api-docs/", "/api/v1/auth/", "/webhook/"],
             // 需要排除的路径（即使在公开前缀下也需要认证）
             excluded_paths: vec!["/api/v1/auth/refresh"],
+            // 可选认证的路径：无 token 也放行，携带有效 token 时注入用户ID
+            optional_auth_paths: vec!["/api/v1/membership/benefits"],
         }
     }
 
+    fn is_optional_auth_path(&self, path: &str) -> bool {
+        self.optional_auth_paths.contains(&path)
+    }
+
     fn is_public_path(&self, path: &str) -> bool {
         // 首先检查是否在排除列表中
         if self
@@ -128,12 +143,22 @@ where
 
         let jwt_service = self.jwt_service.clone();
 
+        if self.public_paths.is_optional_auth_path(path) {
+            if let Some(claims) = token.and_then(|t| jwt_service.verify_access_token(t).ok()) {
+                req.extensions_mut()
+                    .insert(claims.sub.parse::<i64>().unwrap_or(0));
+            }
+            let fut = self.service.call(req);
+            return Box::pin(fut);
+        }
+
         if let Some(token) = token {
             match jwt_service.verify_access_token(token) {
                 Ok(claims) => {
-                    // 将用户ID添加到请求扩展中
+                    // 将用户ID与令牌声明添加到请求扩展中（角色校验见 `require_admin`）
                     req.extensions_mut()
                         .insert(claims.sub.parse::<i64>().unwrap_or(0));
+                    req.extensions_mut().insert(claims);
                     let fut = self.service.call(req);
                     Box::pin(fut)
                 }
@@ -153,3 +178,59 @@ where
 pub fn get_current_user_id(req: &ServiceRequest) -> Option<i64> {
     req.extensions().get::<i64>().copied()
 }
+
+/// 管理端 scope 的角色校验，通过 `middleware::from_fn` 挂在 `/admin` scope 上。
+/// 按路由匹配结果生效，不依赖原始路径前缀，百分号编码的路径也无法绕过
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let is_admin = req
+        .extensions()
+        .get::<Claims>()
+        .is_some_and(JwtService::is_admin);
+    if !is_admin {
+        return Err(AppError::Forbidden.into());
+    }
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::UserRole;
+    use crate::handlers::admin_config;
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test, web};
+
+    #[actix_web::test]
+    async fn test_admin_scope_rejects_non_admin_on_encoded_path() {
+        let jwt = JwtService::new("test-secret", 7200, 2_592_000);
+        let app = test::init_service(
+            App::new()
+                .wrap(AuthMiddleware::new(jwt.clone()))
+                .service(web::scope("/api/v1").configure(admin_config)),
+        )
+        .await;
+
+        let token = jwt
+            .generate_access_token(1, "2345678901", &UserRole::User, 7200)
+            .unwrap();
+        for uri in [
+            "/api/v1/admin/users/search?q=1",
+            "/api/v1/%61dmin/users/search?q=1",
+            "/api/v1/%61%64%6d%69%6e/lucky-draw/stats",
+        ] {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request();
+            let err = test::try_call_service(&app, req).await.unwrap_err();
+            assert_eq!(
+                err.error_response().status(),
+                StatusCode::FORBIDDEN,
+                "{uri}"
+            );
+        }
+    }
+}
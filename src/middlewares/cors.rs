@@ -9,6 +9,8 @@ pub fn create_cors() -> Cors {
         .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
         // 本地开发放宽，防止前端自定义 Header 导致预检失败
         .allow_any_header()
+        // 允许前端读取请求 ID，便于反馈问题时定位日志
+        .expose_headers(vec!["X-Request-Id"])
         // 如果前端使用 Cookie（如刷新令牌）、或需要携带凭据，需开启
         .supports_credentials()
         .max_age(3600)
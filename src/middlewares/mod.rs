@@ -1,5 +1,9 @@
 pub mod auth;
 pub mod cors;
+pub mod rate_limit;
+pub mod request_id;
 
 pub use auth::*;
 pub use cors::*;
+pub use rate_limit::*;
+pub use request_id::*;
@@ -0,0 +1,180 @@
+use crate::config::RateLimitConfig;
+use crate::error::AppError;
+use crate::utils::client_ip;
+use actix_web::http::Method;
+use actix_web::{
+    Error,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+};
+use futures_util::future::LocalBoxFuture;
+use std::collections::HashMap;
+use std::future::{Ready, ready};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// 桶数量超过该值时清理已回满（长时间未访问）的桶
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// 限流分组：登录/注册/发送验证码等认证接口单独计数
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum RouteGroup {
+    Auth,
+    Api,
+}
+
+impl RouteGroup {
+    /// webhook、文档等路径不限流
+    fn from_path(path: &str) -> Option<Self> {
+        if path.starts_with("/api/v1/auth/") {
+            Some(RouteGroup::Auth)
+        } else if path.starts_with("/api/v1/") {
+            Some(RouteGroup::Api)
+        } else {
+            None
+        }
+    }
+}
+
+/// 令牌桶：容量为每分钟请求数，按秒匀速回填
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    /// 尝试消耗一个令牌；不足时返回需等待的秒数
+    fn try_take(&mut self, per_minute: u32, now: Instant) -> Result<(), u64> {
+        let capacity = per_minute as f64;
+        let rate = capacity / 60.0;
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) / rate).ceil().max(1.0) as u64)
+        }
+    }
+
+    fn is_full(&self, per_minute: u32, now: Instant) -> bool {
+        let rate = per_minute as f64 / 60.0;
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens + elapsed * rate >= per_minute as f64
+    }
+}
+
+type Buckets = Arc<Mutex<HashMap<(String, RouteGroup), Bucket>>>;
+
+/// 按 (客户端 IP, 路由分组) 限流，超限返回 429 并附带 Retry-After。
+/// 在 HttpServer 闭包外创建后 clone 给各 worker，令牌桶在 worker 间共享
+#[derive(Clone)]
+pub struct RateLimitMiddleware {
+    config: RateLimitConfig,
+    buckets: Buckets,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddlewareService {
+            service,
+            config: self.config.clone(),
+            buckets: self.buckets.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddlewareService<S> {
+    service: S,
+    config: RateLimitConfig,
+    buckets: Buckets,
+}
+
+impl<S> RateLimitMiddlewareService<S> {
+    fn per_minute(&self, group: RouteGroup) -> u32 {
+        match group {
+            RouteGroup::Auth => self.config.auth_per_minute,
+            RouteGroup::Api => self.config.api_per_minute,
+        }
+    }
+
+    /// 消耗一个令牌；超限时返回 Retry-After 秒数
+    fn check(&self, ip: String, group: RouteGroup) -> Result<(), u64> {
+        let per_minute = self.per_minute(group);
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > MAX_TRACKED_BUCKETS {
+            let config = &self.config;
+            buckets.retain(|(_, g), b| {
+                let limit = match g {
+                    RouteGroup::Auth => config.auth_per_minute,
+                    RouteGroup::Api => config.api_per_minute,
+                };
+                !b.is_full(limit, now)
+            });
+        }
+        buckets
+            .entry((ip, group))
+            .or_insert_with(|| Bucket {
+                tokens: per_minute as f64,
+                updated_at: now,
+            })
+            .try_take(per_minute, now)
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let group = RouteGroup::from_path(req.path());
+        if !self.config.enabled || req.method() == Method::OPTIONS || group.is_none() {
+            let fut = self.service.call(req);
+            return Box::pin(fut);
+        }
+
+        let ip = client_ip(req.request()).unwrap_or_default();
+        if let Some(group) = group
+            && let Err(retry_after) = self.check(ip.clone(), group)
+        {
+            log::warn!("Rate limit exceeded for {ip} on {}", req.path());
+            let error = AppError::RateLimited { retry_after };
+            return Box::pin(async move { Err(error.into()) });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(fut)
+    }
+}
@@ -0,0 +1,149 @@
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{
+    Error, HttpMessage,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{Ready, ready};
+
+/// 请求 ID 头
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 客户端传入的请求 ID 最大长度，超出或含非法字符时重新生成
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 存放在请求 extensions 中的请求 ID
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// 当前请求的 ID（仅在请求处理过程中可用），供错误响应与日志使用
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+fn sanitize(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty()
+        || value.len() > MAX_REQUEST_ID_LEN
+        || !value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+/// 读取请求头 X-Request-Id（缺失时生成 UUID），写入 extensions 并在响应头中回传
+pub struct RequestIdMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddlewareService { service }))
+    }
+}
+
+pub struct RequestIdMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(sanitize)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        // 同步部分（内层中间件的前置检查）与后续 future 都在请求 ID 作用域内执行
+        let fut = CURRENT_REQUEST_ID.sync_scope(request_id.clone(), || self.service.call(req));
+        Box::pin(CURRENT_REQUEST_ID.scope(request_id.clone(), async move {
+            let header = HeaderValue::from_str(&request_id).ok();
+            match fut.await {
+                Ok(mut res) => {
+                    if let Some(value) = header {
+                        res.headers_mut()
+                            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Ok(res)
+                }
+                // 内层中间件返回的错误在此处渲染，保证错误体与响应头中带上请求 ID
+                Err(err) => {
+                    let mut response = err.error_response();
+                    if let Some(value) = header {
+                        response
+                            .headers_mut()
+                            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Err(InternalError::from_response(err, response).into())
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_request_id() {
+        assert_eq!(sanitize(" abc-123 "), Some("abc-123".to_string()));
+        assert_eq!(sanitize(""), None);
+        assert_eq!(sanitize("bad id"), None);
+        assert_eq!(sanitize(&"a".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_in_header_and_error_body() {
+        use crate::error::AppError;
+        use actix_web::{App, HttpResponse, test, web};
+
+        let app =
+            test::init_service(App::new().wrap(RequestIdMiddleware).route(
+                "/fail",
+                web::get().to(|| async {
+                    Err::<HttpResponse, _>(AppError::NotFound("missing".to_string()))
+                }),
+            ))
+            .await;
+
+        let req = test::TestRequest::get()
+            .uri("/fail")
+            .insert_header((REQUEST_ID_HEADER, "req-42"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "req-42");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"]["request_id"], "req-42");
+    }
+}
@@ -89,8 +89,12 @@ pub struct PaginatedOrderResponse {
     pub data: Vec<OrderResponse>,
     pub page: i64,
     pub page_size: i64,
-    pub total: i64,
-    pub total_pages: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
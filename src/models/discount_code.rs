@@ -1,5 +1,6 @@
 use crate::entities::CodeType;
 use crate::entities::discount_code_entity;
+use crate::entities::stamp_redemption_tier_entity;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -20,7 +21,7 @@ pub struct DiscountCodeQuery {
     pub page: Option<u32>,
     pub per_page: Option<u32>,
     pub status: Option<String>,    // available/used/expired
-    pub code_type: Option<String>, // shareholder_reward/super_shareholder_reward/sweets_credits_reward/free_topping
+    pub code_type: Option<String>, // shareholder_reward/super_shareholder_reward/sweets_credits_reward/free_topping/monthly_card_daily/birthday_reward
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -48,6 +49,52 @@ pub struct RedeemBalanceDiscountCodeResponse {
     pub balance_used: i64,
     pub remaining_balance: i64,
 }
+/// 收银核销前的优惠码校验结果（不会标记为已使用）
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DiscountCodeValidation {
+    pub code: String,
+    pub valid: bool,
+    /// 不可用原因：not_found / not_active / already_used / expired
+    pub reason: Option<String>,
+    pub discount_amount: Option<i64>,
+    pub code_type: Option<CodeType>,
+    pub is_used: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 持有人会员号
+    pub owner_member_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransferDiscountCodeRequest {
+    pub code: String,
+    /// 受赠人会员号
+    pub to_member_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransferDiscountCodeResponse {
+    pub discount_code: DiscountCodeResponse,
+    pub to_member_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkGenerateDiscountCodesRequest {
+    pub count: u32,
+    pub discount_amount: i64, // 美分
+    pub code_type: CodeType,
+    pub expire_months: u32, // 1-3
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkGenerateDiscountCodesResponse {
+    pub codes: Vec<String>,
+    /// 七云注册失败而被跳过的数量
+    pub failed: u32,
+    /// 失败比例超过阈值而提前停止，`codes` 只包含停止前已生成的码
+    pub aborted: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
 // Convert from entity Model to API response
 impl From<discount_code_entity::Model> for DiscountCodeResponse {
     fn from(m: discount_code_entity::Model) -> Self {
@@ -62,3 +109,23 @@ impl From<discount_code_entity::Model> for DiscountCodeResponse {
         }
     }
 }
+
+/// 印花兑换档位
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StampRedemptionTierResponse {
+    /// 优惠码面额（美分）
+    pub value_cents: i64,
+    /// 所需 stamps
+    pub stamps_required: i64,
+    pub active: bool,
+}
+
+impl From<stamp_redemption_tier_entity::Model> for StampRedemptionTierResponse {
+    fn from(m: stamp_redemption_tier_entity::Model) -> Self {
+        Self {
+            value_cents: m.value_cents,
+            stamps_required: m.stamps_required,
+            active: m.active,
+        }
+    }
+}
@@ -72,6 +72,70 @@ impl From<prize_entity::Model> for LuckyDrawPrizeResponse {
     }
 }
 
+/// 管理端创建/更新奖品请求（id 为空时创建）
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct UpsertLuckyDrawPrizeRequest {
+    /// 奖品ID（为空表示新建）
+    pub id: Option<i64>,
+    /// 英文名称 (唯一)，仅支持发奖逻辑中的名称：Free Topping Coupon / Free Original Ice Cream Coupon /
+    /// Half Price Ice Cream Coupon / Membership Monthly Card / Thank You
+    pub name_en: String,
+    /// 面值 (美分)
+    pub value_cents: i64,
+    /// 概率 (basis points)；所有启用奖品之和不得超过 10000
+    pub probability_bp: i32,
+    /// 总库存 (None = 无限)
+    pub stock_limit: Option<i64>,
+    /// 是否启用
+    pub is_active: bool,
+}
+
+/// 管理端补充库存请求
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct RefillLuckyDrawPrizeRequest {
+    /// 补充数量（同时增加总库存与剩余库存）
+    pub amount: i64,
+}
+
+/// 管理端奖品分布统计查询参数（时间区间可选）
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct LuckyDrawStatsQuery {
+    /// 起始时间（含）
+    pub from: Option<DateTime<Utc>>,
+    /// 截止时间（不含）
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// 单个奖品的实际分布统计
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LuckyDrawPrizeStat {
+    /// 奖品ID
+    pub prize_id: i64,
+    /// 英文名称（奖品已删除时为空）
+    pub name_en: Option<String>,
+    /// 抽中次数
+    pub count: i64,
+    /// 抽中面值合计(美分)
+    pub total_value_cents: i64,
+    /// 配置概率 (basis points)
+    pub expected_probability_bp: Option<i32>,
+    /// 实际概率 (basis points) = count / total_spins * 10000
+    pub actual_probability_bp: f64,
+    /// 总库存 (None = 无限)
+    pub stock_limit: Option<i64>,
+    /// 剩余库存 (None = 无限)
+    pub stock_remaining: Option<i64>,
+}
+
+/// 奖品分布统计响应
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LuckyDrawStatsResponse {
+    /// 区间内总抽奖次数
+    pub total_spins: i64,
+    /// 各奖品统计（含区间内未被抽中的奖品）
+    pub prizes: Vec<LuckyDrawPrizeStat>,
+}
+
 /// 抽奖后返回给用户的奖品（隐藏不必要字段）
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct LuckyDrawWonPrize {
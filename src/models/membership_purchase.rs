@@ -8,6 +8,13 @@ pub struct CreateMembershipIntentRequest {
     pub target_member_type: MemberType,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateGiftMembershipIntentRequest {
+    /// 受赠人会员号
+    pub recipient_member_code: String,
+    pub target_member_type: MemberType,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateMembershipIntentResponse {
     pub payment_intent_id: String,
@@ -18,6 +25,34 @@ pub struct CreateMembershipIntentResponse {
     pub target_member_type: MemberType,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleDowngradeRequest {
+    /// 到期后生效的等级，必须低于当前等级；需已开启自动续费
+    pub target_member_type: MemberType,
+}
+
+/// 预约降级结果：降级在到期自动续费时按新等级扣款生效（降到 Fan 则不再续费），
+/// 关闭自动续费会取消预约
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleDowngradeResponse {
+    pub member_type: MemberType,
+    pub pending_member_type: MemberType,
+    /// 生效时间，即当前会员到期（自动续费）时间
+    pub effective_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AutoRenewRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AutoRenewResponse {
+    pub auto_renew: bool,
+    /// 是否已有可用于离线扣款的 Stripe Customer
+    pub has_saved_customer: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ConfirmMembershipRequest {
     pub payment_intent_id: String,
@@ -35,6 +70,9 @@ pub struct MembershipPurchaseRecordResponse {
     pub amount: i64,
     pub target_member_type: MemberType,
     pub status: MembershipPurchaseStatus,
+    /// 赠送会员的受赠人
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient_user_id: Option<i64>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -45,7 +83,33 @@ impl From<mp::Model> for MembershipPurchaseRecordResponse {
             amount: m.amount,
             target_member_type: m.target_member_type,
             status: m.status,
+            recipient_user_id: m.recipient_user_id,
             created_at: m.created_at.unwrap_or_else(Utc::now),
         }
     }
 }
+
+/// 会员等级权益摘要（数值与返利/升级奖励/月卡发券逻辑共用同一来源）
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MembershipBenefits {
+    pub member_type: MemberType,
+    /// 订单返利比例（基点，500 = 5%）
+    pub cashback_bps: i64,
+    /// 升级奖励优惠码数量
+    pub upgrade_reward_count: u32,
+    /// 单张升级奖励优惠码面值（美分）
+    pub upgrade_reward_value_cents: i64,
+    /// 月卡每日发放优惠码面值（美分）
+    pub monthly_card_daily_coupon_cents: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MembershipBenefitsResponse {
+    pub tiers: Vec<MembershipBenefits>,
+    /// 当前用户会员等级（仅登录时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_member_type: Option<MemberType>,
+    /// 当前用户会员到期时间（仅登录时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub membership_expires_at: Option<DateTime<Utc>>,
+}
@@ -3,10 +3,13 @@ pub mod discount_code;
 pub mod lucky_draw;
 pub mod membership_purchase;
 pub mod monthly_card;
+pub mod notification;
 pub mod order;
 pub mod pagination;
 pub mod recharge_record;
+pub mod stripe_transaction;
 pub mod sweet_cash_transaction;
+pub mod sync;
 pub mod user;
 pub mod wallet;
 
@@ -15,9 +18,12 @@ pub use discount_code::*;
 pub use lucky_draw::*;
 pub use membership_purchase::*;
 pub use monthly_card::*;
+pub use notification::*;
 pub use order::*;
 pub use pagination::*;
 pub use recharge_record::*;
+pub use stripe_transaction::*;
 pub use sweet_cash_transaction::*;
+pub use sync::*;
 pub use user::*;
 pub use wallet::*;
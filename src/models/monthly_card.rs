@@ -31,6 +31,8 @@ pub struct MonthlyCardRecordResponse {
     pub starts_at: Option<DateTime<Utc>>,
     pub ends_at: Option<DateTime<Utc>>,
     pub last_coupon_granted_on: Option<NaiveDate>,
+    /// 订阅已设置为当期结束后取消
+    pub cancel_at_period_end: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -43,6 +45,7 @@ impl From<mc::Model> for MonthlyCardRecordResponse {
             starts_at: m.starts_at,
             ends_at: m.ends_at,
             last_coupon_granted_on: m.last_coupon_granted_on,
+            cancel_at_period_end: m.cancel_at_period_end,
             created_at: m.created_at.unwrap_or_else(Utc::now),
         }
     }
@@ -52,3 +55,13 @@ impl From<mc::Model> for MonthlyCardRecordResponse {
 pub struct ConfirmMonthlyCardResponse {
     pub monthly_card: MonthlyCardRecordResponse,
 }
+
+/// 当前生效的月卡
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CurrentMonthlyCardResponse {
+    pub monthly_card: MonthlyCardRecordResponse,
+    /// 剩余天数（按 `ends_at` 向上取整）
+    pub days_remaining: i64,
+    /// 下一次发放每日优惠码的日期 (UTC)；月卡在此之前到期时为空
+    pub next_coupon_date: Option<NaiveDate>,
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// 通知类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// 优惠码已过期
+    CouponExpired,
+    /// 会员自动续费成功
+    MembershipRenewed,
+    /// 会员自动续费失败
+    MembershipRenewalFailed,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 3] = [
+        NotificationKind::CouponExpired,
+        NotificationKind::MembershipRenewed,
+        NotificationKind::MembershipRenewalFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::CouponExpired => "coupon_expired",
+            NotificationKind::MembershipRenewed => "membership_renewed",
+            NotificationKind::MembershipRenewalFailed => "membership_renewal_failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationResponse {
+    pub id: i64,
+    /// 通知类型，见 `NotificationKind`
+    pub kind: String,
+    /// 通知内容（随类型不同）
+    pub payload: Option<serde_json::Value>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 通知偏好：各类型是否开启（未设置的类型默认开启）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationPrefs {
+    pub prefs: BTreeMap<NotificationKind, bool>,
+}
@@ -1,5 +1,5 @@
-use crate::entities::order_entity;
-use chrono::{DateTime, Utc};
+use crate::entities::{order_entity, sweet_cash_transaction_entity};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -17,13 +17,50 @@ pub struct OrderResponse {
     pub sweet_cash_earned: i64,
 }
 
+/// 订单关联的返现流水（sweet_cash_transactions 中 earn 类型且 related_order_id 为本订单）
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrderCashbackTransaction {
+    pub id: i64,
+    /// 返现金额 (美分)
+    pub amount: i64,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<sweet_cash_transaction_entity::Model> for OrderCashbackTransaction {
+    fn from(m: sweet_cash_transaction_entity::Model) -> Self {
+        Self {
+            id: m.id,
+            amount: m.amount,
+            description: m.description,
+            created_at: m.created_at.unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+/// 订单详情：订单信息 + 返现流水
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrderDetailResponse {
+    #[serde(flatten)]
+    pub order: OrderResponse,
+    pub cashback_transactions: Vec<OrderCashbackTransaction>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
 pub struct OrderQuery {
     pub page: Option<u32>,
     pub per_page: Option<u32>,
     pub status: Option<i32>,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    /// 起始日期 (含)，按 external_created_at 过滤，格式 YYYY-MM-DD
+    pub from: Option<NaiveDate>,
+    /// 结束日期 (含)，按 external_created_at 过滤，格式 YYYY-MM-DD
+    pub to: Option<NaiveDate>,
+    /// 商品编号
+    pub product_no: Option<String>,
+    /// 游标分页：上一页的 next_cursor，按 (下单时间, id) 倒序从该订单之后继续；提供时忽略 page
+    pub after_id: Option<i64>,
 }
 
 impl From<order_entity::Model> for OrderResponse {
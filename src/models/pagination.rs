@@ -7,6 +7,8 @@ use utoipa::ToSchema;
 pub struct PaginationParams {
     pub page: Option<i64>,
     pub page_size: Option<i64>,
+    /// 游标分页：返回 id 小于该值的记录（按 id 倒序）；提供时忽略 page
+    pub after_id: Option<i64>,
 }
 
 impl Default for PaginationParams {
@@ -14,6 +16,7 @@ impl Default for PaginationParams {
         Self {
             page: Some(1),
             page_size: Some(20),
+            after_id: None,
         }
     }
 }
@@ -23,9 +26,16 @@ impl PaginationParams {
         Self {
             page: page.map(|p| p as i64),
             page_size: per_page.map(|p| p as i64),
+            after_id: None,
         }
     }
 
+    /// 设置游标（存在时使用游标分页）
+    pub fn with_after_id(mut self, after_id: Option<i64>) -> Self {
+        self.after_id = after_id;
+        self
+    }
+
     pub fn get_offset(&self) -> i64 {
         let page = self.page.unwrap_or(1);
         let page_size = self.page_size.unwrap_or(20);
@@ -42,8 +52,14 @@ pub struct PaginatedResponse<T> {
     pub data: Vec<T>,
     pub page: i64,
     pub page_size: i64,
-    pub total: i64,
-    pub total_pages: i64,
+    /// 总数；游标分页时不统计，为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<i64>,
+    /// 下一页的 after_id（满页时返回），没有更多数据时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
 }
 
 impl<T> PaginatedResponse<T> {
@@ -53,8 +69,26 @@ impl<T> PaginatedResponse<T> {
             data,
             page,
             page_size,
-            total,
-            total_pages,
+            total: Some(total),
+            total_pages: Some(total_pages),
+            next_cursor: None,
+        }
+    }
+
+    /// 游标分页结果，不带总数与页码
+    pub fn by_cursor(data: Vec<T>, page_size: i64) -> Self {
+        Self {
+            data,
+            page: 1,
+            page_size,
+            total: None,
+            total_pages: None,
+            next_cursor: None,
         }
     }
+
+    pub fn with_next_cursor(mut self, next_cursor: Option<i64>) -> Self {
+        self.next_cursor = next_cursor;
+        self
+    }
 }
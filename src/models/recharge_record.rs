@@ -1,4 +1,4 @@
-use crate::entities::{RechargeStatus, recharge_record_entity};
+use crate::entities::{RechargeStatus, recharge_record_entity, recharge_tier_entity};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -6,6 +6,15 @@ use utoipa::ToSchema;
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreatePaymentIntentRequest {
     pub amount: i64,
+    /// 自定义金额充值（$1 ~ $500，按比例赠送）；金额命中固定档位时仍按档位赠送
+    #[serde(default)]
+    pub custom: bool,
+    /// 客户端生成的请求ID，重试时保持不变；同一用户、金额与货币下重复提交返回已有的充值记录与 Checkout
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// 支付货币：usd / eur / gbp，默认 usd；金额与档位均按该货币的最小单位计
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -17,6 +26,9 @@ pub struct CreatePaymentIntentResponse {
     pub amount: i64,
     pub bonus_amount: i64,
     pub total_amount: i64,
+    pub currency: String,
+    /// 折算为 USD 后入账余额的美分数（含赠送），USD 充值与 total_amount 相同
+    pub credited_amount: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -30,13 +42,36 @@ pub struct ConfirmRechargeResponse {
     pub new_balance: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RefundRechargeRequest {
+    /// 部分退款金额（美分），不传则全额退款
+    #[serde(default)]
+    pub amount_cents: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RefundRechargeResponse {
+    pub recharge_record: RechargeRecordResponse,
+    pub refund_id: String,
+    /// Stripe 退款金额（美分）
+    pub refunded_amount: i64,
+    /// 实际扣回的余额（美分），部分退款且余额已消费时可能小于应扣金额
+    pub balance_debited: i64,
+    pub new_balance: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct RechargeRecordResponse {
     pub id: i64,
     pub amount: i64,
     pub bonus_amount: i64,
     pub total_amount: i64,
+    pub currency: String,
+    /// 折算为 USD 后入账余额的美分数（含赠送）
+    pub credited_amount: i64,
     pub status: RechargeStatus,
+    /// 累计已退款金额（美分）
+    pub refunded_amount: i64,
     pub created_at: DateTime<Utc>,
 }
 
@@ -54,8 +89,33 @@ impl From<recharge_record_entity::Model> for RechargeRecordResponse {
             amount: m.amount,
             bonus_amount: m.bonus_amount,
             total_amount: m.total_amount,
+            credited_amount: m.credited_amount(),
+            currency: m.currency,
             status: m.status,
+            refunded_amount: m.refunded_amount,
             created_at: m.created_at.unwrap_or_else(Utc::now),
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RechargeTierResponse {
+    /// 充值金额（最小货币单位）
+    pub amount_cents: i64,
+    /// 赠送金额（最小货币单位）
+    pub bonus_cents: i64,
+    /// 档位适用的货币：usd / eur / gbp
+    pub currency: String,
+    pub active: bool,
+}
+
+impl From<recharge_tier_entity::Model> for RechargeTierResponse {
+    fn from(m: recharge_tier_entity::Model) -> Self {
+        Self {
+            amount_cents: m.amount_cents,
+            bonus_cents: m.bonus_cents,
+            currency: m.currency,
+            active: m.active,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::entities::{StripeTransactionCategory, stripe_transaction_entity};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 支付交易记录查询参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct StripeTransactionQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    /// 业务类别：recharge / membership / monthly_card
+    pub category: Option<StripeTransactionCategory>,
+}
+
+/// 充值、会员、月卡统一的支付交易记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StripeTransactionResponse {
+    pub id: i64,
+    pub category: StripeTransactionCategory,
+    pub payment_intent_id: Option<String>,
+    /// 退款记录的 Stripe Refund ID
+    pub refund_id: Option<String>,
+    /// 金额（最小货币单位）
+    pub amount: Option<i64>,
+    pub currency: Option<String>,
+    pub status: Option<String>,
+    pub description: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<stripe_transaction_entity::Model> for StripeTransactionResponse {
+    fn from(m: stripe_transaction_entity::Model) -> Self {
+        Self {
+            id: m.id,
+            category: m.category,
+            payment_intent_id: m.payment_intent_id,
+            refund_id: m.refund_id,
+            amount: m.amount,
+            currency: m.currency,
+            status: m.status,
+            description: m.description,
+            created_at: m.created_at,
+        }
+    }
+}
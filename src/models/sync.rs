@@ -0,0 +1,96 @@
+use crate::entities::{sync_failure_entity, sync_run_entity};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 管理端手动同步订单请求；不传日期时按游标增量同步
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ManualSyncOrdersRequest {
+    /// 起始日期（含）
+    pub start_date: Option<NaiveDate>,
+    /// 结束日期（含），缺省为今天
+    pub end_date: Option<NaiveDate>,
+}
+
+/// 手动同步结果
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ManualSyncResponse {
+    /// 成功处理的记录数
+    pub processed: usize,
+}
+
+/// 同步运行记录查询参数
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct SyncRunQuery {
+    /// 同步类型：orders / discount_codes
+    pub kind: Option<String>,
+    /// 返回条数，默认 20，最大 100
+    pub limit: Option<u64>,
+}
+
+/// 同步运行记录
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncRunResponse {
+    pub id: i64,
+    /// 同步类型：orders / discount_codes
+    pub kind: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// 成功处理的记录数
+    pub processed: i64,
+    /// 处理失败的记录数
+    pub errors: i64,
+    /// running / succeeded / partial / failed
+    pub status: String,
+    /// 整体失败时的错误信息
+    pub error_message: Option<String>,
+}
+
+impl From<sync_run_entity::Model> for SyncRunResponse {
+    fn from(m: sync_run_entity::Model) -> Self {
+        Self {
+            id: m.id,
+            kind: m.kind,
+            started_at: m.started_at,
+            finished_at: m.finished_at,
+            processed: m.processed,
+            errors: m.errors,
+            status: m.status,
+            error_message: m.error_message,
+        }
+    }
+}
+
+/// 订单同步失败记录查询参数
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct SyncFailureQuery {
+    /// 仅返回已标记待人工处理的记录
+    pub flagged: Option<bool>,
+}
+
+/// 订单同步失败记录
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncFailureResponse {
+    /// 七云订单 ID
+    pub order_id: i64,
+    /// 连续失败次数
+    pub attempts: i32,
+    pub last_error: String,
+    /// 已达到重试上限，后续同步跳过，需人工处理
+    pub flagged: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<sync_failure_entity::Model> for SyncFailureResponse {
+    fn from(m: sync_failure_entity::Model) -> Self {
+        Self {
+            order_id: m.order_id,
+            attempts: m.attempts,
+            last_error: m.last_error,
+            flagged: m.flagged,
+            created_at: m.created_at,
+            updated_at: m.updated_at,
+        }
+    }
+}
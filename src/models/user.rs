@@ -1,6 +1,8 @@
-use crate::entities::MemberType;
 use crate::entities::user_entity;
-use chrono::{DateTime, Utc};
+use crate::entities::{CodeType, MemberType, UserRole};
+use crate::external::VerificationChannel;
+use crate::models::{CurrentMonthlyCardResponse, LuckyDrawChancesResponse};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -18,6 +20,10 @@ pub struct CreateUserRequest {
     pub birthday: String, // YYYY-MM-DD
     #[schema(example = "REF123")]
     pub referrer_code: Option<String>,
+    /// Turnstile token from client-side widget（启用 Turnstile 时必填）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "CF_TURNSTILE_TOKEN")]
+    pub cf_turnstile_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -27,6 +33,10 @@ pub struct LoginRequest {
     #[schema(example = "password123")]
     pub password: String,
     pub remember_me: Option<bool>,
+    /// Turnstile token from client-side widget（启用 Turnstile 时必填）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "CF_TURNSTILE_TOKEN")]
+    pub cf_turnstile_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -52,6 +62,9 @@ pub struct UserResponse {
     pub referral_code: Option<String>,
     pub total_referrals: i64,
     pub is_monthly_card: bool,
+    pub role: UserRole,
+    /// 最近一次成功登录时间
+    pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -61,6 +74,72 @@ pub struct UserStatistics {
     pub total_spent: i64,
     pub total_earned_stamps: i64,
     pub available_discount_codes: i64,
+    /// 距下一个可兑换奖励还差的 stamps，0 表示当前即可兑换
+    pub stamps_to_next_reward: i64,
+    /// 下一个可兑换奖励的面额（美分）
+    pub next_reward_value_cents: i64,
+}
+
+/// 当前用户的完整上下文，供 App 启动时一次获取
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MeResponse {
+    pub user: UserResponse,
+    pub statistics: UserStatistics,
+    /// 当前生效的月卡，没有时为 null
+    pub monthly_card: Option<CurrentMonthlyCardResponse>,
+    pub lucky_draw_chances: LuckyDrawChancesResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReferralTreeQuery {
+    /// 展开层级，默认 1，最大 3
+    pub depth: Option<u32>,
+}
+
+/// 推荐树节点
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReferralTreeNode {
+    pub id: i64,
+    pub member_code: String,
+    pub username: String,
+    pub member_type: MemberType,
+    pub created_at: DateTime<Utc>,
+    #[schema(no_recursion)]
+    pub children: Vec<ReferralTreeNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReferralTreeResponse {
+    pub depth: u32,
+    /// 各层下线用户总数
+    pub total_downstream: i64,
+    pub referrals: Vec<ReferralTreeNode>,
+}
+
+/// 管理端用户搜索参数
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserSearchQuery {
+    /// 用户名前缀（不区分大小写）或手机号末尾数字
+    pub q: String,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SpendingQuery {
+    /// 统计最近 N 个月（含当月），默认 12，最大 36
+    pub months: Option<u32>,
+}
+
+/// 单月消费汇总
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MonthlySpending {
+    /// 年月，格式 YYYY-MM (UTC)
+    #[schema(example = "2025-10")]
+    pub month: String,
+    /// 当月消费总额 (美分)
+    pub total_spent: i64,
+    pub order_count: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -79,6 +158,9 @@ pub struct SendCodeRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schema(example = "CF_TURNSTILE_TOKEN")]
     pub cf_turnstile_token: Option<String>,
+    /// 验证码渠道（sms / whatsapp / call），默认 sms
+    #[serde(default)]
+    pub channel: VerificationChannel,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -96,6 +178,14 @@ pub struct ResetPasswordRequest {
     pub new_password: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChangePhoneRequest {
+    #[schema(example = "+12345678901")]
+    pub new_phone: String,
+    #[schema(example = "123456")]
+    pub verification_code: String,
+}
+
 // Convert from entity Model to API response
 impl From<user_entity::Model> for UserResponse {
     fn from(m: user_entity::Model) -> Self {
@@ -113,7 +203,24 @@ impl From<user_entity::Model> for UserResponse {
             referral_code: m.referral_code,
             total_referrals: 0,
             is_monthly_card: false,
+            role: m.role,
+            last_login_at: m.last_login_at,
             created_at: m.created_at.unwrap_or_else(Utc::now),
         }
     }
 }
+
+/// 生日福利预览（按当前会员等级与配置计算）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BirthdayRewardPreview {
+    /// 福利金额（美分）
+    pub amount_cents: i64,
+    /// 以优惠码发放时的类型；为空表示直接计入余额
+    pub code_type: Option<CodeType>,
+    /// 优惠码有效期（月）
+    pub expire_months: Option<u32>,
+    /// 下一次生日日期
+    pub next_birthday: NaiveDate,
+    /// 今年是否已领取
+    pub granted_this_year: bool,
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -11,6 +11,10 @@ pub enum WalletTransactionKind {
     BirthdayReward,
     /// 将余额兑换成优惠码
     Redeem,
+    /// 转赠给其他用户的余额
+    TransferOut,
+    /// 其他用户转赠的余额
+    TransferIn,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -25,3 +29,36 @@ pub struct WalletTransactionResponse {
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
 }
+
+/// 钱包流水查询参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct WalletTransactionQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    /// 游标分页：上一页的 next_cursor，返回 id 小于该值的流水；提供时忽略 page
+    pub after_id: Option<i64>,
+    /// 流水类型：earn / spend / redeem（spend 与 redeem 等价）
+    pub kind: Option<String>,
+    /// 起始日期 (含)，格式 YYYY-MM-DD
+    pub from: Option<NaiveDate>,
+    /// 结束日期 (含)，格式 YYYY-MM-DD
+    pub to: Option<NaiveDate>,
+}
+
+/// 余额转赠请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WalletTransferRequest {
+    /// 受赠人会员号
+    pub to_member_code: String,
+    /// 转赠金额（美分）
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WalletTransferResponse {
+    pub to_member_code: String,
+    /// 转赠金额（美分）
+    pub amount: i64,
+    /// 转赠后自己的余额（美分）
+    pub balance_after: i64,
+}
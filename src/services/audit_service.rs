@@ -0,0 +1,54 @@
+use crate::entities::audit_log_entity as audit;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+use serde_json::Value;
+
+/// 一条审计记录
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// 操作人用户 ID；webhook/定时任务等系统操作为 None
+    pub actor_id: Option<i64>,
+    /// 动作，如 `recharge.credit`、`membership.upgrade`
+    pub action: &'static str,
+    /// 被操作的实体类型，如 `user`、`lucky_draw_prize`
+    pub entity: &'static str,
+    pub entity_id: Option<i64>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// 审计日志：余额入账/扣减、会员升级、优惠码发放及后台操作
+#[derive(Clone)]
+pub struct AuditService {
+    pool: DatabaseConnection,
+}
+
+impl AuditService {
+    pub fn new(pool: DatabaseConnection) -> Self {
+        Self { pool }
+    }
+
+    /// 异步写入审计记录（尽力而为：失败只记日志，不阻塞也不影响业务事务）。
+    /// 应在业务事务提交后调用，避免记录被回滚的操作
+    pub fn record(&self, entry: AuditEntry) {
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let action = entry.action;
+            let res = audit::ActiveModel {
+                actor_id: Set(entry.actor_id),
+                action: Set(entry.action.to_string()),
+                entity: Set(entry.entity.to_string()),
+                entity_id: Set(entry.entity_id),
+                before: Set(entry.before),
+                after: Set(entry.after),
+                created_at: Set(Some(Utc::now())),
+                ..Default::default()
+            }
+            .insert(&pool)
+            .await;
+            if let Err(e) = res {
+                log::error!("Failed to write audit log ({action}): {e:?}");
+            }
+        });
+    }
+}
@@ -1,16 +1,63 @@
+use crate::database::expiring_keys::{KeyClaim, claim_key, release_key};
 use crate::entities::user_entity as users;
-use crate::entities::{CodeType, MemberType, lucky_draw_chance_entity as chances};
+use crate::entities::{
+    CodeType, MemberType, UserRole, login_history_entity as login_history,
+    lucky_draw_chance_entity as chances,
+};
 use crate::error::{AppError, AppResult};
 use crate::external::*;
 use crate::models::*;
 use crate::services::DiscountCodeService;
 use crate::utils::*;
-use chrono::{Datelike, Utc};
+use chrono::{Datelike, Duration, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
-    PaginatorTrait, QueryFilter, Set,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, Statement,
 };
 
+/// 同一手机号重设密码的锁定时长（秒）：处理中或成功重设后的这段时间内拒绝再次重设
+const PASSWORD_RESET_LOCK_SECS: i64 = 60;
+
+/// 连续失败多少次触发一次锁定
+const MAX_FAILED_LOGIN_ATTEMPTS: i32 = 5;
+/// 每个用户保留的最近登录记录条数
+const LOGIN_HISTORY_KEEP: u64 = 20;
+/// User-Agent 最大保存长度
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// 根据累计失败次数计算锁定时长：第 1/2/3+ 次锁定分别为 1/5/15 分钟
+fn lockout_duration(attempts: i32) -> Option<Duration> {
+    if attempts < MAX_FAILED_LOGIN_ATTEMPTS || attempts % MAX_FAILED_LOGIN_ATTEMPTS != 0 {
+        return None;
+    }
+    let minutes = match attempts / MAX_FAILED_LOGIN_ATTEMPTS {
+        1 => 1,
+        2 => 5,
+        _ => 15,
+    };
+    Some(Duration::minutes(minutes))
+}
+
+/// 将单字段校验结果收集到字段错误列表，返回该字段是否通过
+fn collect_field_error(
+    errors: &mut Vec<(String, String)>,
+    field: &str,
+    result: AppResult<()>,
+) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(AppError::ValidationError(msg)) => {
+            errors.push((field.to_string(), msg));
+            false
+        }
+        Err(e) => {
+            errors.push((field.to_string(), e.to_string()));
+            false
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthService {
     pool: DatabaseConnection,
@@ -39,16 +86,24 @@ impl AuthService {
     /// # 参数
     ///
     /// * `phone`: 手机号
+    /// * `channel`: 下发渠道（短信 / WhatsApp / 语音）
     ///
     /// # 返回值
     ///
     /// 返回一个包含验证码有效期的响应
-    pub async fn send_verification_code(&self, phone: &str) -> AppResult<SendCodeResponse> {
-        // 验证手机号格式
+    pub async fn send_verification_code(
+        &self,
+        phone: &str,
+        channel: VerificationChannel,
+    ) -> AppResult<SendCodeResponse> {
+        // 规范化并验证手机号格式
+        let phone = &normalize_us_phone(phone);
         validate_us_phone(phone)?;
 
         // 依赖 Twilio Verify 自身的速率限制与风控，这里不再读写本地库
-        self.twilio_service.start_verification_sms(phone).await?;
+        self.twilio_service
+            .start_verification(phone, channel)
+            .await?;
 
         // Twilio Verify 默认验证码有效期 10 分钟
         Ok(SendCodeResponse { expires_in: 600 })
@@ -63,81 +118,108 @@ impl AuthService {
     /// # 返回值
     ///
     /// 返回一个包含用户信息的响应
-    pub async fn register(&self, request: CreateUserRequest) -> AppResult<AuthResponse> {
-        // 验证输入参数
-        validate_us_phone(&request.phone)?;
-        validate_password(&request.password)?;
+    pub async fn register(&self, mut request: CreateUserRequest) -> AppResult<AuthResponse> {
+        // 验证输入参数（手机号统一规范为 E.164 后再入库），一次性收集所有字段错误
+        request.phone = normalize_us_phone(&request.phone);
+        let mut errors: Vec<(String, String)> = Vec::new();
+        let phone_valid =
+            collect_field_error(&mut errors, "phone", validate_us_phone(&request.phone));
+        collect_field_error(
+            &mut errors,
+            "password",
+            validate_password(&request.password),
+        );
+
+        // 解析生日，生日不能是未来日期
+        let birthday = match chrono::NaiveDate::parse_from_str(&request.birthday, "%Y-%m-%d") {
+            Ok(d) if d > Utc::now().date_naive() => {
+                errors.push((
+                    "birthday".to_string(),
+                    "Birthday cannot be in the future".to_string(),
+                ));
+                None
+            }
+            Ok(d) => Some(d),
+            Err(_) => {
+                errors.push((
+                    "birthday".to_string(),
+                    "Invalid birthday format".to_string(),
+                ));
+                None
+            }
+        };
 
-        // 验证验证码（通过 Twilio Verify）
+        // 检查手机号是否已注册，并从手机号生成会员号（去掉+1前缀的十位数字）
+        let mut member_code = None;
+        if phone_valid {
+            let existing_user = users::Entity::find()
+                .filter(users::Column::Phone.eq(request.phone.clone()))
+                .one(&self.pool)
+                .await?;
+            if existing_user.is_some() {
+                errors.push((
+                    "phone".to_string(),
+                    "The mobile phone number is registered".to_string(),
+                ));
+            } else {
+                let code = extract_member_code_from_phone(&request.phone)?;
+                // 检查会员号是否已存在（防止重复注册）
+                let existing_member = users::Entity::find()
+                    .filter(users::Column::MemberCode.eq(code.clone()))
+                    .one(&self.pool)
+                    .await?;
+                if existing_member.is_some() {
+                    errors.push((
+                        "phone".to_string(),
+                        "The member code corresponding to this phone number already exists"
+                            .to_string(),
+                    ));
+                }
+                member_code = Some(code);
+            }
+        }
+
+        // 处理推荐人
+        let mut referrer_id = None;
+        if let Some(referrer_code) = &request.referrer_code {
+            let ref_row = users::Entity::find()
+                .filter(users::Column::ReferralCode.eq(referrer_code.clone()))
+                .one(&self.pool)
+                .await?;
+            match ref_row {
+                Some(row) => referrer_id = Some(row.id),
+                None => errors.push((
+                    "referrer_code".to_string(),
+                    "The referrer does not exist".to_string(),
+                )),
+            }
+        }
+
+        let (Some(birthday), Some(member_code)) = (birthday, member_code) else {
+            return Err(AppError::FieldValidation(errors));
+        };
+        if !errors.is_empty() {
+            return Err(AppError::FieldValidation(errors));
+        }
+
+        // 字段校验全部通过后再验证验证码（通过 Twilio Verify），避免消耗有效验证码
         let approved = self
             .twilio_service
             .check_verification_code(&request.phone, &request.verification_code)
             .await?;
         if !approved {
-            return Err(AppError::ValidationError(
+            return Err(AppError::FieldValidation(vec![(
+                "verification_code".to_string(),
                 "The verification code is incorrect or expired".to_string(),
-            ));
-        }
-
-        // 检查手机号是否已注册
-        let existing_user = users::Entity::find()
-            .filter(users::Column::Phone.eq(request.phone.clone()))
-            .one(&self.pool)
-            .await?;
-        if existing_user.is_some() {
-            return Err(AppError::ValidationError(
-                "The mobile phone number is registered".to_string(),
-            ));
-        }
-
-        // 解析生日
-        let birthday = chrono::NaiveDate::parse_from_str(&request.birthday, "%Y-%m-%d")
-            .map_err(|_| AppError::ValidationError("Invalid birthday format".to_string()))?;
-        // 验证生日不能是未来日期
-        if birthday > Utc::now().date_naive() {
-            return Err(AppError::ValidationError(
-                "Birthday cannot be in the future".to_string(),
-            ));
+            )]));
         }
         let bmm: i16 = birthday.month() as i16;
         let bdd: i16 = birthday.day() as i16;
-
-        // 从手机号生成会员号（去掉+1前缀的十位数字）
-        let member_code = extract_member_code_from_phone(&request.phone)?;
-
-        // 检查会员号是否已存在（防止重复注册）
-        let existing_member = users::Entity::find()
-            .filter(users::Column::MemberCode.eq(member_code.clone()))
-            .one(&self.pool)
-            .await?;
-        if existing_member.is_some() {
-            return Err(AppError::ValidationError(
-                "The member code corresponding to this phone number already exists".to_string(),
-            ));
-        }
+        let member_type = MemberType::Fan;
 
         // 密码哈希
         let password_hash = hash_password(&request.password)?;
 
-        // 处理推荐人
-        let (referrer_id, _, member_type) = if let Some(referrer_code) = &request.referrer_code {
-            let ref_row = users::Entity::find()
-                .filter(users::Column::ReferralCode.eq(referrer_code.clone()))
-                .one(&self.pool)
-                .await?;
-
-            if let Some(row) = ref_row {
-                let rid = row.id;
-                (Some(rid), (), MemberType::Fan)
-            } else {
-                return Err(AppError::ValidationError(
-                    "The referrer does not exist".to_string(),
-                ));
-            }
-        } else {
-            (None, (), MemberType::Fan)
-        };
-
         // 生成推荐码
         let referral_code = generate_unique_referral_code(&self.pool).await?;
 
@@ -211,12 +293,20 @@ impl AuthService {
         }
 
         // 生成JWT令牌
-        let access_token = self
-            .jwt_service
-            .generate_access_token(user_id, &member_code)?;
-        let refresh_token = self
-            .jwt_service
-            .generate_refresh_token(user_id, &member_code)?;
+        let lifetimes = self.jwt_service.lifetimes_for(&new_user.member_type);
+        let access_token = self.jwt_service.generate_access_token(
+            user_id,
+            &member_code,
+            &UserRole::User,
+            lifetimes.access,
+        )?;
+        let refresh_token = self.jwt_service.generate_refresh_token(
+            user_id,
+            &member_code,
+            &UserRole::User,
+            lifetimes.refresh,
+            new_user.token_version,
+        )?;
 
         // 获取完整用户信息（包含推荐人数）
         let user_response = self.get_user_with_referrals(user_id).await?;
@@ -225,7 +315,7 @@ impl AuthService {
             user: user_response,
             access_token,
             refresh_token,
-            expires_in: self.jwt_service.get_access_token_expires_in(),
+            expires_in: lifetimes.access,
         })
     }
 
@@ -237,29 +327,90 @@ impl AuthService {
     ///
     /// # 返回值
     /// 返回一个包含用户信息的响应
-    pub async fn login(&self, request: LoginRequest) -> AppResult<AuthResponse> {
-        // 验证手机号格式
+    pub async fn login(
+        &self,
+        mut request: LoginRequest,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> AppResult<AuthResponse> {
+        // 规范化并验证手机号格式
+        request.phone = normalize_us_phone(&request.phone);
         validate_us_phone(&request.phone)?;
         // 通过手机号获取用户（避免重复查询）
         let user = self.get_user_by_phone(&request.phone).await.map_err(|_| {
             AppError::AuthError("User does not exist or password is incorrect".to_string())
         })?;
 
+        // 账号处于锁定期内，直接拒绝
+        let now = Utc::now();
+        if let Some(locked_until) = user.locked_until
+            && locked_until > now
+        {
+            return Err(AppError::AuthError(
+                "Account temporarily locked".to_string(),
+            ));
+        }
+
         // 验证密码
         let is_valid = verify_password(&request.password, &user.password_hash)?;
         if !is_valid {
+            // 原子自增，并发的失败请求各自拿到不同的计数，不会丢失累加
+            let row = self
+                .pool
+                .query_one(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    r#"UPDATE users
+                       SET failed_login_attempts = failed_login_attempts + 1, updated_at = NOW()
+                       WHERE id = $1
+                       RETURNING failed_login_attempts"#,
+                    [user.id.into()],
+                ))
+                .await?;
+            let attempts: i32 = match row {
+                Some(row) => row.try_get("", "failed_login_attempts")?,
+                None => 0,
+            };
+            if let Some(window) = lockout_duration(attempts) {
+                users::Entity::update_many()
+                    .col_expr(users::Column::LockedUntil, Expr::value(now + window))
+                    .filter(users::Column::Id.eq(user.id))
+                    .exec(&self.pool)
+                    .await?;
+            }
             return Err(AppError::AuthError(
                 "User does not exist or password is incorrect".to_string(),
             ));
         }
 
+        // 登录成功，清零失败计数并记录登录时间
+        let mut active: users::ActiveModel = user.into();
+        active.failed_login_attempts = Set(0);
+        active.locked_until = Set(None);
+        active.last_login_at = Set(Some(now));
+        active.updated_at = Set(Some(now));
+        let user = active.update(&self.pool).await?;
+
+        // 登录记录尽力而为，失败不影响登录
+        if let Err(e) = self.record_login(user.id, ip, user_agent).await {
+            log::warn!("Failed to record login history for user {}: {e:?}", user.id);
+        }
+
         // 生成JWT令牌
-        let access_token = self
-            .jwt_service
-            .generate_access_token(user.id, &user.member_code)?;
-        let refresh_token = self
-            .jwt_service
-            .generate_refresh_token(user.id, &user.member_code)?;
+        // 令牌有效期按会员等级区分
+        let lifetimes = self.jwt_service.lifetimes_for(&user.member_type);
+        let access_token = self.jwt_service.generate_access_token(
+            user.id,
+            &user.member_code,
+            &user.role,
+            lifetimes.access,
+        )?;
+        let refresh_token = self.jwt_service.generate_refresh_token(
+            user.id,
+            &user.member_code,
+            &user.role,
+            lifetimes.refresh,
+            user.token_version,
+        )?;
 
         // 使用已获取的 user 构建带推荐数的响应，避免再次按 id 查询
         let user_response = self.build_user_response_with_referrals(user).await?;
@@ -268,10 +419,45 @@ impl AuthService {
             user: user_response,
             access_token,
             refresh_token,
-            expires_in: self.jwt_service.get_access_token_expires_in(),
+            expires_in: lifetimes.access,
         })
     }
 
+    /// 写入一条登录记录，并只保留最近 `LOGIN_HISTORY_KEEP` 条
+    async fn record_login(
+        &self,
+        user_id: i64,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> AppResult<()> {
+        let user_agent = user_agent.map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        login_history::ActiveModel {
+            user_id: Set(user_id),
+            ip: Set(ip),
+            user_agent: Set(user_agent),
+            created_at: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .insert(&self.pool)
+        .await?;
+
+        let keep_ids: Vec<i64> = login_history::Entity::find()
+            .select_only()
+            .column(login_history::Column::Id)
+            .filter(login_history::Column::UserId.eq(user_id))
+            .order_by_desc(login_history::Column::Id)
+            .limit(LOGIN_HISTORY_KEEP)
+            .into_tuple()
+            .all(&self.pool)
+            .await?;
+        login_history::Entity::delete_many()
+            .filter(login_history::Column::UserId.eq(user_id))
+            .filter(login_history::Column::Id.is_not_in(keep_ids))
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     /// 刷新用户令牌
     ///
     /// # 参数
@@ -289,19 +475,29 @@ impl AuthService {
             .parse()
             .map_err(|_| AppError::AuthError("Invalid token".to_string()))?;
 
-        // 获取用户信息
-        let user_response = self.get_user_with_referrals(user_id).await?;
+        // 重设密码后版本号递增，旧刷新令牌失效
+        let user = self.get_user_by_id(user_id).await?;
+        if claims.ver != user.token_version {
+            return Err(AppError::AuthError(
+                "Refresh token has been revoked".to_string(),
+            ));
+        }
+        let user_response = self.build_user_response_with_referrals(user).await?;
 
         // 生成新的访问令牌
-        let access_token = self
-            .jwt_service
-            .generate_access_token(user_response.id, &user_response.member_code)?;
+        let lifetimes = self.jwt_service.lifetimes_for(&user_response.member_type);
+        let access_token = self.jwt_service.generate_access_token(
+            user_response.id,
+            &user_response.member_code,
+            &user_response.role,
+            lifetimes.access,
+        )?;
 
         Ok(AuthResponse {
             user: user_response,
             access_token,
             refresh_token: refresh_token.to_string(),
-            expires_in: self.jwt_service.get_access_token_expires_in(),
+            expires_in: lifetimes.access,
         })
     }
 
@@ -375,9 +571,27 @@ impl AuthService {
         new_password: &str,
     ) -> AppResult<()> {
         // 校验输入
+        let phone = &normalize_us_phone(phone);
         validate_us_phone(phone)?;
         validate_password(new_password)?;
 
+        // 同一手机号的重设加锁：失败时释放以便重试，成功后保留作为冷却
+        self.lock_password_reset(phone).await?;
+        let result = self
+            .do_reset_password(phone, verification_code, new_password)
+            .await;
+        if result.is_err() {
+            self.unlock_password_reset(phone).await;
+        }
+        result
+    }
+
+    async fn do_reset_password(
+        &self,
+        phone: &str,
+        verification_code: &str,
+        new_password: &str,
+    ) -> AppResult<()> {
         // 校验验证码（Twilio Verify）
         let approved = self
             .twilio_service
@@ -395,11 +609,97 @@ impl AuthService {
         // 计算新密码哈希
         let new_hash = hash_password(new_password)?;
 
-        // 更新数据库
+        // 更新密码并递增令牌版本，使已签发的刷新令牌全部失效
+        let token_version = user.token_version;
         let mut active: users::ActiveModel = user.into();
         active.password_hash = Set(new_hash);
+        active.token_version = Set(token_version + 1);
+        active.updated_at = Set(Some(Utc::now()));
         active.update(&self.pool).await?;
 
         Ok(())
     }
+
+    /// 为手机号加重设密码锁（存于数据库，多实例共享），锁未过期时返回 `RateLimited`
+    async fn lock_password_reset(&self, phone: &str) -> AppResult<()> {
+        let key = format!("password_reset:{phone}");
+        let lock_for = Duration::seconds(PASSWORD_RESET_LOCK_SECS);
+        match claim_key(&self.pool, &key, lock_for).await? {
+            KeyClaim::Claimed => Ok(()),
+            KeyClaim::Held { expires_at } => Err(AppError::RateLimited {
+                retry_after: (expires_at - Utc::now()).num_seconds().max(1) as u64,
+            }),
+        }
+    }
+
+    async fn unlock_password_reset(&self, phone: &str) {
+        if let Err(e) = release_key(&self.pool, &format!("password_reset:{phone}")).await {
+            log::error!("Failed to release password reset lock for {phone}: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TwilioConfig;
+    use crate::database::test_db;
+    use actix_web::ResponseError;
+    use actix_web::http::StatusCode;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn auth_service(pool: &DatabaseConnection) -> AuthService {
+        // 未配置 Verify Service，验证码校验必然失败
+        let twilio = TwilioService::new(
+            TwilioConfig {
+                account_sid: String::new(),
+                auth_token: String::new(),
+                from_phone: String::new(),
+                verify_service_sid: String::new(),
+            },
+            pool.clone(),
+        );
+        AuthService::new(
+            pool.clone(),
+            JwtService::new("test-secret", 7200, 2_592_000),
+            twilio,
+            DiscountCodeService::new(
+                pool.clone(),
+                Arc::new(Mutex::new(MockPosBackend::default())),
+            ),
+        )
+    }
+
+    #[tokio::test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    async fn test_password_reset_lock_is_shared_through_db() {
+        let pool = test_db::setup_db().await;
+        let service = auth_service(&pool);
+        let suffix = Utc::now().timestamp_micros() % 10_000_000;
+        let phone = format!("+1202{suffix:07}");
+
+        // 失败的重设释放锁，可立即重试
+        for _ in 0..2 {
+            let err = service
+                .reset_password_with_phone_code(&phone, "123456", "NewPassw0rd!")
+                .await
+                .unwrap_err();
+            assert!(!matches!(err, AppError::RateLimited { .. }), "{err:?}");
+        }
+
+        // 另一实例持有的锁同样生效
+        claim_key(
+            &pool,
+            &format!("password_reset:{phone}"),
+            Duration::seconds(PASSWORD_RESET_LOCK_SECS),
+        )
+        .await
+        .unwrap();
+        let err = service
+            .reset_password_with_phone_code(&phone, "123456", "NewPassw0rd!")
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_response().status(), StatusCode::TOO_MANY_REQUESTS);
+    }
 }
@@ -1,9 +1,12 @@
+use crate::config::BirthdayRewardConfig;
 use crate::entities::{
     MemberType, birthday_reward_entity as br, sweet_cash_transaction_entity as sct,
     user_entity as users,
 };
-use crate::error::AppResult;
-use chrono::{Datelike, Utc};
+use crate::error::{AppError, AppResult};
+use crate::models::BirthdayRewardPreview;
+use crate::services::DiscountCodeService;
+use chrono::{Datelike, NaiveDate, Utc};
 use sea_orm::sea_query::{OnConflict, PostgresQueryBuilder, Query};
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
@@ -13,14 +16,70 @@ use sea_orm::{
 #[derive(Clone)]
 pub struct BirthdayRewardService {
     pool: DatabaseConnection,
+    discount_code_service: DiscountCodeService,
+    config: BirthdayRewardConfig,
+}
+
+/// 按会员等级取生日福利金额（美分）
+fn reward_amount(config: &BirthdayRewardConfig, member_type: &MemberType) -> i64 {
+    match member_type {
+        MemberType::Fan => config.fan_cents,
+        MemberType::SweetShareholder => config.sweet_cents,
+        MemberType::SuperShareholder => config.super_cents,
+    }
+}
+
+/// 今天或之后最近的一次生日；2 月 29 日生日跳到下一个闰年
+fn next_birthday(today: NaiveDate, month: u32, day: u32) -> Option<NaiveDate> {
+    (today.year()..=today.year() + 8)
+        .filter_map(|y| NaiveDate::from_ymd_opt(y, month, day))
+        .find(|d| *d >= today)
 }
 
 impl BirthdayRewardService {
-    pub fn new(pool: DatabaseConnection) -> Self {
-        Self { pool }
+    pub fn new(
+        pool: DatabaseConnection,
+        discount_code_service: DiscountCodeService,
+        config: BirthdayRewardConfig,
+    ) -> Self {
+        Self {
+            pool,
+            discount_code_service,
+            config,
+        }
     }
 
-    // 给今天生日且今年未领取过的用户发放生日福利；返回发放人数
+    /// 预览用户当前可获得的生日福利（金额随会员等级与配置变化）
+    pub async fn birthday_reward_preview(&self, user_id: i64) -> AppResult<BirthdayRewardPreview> {
+        let user = users::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+        let today = Utc::now().date_naive();
+        let next_birthday =
+            next_birthday(today, user.birthday_month as u32, user.birthday_day as u32)
+                .ok_or_else(|| AppError::InternalError("Invalid birthday".into()))?;
+        let granted_this_year = br::Entity::find()
+            .filter(br::Column::UserId.eq(user_id))
+            .filter(br::Column::RewardYear.eq(today.year()))
+            .one(&self.pool)
+            .await?
+            .is_some();
+
+        Ok(BirthdayRewardPreview {
+            amount_cents: reward_amount(&self.config, &user.member_type),
+            code_type: self
+                .config
+                .issue_code
+                .then(|| self.config.code_type.clone()),
+            expire_months: self.config.issue_code.then_some(self.config.expire_months),
+            next_birthday,
+            granted_this_year,
+        })
+    }
+
+    // 给今天生日且今年未领取过的用户发放生日福利；返回本次新发放人数
+    // 单个用户失败只记日志，不影响其他用户
     pub async fn grant_today_birthdays(&self) -> AppResult<i64> {
         let today = Utc::now().date_naive();
         let month = today.month();
@@ -36,19 +95,28 @@ impl BirthdayRewardService {
 
         let mut granted = 0i64;
         for u in users_today {
-            let amount = match u.member_type {
-                MemberType::Fan => 50,               // $0.5
-                MemberType::SweetShareholder => 550, // $5.5
-                MemberType::SuperShareholder => 800, // $8
-            };
+            let amount = reward_amount(&self.config, &u.member_type);
+            if amount <= 0 {
+                continue;
+            }
 
-            self.grant_single(u, amount, year).await?;
-            granted += 1;
+            let user_id = u.id;
+            match self.grant_single(u, amount, year).await {
+                Ok(true) => granted += 1,
+                Ok(false) => {
+                    log::info!("Birthday reward for user {user_id} already granted in {year}");
+                }
+                Err(e) => {
+                    log::error!("Failed to grant birthday reward to user {user_id}: {e:?}");
+                }
+            }
         }
         Ok(granted)
     }
 
-    async fn grant_single(&self, user: users::Model, amount: i64, year: i32) -> AppResult<()> {
+    /// 先在事务内写入年度标记（依赖 (user_id, reward_year) 唯一索引，冲突则跳过），
+    /// 再发放福利；并发执行时只有一方能插入成功。返回是否为新发放
+    async fn grant_single(&self, user: users::Model, amount: i64, year: i32) -> AppResult<bool> {
         let txn = self.pool.begin().await?;
         // 使用 Upsert 语义：插入标记，若已存在则不影响（DO NOTHING）
         let insert = Query::insert()
@@ -75,7 +143,36 @@ impl BirthdayRewardService {
         if res.rows_affected() == 0 {
             // 已发放过，跳过
             txn.commit().await?;
-            return Ok(());
+            return Ok(false);
+        }
+
+        // 配置为优惠码发放时：与年度标记一起以 pending 提交后再到七云注册；
+        // 注册失败时删除年度标记，当天后续的任务轮次会重新发放
+        if self.config.issue_code {
+            let code = self
+                .discount_code_service
+                .create_pending_user_discount_code_tx(
+                    &txn,
+                    user.id,
+                    amount,
+                    self.config.code_type.clone(),
+                    self.config.expire_months,
+                )
+                .await?;
+            txn.commit().await?;
+            if let Err(e) = self
+                .discount_code_service
+                .register_pending_code(code.id, &code.code, amount, self.config.expire_months)
+                .await
+            {
+                br::Entity::delete_many()
+                    .filter(br::Column::UserId.eq(user.id))
+                    .filter(br::Column::RewardYear.eq(year))
+                    .exec(&self.pool)
+                    .await?;
+                return Err(e);
+            }
+            return Ok(true);
         }
 
         // 增加用户余额
@@ -100,6 +197,6 @@ impl BirthdayRewardService {
         .await?;
 
         txn.commit().await?;
-        Ok(())
+        Ok(true)
     }
 }
@@ -1,34 +1,134 @@
 use crate::entities::{
-    CodeType, discount_code_entity as discount_codes, sweet_cash_transaction_entity as sct,
-    user_entity as users,
+    CodeType, DiscountCodeStatus, discount_code_entity as discount_codes,
+    discount_code_transfer_entity as transfers, stamp_redemption_tier_entity as stamp_tiers,
+    sweet_cash_transaction_entity as sct, user_entity as users,
 };
 use crate::error::{AppError, AppResult};
 use crate::external::*;
 use crate::models::*;
-use crate::utils::generate_six_digit_code;
+use crate::services::{AuditEntry, AuditService, NotificationService};
+use crate::utils::generate_six_digit_codes;
 use chrono::{Duration, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
-    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
 };
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+
+/// pending 优惠码超过该时长（分钟）仍未完成时由对账任务处理
+const PENDING_RECONCILE_AFTER_MINUTES: i64 = 10;
+/// 单批次最多生成的优惠码数量
+const BULK_GENERATE_MAX: u32 = 1000;
+/// 七云注册失败比例超过该值（百分比）时停止生成剩余的码
+const BULK_FAILURE_THRESHOLD_PERCENT: u32 = 5;
+/// 生成唯一码时每轮预检的候选数量
+const UNIQUE_CODE_BATCH_SIZE: usize = 10;
+/// 生成唯一码的最大轮数
+const UNIQUE_CODE_MAX_ROUNDS: usize = 3;
+/// stamp_redemption_tiers 表为空时使用的默认印花兑换档位：(优惠码面额美分, 所需 stamps)
+const DEFAULT_STAMP_REDEMPTION_TIERS: [(i64, i64); 1] = [(550, 10)];
+
+/// 列出全部印花兑换档位（含未启用的），按所需 stamps 升序；表为空时返回内置默认档位
+pub async fn list_stamp_redemption_tiers<C: ConnectionTrait>(
+    db: &C,
+) -> AppResult<Vec<StampRedemptionTierResponse>> {
+    let rows = stamp_tiers::Entity::find()
+        .order_by_asc(stamp_tiers::Column::StampsRequired)
+        .order_by_asc(stamp_tiers::Column::ValueCents)
+        .all(db)
+        .await?;
+    if rows.is_empty() {
+        return Ok(DEFAULT_STAMP_REDEMPTION_TIERS
+            .iter()
+            .map(
+                |&(value_cents, stamps_required)| StampRedemptionTierResponse {
+                    value_cents,
+                    stamps_required,
+                    active: true,
+                },
+            )
+            .collect());
+    }
+    Ok(rows
+        .into_iter()
+        .map(StampRedemptionTierResponse::from)
+        .collect())
+}
+
+/// 读取当前生效的印花兑换档位
+pub async fn load_stamp_redemption_tiers<C: ConnectionTrait>(
+    db: &C,
+) -> AppResult<Vec<StampRedemptionTierResponse>> {
+    Ok(list_stamp_redemption_tiers(db)
+        .await?
+        .into_iter()
+        .filter(|t| t.active)
+        .collect())
+}
+
+/// 兑换指定面额所需的 stamps；不支持的面额返回列出可选档位的校验错误
+fn stamps_required_for(tiers: &[StampRedemptionTierResponse], value_cents: i64) -> AppResult<i64> {
+    if let Some(tier) = tiers.iter().find(|t| t.value_cents == value_cents) {
+        return Ok(tier.stamps_required);
+    }
+    let options = tiers
+        .iter()
+        .map(|t| format!("{} cents ({} stamps)", t.value_cents, t.stamps_required))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(AppError::ValidationError(format!(
+        "Unsupported discount amount, valid options: {options}"
+    )))
+}
+
+/// 距下一个可兑换奖励还差的 stamps 及其面额：取当前还兑换不起的最低档；
+/// 已够兑换所有档位时返回 (0, 可兑换的最高面额)。`tiers` 需按所需 stamps 升序
+pub fn next_stamp_reward(tiers: &[StampRedemptionTierResponse], stamps: i64) -> (i64, i64) {
+    tiers
+        .iter()
+        .find(|t| t.stamps_required > stamps)
+        .map(|t| (t.stamps_required - stamps, t.value_cents))
+        .or_else(|| tiers.iter().map(|t| t.value_cents).max().map(|v| (0, v)))
+        .unwrap_or((0, 0))
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscountCodeSpec {
+    /// 美分
+    pub amount: i64,
+    pub code_type: CodeType,
+    /// 有效时间（1-3月）
+    pub expire_months: u32,
+}
 
 #[derive(Clone)]
 pub struct DiscountCodeService {
     pool: DatabaseConnection,
-    sevencloud_api: std::sync::Arc<tokio::sync::Mutex<SevenCloudAPI>>,
+    sevencloud_api: SharedPosBackend,
+    audit_service: AuditService,
+    notification_service: NotificationService,
 }
 
 impl DiscountCodeService {
-    pub fn new(
-        pool: DatabaseConnection,
-        sevencloud_api: std::sync::Arc<tokio::sync::Mutex<SevenCloudAPI>>,
-    ) -> Self {
+    pub fn new(pool: DatabaseConnection, sevencloud_api: SharedPosBackend) -> Self {
+        let audit_service = AuditService::new(pool.clone());
+        let notification_service = NotificationService::new(pool.clone());
         Self {
             pool,
             sevencloud_api,
+            audit_service,
+            notification_service,
         }
     }
 
+    /// 列出全部印花兑换档位（含未启用的），用于后台查看
+    pub async fn list_stamp_redemption_tiers(&self) -> AppResult<Vec<StampRedemptionTierResponse>> {
+        list_stamp_redemption_tiers(&self.pool).await
+    }
+
     /// 获取用户的优惠码
     pub async fn get_user_discount_codes(
         &self,
@@ -39,15 +139,17 @@ impl DiscountCodeService {
         let offset = params.get_offset();
         let limit = params.get_limit();
 
+        let cond = Self::list_condition(user_id, query)?;
+
         // 获取总数
         let total = discount_codes::Entity::find()
-            .filter(discount_codes::Column::UserId.eq(user_id))
+            .filter(cond.clone())
             .count(&self.pool)
             .await? as i64;
 
         // 获取优惠码列表
         let models = discount_codes::Entity::find()
-            .filter(discount_codes::Column::UserId.eq(user_id))
+            .filter(cond)
             .order_by_desc(discount_codes::Column::CreatedAt)
             .limit(limit as u64)
             .offset(offset as u64)
@@ -64,6 +166,218 @@ impl DiscountCodeService {
         ))
     }
 
+    /// 构造用户优惠码列表的筛选条件（status: available/used/expired，code_type 为枚举字符串）
+    fn list_condition(user_id: i64, query: &DiscountCodeQuery) -> AppResult<Condition> {
+        let now = Utc::now();
+        let mut cond = Condition::all().add(discount_codes::Column::UserId.eq(user_id));
+
+        let unused = Condition::any()
+            .add(discount_codes::Column::IsUsed.eq(false))
+            .add(discount_codes::Column::IsUsed.is_null());
+
+        if let Some(status) = query.status.as_deref() {
+            cond = match status {
+                "available" => cond
+                    .add(unused)
+                    .add(discount_codes::Column::Status.eq(DiscountCodeStatus::Active))
+                    .add(discount_codes::Column::IsExpired.eq(false))
+                    .add(discount_codes::Column::ExpiresAt.gt(now)),
+                "used" => cond.add(discount_codes::Column::IsUsed.eq(true)),
+                "expired" => cond.add(unused).add(
+                    Condition::any()
+                        .add(discount_codes::Column::IsExpired.eq(true))
+                        .add(discount_codes::Column::ExpiresAt.lte(now)),
+                ),
+                other => {
+                    return Err(AppError::ValidationError(format!(
+                        "Unsupported status filter: {other}"
+                    )));
+                }
+            };
+        }
+
+        if let Some(code_type) = query.code_type.as_deref() {
+            let code_type: CodeType =
+                serde_json::from_value(serde_json::Value::String(code_type.to_string())).map_err(
+                    |_| AppError::ValidationError(format!("Unsupported code_type: {code_type}")),
+                )?;
+            cond = cond.add(discount_codes::Column::CodeType.eq(code_type));
+        }
+
+        Ok(cond)
+    }
+
+    /// 按码查询属于指定用户的优惠码。
+    ///
+    /// 访问策略：用户端接口对“码不存在”与“码属于他人”统一返回 NotFound，
+    /// 避免通过状态码探测他人的优惠码；需要区分两者的管理端场景使用 `find_for_user_admin`。
+    /// 新增的用户端优惠码接口应通过本方法定位优惠码。
+    pub async fn find_owned(&self, user_id: i64, code: &str) -> AppResult<discount_codes::Model> {
+        Self::find_owned_in(&self.pool, user_id, code).await
+    }
+
+    /// 同 `find_owned`，可在事务内使用
+    async fn find_owned_in<C: ConnectionTrait>(
+        conn: &C,
+        user_id: i64,
+        code: &str,
+    ) -> AppResult<discount_codes::Model> {
+        discount_codes::Entity::find()
+            .filter(discount_codes::Column::Code.eq(code))
+            .filter(discount_codes::Column::UserId.eq(user_id))
+            .one(conn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Discount code not found".to_string()))
+    }
+
+    /// 管理端按码查询并核对归属：码不存在返回 NotFound，属于其他用户（或未绑定用户）返回 Forbidden
+    pub async fn find_for_user_admin(
+        &self,
+        user_id: i64,
+        code: &str,
+    ) -> AppResult<discount_codes::Model> {
+        let m = discount_codes::Entity::find()
+            .filter(discount_codes::Column::Code.eq(code))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Discount code not found".to_string()))?;
+        if m.user_id != Some(user_id) {
+            return Err(AppError::Forbidden);
+        }
+        Ok(m)
+    }
+
+    /// 校验优惠码是否可用（供收银核销前查询，只读）
+    pub async fn validate_code(&self, code: &str) -> AppResult<DiscountCodeValidation> {
+        let Some(m) = discount_codes::Entity::find()
+            .filter(discount_codes::Column::Code.eq(code))
+            .one(&self.pool)
+            .await?
+        else {
+            return Ok(DiscountCodeValidation {
+                code: code.to_string(),
+                valid: false,
+                reason: Some("not_found".to_string()),
+                discount_amount: None,
+                code_type: None,
+                is_used: false,
+                expires_at: None,
+                owner_member_code: None,
+            });
+        };
+
+        let owner_member_code = match m.user_id {
+            Some(owner_id) => users::Entity::find_by_id(owner_id)
+                .one(&self.pool)
+                .await?
+                .map(|u| u.member_code),
+            None => None,
+        };
+
+        let is_used = m.is_used.unwrap_or(false);
+        let reason = if m.status != DiscountCodeStatus::Active {
+            Some("not_active".to_string())
+        } else if is_used {
+            Some("already_used".to_string())
+        } else if m.expires_at <= Utc::now() {
+            Some("expired".to_string())
+        } else {
+            None
+        };
+
+        Ok(DiscountCodeValidation {
+            code: m.code,
+            valid: reason.is_none(),
+            reason,
+            discount_amount: Some(m.discount_amount),
+            code_type: Some(m.code_type),
+            is_used,
+            expires_at: Some(m.expires_at),
+            owner_member_code,
+        })
+    }
+
+    /// 将自己未使用且未过期的优惠码转赠给其他用户（按会员号），并记录转赠流水
+    pub async fn transfer_code(
+        &self,
+        from_user_id: i64,
+        code: &str,
+        to_member_code: &str,
+    ) -> AppResult<TransferDiscountCodeResponse> {
+        let recipient = users::Entity::find()
+            .filter(users::Column::MemberCode.eq(to_member_code))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Recipient not found".to_string()))?;
+        if recipient.id == from_user_id {
+            return Err(AppError::ValidationError(
+                "Cannot transfer a discount code to yourself".to_string(),
+            ));
+        }
+
+        let txn = self.pool.begin().await?;
+
+        let dc = Self::find_owned_in(&txn, from_user_id, code).await?;
+        if dc.status != DiscountCodeStatus::Active {
+            return Err(AppError::ValidationError(
+                "Discount code is not active".to_string(),
+            ));
+        }
+        if dc.is_used.unwrap_or(false) {
+            return Err(AppError::ValidationError(
+                "Discount code already used".to_string(),
+            ));
+        }
+        if dc.is_expired || dc.expires_at <= Utc::now() {
+            return Err(AppError::ValidationError(
+                "Discount code expired".to_string(),
+            ));
+        }
+
+        // 条件更新，防止并发核销/重复转赠
+        let res = discount_codes::Entity::update_many()
+            .col_expr(discount_codes::Column::UserId, Expr::value(recipient.id))
+            .col_expr(discount_codes::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(discount_codes::Column::Id.eq(dc.id))
+            .filter(discount_codes::Column::UserId.eq(from_user_id))
+            .filter(discount_codes::Column::IsUsed.eq(false))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::ValidationError(
+                "Discount code state changed, please retry".to_string(),
+            ));
+        }
+
+        transfers::ActiveModel {
+            discount_code_id: Set(dc.id),
+            from_user_id: Set(from_user_id),
+            to_user_id: Set(recipient.id),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let updated = discount_codes::Entity::find_by_id(dc.id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Discount code not found".to_string()))?;
+
+        txn.commit().await?;
+
+        log::info!(
+            "Discount code {} transferred from user {} to user {}",
+            updated.code,
+            from_user_id,
+            recipient.id
+        );
+
+        Ok(TransferDiscountCodeResponse {
+            discount_code: DiscountCodeResponse::from(updated),
+            to_member_code: recipient.member_code,
+        })
+    }
+
     /// 兑换优惠码
     pub async fn redeem_discount_code(
         &self,
@@ -71,17 +385,8 @@ impl DiscountCodeService {
         request: RedeemDiscountCodeRequest,
     ) -> AppResult<RedeemDiscountCodeResponse> {
         // 验证兑换金额
-        let allowed = [(5.5, 10)];
-        let mut stamps_required: Option<i64> = None;
-        for (value_dollars, stamps) in allowed {
-            if request.discount_amount == (value_dollars * 100.0) as i64 {
-                stamps_required = Some(stamps as i64);
-                break;
-            }
-        }
-
-        let stamps_needed = stamps_required
-            .ok_or_else(|| AppError::ValidationError("Unsupported discount amount".to_string()))?;
+        let tiers = load_stamp_redemption_tiers(&self.pool).await?;
+        let stamps_needed = stamps_required_for(&tiers, request.discount_amount)?;
 
         // 验证有效期
         if request.expire_months < 1 || request.expire_months > 3 {
@@ -90,7 +395,7 @@ impl DiscountCodeService {
             ));
         }
 
-        // 开始事务
+        // 开始事务：扣减 stamps 并以 pending 状态落库，提交后再调用七云
         let txn = self.pool.begin().await?;
 
         // 检查用户 stamps 余额
@@ -100,8 +405,6 @@ impl DiscountCodeService {
             .and_then(|u| u.stamps)
             .unwrap_or(0);
 
-        // current_stamps computed above
-
         if current_stamps < stamps_needed {
             return Err(AppError::ValidationError("Insufficient stamps".to_string()));
         }
@@ -115,26 +418,18 @@ impl DiscountCodeService {
         }
 
         // 生成优惠码
-        let code = generate_six_digit_code(); // 生成6位数字码
+        let code = Self::generate_unique_code(&txn).await?;
         let expires_at = Utc::now() + Duration::days(30 * request.expire_months as i64);
-        let discount_dollars = request.discount_amount as f64 / 100.0;
 
-        // 调用七云API生成优惠码
-        {
-            let mut api = self.sevencloud_api.lock().await;
-            api.generate_discount_code(&code, discount_dollars, request.expire_months)
-                .await?;
-        }
-
-        // 保存优惠码到本地数据库
-        let code_type_enum = CodeType::SweetsCreditsReward;
         let created = discount_codes::ActiveModel {
-            user_id: Set(user_id),
+            user_id: Set(Some(user_id)),
             code: Set(code.clone()),
             discount_amount: Set(request.discount_amount),
-            code_type: Set(code_type_enum),
+            code_type: Set(CodeType::SweetsCreditsReward),
             is_used: Set(Some(false)),
             expires_at: Set(expires_at),
+            status: Set(DiscountCodeStatus::Pending),
+            stamps_spent: Set(Some(stamps_needed)),
             ..Default::default()
         }
         .insert(&txn)
@@ -143,6 +438,15 @@ impl DiscountCodeService {
 
         txn.commit().await?;
 
+        // 调用七云API生成优惠码，失败则退还 stamps
+        self.register_pending_code(
+            discount_code_id,
+            &code,
+            request.discount_amount,
+            request.expire_months,
+        )
+        .await?;
+
         // 返回结果
         let discount_code = DiscountCodeResponse {
             id: discount_code_id,
@@ -182,45 +486,44 @@ impl DiscountCodeService {
 
         let txn = self.pool.begin().await?;
 
-        // 查询余额
-        let current_balance = users::Entity::find_by_id(user_id)
-            .one(&txn)
-            .await?
-            .and_then(|u| u.balance)
-            .unwrap_or(0);
-        // current_balance computed above
-        if current_balance < request.discount_amount {
+        // 条件扣减余额：余额不足时不更新任何行，并发兑换不会超额扣减
+        let res = users::Entity::update_many()
+            .col_expr(
+                users::Column::Balance,
+                Expr::cust_with_values("COALESCE(balance, 0) - $1", [request.discount_amount]),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .filter(Expr::cust_with_values(
+                "COALESCE(balance, 0) >= $1",
+                [request.discount_amount],
+            ))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
             return Err(AppError::ValidationError(
                 "Insufficient balance".to_string(),
             ));
         }
+        let new_balance = users::Entity::find_by_id(user_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?
+            .balance
+            .unwrap_or(0);
 
-        // 扣减余额
-        if let Some(u) = users::Entity::find_by_id(user_id).one(&txn).await? {
-            let new_balance = u.balance.unwrap_or(0) - request.discount_amount;
-            let mut am = u.into_active_model();
-            am.balance = Set(Some(new_balance));
-            am.update(&txn).await?;
-        }
-
-        // 生成优惠码
-        let code = generate_six_digit_code();
+        // 生成优惠码，以 pending 状态落库
+        let code = Self::generate_unique_code(&txn).await?;
         let expires_at = Utc::now() + Duration::days(30 * request.expire_months as i64);
-        let discount_dollars = request.discount_amount as f64 / 100.0;
-        {
-            let mut api = self.sevencloud_api.lock().await;
-            api.generate_discount_code(&code, discount_dollars, request.expire_months)
-                .await?;
-        }
 
         let code_type_enum = CodeType::SweetsCreditsReward; // 兑换获得，标记为 sweets_credits_reward
         let created = discount_codes::ActiveModel {
-            user_id: Set(user_id),
+            user_id: Set(Some(user_id)),
             code: Set(code.clone()),
             discount_amount: Set(request.discount_amount),
             code_type: Set(code_type_enum),
             is_used: Set(Some(false)),
             expires_at: Set(expires_at),
+            status: Set(DiscountCodeStatus::Pending),
             ..Default::default()
         }
         .insert(&txn)
@@ -232,7 +535,7 @@ impl DiscountCodeService {
             user_id: Set(user_id),
             transaction_type: Set(crate::entities::TransactionType::Redeem),
             amount: Set(request.discount_amount),
-            balance_after: Set(current_balance - request.discount_amount),
+            balance_after: Set(new_balance),
             related_order_id: Set(None),
             related_discount_code_id: Set(Some(discount_code_id)),
             description: Set(Some(format!("Redeem balance for discount code {code}"))),
@@ -243,6 +546,27 @@ impl DiscountCodeService {
 
         txn.commit().await?;
 
+        self.audit_service.record(AuditEntry {
+            actor_id: Some(user_id),
+            action: "balance.redeem",
+            entity: "user",
+            entity_id: Some(user_id),
+            before: Some(json!({ "balance": new_balance + request.discount_amount })),
+            after: Some(json!({
+                "balance": new_balance,
+                "discount_code_id": discount_code_id,
+            })),
+        });
+
+        // 调用七云API生成优惠码，失败则退还余额
+        self.register_pending_code(
+            discount_code_id,
+            &code,
+            request.discount_amount,
+            request.expire_months,
+        )
+        .await?;
+
         let discount_code = DiscountCodeResponse {
             id: discount_code_id,
             code,
@@ -256,11 +580,11 @@ impl DiscountCodeService {
         Ok(RedeemBalanceDiscountCodeResponse {
             discount_code,
             balance_used: request.discount_amount,
-            remaining_balance: current_balance - request.discount_amount,
+            remaining_balance: new_balance,
         })
     }
 
-    /// 通用创建用户优惠码（注册奖励、推荐奖励等）
+    /// 通用创建用户优惠码（注册奖励等）：先以 pending 落库，再到七云注册
     ///
     /// # 参数
     ///
@@ -275,6 +599,152 @@ impl DiscountCodeService {
         code_type: CodeType,
         expire_months: u32,
     ) -> AppResult<i64> {
+        let txn = self.pool.begin().await?;
+        let created = self
+            .create_pending_user_discount_code_tx(&txn, user_id, amount, code_type, expire_months)
+            .await?;
+        txn.commit().await?;
+        self.register_pending_code(created.id, &created.code, amount, expire_months)
+            .await?;
+        Ok(created.id)
+    }
+
+    /// 在调用方事务内以 pending 状态创建用户优惠码，不调用七云。
+    /// 调用方提交事务后再用 `register_pending_code` 注册；进程中途退出时由对账任务处理
+    pub async fn create_pending_user_discount_code_tx(
+        &self,
+        txn: &sea_orm::DatabaseTransaction,
+        user_id: i64,
+        amount: i64,
+        code_type: CodeType,
+        expire_months: u32,
+    ) -> AppResult<discount_codes::Model> {
+        if amount <= 0 {
+            return Err(AppError::ValidationError(
+                "Discount amount must be positive".into(),
+            ));
+        }
+        if expire_months == 0 || expire_months > 3 {
+            return Err(AppError::ValidationError(
+                "Expiration period must be between 1-3 months".into(),
+            ));
+        }
+
+        let code = Self::generate_unique_code(txn).await?;
+        let created = discount_codes::ActiveModel {
+            user_id: Set(Some(user_id)),
+            code: Set(code),
+            discount_amount: Set(amount),
+            code_type: Set(code_type),
+            is_used: Set(Some(false)),
+            expires_at: Set(Utc::now() + Duration::days(30 * expire_months as i64)),
+            status: Set(DiscountCodeStatus::Pending),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+        Ok(created)
+    }
+
+    /// 批量创建用户优惠码（会员升级奖励等）。
+    ///
+    /// 七云新增接口每次只能注册一个自定义码，因此整批只获取一次七云锁并顺序注册，
+    /// 避免多个并发任务反复争抢锁；注册成功的码一次性批量入库，注册失败的码跳过并记录日志。
+    /// 全部失败时返回第一个错误。返回成功创建的优惠码
+    pub async fn create_user_discount_codes(
+        &self,
+        user_id: i64,
+        specs: Vec<DiscountCodeSpec>,
+    ) -> AppResult<Vec<String>> {
+        if specs.is_empty() {
+            return Ok(Vec::new());
+        }
+        for spec in &specs {
+            if spec.amount <= 0 {
+                return Err(AppError::ValidationError(
+                    "Discount amount must be positive".into(),
+                ));
+            }
+            if spec.expire_months == 0 || spec.expire_months > 3 {
+                return Err(AppError::ValidationError(
+                    "Expiration period must be between 1-3 months".into(),
+                ));
+            }
+        }
+
+        let txn = self.pool.begin().await?;
+        let mut batch = HashSet::new();
+        let mut candidates = Vec::with_capacity(specs.len());
+        for spec in specs {
+            // 同批次内也需去重
+            let code = Self::generate_unique_discount_code_batch(&txn, &batch).await?;
+            batch.insert(code.clone());
+            candidates.push((code, spec));
+        }
+
+        let mut registered = Vec::with_capacity(candidates.len());
+        let mut first_error = None;
+        {
+            let mut api = self.sevencloud_api.lock().await;
+            for (code, spec) in candidates {
+                let discount_dollars = spec.amount as f64 / 100.0;
+                match api
+                    .generate_discount_code(&code, discount_dollars, spec.expire_months)
+                    .await
+                {
+                    Ok(_) => registered.push((code, spec)),
+                    Err(e) => {
+                        log::error!(
+                            "Failed to register discount code {code} for user {user_id}: {e:?}"
+                        );
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+        }
+
+        if registered.is_empty() {
+            txn.rollback().await?;
+            return Err(first_error.unwrap_or_else(|| {
+                AppError::InternalError("No discount codes were created".into())
+            }));
+        }
+
+        let now = Utc::now();
+        let codes: Vec<String> = registered.iter().map(|(code, _)| code.clone()).collect();
+        let models = registered
+            .into_iter()
+            .map(|(code, spec)| discount_codes::ActiveModel {
+                user_id: Set(Some(user_id)),
+                code: Set(code),
+                discount_amount: Set(spec.amount),
+                code_type: Set(spec.code_type),
+                is_used: Set(Some(false)),
+                expires_at: Set(now + Duration::days(30 * spec.expire_months as i64)),
+                ..Default::default()
+            });
+        discount_codes::Entity::insert_many(models)
+            .exec_without_returning(&txn)
+            .await?;
+        txn.commit().await?;
+
+        Ok(codes)
+    }
+
+    /// 批量生成不绑定用户的活动优惠码。逐个在七云注册，注册成功的码立即入库，注册失败的码跳过；
+    /// 不在数据库事务中等待七云。失败比例超过阈值时停止生成剩余的码，已注册的码保留并返回
+    pub async fn bulk_generate(
+        &self,
+        count: u32,
+        amount: i64,
+        code_type: CodeType,
+        expire_months: u32,
+    ) -> AppResult<BulkGenerateDiscountCodesResponse> {
+        if count == 0 || count > BULK_GENERATE_MAX {
+            return Err(AppError::ValidationError(format!(
+                "Count must be between 1 and {BULK_GENERATE_MAX}"
+            )));
+        }
         if amount <= 0 {
             return Err(AppError::ValidationError(
                 "Discount amount must be positive".into(),
@@ -287,49 +757,326 @@ impl DiscountCodeService {
         }
 
         let expires_at = Utc::now() + Duration::days(30 * expire_months as i64);
+        let discount_dollars = amount as f64 / 100.0;
+        let mut attempted = HashSet::new();
+        let mut codes = Vec::with_capacity(count as usize);
+        let mut failed: u32 = 0;
+        let mut aborted = false;
 
-        // 生成唯一 6 位数字码
-        let code = {
-            let mut tries = 0;
-            loop {
-                tries += 1;
-                let candidate = generate_six_digit_code();
-                let exists = discount_codes::Entity::find()
-                    .filter(discount_codes::Column::Code.eq(candidate.clone()))
-                    .one(&self.pool)
-                    .await?;
-                if exists.is_none() {
-                    break candidate;
+        for _ in 0..count {
+            // 注册失败的码不入库，也需排除
+            let code = Self::generate_unique_discount_code_batch(&self.pool, &attempted).await?;
+            attempted.insert(code.clone());
+
+            let registered = {
+                let mut api = self.sevencloud_api.lock().await;
+                api.generate_discount_code(&code, discount_dollars, expire_months)
+                    .await
+            };
+            if let Err(e) = registered {
+                log::warn!("Bulk discount code registration failed: code={code}, error={e:?}");
+                failed += 1;
+                if failed * 100 > count * BULK_FAILURE_THRESHOLD_PERCENT {
+                    log::error!(
+                        "Too many discount codes rejected by SevenCloud ({failed}/{count}), stopping after {} codes",
+                        codes.len()
+                    );
+                    aborted = true;
+                    break;
                 }
-                if tries >= 10 {
-                    return Err(AppError::InternalError(
-                        "Failed to generate unique discount code".into(),
-                    ));
+                continue;
+            }
+
+            discount_codes::ActiveModel {
+                user_id: Set(None),
+                code: Set(code.clone()),
+                discount_amount: Set(amount),
+                code_type: Set(code_type.clone()),
+                is_used: Set(Some(false)),
+                expires_at: Set(expires_at),
+                ..Default::default()
+            }
+            .insert(&self.pool)
+            .await
+            .inspect_err(|e| {
+                log::error!(
+                    "Discount code registered in SevenCloud but not saved: code={code}, error={e:?}"
+                )
+            })?;
+            codes.push(code);
+        }
+
+        log::info!(
+            "Bulk generated {} discount codes ({} failed), amount: {amount}",
+            codes.len(),
+            failed
+        );
+
+        Ok(BulkGenerateDiscountCodesResponse {
+            codes,
+            failed,
+            aborted,
+            expires_at,
+        })
+    }
+
+    /// 在七云注册 pending 优惠码：成功置为 active，失败置为 failed 并退还扣减的 stamps/余额
+    pub async fn register_pending_code(
+        &self,
+        code_id: i64,
+        code: &str,
+        amount: i64,
+        expire_months: u32,
+    ) -> AppResult<()> {
+        let discount_dollars = amount as f64 / 100.0;
+        let registered = {
+            let mut api = self.sevencloud_api.lock().await;
+            api.generate_discount_code(code, discount_dollars, expire_months)
+                .await
+        };
+
+        match registered {
+            Ok(_) => {
+                self.activate_pending_code(code_id).await?;
+                Ok(())
+            }
+            Err(e) => {
+                if let Err(refund_err) = self.fail_pending_code(code_id).await {
+                    // 退还失败时保持 pending，交由对账任务处理
+                    log::error!("Failed to refund pending discount code {code_id}: {refund_err:?}");
                 }
+                Err(e)
             }
+        }
+    }
+
+    /// pending -> active
+    async fn activate_pending_code(&self, code_id: i64) -> AppResult<bool> {
+        let res = discount_codes::Entity::update_many()
+            .col_expr(
+                discount_codes::Column::Status,
+                DiscountCodeStatus::Active.as_enum(),
+            )
+            .col_expr(discount_codes::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(discount_codes::Column::Id.eq(code_id))
+            .filter(discount_codes::Column::Status.eq(DiscountCodeStatus::Pending))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    /// pending -> failed，并在同一事务内退还兑换时扣除的 stamps 或余额
+    async fn fail_pending_code(&self, code_id: i64) -> AppResult<bool> {
+        let txn = self.pool.begin().await?;
+
+        let res = discount_codes::Entity::update_many()
+            .col_expr(
+                discount_codes::Column::Status,
+                DiscountCodeStatus::Failed.as_enum(),
+            )
+            .col_expr(discount_codes::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(discount_codes::Column::Id.eq(code_id))
+            .filter(discount_codes::Column::Status.eq(DiscountCodeStatus::Pending))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
+            txn.rollback().await?;
+            return Ok(false);
+        }
+
+        let Some(dc) = discount_codes::Entity::find_by_id(code_id)
+            .one(&txn)
+            .await?
+        else {
+            txn.rollback().await?;
+            return Ok(false);
+        };
+        let Some(user_id) = dc.user_id else {
+            txn.commit().await?;
+            return Ok(true);
         };
 
-        let discount_dollars = amount as f64 / 100.0;
+        if let Some(stamps) = dc.stamps_spent {
+            // 印花兑换：退还 stamps
+            if let Some(u) = users::Entity::find_by_id(user_id).one(&txn).await? {
+                let new_stamps = u.stamps.unwrap_or(0) + stamps;
+                let mut am = u.into_active_model();
+                am.stamps = Set(Some(new_stamps));
+                am.update(&txn).await?;
+            }
+        } else if let Some(redeem) = sct::Entity::find()
+            .filter(sct::Column::RelatedDiscountCodeId.eq(code_id))
+            .filter(sct::Column::TransactionType.eq(crate::entities::TransactionType::Redeem))
+            .one(&txn)
+            .await?
         {
-            let mut api = self.sevencloud_api.lock().await;
-            api.generate_discount_code(&code, discount_dollars, expire_months)
+            // 余额兑换：退还余额并记录流水
+            if let Some(u) = users::Entity::find_by_id(user_id).one(&txn).await? {
+                let new_balance = u.balance.unwrap_or(0) + redeem.amount;
+                let mut am = u.into_active_model();
+                am.balance = Set(Some(new_balance));
+                am.update(&txn).await?;
+
+                sct::ActiveModel {
+                    user_id: Set(user_id),
+                    transaction_type: Set(crate::entities::TransactionType::Earn),
+                    amount: Set(redeem.amount),
+                    balance_after: Set(new_balance),
+                    related_order_id: Set(None),
+                    related_discount_code_id: Set(Some(code_id)),
+                    description: Set(Some(format!("Refund for failed discount code {}", dc.code))),
+                    ..Default::default()
+                }
+                .insert(&txn)
                 .await?;
+            }
         }
 
-        // 插入数据库
-        let created = discount_codes::ActiveModel {
-            user_id: Set(user_id),
-            code: Set(code),
-            discount_amount: Set(amount),
-            code_type: Set(code_type),
-            is_used: Set(Some(false)),
-            expires_at: Set(expires_at),
-            ..Default::default()
+        txn.commit().await?;
+        log::warn!("Discount code {} failed to register, refunded", dc.code);
+        Ok(true)
+    }
+
+    /// 对账长时间停留在 pending 的优惠码：七云已存在则置为 active，否则置为 failed 并退还。
+    /// 返回处理的数量
+    pub async fn reconcile_pending_codes(&self) -> AppResult<u64> {
+        let cutoff = Utc::now() - Duration::minutes(PENDING_RECONCILE_AFTER_MINUTES);
+        let pending = discount_codes::Entity::find()
+            .filter(discount_codes::Column::Status.eq(DiscountCodeStatus::Pending))
+            .filter(discount_codes::Column::CreatedAt.lt(cutoff))
+            .all(&self.pool)
+            .await?;
+        if pending.is_empty() {
+            return Ok(0);
         }
-        .insert(&self.pool)
-        .await?;
-        let id = created.id;
 
-        Ok(id)
+        let external: std::collections::HashSet<String> = {
+            let mut api = self.sevencloud_api.lock().await;
+            api.get_discount_codes(None)
+                .await?
+                .into_iter()
+                .map(|c| c.code.to_string())
+                .collect()
+        };
+
+        let mut handled = 0u64;
+        for dc in pending {
+            let done = if external.contains(&dc.code) {
+                self.activate_pending_code(dc.id).await
+            } else {
+                self.fail_pending_code(dc.id).await
+            };
+            match done {
+                Ok(true) => handled += 1,
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to reconcile pending discount code {}: {e:?}", dc.id),
+            }
+        }
+        Ok(handled)
+    }
+
+    /// 将已过期且未使用的优惠码标记为 is_expired，返回本次标记的数量
+    pub async fn expire_codes(&self) -> AppResult<u64> {
+        let now = Utc::now();
+        let res = discount_codes::Entity::update_many()
+            .col_expr(discount_codes::Column::IsExpired, Expr::value(true))
+            .col_expr(discount_codes::Column::UpdatedAt, Expr::value(now))
+            .filter(discount_codes::Column::IsExpired.eq(false))
+            .filter(
+                discount_codes::Column::IsUsed
+                    .eq(false)
+                    .or(discount_codes::Column::IsUsed.is_null()),
+            )
+            .filter(discount_codes::Column::ExpiresAt.lte(now))
+            .exec_with_returning(&self.pool)
+            .await?;
+        let expired = res.len() as u64;
+
+        // 按用户汇总本次过期的可用优惠码并发送通知
+        let mut by_user: BTreeMap<i64, Vec<discount_codes::Model>> = BTreeMap::new();
+        for code in res {
+            if let (Some(user_id), DiscountCodeStatus::Active) = (code.user_id, &code.status) {
+                by_user.entry(user_id).or_default().push(code);
+            }
+        }
+        for (user_id, codes) in by_user {
+            let payload = json!({
+                "count": codes.len(),
+                "codes": codes.iter().map(|c| json!({
+                    "code": c.code,
+                    "discount_amount": c.discount_amount,
+                    "code_type": c.code_type,
+                    "expires_at": c.expires_at,
+                })).collect::<Vec<_>>(),
+            });
+            if let Err(e) = self
+                .notification_service
+                .create(user_id, NotificationKind::CouponExpired, payload)
+                .await
+            {
+                log::warn!("Failed to notify user {user_id} of expired codes: {e:?}");
+            }
+        }
+        Ok(expired)
+    }
+
+    /// 生成本地唯一的 6 位数字码
+    async fn generate_unique_code(txn: &sea_orm::DatabaseTransaction) -> AppResult<String> {
+        Self::generate_unique_discount_code_batch(txn, &HashSet::new()).await
+    }
+
+    /// 一次生成一批候选码，用单条查询排除已存在的码后取第一个可用的，
+    /// 减少逐个候选查库的往返；`exclude` 用于排除同批次内已分配的码
+    async fn generate_unique_discount_code_batch<C: ConnectionTrait>(
+        conn: &C,
+        exclude: &HashSet<String>,
+    ) -> AppResult<String> {
+        for _ in 0..UNIQUE_CODE_MAX_ROUNDS {
+            let candidates: Vec<String> = generate_six_digit_codes(UNIQUE_CODE_BATCH_SIZE)
+                .into_iter()
+                .filter(|c| !exclude.contains(c))
+                .collect();
+            let taken: HashSet<String> = discount_codes::Entity::find()
+                .select_only()
+                .column(discount_codes::Column::Code)
+                .filter(discount_codes::Column::Code.is_in(candidates.clone()))
+                .into_tuple::<String>()
+                .all(conn)
+                .await?
+                .into_iter()
+                .collect();
+            if let Some(code) = candidates.into_iter().find(|c| !taken.contains(c)) {
+                return Ok(code);
+            }
+        }
+        Err(AppError::InternalError(
+            "Failed to generate unique discount code".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(value_cents: i64, stamps_required: i64) -> StampRedemptionTierResponse {
+        StampRedemptionTierResponse {
+            value_cents,
+            stamps_required,
+            active: true,
+        }
+    }
+
+    #[test]
+    fn test_next_stamp_reward() {
+        let tiers = [tier(550, 10), tier(1000, 18)];
+        assert_eq!(stamps_required_for(&tiers, 550).unwrap(), 10);
+        assert_eq!(stamps_required_for(&tiers, 1000).unwrap(), 18);
+        assert!(stamps_required_for(&tiers, 500).is_err());
+        assert_eq!(next_stamp_reward(&tiers, 0), (10, 550));
+        assert_eq!(next_stamp_reward(&tiers, 7), (3, 550));
+        assert_eq!(next_stamp_reward(&tiers, 12), (6, 1000));
+        assert_eq!(next_stamp_reward(&tiers, 20), (0, 1000));
+        assert_eq!(next_stamp_reward(&[], 5), (0, 0));
     }
 }
@@ -1,35 +1,105 @@
+use crate::config::LuckyDrawConfig;
 use crate::entities::{
-    CodeType, MonthlyCardPlanType, MonthlyCardStatus, lucky_draw_chance_entity as chances,
-    lucky_draw_prize_entity as prizes, lucky_draw_record_entity as records,
-    monthly_card_entity as mc,
+    CodeType, MonthlyCardPlanType, MonthlyCardStatus, discount_code_entity as discount_codes,
+    lucky_draw_chance_entity as chances, lucky_draw_prize_entity as prizes,
+    lucky_draw_record_entity as records, monthly_card_entity as mc,
 };
 use crate::error::{AppError, AppResult};
 use crate::models::{
-    LuckyDrawChancesResponse, LuckyDrawPrizeResponse, LuckyDrawRecordPageResponse,
-    LuckyDrawRecordQuery, LuckyDrawRecordResponse, LuckyDrawSpinResponse, LuckyDrawWonPrize,
-    PaginatedResponse, PaginationParams,
+    LuckyDrawChancesResponse, LuckyDrawPrizeResponse, LuckyDrawPrizeStat,
+    LuckyDrawRecordPageResponse, LuckyDrawRecordQuery, LuckyDrawRecordResponse,
+    LuckyDrawSpinResponse, LuckyDrawStatsResponse, LuckyDrawWonPrize, PaginatedResponse,
+    PaginationParams, RefillLuckyDrawPrizeRequest, UpsertLuckyDrawPrizeRequest,
 };
 use crate::services::DiscountCodeService;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
 use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
-    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseBackend, DatabaseConnection, DbErr,
+    EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    Statement, TransactionTrait,
 };
 use sea_orm::{Condition, Order, UpdateResult};
 
+/// 启用奖品概率之和上限 (100%)
+const TOTAL_PROBABILITY_BP: i32 = 10000;
+/// 未中奖奖品名称
+const THANK_YOU_PRIZE: &str = "Thank You";
+const FREE_TOPPING_PRIZE: &str = "Free Topping Coupon";
+const FREE_ICE_CREAM_PRIZE: &str = "Free Original Ice Cream Coupon";
+const HALF_PRICE_ICE_CREAM_PRIZE: &str = "Half Price Ice Cream Coupon";
+const MONTHLY_CARD_PRIZE: &str = "Membership Monthly Card";
+/// `award_prize` 能发放的奖品名称，管理端只能配置这些奖品
+const SUPPORTED_PRIZES: [&str; 5] = [
+    FREE_TOPPING_PRIZE,
+    FREE_ICE_CREAM_PRIZE,
+    HALF_PRICE_ICE_CREAM_PRIZE,
+    MONTHLY_CARD_PRIZE,
+    THANK_YOU_PRIZE,
+];
+/// 奖品优惠券有效期（月）
+const PRIZE_COUPON_EXPIRE_MONTHS: u32 = 1;
+
+/// 按概率 (basis points) 加权随机选择奖品。
+/// force_win 为 true 时排除 "Thank You"（若排除后无可选奖品则退回全部）
+fn pick_prize<R: Rng + ?Sized>(
+    rng: &mut R,
+    candidates: &[prizes::Model],
+    force_win: bool,
+) -> Option<prizes::Model> {
+    let winning: Vec<&prizes::Model> = candidates
+        .iter()
+        .filter(|p| p.name_en != THANK_YOU_PRIZE && p.probability_bp > 0)
+        .collect();
+    let pool: Vec<&prizes::Model> = if force_win && !winning.is_empty() {
+        winning
+    } else {
+        candidates.iter().collect()
+    };
+
+    let total_bp: i32 = pool.iter().map(|p| p.probability_bp).sum();
+    if total_bp <= 0 {
+        return None;
+    }
+
+    let pick: i32 = rng.random_range(0..total_bp);
+    let mut acc = 0;
+    for p in &pool {
+        acc += p.probability_bp;
+        if pick < acc {
+            return Some((*p).clone());
+        }
+    }
+    pool.last().map(|p| (*p).clone())
+}
+
+/// 抽奖后的连续未中奖次数：抽中 "Thank You" 累加，否则清零
+fn next_losses_since_win(current: i32, prize: &prizes::Model) -> i32 {
+    if prize.name_en == THANK_YOU_PRIZE {
+        current + 1
+    } else {
+        0
+    }
+}
+
 #[derive(Clone)]
 pub struct LuckyDrawService {
     pool: DatabaseConnection,
     discount_code_service: DiscountCodeService,
+    config: LuckyDrawConfig,
 }
 
 impl LuckyDrawService {
-    pub fn new(pool: DatabaseConnection, discount_code_service: DiscountCodeService) -> Self {
+    pub fn new(
+        pool: DatabaseConnection,
+        discount_code_service: DiscountCodeService,
+        config: LuckyDrawConfig,
+    ) -> Self {
         Self {
             pool,
             discount_code_service,
+            config,
         }
     }
 
@@ -49,6 +119,226 @@ impl LuckyDrawService {
         Ok(list.into_iter().map(Into::into).collect())
     }
 
+    /// 获取全部奖品（含未启用的，管理端使用）
+    pub async fn list_all_prizes(&self) -> AppResult<Vec<LuckyDrawPrizeResponse>> {
+        let list = prizes::Entity::find()
+            .order_by_asc(prizes::Column::Id)
+            .all(&self.pool)
+            .await?;
+        Ok(list.into_iter().map(Into::into).collect())
+    }
+
+    /// 按奖品汇总区间内的抽奖记录，对比实际概率与配置概率
+    pub async fn prize_stats(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> AppResult<LuckyDrawStatsResponse> {
+        let mut query = records::Entity::find()
+            .select_only()
+            .column(records::Column::PrizeId)
+            .column_as(records::Column::Id.count(), "count")
+            .column_as(
+                Expr::cust("COALESCE(SUM(value_cents), 0)::bigint"),
+                "total_value_cents",
+            )
+            .group_by(records::Column::PrizeId);
+        if let Some(from) = from {
+            query = query.filter(records::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(records::Column::CreatedAt.lt(to));
+        }
+        let rows: Vec<(i64, i64, i64)> = query.into_tuple().all(&self.pool).await?;
+
+        let total_spins: i64 = rows.iter().map(|(_, count, _)| count).sum();
+        let actual_bp = |count: i64| {
+            if total_spins > 0 {
+                count as f64 * TOTAL_PROBABILITY_BP as f64 / total_spins as f64
+            } else {
+                0.0
+            }
+        };
+
+        let prize_list = prizes::Entity::find()
+            .order_by_asc(prizes::Column::Id)
+            .all(&self.pool)
+            .await?;
+        let mut stats: Vec<LuckyDrawPrizeStat> = prize_list
+            .iter()
+            .map(|p| {
+                let (count, total_value_cents) = rows
+                    .iter()
+                    .find(|(prize_id, _, _)| *prize_id == p.id)
+                    .map(|(_, c, v)| (*c, *v))
+                    .unwrap_or((0, 0));
+                LuckyDrawPrizeStat {
+                    prize_id: p.id,
+                    name_en: Some(p.name_en.clone()),
+                    count,
+                    total_value_cents,
+                    expected_probability_bp: Some(p.probability_bp),
+                    actual_probability_bp: actual_bp(count),
+                    stock_limit: p.stock_limit,
+                    stock_remaining: p.stock_remaining,
+                }
+            })
+            .collect();
+        // 记录中存在但奖品已被删除的情况
+        for (prize_id, count, total_value_cents) in &rows {
+            if !prize_list.iter().any(|p| p.id == *prize_id) {
+                stats.push(LuckyDrawPrizeStat {
+                    prize_id: *prize_id,
+                    name_en: None,
+                    count: *count,
+                    total_value_cents: *total_value_cents,
+                    expected_probability_bp: None,
+                    actual_probability_bp: actual_bp(*count),
+                    stock_limit: None,
+                    stock_remaining: None,
+                });
+            }
+        }
+
+        Ok(LuckyDrawStatsResponse {
+            total_spins,
+            prizes: stats,
+        })
+    }
+
+    /// 创建或更新奖品；调整总库存时剩余库存按差值同步增减（最低为 0）。
+    /// 写入后启用奖品的概率之和超过 10000bp 则整体回滚
+    pub async fn upsert_prize(
+        &self,
+        request: UpsertLuckyDrawPrizeRequest,
+    ) -> AppResult<LuckyDrawPrizeResponse> {
+        if request.name_en.trim().is_empty() {
+            return Err(AppError::ValidationError("Prize name is required".into()));
+        }
+        if !SUPPORTED_PRIZES.contains(&request.name_en.trim()) {
+            return Err(AppError::ValidationError(format!(
+                "Unsupported prize name, valid options: {}",
+                SUPPORTED_PRIZES.join(", ")
+            )));
+        }
+        if request.value_cents < 0 {
+            return Err(AppError::ValidationError(
+                "Prize value must not be negative".into(),
+            ));
+        }
+        if !(0..=TOTAL_PROBABILITY_BP).contains(&request.probability_bp) {
+            return Err(AppError::ValidationError(format!(
+                "probability_bp must be between 0 and {TOTAL_PROBABILITY_BP}"
+            )));
+        }
+        if request.stock_limit.is_some_and(|l| l < 0) {
+            return Err(AppError::ValidationError(
+                "Stock limit must not be negative".into(),
+            ));
+        }
+
+        let txn = self.pool.begin().await?;
+        let now = Utc::now();
+
+        let saved = match request.id {
+            Some(id) => {
+                let existing = prizes::Entity::find_by_id(id)
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound("Prize not found".into()))?;
+                let stock_remaining = match (existing.stock_limit, request.stock_limit) {
+                    (_, None) => None,
+                    (Some(old), Some(new)) => {
+                        Some((existing.stock_remaining.unwrap_or(0) + new - old).max(0))
+                    }
+                    (None, Some(new)) => Some(new),
+                };
+                let mut am = existing.into_active_model();
+                am.name_en = Set(request.name_en.trim().to_string());
+                am.value_cents = Set(request.value_cents);
+                am.probability_bp = Set(request.probability_bp);
+                am.stock_limit = Set(request.stock_limit);
+                am.stock_remaining = Set(stock_remaining);
+                am.is_active = Set(request.is_active);
+                am.updated_at = Set(Some(now));
+                am.update(&txn).await?
+            }
+            None => {
+                prizes::ActiveModel {
+                    name_en: Set(request.name_en.trim().to_string()),
+                    value_cents: Set(request.value_cents),
+                    probability_bp: Set(request.probability_bp),
+                    stock_limit: Set(request.stock_limit),
+                    stock_remaining: Set(request.stock_limit),
+                    is_active: Set(request.is_active),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?
+            }
+        };
+
+        let total_bp: i32 = prizes::Entity::find()
+            .filter(prizes::Column::IsActive.eq(true))
+            .all(&txn)
+            .await?
+            .iter()
+            .map(|p| p.probability_bp)
+            .sum();
+        if total_bp > TOTAL_PROBABILITY_BP {
+            txn.rollback().await?;
+            return Err(AppError::ValidationError(format!(
+                "Active prizes probability sum {total_bp}bp exceeds {TOTAL_PROBABILITY_BP}bp"
+            )));
+        }
+
+        txn.commit().await?;
+        Ok(saved.into())
+    }
+
+    /// 补充限量奖品库存（同时增加 stock_limit 与 stock_remaining）
+    pub async fn refill_prize_stock(
+        &self,
+        prize_id: i64,
+        request: RefillLuckyDrawPrizeRequest,
+    ) -> AppResult<LuckyDrawPrizeResponse> {
+        if request.amount <= 0 {
+            return Err(AppError::ValidationError(
+                "Refill amount must be positive".into(),
+            ));
+        }
+        let prize = prizes::Entity::find_by_id(prize_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Prize not found".into()))?;
+        if !prize.is_limited() {
+            return Err(AppError::ValidationError(
+                "Prize has unlimited stock".into(),
+            ));
+        }
+
+        // 原子增加，避免与抽奖扣减竞争
+        prizes::Entity::update_many()
+            .col_expr(
+                prizes::Column::StockLimit,
+                Expr::col(prizes::Column::StockLimit).add(request.amount),
+            )
+            .col_expr(
+                prizes::Column::StockRemaining,
+                Expr::cust_with_values("COALESCE(stock_remaining, 0) + $1", [request.amount]),
+            )
+            .col_expr(prizes::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(prizes::Column::Id.eq(prize_id))
+            .exec(&self.pool)
+            .await?;
+
+        let updated = prizes::Entity::find_by_id(prize_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Prize not found".into()))?;
+        Ok(updated.into())
+    }
+
     /// 获取抽奖记录（分页）
     pub async fn list_records(
         &self,
@@ -117,24 +407,34 @@ impl LuckyDrawService {
             ));
         }
 
+        // 连续未中奖达到保底阈值时，本次必中非 "Thank You" 奖品
+        let pity_threshold = self.config.pity_threshold;
+        let force_win = pity_threshold > 0 && user_chances.losses_since_win >= pity_threshold;
+
         // 选择奖品（支持在某个限量奖品并发扣减失败后重试）
         let selected_prize = self
-            .select_and_secure_prize(&txn, &prize_list)
+            .select_and_secure_prize(&txn, &prize_list, force_win)
             .await
-            .map_err(|e| {
-                AppError::InternalError(format!("Prize selection failed: {}", e.to_string()))
-            })?;
+            .map_err(|e| AppError::InternalError(format!("Prize selection failed: {e}")))?;
+
+        // 先发放实际奖品：月卡在本事务内直接生效，优惠券以 pending 落库，
+        // 提交后再到七云注册，不在持有库存行锁的事务中等待外部接口
+        let coupon = self.award_prize(&txn, user_id, &selected_prize).await?;
 
         // 更新已用次数
         {
             let mut am = user_chances.clone().into_active_model();
             am.total_used = Set(user_chances.total_used + 1);
+            am.losses_since_win = Set(next_losses_since_win(
+                user_chances.losses_since_win,
+                &selected_prize,
+            ));
             am.updated_at = Set(Some(Utc::now()));
             am.update(&txn).await?;
         }
 
         // 写抽奖记录
-        records::ActiveModel {
+        let record = records::ActiveModel {
             user_id: Set(user_id),
             prize_id: Set(selected_prize.id),
             prize_name_en: Set(selected_prize.name_en.clone()),
@@ -144,21 +444,82 @@ impl LuckyDrawService {
         .insert(&txn)
         .await?;
 
-        // 发放实际奖品（优惠券 / 月卡等）
-        // 注意：优惠券创建内部会使用新的事务与外部接口；若失败将返回错误并导致本次 spin 事务回滚
-        self.award_prize(user_id, &selected_prize).await?;
-
         // 计算剩余次数
         let remaining_after = user_chances.total_awarded - (user_chances.total_used + 1);
 
         txn.commit().await?;
 
+        // 七云注册失败时优惠码置为 failed，并撤销本次抽奖：退还库存与次数
+        if let Some(coupon) = coupon
+            && let Err(e) = self
+                .discount_code_service
+                .register_pending_code(
+                    coupon.id,
+                    &coupon.code,
+                    coupon.discount_amount,
+                    PRIZE_COUPON_EXPIRE_MONTHS,
+                )
+                .await
+        {
+            if let Err(revert_err) = self
+                .revert_spin(&user_chances, &selected_prize, record.id)
+                .await
+            {
+                log::error!(
+                    "Failed to revert lucky draw spin {} for user {user_id}: {revert_err:?}",
+                    record.id
+                );
+            }
+            return Err(e);
+        }
+
         Ok(LuckyDrawSpinResponse {
             prize: LuckyDrawWonPrize::from(selected_prize),
             remaining_chances: remaining_after,
         })
     }
 
+    /// 撤销一次已提交但奖品发放失败的抽奖：退还限量奖品库存与已用次数，删除抽奖记录
+    async fn revert_spin(
+        &self,
+        before: &chances::Model,
+        prize: &prizes::Model,
+        record_id: i64,
+    ) -> AppResult<()> {
+        let txn = self.pool.begin().await?;
+        let deleted = records::Entity::delete_by_id(record_id).exec(&txn).await?;
+        if deleted.rows_affected == 0 {
+            txn.rollback().await?;
+            return Ok(());
+        }
+        if prize.stock_limit.is_some() {
+            prizes::Entity::update_many()
+                .col_expr(
+                    prizes::Column::StockRemaining,
+                    Expr::col(prizes::Column::StockRemaining).add(1),
+                )
+                .filter(prizes::Column::Id.eq(prize.id))
+                .filter(prizes::Column::StockRemaining.is_not_null())
+                .exec(&txn)
+                .await?;
+        }
+        chances::Entity::update_many()
+            .col_expr(
+                chances::Column::TotalUsed,
+                Expr::col(chances::Column::TotalUsed).sub(1),
+            )
+            .col_expr(
+                chances::Column::LossesSinceWin,
+                Expr::value(before.losses_since_win),
+            )
+            .col_expr(chances::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(chances::Column::Id.eq(before.id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
     /// 为用户增加抽奖次数（任务/充值触发）
     /// 业务方可调用此方法进行发放。
     pub async fn award_chances(
@@ -183,6 +544,50 @@ impl LuckyDrawService {
         Ok(updated.into())
     }
 
+    /// 每日免费抽奖：为今天尚未领取的用户 total_awarded +1（可配置仅发给活跃用户）。
+    /// 以 last_free_spin_on 判重，同一天重复执行不会重复发放。返回发放人数
+    pub async fn grant_daily_free_spins(&self) -> AppResult<u64> {
+        let today = Utc::now().date_naive();
+        let active_since = Utc::now() - Duration::days(30);
+        // 仅活跃用户时，限定为近 30 天有订单的用户
+        let user_filter = if self.config.free_spin_active_only {
+            "u.id IN (SELECT o.user_id FROM orders o WHERE o.external_created_at >= $1)"
+        } else {
+            "$1::timestamptz IS NOT NULL"
+        };
+
+        let txn = self.pool.begin().await?;
+
+        // 为尚无统计记录的用户补建记录
+        txn.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            format!(
+                "INSERT INTO lucky_draw_chances (user_id, total_awarded, total_used) \
+                 SELECT u.id, 0, 0 FROM users u WHERE {user_filter} \
+                 ON CONFLICT (user_id) DO NOTHING"
+            ),
+            [active_since.into()],
+        ))
+        .await?;
+
+        let res = txn
+            .execute(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                format!(
+                    "UPDATE lucky_draw_chances c \
+                     SET total_awarded = c.total_awarded + 1, last_free_spin_on = $2, updated_at = NOW() \
+                     FROM users u \
+                     WHERE u.id = c.user_id AND {user_filter} \
+                     AND c.last_free_spin_on IS DISTINCT FROM $2"
+                ),
+                [active_since.into(), today.into()],
+            ))
+            .await?;
+
+        txn.commit().await?;
+        Ok(res.rows_affected())
+    }
+
     // -----------------------------
     // 内部辅助方法
     // -----------------------------
@@ -232,6 +637,7 @@ impl LuckyDrawService {
         &self,
         txn: &sea_orm::DatabaseTransaction,
         available: &[prizes::Model],
+        force_win: bool,
     ) -> Result<prizes::Model, DbErr> {
         // 使用循环以处理限量奖品竞争失败的情况
         let mut attempts = 0;
@@ -240,25 +646,9 @@ impl LuckyDrawService {
         while attempts < 5 {
             attempts += 1;
 
-            // 重新计算总概率 (只使用当前可用 prize)
-            let total_bp: i32 = filtered.iter().map(|p| p.probability_bp).sum();
-            if total_bp <= 0 {
+            let chosen = pick_prize(&mut rand::rng(), &filtered, force_win)
                 // 理论上不应发生
-                return Err(DbErr::Custom("Total probability <= 0".into()));
-            }
-
-            let mut rng = rand::rng();
-            let pick: i32 = rng.random_range(0..total_bp);
-            let mut acc = 0;
-            let mut chosen = filtered.last().expect("Non-empty vector").clone(); // fallback
-
-            for p in &filtered {
-                acc += p.probability_bp;
-                if pick < acc {
-                    chosen = p.clone();
-                    break;
-                }
-            }
+                .ok_or_else(|| DbErr::Custom("Total probability <= 0".into()))?;
 
             // 若非限量或无限库存直接返回
             if chosen.stock_limit.is_none() {
@@ -312,30 +702,36 @@ impl LuckyDrawService {
     /// - Half Price Ice Cream Coupon -> 250 cents, CodeType::SweetsCreditsReward
     /// - Membership Monthly Card -> 创建一条月卡记录（立即生效，30天有效）
     /// - Thank You -> 无发放
-    async fn award_prize(&self, user_id: i64, prize: &prizes::Model) -> AppResult<()> {
+    ///
+    /// 优惠券只在事务内以 pending 落库并返回，由调用方提交后注册
+    async fn award_prize(
+        &self,
+        txn: &sea_orm::DatabaseTransaction,
+        user_id: i64,
+        prize: &prizes::Model,
+    ) -> AppResult<Option<discount_codes::Model>> {
+        let coupon = match prize.name_en.as_str() {
+            FREE_TOPPING_PRIZE => Some((50, CodeType::FreeTopping)),
+            FREE_ICE_CREAM_PRIZE => Some((500, CodeType::SweetsCreditsReward)),
+            HALF_PRICE_ICE_CREAM_PRIZE => Some((250, CodeType::SweetsCreditsReward)),
+            _ => None,
+        };
+        if let Some((amount, code_type)) = coupon {
+            let code = self
+                .discount_code_service
+                .create_pending_user_discount_code_tx(
+                    txn,
+                    user_id,
+                    amount,
+                    code_type,
+                    PRIZE_COUPON_EXPIRE_MONTHS,
+                )
+                .await?;
+            return Ok(Some(code));
+        }
+
         match prize.name_en.as_str() {
-            "Free Topping Coupon" => {
-                // 发放免费小料券 (50 cents)
-                self.discount_code_service
-                    .create_user_discount_code(
-                        user_id,
-                        50,
-                        CodeType::FreeTopping,
-                        1, // 有效期 1 个月
-                    )
-                    .await?;
-            }
-            "Free Original Ice Cream Coupon" => {
-                self.discount_code_service
-                    .create_user_discount_code(user_id, 500, CodeType::SweetsCreditsReward, 1)
-                    .await?;
-            }
-            "Half Price Ice Cream Coupon" => {
-                self.discount_code_service
-                    .create_user_discount_code(user_id, 250, CodeType::SweetsCreditsReward, 1)
-                    .await?;
-            }
-            "Membership Monthly Card" => {
+            MONTHLY_CARD_PRIZE => {
                 // 月卡叠加策略:
                 // 若存在仍在有效期内的 Active 月卡, 将其 ends_at 顺延 30 天
                 // 否则创建新的月卡记录 (one_time)
@@ -345,14 +741,14 @@ impl LuckyDrawService {
                     .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
                     .filter(mc::Column::EndsAt.gte(now))
                     .order_by_desc(mc::Column::EndsAt)
-                    .one(&self.pool)
+                    .one(txn)
                     .await?
                 {
                     // 顺延
                     let base_end = existing.ends_at.unwrap_or(now);
                     let mut am = existing.into_active_model();
                     am.ends_at = Set(Some(base_end + Duration::days(30)));
-                    am.update(&self.pool).await?;
+                    am.update(txn).await?;
                 } else {
                     // 创建新月卡
                     mc::ActiveModel {
@@ -363,18 +759,69 @@ impl LuckyDrawService {
                         ends_at: Set(Some(now + Duration::days(30))),
                         ..Default::default()
                     }
-                    .insert(&self.pool)
+                    .insert(txn)
                     .await?;
                 }
             }
-            "Thank You" => {
+            THANK_YOU_PRIZE => {
                 // 无奖励发放
             }
             _ => {
-                // 未知奖品名称（配置错误）- 记日志但不报错，避免用户丢失一次机会
+                // upsert_prize 已拒绝未知名称，这里只可能是库里遗留的旧数据 - 记日志但不报错，避免用户丢失一次机会
                 log::warn!("Unknown prize name encountered: {}", prize.name_en);
             }
         }
-        Ok(())
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn prize(id: i64, name: &str, bp: i32) -> prizes::Model {
+        prizes::Model {
+            id,
+            name_en: name.to_string(),
+            value_cents: 0,
+            probability_bp: bp,
+            stock_limit: None,
+            stock_remaining: None,
+            is_active: true,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_pity_forces_win_after_threshold() {
+        let threshold = 10;
+        let list = vec![
+            prize(1, THANK_YOU_PRIZE, 9990),
+            prize(2, "Free Topping Coupon", 10),
+        ];
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut losses = 0;
+
+        for _ in 0..50 {
+            let force_win = losses >= threshold;
+            let won = pick_prize(&mut rng, &list, force_win).unwrap();
+            if force_win {
+                assert_ne!(won.name_en, THANK_YOU_PRIZE);
+            }
+            losses = next_losses_since_win(losses, &won);
+            assert!(losses <= threshold);
+        }
+    }
+
+    #[test]
+    fn test_force_win_falls_back_when_only_thank_you() {
+        let list = vec![prize(1, THANK_YOU_PRIZE, 10000)];
+        let mut rng = StdRng::seed_from_u64(7);
+        let won = pick_prize(&mut rng, &list, true).unwrap();
+        assert_eq!(won.name_en, THANK_YOU_PRIZE);
+        assert_eq!(next_losses_since_win(3, &won), 4);
     }
 }
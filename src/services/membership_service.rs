@@ -1,41 +1,137 @@
+use crate::config::{CashbackConfig, MonthlyCardConfig};
 use crate::entities::StripeTransactionCategory;
 use crate::entities::{
     CodeType, MemberType, MembershipPurchaseStatus, membership_purchase_entity as mp,
+    membership_reward_grant_entity as reward_grants, referral_reward_entity as referral_rewards,
     user_entity as users,
 };
 use crate::error::{AppError, AppResult};
-use crate::external::StripeService;
+use crate::external::{StripeGateway, StripeService};
 use crate::models::*;
-use crate::services::{DiscountCodeService, StripeTransactionService};
+use crate::services::{
+    AuditEntry, AuditService, DiscountCodeService, DiscountCodeSpec, NotificationService,
+    StripeTransactionService, cashback_bps,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
-    QueryOrder, Set, TransactionTrait,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
+use serde_json::json;
+use std::sync::Arc;
 use stripe::PaymentIntentStatus;
 
+/// 会员有效期（天）
+const MEMBERSHIP_PERIOD_DAYS: i64 = 365;
+
+/// 被推荐人首次购买会员时，推荐人获得的奖励券面值（美分）
+const REFERRAL_REWARD_CENTS: i64 = 500;
+
+/// 推荐奖励券有效期（月）
+const REFERRAL_REWARD_EXPIRE_MONTHS: u32 = 1;
+
+/// 升级奖励发放的认领租约（分钟），超时未释放的记录可被重试任务重新认领
+const REWARD_GRANT_LEASE_MINUTES: i64 = 10;
+
+/// 每轮重试的升级奖励记录上限
+const REWARD_GRANT_RETRY_BATCH: u64 = 100;
+
+/// 升级会员时发放的奖励优惠码
+struct UpgradeReward {
+    count: u32,
+    value_cents: i64,
+    code_type: CodeType,
+}
+
 #[derive(Clone)]
 pub struct MembershipService {
     pool: DatabaseConnection,
-    stripe_service: StripeService,
+    stripe_service: Arc<dyn StripeGateway>,
     discount_code_service: DiscountCodeService,
     stx_service: StripeTransactionService,
+    audit_service: AuditService,
+    notification_service: NotificationService,
+    cashback: CashbackConfig,
+    monthly_card: MonthlyCardConfig,
 }
 
 impl MembershipService {
     pub fn new(
         pool: DatabaseConnection,
-        stripe_service: StripeService,
+        stripe_service: Arc<dyn StripeGateway>,
         discount_code_service: DiscountCodeService,
+        cashback: CashbackConfig,
+        monthly_card: MonthlyCardConfig,
     ) -> Self {
         let stx_service = StripeTransactionService::new(pool.clone());
+        let audit_service = AuditService::new(pool.clone());
+        let notification_service = NotificationService::new(pool.clone());
         Self {
             pool,
             stripe_service,
             discount_code_service,
             stx_service,
+            audit_service,
+            notification_service,
+            cashback,
+            monthly_card,
+        }
+    }
+
+    /// 各等级升级奖励：Sweet 1 张 $8，Super 10 张 $3
+    fn upgrade_reward(member_type: &MemberType) -> Option<UpgradeReward> {
+        match member_type {
+            MemberType::SweetShareholder => Some(UpgradeReward {
+                count: 1,
+                value_cents: 800,
+                code_type: CodeType::ShareholderReward,
+            }),
+            MemberType::SuperShareholder => Some(UpgradeReward {
+                count: 10,
+                value_cents: 300,
+                code_type: CodeType::SuperShareholderReward,
+            }),
+            MemberType::Fan => None,
+        }
+    }
+
+    /// 指定等级的权益摘要
+    pub fn benefits(&self, member_type: &MemberType) -> MembershipBenefits {
+        let reward = Self::upgrade_reward(member_type);
+        MembershipBenefits {
+            member_type: member_type.clone(),
+            cashback_bps: cashback_bps(&self.cashback, member_type),
+            upgrade_reward_count: reward.as_ref().map(|r| r.count).unwrap_or(0),
+            upgrade_reward_value_cents: reward.as_ref().map(|r| r.value_cents).unwrap_or(0),
+            monthly_card_daily_coupon_cents: self.monthly_card.daily_coupon_cents,
         }
     }
 
+    /// 全部等级权益；登录用户额外返回当前等级与到期时间
+    pub async fn benefits_summary(
+        &self,
+        user_id: Option<i64>,
+    ) -> AppResult<MembershipBenefitsResponse> {
+        let tiers = [
+            MemberType::Fan,
+            MemberType::SweetShareholder,
+            MemberType::SuperShareholder,
+        ]
+        .iter()
+        .map(|mt| self.benefits(mt))
+        .collect();
+        let user = match user_id {
+            Some(id) => users::Entity::find_by_id(id).one(&self.pool).await?,
+            None => None,
+        };
+        Ok(MembershipBenefitsResponse {
+            tiers,
+            current_member_type: user.as_ref().map(|u| u.member_type.clone()),
+            membership_expires_at: user.and_then(|u| u.membership_expires_at),
+        })
+    }
+
     fn membership_price_cents(target: &MemberType) -> Option<i64> {
         match target {
             MemberType::SweetShareholder => Some(800),  // $8
@@ -44,6 +140,76 @@ impl MembershipService {
         }
     }
 
+    /// 计算升级应付金额（美分）
+    ///
+    /// Sweet → Super 且 Sweet 尚未过期时，按剩余天数折算未使用的 Sweet 价值并从 Super 全价中扣除；
+    /// 其它情况（包括会员已过期）收取目标等级全价。
+    fn prorate_upgrade(
+        current: &MemberType,
+        target: &MemberType,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Option<i64> {
+        let full_price = Self::membership_price_cents(target)?;
+        if *current != MemberType::SweetShareholder || *target != MemberType::SuperShareholder {
+            return Some(full_price);
+        }
+        let Some(expires_at) = expires_at else {
+            return Some(full_price);
+        };
+        let remaining_days = (expires_at - Utc::now()).num_days();
+        if remaining_days <= 0 {
+            return Some(full_price);
+        }
+        let sweet_price = Self::membership_price_cents(current)?;
+        let unused_value =
+            sweet_price * remaining_days.min(MEMBERSHIP_PERIOD_DAYS) / MEMBERSHIP_PERIOD_DAYS;
+        // Stripe 最低收款 $0.50
+        Some((full_price - unused_value).max(50))
+    }
+
+    /// 自助购买会员时允许的等级变化：只能升级（fan -> sweet / super，sweet -> super），
+    /// 其余情况给出具体原因
+    fn allowed_transition(current: &MemberType, target: &MemberType) -> AppResult<()> {
+        match (current, target) {
+            (MemberType::Fan, MemberType::SweetShareholder | MemberType::SuperShareholder)
+            | (MemberType::SweetShareholder, MemberType::SuperShareholder) => Ok(()),
+            (MemberType::Fan, MemberType::Fan) => Err(AppError::ValidationError(
+                "Fan membership cannot be purchased".into(),
+            )),
+            (MemberType::SuperShareholder, MemberType::SuperShareholder) => Err(
+                AppError::ValidationError("Already highest membership".into()),
+            ),
+            (MemberType::SweetShareholder, MemberType::SweetShareholder) => Err(
+                AppError::ValidationError("Already sweet shareholder".into()),
+            ),
+            _ => Err(AppError::ValidationError(format!(
+                "Cannot downgrade from {} to {}",
+                Self::format_member_type(current),
+                Self::format_member_type(target)
+            ))),
+        }
+    }
+
+    /// 实际生效的会员等级：到期时间已过（尚未被 expire_memberships 降级）时视为 fan
+    fn effective_member_type(
+        member_type: &MemberType,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> MemberType {
+        match expires_at {
+            Some(exp) if exp <= Utc::now() => MemberType::Fan,
+            _ => member_type.clone(),
+        }
+    }
+
+    /// 会员等级高低，用于判断升降级
+    fn member_rank(member_type: &MemberType) -> u8 {
+        match member_type {
+            MemberType::Fan => 0,
+            MemberType::SweetShareholder => 1,
+            MemberType::SuperShareholder => 2,
+        }
+    }
+
     fn format_member_type(member_type: &MemberType) -> String {
         match member_type {
             MemberType::Fan => "Fan".to_string(),
@@ -63,42 +229,96 @@ impl MembershipService {
             .await?
             .ok_or_else(|| AppError::NotFound("User not found".into()))?;
 
-        let current = user.member_type.clone();
+        let current = Self::effective_member_type(&user.member_type, user.membership_expires_at);
         let username = user.username.clone();
 
-        // 不允许降级或重复购买同级
-        if current == req.target_member_type {
-            return Err(AppError::ValidationError("Already this membership".into()));
-        }
-        // 只能从 fan 升级到 sweet 或 super；从 sweet 升到 super
-        if current == MemberType::SuperShareholder {
-            return Err(AppError::ValidationError(
-                "Already highest membership".into(),
-            ));
-        }
-        if current == MemberType::SweetShareholder
-            && req.target_member_type == MemberType::SweetShareholder
-        {
+        Self::allowed_transition(&current, &req.target_member_type)?;
+
+        let target_type = req.target_member_type.clone();
+        let amount = Self::prorate_upgrade(&current, &target_type, user.membership_expires_at)
+            .ok_or_else(|| AppError::ValidationError("Unsupported target member type".into()))?;
+
+        let formatted_member_type = Self::format_member_type(&target_type);
+        let description = format!("{} upgrade to {}", username, formatted_member_type);
+
+        self.create_purchase_intent(user_id, None, target_type, amount, description)
+            .await
+    }
+
+    /// 为其他用户（按会员号）购买会员；支付成功后升级受赠人而不是购买人
+    pub async fn create_gift_membership_intent(
+        &self,
+        buyer_id: i64,
+        recipient_member_code: &str,
+        target_type: MemberType,
+    ) -> AppResult<CreateMembershipIntentResponse> {
+        let buyer = users::Entity::find_by_id(buyer_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+        let recipient = users::Entity::find()
+            .filter(users::Column::MemberCode.eq(recipient_member_code.to_string()))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Recipient not found".into()))?;
+
+        if recipient.id == buyer_id {
             return Err(AppError::ValidationError(
-                "Already sweet shareholder".into(),
+                "Cannot gift a membership to yourself".into(),
             ));
         }
-        if current == MemberType::Fan && req.target_member_type == MemberType::Fan {
+        // 只能赠送比受赠人当前更高的等级（已过期的会员按 fan 计）
+        let recipient_type =
+            Self::effective_member_type(&recipient.member_type, recipient.membership_expires_at);
+        if Self::member_rank(&target_type) <= Self::member_rank(&recipient_type) {
             return Err(AppError::ValidationError(
-                "Invalid target membership".into(),
+                "Gifted membership must be higher than the recipient's current membership".into(),
             ));
         }
-        if current == MemberType::SweetShareholder && req.target_member_type == MemberType::Fan {
-            return Err(AppError::ValidationError("Cannot downgrade".into()));
-        }
 
-        let target_type = req.target_member_type.clone();
-        let amount = Self::membership_price_cents(&target_type)
-            .ok_or_else(|| AppError::ValidationError("Unsupported target member type".into()))?;
+        let amount = Self::prorate_upgrade(
+            &recipient_type,
+            &target_type,
+            recipient.membership_expires_at,
+        )
+        .ok_or_else(|| AppError::ValidationError("Unsupported target member type".into()))?;
 
-        let formatted_member_type = Self::format_member_type(&target_type);
-        let description = format!("{} upgrade to {}", username, formatted_member_type);
+        let description = format!(
+            "{} gifts {} to {}",
+            buyer.username,
+            Self::format_member_type(&target_type),
+            recipient.username
+        );
+
+        self.create_purchase_intent(
+            buyer_id,
+            Some(recipient.id),
+            target_type,
+            amount,
+            description,
+        )
+        .await
+    }
 
+    /// 创建会员购买的 PaymentIntent / Checkout Session 并落库 pending 记录
+    async fn create_purchase_intent(
+        &self,
+        user_id: i64,
+        recipient_user_id: Option<i64>,
+        target_type: MemberType,
+        amount: i64,
+        description: String,
+    ) -> AppResult<CreateMembershipIntentResponse> {
+        let extra_metadata = recipient_user_id.map(|rid| {
+            let mut extra = std::collections::HashMap::new();
+            extra.insert("recipient_user_id".to_string(), rid.to_string());
+            extra
+        });
+
+        let customer_id = self
+            .stripe_service
+            .get_or_create_customer(&self.pool, user_id)
+            .await?;
         let payment_intent = self
             .stripe_service
             .create_payment_intent_with_category(
@@ -107,7 +327,9 @@ impl MembershipService {
                 "membership",
                 Some("usd".to_string()),
                 Some(description.clone()),
+                extra_metadata.clone(),
                 None,
+                Some(customer_id),
             )
             .await?;
 
@@ -120,6 +342,7 @@ impl MembershipService {
                 user_id,
                 "membership",
                 Some(description.clone()),
+                extra_metadata,
                 None,
             )
             .await?;
@@ -129,13 +352,19 @@ impl MembershipService {
             .payment_intent_id
             .clone()
             .unwrap_or_else(|| payment_intent.id.to_string());
+        let client_secret = StripeService::require_client_secret(
+            &payment_intent_id,
+            checkout.client_secret.clone(),
+            payment_intent.client_secret.clone(),
+        )?;
         // upsert-like: try insert, ignore unique conflict
         let _ = mp::ActiveModel {
             user_id: Set(user_id),
             stripe_payment_intent_id: Set(payment_intent_id.clone()),
-            target_member_type: Set(req.target_member_type.clone()),
+            target_member_type: Set(target_type.clone()),
             amount: Set(amount),
             status: Set(status),
+            recipient_user_id: Set(recipient_user_id),
             ..Default::default()
         }
         .insert(&self.pool)
@@ -153,14 +382,13 @@ impl MembershipService {
                 Some("usd".to_string()),
                 Some(format!("{:?}", payment_intent.status)),
                 payment_intent.description.clone(),
+                None,
             )
             .await;
 
         Ok(CreateMembershipIntentResponse {
             payment_intent_id,
-            client_secret: checkout
-                .client_secret
-                .unwrap_or_else(|| payment_intent.client_secret.unwrap_or_default()),
+            client_secret,
             checkout_url: checkout.url,
             amount,
             target_member_type: target_type,
@@ -182,7 +410,7 @@ impl MembershipService {
         }
 
         let txn = self.pool.begin().await?;
-        // 读取记录：优先按 payment_intent_id 精确匹配；若找不到，回退到按用户+金额+pending 匹配，并修正记录中的 PIID
+        // 读取记录：优先按 payment_intent_id 精确匹配；若找不到，回退到按用户+金额+pending 匹配
         let rec = match mp::Entity::find()
             .filter(mp::Column::StripePaymentIntentId.eq(req.payment_intent_id.clone()))
             .filter(mp::Column::UserId.eq(user_id))
@@ -204,101 +432,152 @@ impl MembershipService {
                         "Membership purchase record not found".into(),
                     ));
                 };
-                // 更新其 payment_intent_id 为实际支付成功的 PI，避免后续再次不匹配
-                let mut am = alt_rec.clone().into_active_model();
-                am.stripe_payment_intent_id = Set(req.payment_intent_id.clone());
-                am.update(&txn).await?;
                 alt_rec
             }
         };
         let mut rec = rec;
 
-        if rec.status == MembershipPurchaseStatus::Succeeded {
+        // 赠送订单升级受赠人，否则升级购买人自己
+        let beneficiary_id = rec.recipient_user_id.unwrap_or(user_id);
+
+        // 条件更新：只有把这条购买记录从 pending 置为 succeeded 的调用才会升级并发放福利，
+        // 并发的确认/webhook 重试都会落到下面的“已处理”分支。
+        // 回退匹配时同时把记录的 payment_intent_id 修正为实际支付成功的 PI，避免后续再次不匹配
+        let res = mp::Entity::update_many()
+            .col_expr(
+                mp::Column::Status,
+                MembershipPurchaseStatus::Succeeded.as_enum(),
+            )
+            .col_expr(
+                mp::Column::StripeStatus,
+                Expr::value(format!("{:?}", payment_intent.status)),
+            )
+            .col_expr(
+                mp::Column::StripePaymentIntentId,
+                Expr::value(req.payment_intent_id.clone()),
+            )
+            .col_expr(mp::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(mp::Column::Id.eq(rec.id))
+            .filter(mp::Column::Status.eq(MembershipPurchaseStatus::Pending))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
             // 已经处理，直接返回用户当前会员类型
-            let mt = users::Entity::find_by_id(user_id)
+            let mt = users::Entity::find_by_id(beneficiary_id)
                 .one(&txn)
                 .await?
                 .map(|u| u.member_type)
                 .unwrap_or(MemberType::Fan);
-            let resp = MembershipPurchaseRecordResponse::from(rec);
+            let current = mp::Entity::find_by_id(rec.id)
+                .one(&txn)
+                .await?
+                .unwrap_or(rec);
+            let resp = MembershipPurchaseRecordResponse::from(current);
             return Ok(ConfirmMembershipResponse {
                 membership_record: resp,
                 new_member_type: mt,
             });
         }
 
-        // 升级用户会员类型并设置到期时间为NOW() + 1 year
-        let new_member_type = rec.target_member_type.clone();
-        if let Some(u) = users::Entity::find_by_id(user_id).one(&txn).await? {
+        // 升级用户会员类型并设置到期时间为NOW() + 1 year。
+        // 锁定受益人后按当前等级重新校验：付款期间对方已达到或超过目标等级（如同时收到赠送）时
+        // 不覆盖，而是按实付金额折算天数顺延其当前等级，也不再发放升级奖励
+        let target_member_type = rec.target_member_type.clone();
+        let mut new_member_type = target_member_type.clone();
+        let mut upgraded = false;
+        let mut audit_before = None;
+        let mut audit_after = None;
+        if let Some(u) = users::Entity::find_by_id(beneficiary_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+        {
+            audit_before = Some(json!({
+                "member_type": u.member_type,
+                "membership_expires_at": u.membership_expires_at,
+            }));
+            let now = Utc::now();
+            let current = Self::effective_member_type(&u.member_type, u.membership_expires_at);
+            let next = match Self::allowed_transition(&current, &target_member_type) {
+                Ok(()) => {
+                    upgraded = true;
+                    now + chrono::Duration::days(MEMBERSHIP_PERIOD_DAYS)
+                }
+                Err(_) => {
+                    new_member_type = current.clone();
+                    let days = Self::membership_price_cents(&current)
+                        .map(|price| rec.amount * MEMBERSHIP_PERIOD_DAYS / price)
+                        .unwrap_or(0);
+                    let base = u.membership_expires_at.filter(|e| *e > now).unwrap_or(now);
+                    base + chrono::Duration::days(days)
+                }
+            };
             let mut am = u.into_active_model();
             am.member_type = Set(new_member_type.clone());
-            let next = chrono::Utc::now() + chrono::Duration::days(365);
+            // 重新购买后取消之前预约的降级
+            am.pending_member_type = Set(None);
             am.membership_expires_at = Set(Some(next));
             am.update(&txn).await?;
+            audit_after = Some(json!({
+                "member_type": new_member_type,
+                "membership_expires_at": next,
+                "purchase_id": rec.id,
+            }));
         }
 
-        // 更新记录状态
-        let success = MembershipPurchaseStatus::Succeeded;
-        if let Some(m) = mp::Entity::find_by_id(rec.id).one(&txn).await? {
-            let mut am = m.into_active_model();
-            am.status = Set(success);
-            am.stripe_status = Set(Some(format!("{:?}", payment_intent.status)));
-            am.update(&txn).await?;
+        // 升级奖励先在同一事务内落一条发放记录，外部发放失败或进程中断时由定时任务补发
+        let mut reward_grant_id = None;
+        if upgraded && let Some(reward) = Self::upgrade_reward(&new_member_type) {
+            let now = Utc::now();
+            let grant = reward_grants::ActiveModel {
+                purchase_id: Set(rec.id),
+                user_id: Set(beneficiary_id),
+                member_type: Set(new_member_type.clone()),
+                codes_total: Set(reward.count as i32),
+                codes_granted: Set(0),
+                granted: Set(false),
+                claimed_at: Set(None),
+                last_error: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            reward_grant_id = Some(grant.id);
         }
 
         // 提交事务后再进行外部福利发放，避免长事务或潜在锁冲突
         txn.commit().await?;
 
-        // 异步后台发放福利（不阻塞 webhook 返回）
-        let svc = self.discount_code_service.clone();
-        let mt_for_task = new_member_type.clone();
-        tokio::spawn(async move {
-            match mt_for_task {
-                MemberType::SweetShareholder => {
-                    if let Err(e) = svc
-                        .create_user_discount_code(user_id, 800, CodeType::ShareholderReward, 1)
-                        .await
-                    {
-                        log::error!(
-                            "Failed to create shareholder reward code for user {user_id}: {e:?}"
-                        );
-                    }
-                }
-                MemberType::SuperShareholder => {
-                    let mut handles = Vec::with_capacity(10);
-                    for _ in 0..10 {
-                        let svc_in = svc.clone();
-                        handles.push(tokio::spawn(async move {
-                            svc_in
-                                .create_user_discount_code(
-                                    user_id,
-                                    300,
-                                    CodeType::SuperShareholderReward,
-                                    1,
-                                )
-                                .await
-                        }));
-                    }
-                    for h in handles {
-                        match h.await {
-                            Ok(Ok(_)) => {}
-                            Ok(Err(e)) => {
-                                log::error!(
-                                    "Failed to create one of super shareholder codes for user {user_id}: {e:?}"
-                                );
-                            }
-                            Err(join_err) => {
-                                log::error!(
-                                    "Join error creating super shareholder codes for user {user_id}: {join_err}"
-                                );
-                            }
-                        }
-                    }
-                }
-                MemberType::Fan => {}
-            }
+        self.audit_service.record(AuditEntry {
+            actor_id: Some(user_id),
+            action: if upgraded {
+                "membership.upgrade"
+            } else {
+                "membership.extend"
+            },
+            entity: "user",
+            entity_id: Some(beneficiary_id),
+            before: audit_before,
+            after: audit_after,
         });
 
+        // 异步后台发放福利（不阻塞 webhook 返回）；发放记录已持久化，失败由 retry_reward_grants 补发
+        if let Some(grant_id) = reward_grant_id {
+            let svc = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = svc.grant_upgrade_reward(grant_id).await {
+                    log::error!("Failed to grant upgrade reward {grant_id}: {e:?}");
+                }
+            });
+        }
+
+        // 推荐奖励（尽力而为，不影响升级结果）
+        if let Err(e) = self.grant_referral_reward(beneficiary_id).await {
+            log::error!("Failed to grant referral reward for referee {beneficiary_id}: {e:?}");
+        }
+
         // 记录统一交易表
         let _ = self
             .stx_service
@@ -310,14 +589,16 @@ impl MembershipService {
                 Some("usd".to_string()),
                 Some(format!("{:?}", payment_intent.status)),
                 Some(format!("Membership confirmed: {:?}", new_member_type)),
+                None,
             )
             .await;
         rec.status = MembershipPurchaseStatus::Succeeded;
         let new_type = new_member_type;
         let resp = MembershipPurchaseRecordResponse::from(rec);
         log::info!(
-            "Membership confirmed for user_id={}, new_type={:?}",
+            "Membership confirmed for user_id={}, beneficiary_id={}, new_type={:?}",
             user_id,
+            beneficiary_id,
             new_type
         );
         Ok(ConfirmMembershipResponse {
@@ -326,22 +607,480 @@ impl MembershipService {
         })
     }
 
-    /// 将已过期的会员降级为 Fan，返回处理的用户数量
+    /// 发放一条升级奖励记录中尚未发放的奖励码，返回本次新发放的数量。
+    /// 先以 claimed_at 租约认领，确认流程与重试任务不会同时发放同一条记录
+    pub async fn grant_upgrade_reward(&self, grant_id: i64) -> AppResult<u32> {
+        let now = Utc::now();
+        let lease_expired = now - chrono::Duration::minutes(REWARD_GRANT_LEASE_MINUTES);
+        let claimed = reward_grants::Entity::update_many()
+            .col_expr(reward_grants::Column::ClaimedAt, Expr::value(now))
+            .filter(reward_grants::Column::Id.eq(grant_id))
+            .filter(reward_grants::Column::Granted.eq(false))
+            .filter(
+                Condition::any()
+                    .add(reward_grants::Column::ClaimedAt.is_null())
+                    .add(reward_grants::Column::ClaimedAt.lt(lease_expired)),
+            )
+            .exec(&self.pool)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Ok(0);
+        }
+        let Some(grant) = reward_grants::Entity::find_by_id(grant_id)
+            .one(&self.pool)
+            .await?
+        else {
+            return Ok(0);
+        };
+
+        let remaining = (grant.codes_total - grant.codes_granted).max(0) as usize;
+        let (created, last_error) = match Self::upgrade_reward(&grant.member_type) {
+            Some(reward) if remaining > 0 => {
+                let specs = (0..remaining)
+                    .map(|_| DiscountCodeSpec {
+                        amount: reward.value_cents,
+                        code_type: reward.code_type.clone(),
+                        expire_months: 1,
+                    })
+                    .collect();
+                match self
+                    .discount_code_service
+                    .create_user_discount_codes(grant.user_id, specs)
+                    .await
+                {
+                    Ok(codes) if codes.len() < remaining => (
+                        codes.len(),
+                        Some(format!("Only {}/{remaining} codes created", codes.len())),
+                    ),
+                    Ok(codes) => (codes.len(), None),
+                    Err(e) => (0, Some(format!("{e:?}"))),
+                }
+            }
+            _ => (0, None),
+        };
+
+        let codes_granted = grant.codes_granted + created as i32;
+        let granted = last_error.is_none();
+        reward_grants::Entity::update_many()
+            .col_expr(
+                reward_grants::Column::CodesGranted,
+                Expr::value(codes_granted),
+            )
+            .col_expr(reward_grants::Column::Granted, Expr::value(granted))
+            .col_expr(reward_grants::Column::ClaimedAt, Expr::cust("NULL"))
+            .col_expr(
+                reward_grants::Column::LastError,
+                Expr::value(last_error.clone()),
+            )
+            .col_expr(reward_grants::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(reward_grants::Column::Id.eq(grant_id))
+            .exec(&self.pool)
+            .await?;
+        if let Some(err) = last_error {
+            log::error!(
+                "Upgrade reward for user {} (purchase {}) incomplete, {codes_granted}/{} codes granted: {err}",
+                grant.user_id,
+                grant.purchase_id,
+                grant.codes_total
+            );
+        }
+        Ok(created as u32)
+    }
+
+    /// 补发未完成的升级奖励（发放失败或确认后进程中断），返回本轮新发放的奖励码数量
+    pub async fn retry_reward_grants(&self) -> AppResult<u32> {
+        let lease_expired = Utc::now() - chrono::Duration::minutes(REWARD_GRANT_LEASE_MINUTES);
+        let ids: Vec<i64> = reward_grants::Entity::find()
+            .select_only()
+            .column(reward_grants::Column::Id)
+            .filter(reward_grants::Column::Granted.eq(false))
+            .filter(
+                Condition::any()
+                    .add(reward_grants::Column::ClaimedAt.is_null())
+                    .add(reward_grants::Column::ClaimedAt.lt(lease_expired)),
+            )
+            .order_by_asc(reward_grants::Column::Id)
+            .limit(REWARD_GRANT_RETRY_BATCH)
+            .into_tuple()
+            .all(&self.pool)
+            .await?;
+
+        let mut total = 0;
+        for id in ids {
+            match self.grant_upgrade_reward(id).await {
+                Ok(n) => total += n,
+                Err(e) => log::error!("Failed to retry upgrade reward {id}: {e:?}"),
+            }
+        }
+        Ok(total)
+    }
+
+    /// 被推荐人首次购买会员时给推荐人发放奖励券；推荐人需为有效期内的股东会员。
+    /// referral_rewards.referee_id 唯一约束保证每个被推荐人只发放一次，返回是否新发放
+    async fn grant_referral_reward(&self, referee_id: i64) -> AppResult<bool> {
+        let Some(referrer_id) = users::Entity::find_by_id(referee_id)
+            .one(&self.pool)
+            .await?
+            .and_then(|u| u.referrer_id)
+        else {
+            return Ok(false);
+        };
+        let Some(referrer) = users::Entity::find_by_id(referrer_id)
+            .one(&self.pool)
+            .await?
+        else {
+            return Ok(false);
+        };
+        let active = referrer.member_type != MemberType::Fan
+            && referrer
+                .membership_expires_at
+                .is_some_and(|exp| exp > Utc::now());
+        if !active {
+            return Ok(false);
+        }
+
+        let txn = self.pool.begin().await?;
+        let inserted = referral_rewards::Entity::insert(referral_rewards::ActiveModel {
+            referrer_id: Set(referrer_id),
+            referee_id: Set(referee_id),
+            amount_cents: Set(REFERRAL_REWARD_CENTS),
+            created_at: Set(Some(Utc::now())),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(referral_rewards::Column::RefereeId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+        if inserted == 0 {
+            // 已发放过
+            return Ok(false);
+        }
+
+        // 奖励券先以 pending 落库并随推荐记录一起提交，提交后再到七云注册
+        let code = self
+            .discount_code_service
+            .create_pending_user_discount_code_tx(
+                &txn,
+                referrer_id,
+                REFERRAL_REWARD_CENTS,
+                CodeType::ShareholderReward,
+                REFERRAL_REWARD_EXPIRE_MONTHS,
+            )
+            .await?;
+        referral_rewards::Entity::update_many()
+            .col_expr(
+                referral_rewards::Column::DiscountCodeId,
+                sea_orm::sea_query::Expr::value(code.id),
+            )
+            .filter(referral_rewards::Column::RefereeId.eq(referee_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+
+        self.discount_code_service
+            .register_pending_code(
+                code.id,
+                &code.code,
+                REFERRAL_REWARD_CENTS,
+                REFERRAL_REWARD_EXPIRE_MONTHS,
+            )
+            .await?;
+        log::info!(
+            "Referral reward granted: referrer_id={referrer_id}, referee_id={referee_id}, code_id={}",
+            code.id
+        );
+        Ok(true)
+    }
+
+    /// 预约在当前会员到期时降级到更低等级（如 Super → Sweet）。
+    /// 降级只在到期自动续费时按新等级扣款生效，未开启自动续费时拒绝（到期后直接回到 Fan）
+    pub async fn schedule_downgrade(
+        &self,
+        user_id: i64,
+        target: MemberType,
+    ) -> AppResult<ScheduleDowngradeResponse> {
+        let user = users::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+        if Self::member_rank(&target) >= Self::member_rank(&user.member_type) {
+            return Err(AppError::ValidationError(
+                "Target membership must be lower than current membership".into(),
+            ));
+        }
+        if !user.membership_auto_renew {
+            return Err(AppError::ValidationError(
+                "Scheduled downgrade takes effect on auto-renewal, enable auto-renew first".into(),
+            ));
+        }
+
+        let member_type = user.member_type.clone();
+        let effective_at = user.membership_expires_at;
+        let mut am = user.into_active_model();
+        am.pending_member_type = Set(Some(target.clone()));
+        am.updated_at = Set(Some(Utc::now()));
+        am.update(&self.pool).await?;
+
+        Ok(ScheduleDowngradeResponse {
+            member_type,
+            pending_member_type: target,
+            effective_at,
+        })
+    }
+
+    /// 开启或关闭会员到期自动续费；关闭时一并取消已预约的降级（降级只在自动续费时生效）
+    pub async fn set_auto_renew(
+        &self,
+        user_id: i64,
+        enabled: bool,
+    ) -> AppResult<AutoRenewResponse> {
+        let user = users::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+        let mut am = user.into_active_model();
+        am.membership_auto_renew = Set(enabled);
+        if !enabled {
+            am.pending_member_type = Set(None);
+        }
+        am.updated_at = Set(Some(Utc::now()));
+        let user = am.update(&self.pool).await?;
+        Ok(AutoRenewResponse {
+            auto_renew: user.membership_auto_renew,
+            has_saved_customer: user.stripe_customer_id.is_some(),
+        })
+    }
+
+    /// 为 24 小时内到期且开启自动续费的会员发起离线扣款，成功后顺延 365 天。
+    /// 扣款失败只记录日志，交由正常过期流程处理。返回续费成功的数量
+    pub async fn attempt_renewals(&self) -> AppResult<i64> {
+        let now = Utc::now();
+        let candidates = users::Entity::find()
+            .filter(users::Column::MembershipAutoRenew.eq(true))
+            .filter(users::Column::StripeCustomerId.is_not_null())
+            .filter(users::Column::MemberType.ne(MemberType::Fan))
+            .filter(users::Column::MembershipExpiresAt.gt(now))
+            .filter(users::Column::MembershipExpiresAt.lte(now + chrono::Duration::hours(24)))
+            .all(&self.pool)
+            .await?;
+
+        let mut renewed = 0i64;
+        for user in candidates {
+            let user_id = user.id;
+            let expires_at = user.membership_expires_at;
+            match self.renew_membership(user).await {
+                Ok(true) => renewed += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!("Membership auto-renew failed for user {user_id}: {e:?}");
+                    self.notify_renewal(
+                        user_id,
+                        NotificationKind::MembershipRenewalFailed,
+                        json!({ "expires_at": expires_at }),
+                    )
+                    .await;
+                }
+            }
+        }
+        Ok(renewed)
+    }
+
+    /// 发送续费结果通知（失败只记日志）
+    async fn notify_renewal(
+        &self,
+        user_id: i64,
+        kind: NotificationKind,
+        payload: serde_json::Value,
+    ) {
+        if let Err(e) = self
+            .notification_service
+            .create(user_id, kind, payload)
+            .await
+        {
+            log::warn!("Failed to send renewal notification to user {user_id}: {e:?}");
+        }
+    }
+
+    /// 单个用户的续费扣款，返回是否续费成功
+    async fn renew_membership(&self, user: users::Model) -> AppResult<bool> {
+        // 预约了降级则按新等级续费；降到 Fan 不需要续费
+        let renew_type = user
+            .pending_member_type
+            .clone()
+            .unwrap_or_else(|| user.member_type.clone());
+        let Some(amount) = Self::membership_price_cents(&renew_type) else {
+            return Ok(false);
+        };
+        let (Some(customer_id), Some(expires_at)) =
+            (user.stripe_customer_id.clone(), user.membership_expires_at)
+        else {
+            return Ok(false);
+        };
+
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("user_id".to_string(), user.id.to_string());
+        metadata.insert("category".to_string(), "membership_renewal".to_string());
+        metadata.insert("member_type".to_string(), renew_type.to_string());
+        let description = format!(
+            "{} renews {}",
+            user.username,
+            Self::format_member_type(&renew_type)
+        );
+        // 同一周期（按到期时间）只扣一次
+        let idempotency_key = format!("membership-renewal-{}-{}", user.id, expires_at.timestamp());
+
+        let payment_intent = self
+            .stripe_service
+            .create_offsession_payment_intent(
+                &customer_id,
+                amount,
+                metadata,
+                description,
+                idempotency_key,
+            )
+            .await?;
+
+        // 幂等 key 相同时 Stripe 返回同一个 PI（上次扣款成功但本地更新失败），不重复记账
+        if self
+            .stx_service
+            .find_by_payment_intent(payment_intent.id.as_ref())
+            .await?
+            .is_none()
+        {
+            let _ = self
+                .stx_service
+                .record_payment_intent(
+                    user.id,
+                    StripeTransactionCategory::Membership,
+                    payment_intent.id.as_ref(),
+                    Some(amount),
+                    Some("usd".to_string()),
+                    Some(format!("{:?}", payment_intent.status)),
+                    payment_intent.description.clone(),
+                    None,
+                )
+                .await;
+        }
+
+        if payment_intent.status != PaymentIntentStatus::Succeeded {
+            log::warn!(
+                "Membership renewal payment for user {} not succeeded: {:?}",
+                user.id,
+                payment_intent.status
+            );
+            self.notify_renewal(
+                user.id,
+                NotificationKind::MembershipRenewalFailed,
+                json!({ "expires_at": expires_at }),
+            )
+            .await;
+            return Ok(false);
+        }
+
+        let user_id = user.id;
+        let new_expires_at = expires_at + chrono::Duration::days(MEMBERSHIP_PERIOD_DAYS);
+        let mut am = user.into_active_model();
+        am.member_type = Set(renew_type.clone());
+        am.pending_member_type = Set(None);
+        am.membership_expires_at = Set(Some(new_expires_at));
+        am.updated_at = Set(Some(Utc::now()));
+        am.update(&self.pool).await?;
+        log::info!("Membership auto-renewed for user {user_id}");
+        self.notify_renewal(
+            user_id,
+            NotificationKind::MembershipRenewed,
+            json!({
+                "member_type": renew_type,
+                "amount": amount,
+                "expires_at": new_expires_at,
+            }),
+        )
+        .await;
+        Ok(true)
+    }
+
+    /// 单条 UPDATE 批量处理已到期的会员：一律降为 fan 并清除预约等级
+    /// （预约等级只在 attempt_renewals 付费续费时生效）；返回处理的用户数
     pub async fn expire_memberships(&self) -> AppResult<i64> {
-        // approximate bulk update by scanning and updating; for simplicity
-        let to_downgrade = users::Entity::find()
+        let res = users::Entity::update_many()
+            .col_expr(users::Column::MemberType, MemberType::Fan.as_enum())
+            .col_expr(users::Column::PendingMemberType, Expr::cust("NULL"))
             .filter(users::Column::MembershipExpiresAt.lte(chrono::Utc::now()))
             .filter(users::Column::MembershipExpiresAt.is_not_null())
             .filter(users::Column::MemberType.ne(MemberType::Fan))
-            .all(&self.pool)
+            .exec(&self.pool)
             .await?;
-        let mut count = 0i64;
-        for u in to_downgrade {
-            let mut am = u.into_active_model();
-            am.member_type = Set(MemberType::Fan);
-            am.update(&self.pool).await?;
-            count += 1;
+        Ok(res.rows_affected as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(current: MemberType, target: MemberType) -> Option<String> {
+        match MembershipService::allowed_transition(&current, &target) {
+            Ok(()) => None,
+            Err(AppError::ValidationError(msg)) => Some(msg),
+            Err(e) => panic!("unexpected error: {e:?}"),
         }
-        Ok(count)
+    }
+
+    #[test]
+    fn test_allowed_transition_all_combinations() {
+        use MemberType::*;
+
+        assert_eq!(
+            message(Fan, Fan).as_deref(),
+            Some("Fan membership cannot be purchased")
+        );
+        assert_eq!(message(Fan, SweetShareholder), None);
+        assert_eq!(message(Fan, SuperShareholder), None);
+        assert_eq!(
+            message(SweetShareholder, Fan).as_deref(),
+            Some("Cannot downgrade from Sweets Shareholder to Fan")
+        );
+        assert_eq!(
+            message(SweetShareholder, SweetShareholder).as_deref(),
+            Some("Already sweet shareholder")
+        );
+        assert_eq!(message(SweetShareholder, SuperShareholder), None);
+        assert_eq!(
+            message(SuperShareholder, Fan).as_deref(),
+            Some("Cannot downgrade from Super Shareholder to Fan")
+        );
+        assert_eq!(
+            message(SuperShareholder, SweetShareholder).as_deref(),
+            Some("Cannot downgrade from Super Shareholder to Sweets Shareholder")
+        );
+        assert_eq!(
+            message(SuperShareholder, SuperShareholder).as_deref(),
+            Some("Already highest membership")
+        );
+    }
+
+    #[test]
+    fn test_expired_membership_counts_as_fan() {
+        let now = Utc::now();
+        let expired = MembershipService::effective_member_type(
+            &MemberType::SweetShareholder,
+            Some(now - chrono::Duration::days(1)),
+        );
+        assert_eq!(expired, MemberType::Fan);
+        assert_eq!(message(expired, MemberType::SweetShareholder), None);
+
+        let active = MembershipService::effective_member_type(
+            &MemberType::SweetShareholder,
+            Some(now + chrono::Duration::days(1)),
+        );
+        assert_eq!(active, MemberType::SweetShareholder);
+        assert_eq!(
+            MembershipService::effective_member_type(&MemberType::SuperShareholder, None),
+            MemberType::SuperShareholder
+        );
     }
 }
@@ -1,21 +1,25 @@
+pub mod audit_service;
 pub mod auth_service;
 pub mod birthday_reward_service;
 pub mod discount_code_service;
 pub mod lucky_draw_service;
 pub mod membership_service;
 pub mod monthly_card_service;
+pub mod notification_service;
 pub mod order_service;
 pub mod recharge_service;
 pub mod stripe_transaction_service;
 pub mod sync_service;
 pub mod user_service;
 
+pub use audit_service::*;
 pub use auth_service::*;
 pub use birthday_reward_service::*;
 pub use discount_code_service::*;
 pub use lucky_draw_service::*;
 pub use membership_service::*;
 pub use monthly_card_service::*;
+pub use notification_service::*;
 pub use order_service::*;
 pub use recharge_service::*;
 pub use stripe_transaction_service::*;
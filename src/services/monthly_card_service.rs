@@ -1,28 +1,58 @@
+use crate::config::MonthlyCardConfig;
 use crate::entities::StripeTransactionCategory;
-use crate::entities::{MonthlyCardStatus, monthly_card_entity as mc};
+use crate::entities::{MonthlyCardPlanType, MonthlyCardStatus, monthly_card_entity as mc};
 use crate::error::{AppError, AppResult};
-use crate::external::StripeService;
+use crate::external::{StripeGateway, StripeService};
 use crate::models::*;
 use crate::services::{DiscountCodeService, StripeTransactionService};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
-    QueryOrder, Set, TransactionTrait,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
+use std::sync::Arc;
+
+/// 距到期的剩余天数，不足一天按一天计，已到期为 0
+fn days_remaining(ends_at: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+    let secs = (ends_at - now).num_seconds();
+    if secs <= 0 {
+        0
+    } else {
+        (secs + 86_399) / 86_400
+    }
+}
+
+/// 下一次发券日期：今天已发则为明天，否则为今天；超过到期日则没有
+fn next_coupon_date(
+    last_granted_on: Option<NaiveDate>,
+    ends_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<NaiveDate> {
+    let today = now.date_naive();
+    let next = if last_granted_on == Some(today) {
+        today.succ_opt()?
+    } else {
+        today
+    };
+    (next <= ends_at.date_naive()).then_some(next)
+}
 
 #[derive(Clone)]
 pub struct MonthlyCardService {
     pool: DatabaseConnection,
-    stripe_service: StripeService,
+    stripe_service: Arc<dyn StripeGateway>,
     discount_code_service: DiscountCodeService,
     stx_service: StripeTransactionService,
+    config: MonthlyCardConfig,
 }
 
 impl MonthlyCardService {
     pub fn new(
         pool: DatabaseConnection,
-        stripe_service: StripeService,
+        stripe_service: Arc<dyn StripeGateway>,
         discount_code_service: DiscountCodeService,
+        config: MonthlyCardConfig,
     ) -> Self {
         let stx_service = StripeTransactionService::new(pool.clone());
         Self {
@@ -30,6 +60,7 @@ impl MonthlyCardService {
             stripe_service,
             discount_code_service,
             stx_service,
+            config,
         }
     }
 
@@ -38,11 +69,27 @@ impl MonthlyCardService {
         user_id: i64,
         req: CreateMonthlyCardIntentRequest,
     ) -> AppResult<CreateMonthlyCardIntentResponse> {
-        // 优先从配置读取对应 price 的金额，否则退回到本地常量（2000）
+        // 已有生效月卡时拒绝重复购买；仅一次性月卡之间允许排队（当前卡到期后开始）
+        let cards = self.find_unexpired_cards(user_id).await?;
+        if !cards.is_empty() {
+            let now = Utc::now();
+            let queueable = req.plan_type == MonthlyCardPlanType::OneTime
+                && cards.iter().all(|c| {
+                    c.plan_type == MonthlyCardPlanType::OneTime
+                        && c.starts_at.is_none_or(|s| s <= now)
+                });
+            if !queueable {
+                return Err(AppError::ValidationError(
+                    "Active monthly card already exists".to_string(),
+                ));
+            }
+        }
+
+        // 优先从配置的 price 读取 Stripe 上的金额，未配置时使用配置的兜底金额
         let (_prod, one_time_pid, sub_pid) = self.stripe_service.monthly_card_ids();
         let chosen_price_id = match req.plan_type {
-            crate::entities::MonthlyCardPlanType::OneTime => one_time_pid,
-            crate::entities::MonthlyCardPlanType::Subscription => sub_pid,
+            MonthlyCardPlanType::OneTime => one_time_pid,
+            MonthlyCardPlanType::Subscription => sub_pid,
         };
         let amount = if let Some(pid) = chosen_price_id.as_deref() {
             // 如果配置了 price_id，则到 Stripe 查询 unit_amount
@@ -56,7 +103,7 @@ impl MonthlyCardService {
                 }
             }
         } else {
-            return Err(AppError::ValidationError("No valid price ID found".into()));
+            self.stripe_service.monthly_card_fallback_amount_cents()
         };
 
         // Create PaymentIntent，附带 plan_type 与（可用时）price_id/product_id 方便审计
@@ -68,6 +115,10 @@ impl MonthlyCardService {
         if let Some(prod) = _prod {
             extra.insert("product_id".to_string(), prod);
         }
+        let customer_id = self
+            .stripe_service
+            .get_or_create_customer(&self.pool, user_id)
+            .await?;
         let pi = self
             .stripe_service
             .create_payment_intent_with_category(
@@ -80,6 +131,8 @@ impl MonthlyCardService {
                     req.plan_type
                 )),
                 Some(extra),
+                None,
+                Some(customer_id),
             )
             .await?;
 
@@ -96,9 +149,20 @@ impl MonthlyCardService {
                     req.plan_type
                 )),
                 None,
+                None,
             )
             .await?;
 
+        let payment_intent_id = checkout
+            .payment_intent_id
+            .clone()
+            .unwrap_or_else(|| pi.id.to_string());
+        let client_secret = StripeService::require_client_secret(
+            &payment_intent_id,
+            checkout.client_secret.clone(),
+            pi.client_secret.clone(),
+        )?;
+
         let status = MonthlyCardStatus::Pending;
         let _ = mc::ActiveModel {
             user_id: Set(user_id),
@@ -115,26 +179,18 @@ impl MonthlyCardService {
             .record_payment_intent(
                 user_id,
                 StripeTransactionCategory::MonthlyCard,
-                checkout
-                    .payment_intent_id
-                    .as_deref()
-                    .unwrap_or_else(|| pi.id.as_ref()),
+                &payment_intent_id,
                 Some(amount),
                 Some("usd".to_string()),
                 Some(format!("{:?}", pi.status)),
                 pi.description.clone(),
+                None,
             )
             .await;
 
         Ok(CreateMonthlyCardIntentResponse {
-            payment_intent_id: checkout
-                .payment_intent_id
-                .clone()
-                .unwrap_or_else(|| pi.id.to_string()),
-            client_secret: checkout
-                .client_secret
-                .clone()
-                .unwrap_or_else(|| pi.client_secret.clone().unwrap_or_default()),
+            payment_intent_id,
+            client_secret,
             checkout_url: checkout.url,
             amount,
             plan_type: req.plan_type,
@@ -165,10 +221,21 @@ impl MonthlyCardService {
             let resp = MonthlyCardRecordResponse::from(rec);
             return Ok(ConfirmMonthlyCardResponse { monthly_card: resp });
         }
+        // 已有未到期的月卡时排在其后开始，避免有效期重叠导致重复发券
+        let now = Utc::now();
+        let starts_at = mc::Entity::find()
+            .filter(mc::Column::UserId.eq(user_id))
+            .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
+            .filter(mc::Column::EndsAt.gt(now))
+            .order_by_desc(mc::Column::EndsAt)
+            .one(&txn)
+            .await?
+            .and_then(|c| c.ends_at)
+            .map_or(now, |ends_at| ends_at.max(now));
         let mut am = rec.into_active_model();
         am.status = Set(MonthlyCardStatus::Active);
-        am.starts_at = Set(Some(Utc::now()));
-        am.ends_at = Set(Some(Utc::now() + Duration::days(30)));
+        am.starts_at = Set(Some(starts_at));
+        am.ends_at = Set(Some(starts_at + Duration::days(30)));
         am.update(&txn).await?;
         txn.commit().await?;
         let rec = mc::Entity::find()
@@ -182,34 +249,174 @@ impl MonthlyCardService {
         })
     }
 
-    /// 每日为活跃月卡用户发放 $5.5 优惠码，保证一天 1 次。
+    /// 用户所有未到期的生效月卡（含排队中尚未开始的）
+    async fn find_unexpired_cards(&self, user_id: i64) -> AppResult<Vec<mc::Model>> {
+        Ok(mc::Entity::find()
+            .filter(mc::Column::UserId.eq(user_id))
+            .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
+            .filter(mc::Column::EndsAt.gt(Utc::now()))
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// 获取用户当前生效的月卡，没有时返回 404
+    pub async fn get_current(&self, user_id: i64) -> AppResult<CurrentMonthlyCardResponse> {
+        let now = Utc::now();
+        let card = mc::Entity::find()
+            .filter(mc::Column::UserId.eq(user_id))
+            .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
+            .filter(mc::Column::StartsAt.lte(now))
+            .filter(mc::Column::EndsAt.gt(now))
+            .order_by_desc(mc::Column::EndsAt)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("No active monthly card".to_string()))?;
+        let ends_at = card.ends_at.unwrap_or(now);
+        Ok(CurrentMonthlyCardResponse {
+            days_remaining: days_remaining(ends_at, now),
+            next_coupon_date: next_coupon_date(card.last_coupon_granted_on, ends_at, now),
+            monthly_card: MonthlyCardRecordResponse::from(card),
+        })
+    }
+
+    /// 分页获取用户的月卡记录（不含未支付的 pending 记录），最新在前
+    pub async fn list_history(
+        &self,
+        user_id: i64,
+        params: &PaginationParams,
+    ) -> AppResult<PaginatedResponse<MonthlyCardRecordResponse>> {
+        let total = mc::Entity::find()
+            .filter(mc::Column::UserId.eq(user_id))
+            .filter(mc::Column::Status.ne(MonthlyCardStatus::Pending))
+            .count(&self.pool)
+            .await? as i64;
+        let items = mc::Entity::find()
+            .filter(mc::Column::UserId.eq(user_id))
+            .filter(mc::Column::Status.ne(MonthlyCardStatus::Pending))
+            .order_by_desc(mc::Column::Id)
+            .limit(params.get_limit() as u64)
+            .offset(params.get_offset() as u64)
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(MonthlyCardRecordResponse::from)
+            .collect();
+
+        Ok(PaginatedResponse::new(
+            items,
+            params.page.unwrap_or(1),
+            params.page_size.unwrap_or(20),
+            total,
+        ))
+    }
+
+    /// 每日为活跃月卡用户发放一张优惠码（面值与类型见 `MonthlyCardConfig`），保证一天 1 次。
+    ///
+    /// 每张卡在独立事务中先条件更新 `last_coupon_granted_on`（仅当不是今天），
+    /// 抢到更新的实例才发券，券与日期一起提交，避免崩溃或并发导致重复发放。
     pub async fn grant_daily_coupons(&self) -> AppResult<i64> {
         let today = Utc::now().date_naive();
+        // 排队中尚未开始的月卡不发券
         let active_cards = mc::Entity::find()
             .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
+            .filter(mc::Column::StartsAt.lte(Utc::now()))
             .filter(mc::Column::EndsAt.gte(Utc::now()))
+            .filter(
+                Condition::any()
+                    .add(mc::Column::LastCouponGrantedOn.is_null())
+                    .add(mc::Column::LastCouponGrantedOn.ne(today)),
+            )
             .all(&self.pool)
             .await?;
         let mut granted = 0i64;
         for card in active_cards {
-            if card.last_coupon_granted_on == Some(today) {
-                continue;
+            match self.grant_daily_coupon_for_card(&card, today).await {
+                Ok(true) => granted += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!(
+                        "Failed to grant daily coupon for monthly card {} (user {}): {e:?}",
+                        card.id,
+                        card.user_id
+                    );
+                }
             }
-            // 发放 550 cents 优惠码，有效期 1 个月
-            self.discount_code_service
-                .create_user_discount_code(
-                    card.user_id,
-                    550,
-                    crate::entities::CodeType::SweetsCreditsReward,
-                    1,
+        }
+        Ok(granted)
+    }
+
+    /// 单张月卡的发券事务，返回是否实际发放
+    async fn grant_daily_coupon_for_card(
+        &self,
+        card: &mc::Model,
+        today: chrono::NaiveDate,
+    ) -> AppResult<bool> {
+        let (card_id, user_id) = (card.id, card.user_id);
+        let txn = self.pool.begin().await?;
+        // 等价于 WHERE last_coupon_granted_on IS DISTINCT FROM today
+        let res = mc::Entity::update_many()
+            .col_expr(mc::Column::LastCouponGrantedOn, Expr::value(today))
+            .col_expr(mc::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(mc::Column::Id.eq(card_id))
+            .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
+            .filter(
+                Condition::any()
+                    .add(mc::Column::LastCouponGrantedOn.is_null())
+                    .add(mc::Column::LastCouponGrantedOn.ne(today)),
+            )
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
+            // 已被其它实例发放
+            txn.rollback().await?;
+            return Ok(false);
+        }
+        // 发放每日优惠码，有效期 1 个月：以 pending 随发券标记一起提交后再到七云注册；
+        // 注册失败时恢复发券标记，当天后续的任务轮次会重新发放
+        let amount = self.config.daily_coupon_cents_for(&card.plan_type);
+        let code = self
+            .discount_code_service
+            .create_pending_user_discount_code_tx(
+                &txn,
+                user_id,
+                amount,
+                self.config.daily_coupon_code_type.clone(),
+                1,
+            )
+            .await?;
+        txn.commit().await?;
+        if let Err(e) = self
+            .discount_code_service
+            .register_pending_code(code.id, &code.code, amount, 1)
+            .await
+        {
+            mc::Entity::update_many()
+                .col_expr(
+                    mc::Column::LastCouponGrantedOn,
+                    Expr::value(card.last_coupon_granted_on),
                 )
+                .col_expr(mc::Column::UpdatedAt, Expr::value(Utc::now()))
+                .filter(mc::Column::Id.eq(card_id))
+                .filter(mc::Column::LastCouponGrantedOn.eq(today))
+                .exec(&self.pool)
                 .await?;
-            let mut am = card.into_active_model();
-            am.last_coupon_granted_on = Set(Some(today));
-            am.update(&self.pool).await?;
-            granted += 1;
+            return Err(e);
         }
-        Ok(granted)
+        Ok(true)
+    }
+
+    /// 将已过 `ends_at` 且没有有效订阅的活跃月卡批量置为过期，返回过期数量
+    pub async fn expire_cards(&self) -> AppResult<u64> {
+        let now = Utc::now();
+        let res = mc::Entity::update_many()
+            .col_expr(mc::Column::Status, MonthlyCardStatus::Expired.as_enum())
+            .col_expr(mc::Column::UpdatedAt, Expr::value(now))
+            .filter(mc::Column::Status.eq(MonthlyCardStatus::Active))
+            .filter(mc::Column::EndsAt.lt(now))
+            .filter(mc::Column::StripeSubscriptionId.is_null())
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected)
     }
 
     /// 订阅续费成功，延长有效期 30 天
@@ -227,4 +434,68 @@ impl MonthlyCardService {
         }
         Ok(())
     }
+
+    /// 订阅在 Stripe 侧被删除（取消生效），将对应月卡置为已取消
+    pub async fn cancel_by_subscription(&self, subscription_id: &str) -> AppResult<u64> {
+        let res = mc::Entity::update_many()
+            .col_expr(mc::Column::Status, MonthlyCardStatus::Canceled.as_enum())
+            .col_expr(mc::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(mc::Column::StripeSubscriptionId.eq(subscription_id.to_string()))
+            .filter(
+                mc::Column::Status.is_in([MonthlyCardStatus::Pending, MonthlyCardStatus::Active]),
+            )
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    /// 订阅更新时同步“当期结束后取消”标记
+    pub async fn set_cancel_at_period_end(
+        &self,
+        subscription_id: &str,
+        cancel_at_period_end: bool,
+    ) -> AppResult<u64> {
+        let res = mc::Entity::update_many()
+            .col_expr(
+                mc::Column::CancelAtPeriodEnd,
+                Expr::value(cancel_at_period_end),
+            )
+            .col_expr(mc::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(mc::Column::StripeSubscriptionId.eq(subscription_id.to_string()))
+            .filter(mc::Column::CancelAtPeriodEnd.ne(cancel_at_period_end))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_days_remaining_rounds_up() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        assert_eq!(days_remaining(now + Duration::hours(1), now), 1);
+        assert_eq!(days_remaining(now + Duration::days(30), now), 30);
+        assert_eq!(days_remaining(now - Duration::hours(1), now), 0);
+    }
+
+    #[test]
+    fn test_next_coupon_date() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        let today = now.date_naive();
+        let ends_at = now + Duration::days(10);
+        assert_eq!(next_coupon_date(None, ends_at, now), Some(today));
+        assert_eq!(
+            next_coupon_date(Some(today), ends_at, now),
+            today.succ_opt()
+        );
+        // 今天已发且今天到期，没有下一次
+        assert_eq!(
+            next_coupon_date(Some(today), now + Duration::hours(2), now),
+            None
+        );
+    }
 }
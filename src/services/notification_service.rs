@@ -0,0 +1,186 @@
+use crate::entities::{notification_entity as notifications, user_entity as users};
+use crate::error::{AppError, AppResult};
+use crate::models::*;
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde_json::Value;
+
+/// 按用户偏好判断是否发送某类通知（偏好中未设置的类型默认开启）
+fn is_enabled(prefs: Option<&Value>, kind: NotificationKind) -> bool {
+    prefs
+        .and_then(|p| p.get(kind.as_str()))
+        .and_then(Value::as_bool)
+        .unwrap_or(true)
+}
+
+/// 站内通知：定时任务等按用户偏好写入，用户端分页查看并标记已读
+#[derive(Clone)]
+pub struct NotificationService {
+    pool: DatabaseConnection,
+}
+
+impl NotificationService {
+    pub fn new(pool: DatabaseConnection) -> Self {
+        Self { pool }
+    }
+
+    /// 创建通知；用户关闭了该类通知时跳过，返回是否实际写入
+    pub async fn create(
+        &self,
+        user_id: i64,
+        kind: NotificationKind,
+        payload: Value,
+    ) -> AppResult<bool> {
+        let prefs: Option<Option<Value>> = users::Entity::find_by_id(user_id)
+            .select_only()
+            .column(users::Column::NotificationPrefs)
+            .into_tuple()
+            .one(&self.pool)
+            .await?;
+        let Some(prefs) = prefs else {
+            return Ok(false);
+        };
+        if !is_enabled(prefs.as_ref(), kind) {
+            return Ok(false);
+        }
+
+        notifications::ActiveModel {
+            user_id: Set(user_id),
+            kind: Set(kind.as_str().to_string()),
+            payload: Set(Some(payload)),
+            created_at: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .insert(&self.pool)
+        .await?;
+        Ok(true)
+    }
+
+    /// 分页获取用户通知（最新在前）
+    pub async fn list(
+        &self,
+        user_id: i64,
+        params: &PaginationParams,
+    ) -> AppResult<PaginatedResponse<NotificationResponse>> {
+        let total = notifications::Entity::find()
+            .filter(notifications::Column::UserId.eq(user_id))
+            .count(&self.pool)
+            .await? as i64;
+        let items = notifications::Entity::find()
+            .filter(notifications::Column::UserId.eq(user_id))
+            .order_by_desc(notifications::Column::Id)
+            .limit(params.get_limit() as u64)
+            .offset(params.get_offset() as u64)
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|n| NotificationResponse {
+                id: n.id,
+                kind: n.kind,
+                payload: n.payload,
+                read_at: n.read_at,
+                created_at: n.created_at.unwrap_or_else(Utc::now),
+            })
+            .collect();
+
+        Ok(PaginatedResponse::new(
+            items,
+            params.page.unwrap_or(1),
+            params.page_size.unwrap_or(20),
+            total,
+        ))
+    }
+
+    /// 标记单条通知为已读（已读的通知重复标记不报错）
+    pub async fn mark_read(&self, user_id: i64, notification_id: i64) -> AppResult<()> {
+        let res = notifications::Entity::update_many()
+            .col_expr(notifications::Column::ReadAt, Expr::value(Utc::now()))
+            .filter(notifications::Column::Id.eq(notification_id))
+            .filter(notifications::Column::UserId.eq(user_id))
+            .filter(notifications::Column::ReadAt.is_null())
+            .exec(&self.pool)
+            .await?;
+        if res.rows_affected == 0 {
+            let exists = notifications::Entity::find()
+                .filter(notifications::Column::Id.eq(notification_id))
+                .filter(notifications::Column::UserId.eq(user_id))
+                .count(&self.pool)
+                .await?
+                > 0;
+            if !exists {
+                return Err(AppError::NotFound("Notification not found".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取用户各类通知的开关
+    pub async fn get_prefs(&self, user_id: i64) -> AppResult<NotificationPrefs> {
+        let user = users::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        Ok(Self::effective_prefs(user.notification_prefs.as_ref()))
+    }
+
+    /// 更新通知开关（仅覆盖请求中出现的类型），返回更新后的完整偏好
+    pub async fn update_prefs(
+        &self,
+        user_id: i64,
+        request: NotificationPrefs,
+    ) -> AppResult<NotificationPrefs> {
+        let user = users::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let mut stored = match &user.notification_prefs {
+            Some(Value::Object(map)) => map.clone(),
+            _ => serde_json::Map::new(),
+        };
+        for (kind, enabled) in request.prefs {
+            stored.insert(kind.as_str().to_string(), Value::Bool(enabled));
+        }
+        let stored = Value::Object(stored);
+        let response = Self::effective_prefs(Some(&stored));
+
+        let mut am = user.into_active_model();
+        am.notification_prefs = Set(Some(stored));
+        am.updated_at = Set(Some(Utc::now()));
+        am.update(&self.pool).await?;
+        Ok(response)
+    }
+
+    fn effective_prefs(prefs: Option<&Value>) -> NotificationPrefs {
+        NotificationPrefs {
+            prefs: NotificationKind::ALL
+                .into_iter()
+                .map(|kind| (kind, is_enabled(prefs, kind)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_enabled_defaults_to_true() {
+        let prefs = json!({"coupon_expired": false, "membership_renewed": true});
+        assert!(!is_enabled(Some(&prefs), NotificationKind::CouponExpired));
+        assert!(is_enabled(
+            Some(&prefs),
+            NotificationKind::MembershipRenewed
+        ));
+        assert!(is_enabled(
+            Some(&prefs),
+            NotificationKind::MembershipRenewalFailed
+        ));
+        assert!(is_enabled(None, NotificationKind::CouponExpired));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::entities::TransactionType;
 use crate::entities::order_entity as orders;
 use crate::entities::sweet_cash_transaction_entity as sct;
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
 use crate::models::*;
 use chrono::{NaiveDate, TimeZone, Utc};
 use sea_orm::Condition;
@@ -21,15 +21,13 @@ impl OrderService {
         Self { pool }
     }
 
-    /// 获取用户订单记录
-    pub async fn get_user_orders(
-        &self,
-        user_id: i64,
-        query: &OrderQuery,
-    ) -> AppResult<PaginatedResponse<OrderResponse>> {
-        let params = PaginationParams::new(query.page, query.per_page);
-        let offset = params.get_offset();
-        let limit = params.get_limit();
+    /// 根据查询参数构建订单过滤条件（列表与导出共用）
+    fn build_filter(user_id: i64, query: &OrderQuery) -> AppResult<Condition> {
+        if let (Some(from), Some(to)) = (query.from, query.to)
+            && from > to
+        {
+            return Err(AppError::ValidationError("from 不能晚于 to".to_string()));
+        }
         // 构建 SeaORM 过滤条件
         let mut cond = Condition::all().add(orders::Column::UserId.eq(user_id));
         if let Some(status) = query.status {
@@ -47,20 +45,77 @@ impl OrderService {
             let end_dt = Utc.from_utc_datetime(&nd.and_hms_opt(23, 59, 59).unwrap());
             cond = cond.add(orders::Column::ExternalCreatedAt.lte(end_dt));
         }
+        if let Some(from) = query.from {
+            let from_dt = Utc.from_utc_datetime(&from.and_hms_opt(0, 0, 0).unwrap());
+            cond = cond.add(orders::Column::ExternalCreatedAt.gte(from_dt));
+        }
+        if let Some(to) = query.to {
+            let to_dt = Utc.from_utc_datetime(&to.and_hms_opt(23, 59, 59).unwrap());
+            cond = cond.add(orders::Column::ExternalCreatedAt.lte(to_dt));
+        }
+        if let Some(product_no) = query.product_no.as_deref().filter(|s| !s.is_empty()) {
+            cond = cond.add(orders::Column::ProductNo.eq(product_no));
+        }
+        Ok(cond)
+    }
 
-        let total = orders::Entity::find()
-            .filter(cond.clone())
-            .count(&self.pool)
-            .await? as i64;
+    /// 获取用户订单记录
+    pub async fn get_user_orders(
+        &self,
+        user_id: i64,
+        query: &OrderQuery,
+    ) -> AppResult<PaginatedResponse<OrderResponse>> {
+        let params =
+            PaginationParams::new(query.page, query.per_page).with_after_id(query.after_id);
+        let offset = params.get_offset();
+        let limit = params.get_limit();
+        let cond = Self::build_filter(user_id, query)?;
 
-        // 获取订单列表
-        let models = orders::Entity::find()
-            .filter(cond)
+        // 两种分页方式统一按 (下单时间, id) 倒序；游标模式从 after_id 对应订单之后继续，且不统计总数
+        let select = orders::Entity::find()
+            .filter(cond.clone())
             .order_by_desc(orders::Column::ExternalCreatedAt)
-            .limit(limit as u64)
-            .offset(offset as u64)
-            .all(&self.pool)
-            .await?;
+            .order_by_desc(orders::Column::Id)
+            .limit(limit as u64);
+        let (models, total) = match params.after_id {
+            Some(after_id) => {
+                let cursor = orders::Entity::find_by_id(after_id)
+                    .filter(orders::Column::UserId.eq(user_id))
+                    .one(&self.pool)
+                    .await?
+                    .ok_or_else(|| AppError::ValidationError("无效的 after_id".to_string()))?;
+                let models = select
+                    .filter(
+                        Condition::any()
+                            .add(orders::Column::ExternalCreatedAt.lt(cursor.external_created_at))
+                            .add(
+                                Condition::all()
+                                    .add(
+                                        orders::Column::ExternalCreatedAt
+                                            .eq(cursor.external_created_at),
+                                    )
+                                    .add(orders::Column::Id.lt(after_id)),
+                            ),
+                    )
+                    .all(&self.pool)
+                    .await?;
+                (models, None)
+            }
+            None => {
+                let total = orders::Entity::find()
+                    .filter(cond)
+                    .count(&self.pool)
+                    .await? as i64;
+                let models = select.offset(offset as u64).all(&self.pool).await?;
+                (models, Some(total))
+            }
+        };
+        // 满页时返回最后一条的 id 作为下一页游标（首页同样返回，便于切换到游标分页）
+        let next_cursor = if models.len() as i64 == limit {
+            models.last().map(|m| m.id)
+        } else {
+            None
+        };
         // 组装 sweet_cash_earned
         let order_ids: Vec<i64> = models.iter().map(|m| m.id).collect();
         let mut earned_map: HashMap<i64, i64> = HashMap::new();
@@ -87,11 +142,86 @@ impl OrderService {
             items.push(resp);
         }
 
-        Ok(PaginatedResponse::new(
-            items,
-            params.get_offset() / params.get_limit() + 1,
-            params.get_limit(),
-            total,
-        ))
+        let page = match total {
+            Some(total) => PaginatedResponse::new(
+                items,
+                params.get_offset() / params.get_limit() + 1,
+                params.get_limit(),
+                total,
+            ),
+            None => PaginatedResponse::by_cursor(items, params.get_limit()),
+        };
+        Ok(page.with_next_cursor(next_cursor))
+    }
+
+    /// 获取单个订单详情（仅限本人订单），附带关联的返现流水
+    pub async fn get_order(&self, user_id: i64, order_id: i64) -> AppResult<OrderDetailResponse> {
+        let model = orders::Entity::find_by_id(order_id)
+            .filter(orders::Column::UserId.eq(user_id))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("订单不存在".to_string()))?;
+
+        let txs = sct::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(sct::Column::UserId.eq(user_id))
+                    .add(sct::Column::TransactionType.eq(TransactionType::Earn))
+                    .add(sct::Column::RelatedOrderId.eq(order_id)),
+            )
+            .order_by_asc(sct::Column::CreatedAt)
+            .all(&self.pool)
+            .await?;
+
+        let mut order = OrderResponse::from(model);
+        order.sweet_cash_earned = txs.iter().map(|t| t.amount).sum();
+        Ok(OrderDetailResponse {
+            order,
+            cashback_transactions: txs.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// 导出用户订单为 CSV（与列表接口使用相同的筛选条件，不分页）
+    pub async fn export_orders_csv(&self, user_id: i64, query: &OrderQuery) -> AppResult<String> {
+        let cond = Self::build_filter(user_id, query)?;
+        let models = orders::Entity::find()
+            .filter(cond)
+            .order_by_desc(orders::Column::ExternalCreatedAt)
+            .all(&self.pool)
+            .await?;
+
+        let mut out = String::from("id,product_name,price,stamps_earned,external_created_at\n");
+        for m in models {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                m.id,
+                csv_escape(&m.product_name),
+                m.price,
+                m.stamps_earned.unwrap_or(0),
+                m.external_created_at.to_rfc3339()
+            ));
+        }
+        Ok(out)
+    }
+}
+
+/// CSV 字段转义：包含逗号、引号或换行时用双引号包裹，内部引号加倍
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::csv_escape;
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("Mango Sago"), "Mango Sago");
+        assert_eq!(csv_escape("Tea, large"), "\"Tea, large\"");
+        assert_eq!(csv_escape("12\" cake"), "\"12\"\" cake\"");
     }
 }
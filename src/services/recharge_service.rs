@@ -1,36 +1,170 @@
+use crate::config::RechargeConfig;
 use crate::entities::StripeTransactionCategory;
 use crate::entities::{
-    RechargeStatus, TransactionType, recharge_record_entity as rr,
+    RechargeStatus, TransactionType, recharge_record_entity as rr, recharge_tier_entity as tiers,
     sweet_cash_transaction_entity as sct, user_entity as users,
 };
 use crate::error::{AppError, AppResult};
-use crate::external::stripe::StripeService;
+use crate::external::stripe::{StripeGateway, StripeService};
 use crate::models::{
     ConfirmRechargeRequest, ConfirmRechargeResponse, CreatePaymentIntentResponse,
     PaginatedResponse, PaginationParams, RechargeQuery, RechargeRecordResponse,
+    RechargeTierResponse, RefundRechargeResponse,
 };
-use crate::services::StripeTransactionService;
+use crate::services::{AuditEntry, AuditService, LuckyDrawService, StripeTransactionService};
+use chrono::Utc;
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
-    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbErr, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
 };
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use stripe::PaymentIntentStatus;
+use tokio::sync::RwLock;
+
+/// recharge_tiers 表为空时使用的默认档位：(充值金额, 赠送金额)，单位美分
+const DEFAULT_RECHARGE_TIERS: [(i64, i64); 4] = [
+    (500, 50),     // $5 -> $0.5
+    (1000, 200),   // $10 -> $2
+    (2000, 400),   // $20 -> $4
+    (10000, 2500), // $100 -> $25
+];
+
+/// 自定义充值金额范围：$1 ~ $500
+const CUSTOM_RECHARGE_MIN_CENTS: i64 = 100;
+const CUSTOM_RECHARGE_MAX_CENTS: i64 = 50_000;
+
+/// 自定义充值赠送比例（basis points，200bp = 2%）
+const CUSTOM_RECHARGE_BONUS_BP: i64 = 200;
+
+/// 支持充值的货币（小写 ISO 代码），未指定时为 USD
+pub const SUPPORTED_RECHARGE_CURRENCIES: [&str; 3] = ["usd", "eur", "gbp"];
+const DEFAULT_RECHARGE_CURRENCY: &str = "usd";
+
+/// 充值赠送抽奖次数：每实付 $20 赠送 1 次（$100 -> 5 次）
+const LUCKY_DRAW_SPIN_PER_CENTS: i64 = 2000;
+
+/// webhook 找不到充值记录时，等待创建接口落库后再重查的间隔
+const WEBHOOK_RECORD_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// RefundPending 超过该时长（分钟）仍未完成时由对账任务按 Stripe 上的退款处理
+const REFUND_PENDING_RECONCILE_AFTER_MINUTES: i64 = 30;
+
+/// 档位缓存有效期
+const TIERS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// 档位缓存：(加载时间, 生效档位)
+type TiersCache = Arc<RwLock<Option<(Instant, Vec<RechargeTierResponse>)>>>;
 
 #[derive(Clone)]
 pub struct RechargeService {
     pool: DatabaseConnection,
-    stripe_service: StripeService,
+    stripe_service: Arc<dyn StripeGateway>,
     stx_service: StripeTransactionService,
+    lucky_draw_service: LuckyDrawService,
+    audit_service: AuditService,
+    recharge: RechargeConfig,
+    tiers_cache: TiersCache,
+}
+
+/// 按实付金额计算赠送的抽奖次数
+fn lucky_draw_spins_for(amount_cents: i64) -> i64 {
+    (amount_cents / LUCKY_DRAW_SPIN_PER_CENTS).max(0)
+}
+
+/// 部分退款时应扣回的余额：按退款占实付的比例扣回入账余额（含赠送，已折算为 USD），向下取整
+fn partial_refund_debit(refund_cents: i64, amount: i64, credited_amount: i64) -> i64 {
+    if amount <= 0 {
+        return 0;
+    }
+    ((refund_cents as i128 * credited_amount as i128) / amount as i128) as i64
 }
 
 impl RechargeService {
-    pub fn new(pool: DatabaseConnection, stripe_service: StripeService) -> Self {
+    pub fn new(
+        pool: DatabaseConnection,
+        stripe_service: Arc<dyn StripeGateway>,
+        lucky_draw_service: LuckyDrawService,
+        recharge: RechargeConfig,
+    ) -> Self {
         let stx_service = StripeTransactionService::new(pool.clone());
+        let audit_service = AuditService::new(pool.clone());
         Self {
             pool,
             stripe_service,
             stx_service,
+            lucky_draw_service,
+            audit_service,
+            recharge,
+            tiers_cache: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// 指定充值货币入账时折合 USD 的汇率（基点）
+    fn exchange_rate_bps(&self, currency: &str) -> AppResult<i64> {
+        self.recharge
+            .usd_rate_bps(currency)
+            .ok_or_else(|| AppError::ValidationError(format!("Unsupported currency: {currency}")))
+    }
+
+    /// 读取指定货币当前生效的充值档位（带 60 秒缓存）
+    ///
+    /// 表中没有该货币的任何记录时回退到内置默认档位
+    pub async fn load_tiers(&self, currency: &str) -> AppResult<Vec<RechargeTierResponse>> {
+        let cached = match self.tiers_cache.read().await.as_ref() {
+            Some((loaded_at, tiers)) if loaded_at.elapsed() < TIERS_CACHE_TTL => {
+                Some(tiers.clone())
+            }
+            _ => None,
+        };
+        let tiers = match cached {
+            Some(tiers) => tiers,
+            None => {
+                let tiers: Vec<RechargeTierResponse> = self
+                    .list_tiers()
+                    .await?
+                    .into_iter()
+                    .filter(|t| t.active)
+                    .collect();
+                *self.tiers_cache.write().await = Some((Instant::now(), tiers.clone()));
+                tiers
+            }
+        };
+        Ok(tiers
+            .into_iter()
+            .filter(|t| t.currency == currency)
+            .collect())
+    }
+
+    /// 列出所有充值档位（含未启用的），用于后台查看；
+    /// 没有配置任何档位的货币列出内置默认档位
+    pub async fn list_tiers(&self) -> AppResult<Vec<RechargeTierResponse>> {
+        let rows = tiers::Entity::find()
+            .order_by_asc(tiers::Column::Currency)
+            .order_by_asc(tiers::Column::AmountCents)
+            .all(&self.pool)
+            .await?;
+        let mut result: Vec<RechargeTierResponse> =
+            rows.into_iter().map(RechargeTierResponse::from).collect();
+        for currency in SUPPORTED_RECHARGE_CURRENCIES {
+            if result.iter().any(|t| t.currency == currency) {
+                continue;
+            }
+            result.extend(
+                DEFAULT_RECHARGE_TIERS
+                    .iter()
+                    .map(|&(amount_cents, bonus_cents)| RechargeTierResponse {
+                        amount_cents,
+                        bonus_cents,
+                        currency: currency.to_string(),
+                        active: true,
+                    }),
+            );
         }
+        Ok(result)
     }
 
     pub async fn create_payment_intent(
@@ -38,17 +172,65 @@ impl RechargeService {
         user_id: i64,
         request: crate::models::CreatePaymentIntentRequest,
     ) -> AppResult<CreatePaymentIntentResponse> {
-        // 验证充值金额
-        if ![500, 1000, 2000, 10000].contains(&request.amount) {
-            return Err(AppError::ValidationError(
-                "The recharge amount must be $5, $10, $20, or $100".to_string(),
-            ));
-        }
+        let currency = normalize_currency(request.currency.as_deref())?;
+        let exchange_rate_bps = self.exchange_rate_bps(&currency)?;
 
-        // 计算奖励金额
-        let bonus_amount = calculate_bonus_amount(request.amount);
+        // 验证充值金额并计算奖励金额（命中该货币的固定档位时优先使用档位赠送）
+        let tiers = self.load_tiers(&currency).await?;
+        let bonus_amount = match tiers.iter().find(|t| t.amount_cents == request.amount) {
+            Some(tier) => tier.bonus_cents,
+            None if request.custom => {
+                StripeService::validate_amount(request.amount, &currency)?;
+                if !(CUSTOM_RECHARGE_MIN_CENTS..=CUSTOM_RECHARGE_MAX_CENTS)
+                    .contains(&request.amount)
+                {
+                    return Err(AppError::ValidationError(format!(
+                        "Custom recharge amount must be between {} and {}",
+                        format_money(CUSTOM_RECHARGE_MIN_CENTS, &currency),
+                        format_money(CUSTOM_RECHARGE_MAX_CENTS, &currency)
+                    )));
+                }
+                bonus_for_custom(request.amount)
+            }
+            None => {
+                let allowed = tiers
+                    .iter()
+                    .map(|t| format_money(t.amount_cents, &currency))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(AppError::ValidationError(format!(
+                    "The recharge amount must be one of {allowed}"
+                )));
+            }
+        };
         let total_amount = request.amount + bonus_amount;
 
+        // 客户端提供 request_id 时派生稳定的幂等 key：已有记录直接返回原记录与 Checkout，
+        // 否则以该 key 创建 PaymentIntent 与 Checkout Session，Stripe 侧重试也不会重复创建
+        let idempotency_key = request
+            .request_id
+            .as_deref()
+            .map(|rid| recharge_idempotency_key(user_id, request.amount, &currency, rid));
+        if let Some(key) = &idempotency_key
+            && let Some(existing) = rr::Entity::find()
+                .filter(rr::Column::RequestKey.eq(key.as_str()))
+                .filter(rr::Column::UserId.eq(user_id))
+                .one(&self.pool)
+                .await?
+        {
+            return self.existing_payment_intent_response(existing).await;
+        }
+
+        let customer_id = self
+            .stripe_service
+            .get_or_create_customer(&self.pool, user_id)
+            .await?;
+        let description = format!(
+            "Add Sweets Credits {}(include {} bonus)",
+            format_money(total_amount, &currency),
+            format_money(bonus_amount, &currency)
+        );
+
         // 创建Stripe支付意图
         // 先创建 PaymentIntent 以保持现有记录逻辑
         let payment_intent = self
@@ -57,13 +239,11 @@ impl RechargeService {
                 request.amount,
                 user_id,
                 "recharge",
-                Some("usd".to_string()),
-                Some(format!(
-                    "Add Sweets Credits ${:.2}(include ${:.2} bonus)",
-                    total_amount as f64 / 100.0,
-                    bonus_amount as f64 / 100.0
-                )),
+                Some(currency.clone()),
+                Some(description.clone()),
                 None,
+                idempotency_key.clone(),
+                Some(customer_id),
             )
             .await?;
 
@@ -72,15 +252,14 @@ impl RechargeService {
             .stripe_service
             .create_checkout_session_for_amount(
                 request.amount,
-                Some("usd".to_string()),
+                Some(currency.clone()),
                 user_id,
                 "recharge",
-                Some(format!(
-                    "Add Sweets Credits ${:.2}(include ${:.2} bonus)",
-                    total_amount as f64 / 100.0,
-                    bonus_amount as f64 / 100.0
-                )),
+                Some(description.clone()),
                 None,
+                idempotency_key
+                    .as_ref()
+                    .map(|key| format!("{key}-checkout")),
             )
             .await?;
 
@@ -90,17 +269,48 @@ impl RechargeService {
             .payment_intent_id
             .clone()
             .unwrap_or_else(|| payment_intent.id.to_string());
-        let _ = rr::ActiveModel {
+        // 没有 client_secret 时直接失败，不落库也不返回空字符串
+        let client_secret = StripeService::require_client_secret(
+            &payment_intent_id_str,
+            checkout.client_secret.clone(),
+            payment_intent.client_secret.clone(),
+        )?;
+        let record = rr::ActiveModel {
             user_id: Set(user_id),
             stripe_payment_intent_id: Set(payment_intent_id_str.clone()),
             amount: Set(request.amount),
             bonus_amount: Set(bonus_amount),
             total_amount: Set(total_amount),
+            currency: Set(currency.clone()),
+            exchange_rate_bps: Set(exchange_rate_bps),
             status: Set(status),
+            request_key: Set(idempotency_key.clone()),
+            checkout_url: Set(Some(checkout.url.clone())),
             ..Default::default()
-        }
-        .insert(&self.pool)
-        .await?;
+        };
+        // 并发的相同请求只落库一条，后到者返回先落库的记录
+        let record = match rr::Entity::insert(record)
+            .on_conflict(
+                OnConflict::column(rr::Column::RequestKey)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_with_returning(&self.pool)
+            .await
+        {
+            Ok(record) => record,
+            Err(DbErr::RecordNotInserted) => {
+                let existing = rr::Entity::find()
+                    .filter(rr::Column::RequestKey.eq(idempotency_key.as_deref()))
+                    .one(&self.pool)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::InternalError("Recharge record not inserted".to_string())
+                    })?;
+                return self.existing_payment_intent_response(existing).await;
+            }
+            Err(e) => return Err(e.into()),
+        };
 
         // 记录 unified stripe transaction
         let _ = self
@@ -110,21 +320,51 @@ impl RechargeService {
                 StripeTransactionCategory::Recharge,
                 payment_intent_id_str.as_str(),
                 Some(request.amount),
-                Some("usd".to_string()),
+                Some(currency.clone()),
                 Some(format!("{:?}", payment_intent.status)),
                 payment_intent.description.clone(),
+                None,
             )
             .await;
 
         Ok(CreatePaymentIntentResponse {
             payment_intent_id: payment_intent_id_str,
-            client_secret: checkout
-                .client_secret
-                .unwrap_or_else(|| payment_intent.client_secret.unwrap_or_default()),
+            client_secret,
             checkout_url: checkout.url,
             amount: request.amount,
             bonus_amount,
             total_amount,
+            currency,
+            credited_amount: record.credited_amount(),
+        })
+    }
+
+    /// 相同 request_id 的重复请求：返回已有记录的 PaymentIntent 与 Checkout 地址
+    async fn existing_payment_intent_response(
+        &self,
+        record: rr::Model,
+    ) -> AppResult<CreatePaymentIntentResponse> {
+        let payment_intent = self
+            .stripe_service
+            .retrieve_payment_intent(&record.stripe_payment_intent_id)
+            .await?;
+        let client_secret = StripeService::require_client_secret(
+            &record.stripe_payment_intent_id,
+            None,
+            payment_intent.client_secret,
+        )?;
+        let checkout_url = record.checkout_url.clone().ok_or_else(|| {
+            AppError::InternalError(format!("Recharge record {} has no checkout url", record.id))
+        })?;
+        Ok(CreatePaymentIntentResponse {
+            credited_amount: record.credited_amount(),
+            payment_intent_id: record.stripe_payment_intent_id,
+            client_secret,
+            checkout_url,
+            amount: record.amount,
+            bonus_amount: record.bonus_amount,
+            total_amount: record.total_amount,
+            currency: record.currency,
         })
     }
 
@@ -155,74 +395,491 @@ impl RechargeService {
             .one(&txn)
             .await?
             .ok_or_else(|| AppError::NotFound("Recharge record not found".into()))?;
+        // 入账金额按创建时的货币与汇率计算，实付货币不一致时拒绝入账
+        if payment_intent.currency.to_string() != recharge_record.currency {
+            return Err(AppError::ValidationError(format!(
+                "Payment currency {} does not match recharge currency {}",
+                payment_intent.currency, recharge_record.currency
+            )));
+        }
 
-        // 检查是否已经处理过
-        if recharge_record.status == RechargeStatus::Succeeded {
+        // 条件更新保证并发确认只入账一次；已处理过则直接返回当前余额
+        let credited = Self::credit_recharge_tx(
+            &txn,
+            &recharge_record,
+            &format!("{:?}", payment_intent.status),
+            format!(
+                "Recharge confirmed via Stripe {}",
+                request.payment_intent_id
+            ),
+        )
+        .await?;
+        let Some(current_balance) = credited else {
             let current_balance = users::Entity::find_by_id(user_id)
                 .one(&txn)
                 .await?
                 .and_then(|u| u.balance)
                 .unwrap_or(0);
-
+            let record = rr::Entity::find_by_id(recharge_record.id)
+                .one(&txn)
+                .await?
+                .unwrap_or(recharge_record);
+            txn.commit().await?;
             return Ok(ConfirmRechargeResponse {
-                recharge_record: RechargeRecordResponse::from(recharge_record),
+                recharge_record: RechargeRecordResponse::from(record),
                 new_balance: current_balance,
             });
-        }
+        };
 
-        // 更新充值记录状态 (使用枚举)
-        let success_status = RechargeStatus::Succeeded;
-        let stripe_status_str = format!("{:?}", payment_intent.status);
-        if let Some(m) = rr::Entity::find_by_id(recharge_record.id).one(&txn).await? {
-            let mut am = m.into_active_model();
-            am.status = Set(success_status);
-            am.stripe_status = Set(Some(stripe_status_str));
-            am.update(&txn).await?;
-        }
+        txn.commit().await?;
 
-        // 更新用户余额
-        if let Some(u) = users::Entity::find_by_id(user_id).one(&txn).await? {
-            let cur = u.balance.unwrap_or(0);
-            let delta = recharge_record.total_amount;
-            let mut am = u.into_active_model();
-            am.balance = Set(Some(cur + delta));
-            am.update(&txn).await?;
+        self.audit_credit(Some(user_id), &recharge_record, current_balance);
+        self.award_recharge_spins(
+            user_id,
+            recharge_record.to_usd_cents(recharge_record.amount),
+        )
+        .await;
+
+        recharge_record.status = RechargeStatus::Succeeded;
+
+        Ok(ConfirmRechargeResponse {
+            recharge_record: RechargeRecordResponse::from(recharge_record),
+            new_balance: current_balance,
+        })
+    }
+
+    /// 将充值记录置为成功并为用户入账（幂等）
+    ///
+    /// 通过 `UPDATE ... WHERE status IN (pending, failed, canceled)` 的 rows_affected 判断是否由本次调用入账，
+    /// 并发的确认/webhook 只有一个会成功；余额使用原子自增。返回入账后的余额，已处理过时返回 None
+    async fn credit_recharge_tx(
+        txn: &sea_orm::DatabaseTransaction,
+        record: &rr::Model,
+        stripe_status: &str,
+        description: String,
+    ) -> AppResult<Option<i64>> {
+        let res = rr::Entity::update_many()
+            .col_expr(rr::Column::Status, RechargeStatus::Succeeded.as_enum())
+            .col_expr(rr::Column::StripeStatus, Expr::value(stripe_status))
+            .col_expr(rr::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(rr::Column::Id.eq(record.id))
+            .filter(rr::Column::Status.is_in([
+                RechargeStatus::Pending,
+                RechargeStatus::Failed,
+                RechargeStatus::Canceled,
+            ]))
+            .exec(txn)
+            .await?;
+        if res.rows_affected == 0 {
+            return Ok(None);
         }
 
-        // 获取新余额
-        let current_balance = users::Entity::find_by_id(user_id)
-            .one(&txn)
+        users::Entity::update_many()
+            .col_expr(
+                users::Column::Balance,
+                Expr::cust_with_values("COALESCE(balance, 0) + $1", [record.credited_amount()]),
+            )
+            .filter(users::Column::Id.eq(record.user_id))
+            .exec(txn)
+            .await?;
+        let balance_after = users::Entity::find_by_id(record.user_id)
+            .one(txn)
             .await?
-            .and_then(|u| u.balance)
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?
+            .balance
             .unwrap_or(0);
 
-        // 记录 sweet_cash_transactions (Earn)
+        // 记录 sweet_cash_transactions (Earn)，金额为折算后的 USD 美分
         sct::ActiveModel {
-            user_id: Set(user_id),
+            user_id: Set(record.user_id),
             transaction_type: Set(TransactionType::Earn),
-            amount: Set(recharge_record.total_amount),
-            balance_after: Set(current_balance),
+            amount: Set(record.credited_amount()),
+            balance_after: Set(balance_after),
             related_order_id: Set(None),
             related_discount_code_id: Set(None),
-            description: Set(Some(format!(
-                "Recharge confirmed via Stripe {}",
-                request.payment_intent_id
-            ))),
+            description: Set(Some(description)),
             ..Default::default()
         }
-        .insert(&txn)
+        .insert(txn)
         .await?;
 
+        Ok(Some(balance_after))
+    }
+
+    /// 记录充值入账审计（webhook 入账时 actor 为 None）
+    fn audit_credit(&self, actor_id: Option<i64>, record: &rr::Model, balance_after: i64) {
+        self.audit_service.record(AuditEntry {
+            actor_id,
+            action: "recharge.credit",
+            entity: "user",
+            entity_id: Some(record.user_id),
+            before: Some(json!({ "balance": balance_after - record.credited_amount() })),
+            after: Some(json!({
+                "balance": balance_after,
+                "recharge_id": record.id,
+                "amount": record.amount,
+                "bonus_amount": record.bonus_amount,
+            })),
+        });
+    }
+
+    /// 充值入账后赠送抽奖次数（尽力而为：失败只记日志，不影响已提交的余额）；`amount_cents` 为折算后的 USD 实付
+    async fn award_recharge_spins(&self, user_id: i64, amount_cents: i64) {
+        let spins = lucky_draw_spins_for(amount_cents);
+        if spins <= 0 {
+            return;
+        }
+        if let Err(e) = self.lucky_draw_service.award_chances(user_id, spins).await {
+            log::error!(
+                "Failed to award {spins} lucky draw chances to user {user_id} for recharge: {e:?}"
+            );
+        }
+    }
+
+    /// 管理员发起 Stripe 退款并扣回余额
+    ///
+    /// 不在数据库事务中等待 Stripe，分三步完成：
+    /// 1. 事务内锁定记录，从 Succeeded / PartiallyRefunded 置为 RefundPending，扣回余额、记录流水并记下待退金额后提交；
+    /// 2. 调用 Stripe 退款，失败时撤销第 1 步（退回余额并恢复原状态）；
+    /// 3. 累计退款计入 refunded_amount，达到实付金额时置为 Refunded，否则为 PartiallyRefunded。
+    ///    第 2、3 步之间进程退出时由 charge.refunded webhook 完成。
+    ///
+    /// 累计扣回的余额按累计退款占实付的比例计算（含赠送），每次只扣回本次新增的部分：
+    /// * 全额退款（`amount_cents` 为 None）：Stripe 退剩余未退的金额，余额不足时拒绝
+    /// * 部分退款：Stripe 退 `amount_cents`（不超过剩余未退金额），余额不足时扣到 0 为止
+    pub async fn refund_recharge(
+        &self,
+        actor_id: Option<i64>,
+        payment_intent_id: &str,
+        amount_cents: Option<i64>,
+    ) -> AppResult<RefundRechargeResponse> {
+        let txn = self.pool.begin().await?;
+
+        let recharge_record = rr::Entity::find()
+            .filter(rr::Column::StripePaymentIntentId.eq(payment_intent_id.to_string()))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Recharge record not found".into()))?;
+
+        match recharge_record.status {
+            RechargeStatus::Succeeded | RechargeStatus::PartiallyRefunded => {}
+            RechargeStatus::Refunded => {
+                return Err(AppError::ValidationError(
+                    "Recharge already refunded".to_string(),
+                ));
+            }
+            RechargeStatus::RefundPending => {
+                return Err(AppError::Conflict("Refund already in progress".to_string()));
+            }
+            _ => {
+                return Err(AppError::ValidationError(
+                    "Only succeeded recharges can be refunded".to_string(),
+                ));
+            }
+        }
+
+        // Stripe 累计最多只能退实际支付的金额
+        let refundable = recharge_record.amount - recharge_record.refunded_amount;
+        if refundable <= 0 {
+            return Err(AppError::ValidationError(
+                "Recharge already refunded".to_string(),
+            ));
+        }
+        if let Some(amount) = amount_cents
+            && (amount <= 0 || amount > refundable)
+        {
+            return Err(AppError::ValidationError(format!(
+                "Refund amount must be between 1 and {refundable} cents"
+            )));
+        }
+        let refund_amount = amount_cents.unwrap_or(refundable);
+        let refunded_total = recharge_record.refunded_amount + refund_amount;
+        let user_id = recharge_record.user_id;
+
+        // 本次应扣回的余额：累计应扣回减去此前已按比例扣回的部分，多次部分退款后再全额退款也不会超扣
+        let credited = recharge_record.credited_amount();
+        let debit_due = partial_refund_debit(refunded_total, recharge_record.amount, credited)
+            - partial_refund_debit(
+                recharge_record.refunded_amount,
+                recharge_record.amount,
+                credited,
+            );
+
+        // 扣回余额（已消费的部分无法退款）
+        let user = users::Entity::find_by_id(user_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let current_balance = user.balance.unwrap_or(0);
+        let balance_debited = if amount_cents.is_some() {
+            debit_due.min(current_balance.max(0))
+        } else {
+            if current_balance < debit_due {
+                return Err(AppError::ValidationError(
+                    "Insufficient balance to refund this recharge".to_string(),
+                ));
+            }
+            debit_due
+        };
+        let new_balance = current_balance - balance_debited;
+        let mut am = user.into_active_model();
+        am.balance = Set(Some(new_balance));
+        am.update(&txn).await?;
+
+        // 记录 sweet_cash_transactions (Redeem，余额扣减)，金额为实际扣回的部分
+        if balance_debited > 0 {
+            sct::ActiveModel {
+                user_id: Set(user_id),
+                transaction_type: Set(TransactionType::Redeem),
+                amount: Set(balance_debited),
+                balance_after: Set(new_balance),
+                related_order_id: Set(None),
+                related_discount_code_id: Set(None),
+                description: Set(Some(format!(
+                    "Recharge refunded via Stripe {payment_intent_id} ({refund_amount} cents)"
+                ))),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        let mut am = recharge_record.into_active_model();
+        am.status = Set(RechargeStatus::RefundPending);
+        am.pending_refund_amount = Set(Some(refund_amount));
+        am.pending_refund_debit = Set(Some(balance_debited));
+        am.updated_at = Set(Some(Utc::now()));
+        let mut recharge_record = am.update(&txn).await?;
         txn.commit().await?;
 
-        recharge_record.status = RechargeStatus::Succeeded;
+        let refund_id = match self
+            .stripe_service
+            .create_refund(payment_intent_id, Some(refund_amount))
+            .await
+        {
+            Ok(refund_id) => refund_id,
+            Err(e) => {
+                if let Err(revert_err) = self.revert_pending_refund(recharge_record.id).await {
+                    log::error!(
+                        "Failed to revert pending refund for recharge {}: {revert_err:?}",
+                        recharge_record.id
+                    );
+                }
+                return Err(e);
+            }
+        };
 
-        Ok(ConfirmRechargeResponse {
+        // webhook 可能已先一步完成，此时更新 0 行
+        self.finalize_pending_refund(recharge_record.id).await?;
+
+        self.audit_service.record(AuditEntry {
+            actor_id,
+            action: "recharge.refund",
+            entity: "user",
+            entity_id: Some(user_id),
+            before: Some(json!({ "balance": current_balance })),
+            after: Some(json!({
+                "balance": new_balance,
+                "recharge_id": recharge_record.id,
+                "refund_id": refund_id,
+                "refunded_amount": refund_amount,
+            })),
+        });
+
+        recharge_record.status = if refunded_total >= recharge_record.amount {
+            RechargeStatus::Refunded
+        } else {
+            RechargeStatus::PartiallyRefunded
+        };
+        recharge_record.refunded_amount = refunded_total;
+        recharge_record.pending_refund_amount = None;
+        recharge_record.pending_refund_debit = None;
+
+        Ok(RefundRechargeResponse {
             recharge_record: RechargeRecordResponse::from(recharge_record),
-            new_balance: current_balance,
+            refund_id,
+            refunded_amount: refund_amount,
+            balance_debited,
+            new_balance,
         })
     }
 
+    /// 将进行中的退款计入累计退款，累计达到实付金额时置为 Refunded，否则为 PartiallyRefunded；
+    /// 记录已不是 RefundPending 时返回 false
+    async fn finalize_pending_refund(&self, recharge_id: i64) -> AppResult<bool> {
+        let res = rr::Entity::update_many()
+            .col_expr(
+                rr::Column::Status,
+                Expr::cust(
+                    "CASE WHEN refunded_amount + COALESCE(pending_refund_amount, 0) >= amount \
+                     THEN 'refunded'::recharge_status ELSE 'partially_refunded'::recharge_status END",
+                ),
+            )
+            .col_expr(
+                rr::Column::RefundedAmount,
+                Expr::cust("refunded_amount + COALESCE(pending_refund_amount, 0)"),
+            )
+            .col_expr(rr::Column::PendingRefundAmount, Expr::cust("NULL"))
+            .col_expr(rr::Column::PendingRefundDebit, Expr::cust("NULL"))
+            .col_expr(rr::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(rr::Column::Id.eq(recharge_id))
+            .filter(rr::Column::Status.eq(RechargeStatus::RefundPending))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    /// Stripe 退款失败：退回已扣的余额并恢复发起退款前的状态；记录已不是 RefundPending 时返回 false
+    async fn revert_pending_refund(&self, recharge_id: i64) -> AppResult<bool> {
+        let txn = self.pool.begin().await?;
+        let Some(record) = rr::Entity::find_by_id(recharge_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+        else {
+            return Ok(false);
+        };
+        if record.status != RechargeStatus::RefundPending {
+            return Ok(false);
+        }
+
+        let debited = record.pending_refund_debit.unwrap_or(0);
+        if debited > 0 {
+            let user = users::Entity::find_by_id(record.user_id)
+                .lock_exclusive()
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+            let new_balance = user.balance.unwrap_or(0) + debited;
+            let mut am = user.into_active_model();
+            am.balance = Set(Some(new_balance));
+            am.update(&txn).await?;
+
+            sct::ActiveModel {
+                user_id: Set(record.user_id),
+                transaction_type: Set(TransactionType::Earn),
+                amount: Set(debited),
+                balance_after: Set(new_balance),
+                related_order_id: Set(None),
+                related_discount_code_id: Set(None),
+                description: Set(Some(format!(
+                    "Stripe refund failed for {}, balance restored",
+                    record.stripe_payment_intent_id
+                ))),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        let status = if record.refunded_amount > 0 {
+            RechargeStatus::PartiallyRefunded
+        } else {
+            RechargeStatus::Succeeded
+        };
+        let mut am = record.into_active_model();
+        am.status = Set(status);
+        am.pending_refund_amount = Set(None);
+        am.pending_refund_debit = Set(None);
+        am.updated_at = Set(Some(Utc::now()));
+        am.update(&txn).await?;
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    /// charge.refunded webhook：完成因进程中断而停留在 RefundPending 的退款。
+    /// `amount_refunded` 为 Stripe 上该笔支付的累计退款金额，只有覆盖了进行中的退款才完成，
+    /// 避免先前部分退款的迟到事件提前完成尚未在 Stripe 生效的退款
+    pub async fn handle_refund_webhook(
+        &self,
+        payment_intent_id: &str,
+        amount_refunded: i64,
+    ) -> AppResult<()> {
+        let Some(record) = rr::Entity::find()
+            .filter(rr::Column::StripePaymentIntentId.eq(payment_intent_id.to_string()))
+            .one(&self.pool)
+            .await?
+        else {
+            return Ok(());
+        };
+        match record.status {
+            RechargeStatus::RefundPending => {
+                let expected = record.refunded_amount + record.pending_refund_amount.unwrap_or(0);
+                if amount_refunded >= expected {
+                    let finalized = self.finalize_pending_refund(record.id).await?;
+                    if finalized {
+                        log::info!("Finalized pending refund for recharge {}", record.id);
+                    }
+                }
+            }
+            RechargeStatus::Succeeded | RechargeStatus::PartiallyRefunded
+                if amount_refunded > record.refunded_amount =>
+            {
+                // 未经 refund_recharge 发起（如 Stripe 后台退款），余额未扣回
+                log::warn!(
+                    "Recharge {} refunded in Stripe without a local refund ({amount_refunded} > {} cents), manual review required",
+                    record.id,
+                    record.refunded_amount
+                );
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 对账停留在 RefundPending 超过一定时长的退款（发起退款后进程中断且未收到 webhook）：
+    /// 按 Stripe 上的累计退款判断，已覆盖进行中的退款则完成；Stripe 没有新的退款则退回余额并恢复原状态；
+    /// 只退了一部分的情况无法自动判断，记日志交由人工处理。返回处理的数量
+    pub async fn reconcile_pending_refunds(&self) -> AppResult<u64> {
+        let cutoff = Utc::now() - chrono::Duration::minutes(REFUND_PENDING_RECONCILE_AFTER_MINUTES);
+        let pending = rr::Entity::find()
+            .filter(rr::Column::Status.eq(RechargeStatus::RefundPending))
+            .filter(rr::Column::UpdatedAt.lt(cutoff))
+            .all(&self.pool)
+            .await?;
+
+        let mut handled = 0u64;
+        for record in pending {
+            let amount_refunded = match self
+                .stripe_service
+                .refunded_amount(&record.stripe_payment_intent_id)
+                .await
+            {
+                Ok(amount) => amount,
+                Err(e) => {
+                    log::error!(
+                        "Failed to look up Stripe refunds for recharge {}: {e:?}",
+                        record.id
+                    );
+                    continue;
+                }
+            };
+            let expected = record.refunded_amount + record.pending_refund_amount.unwrap_or(0);
+            let done = if amount_refunded >= expected {
+                self.finalize_pending_refund(record.id).await
+            } else if amount_refunded <= record.refunded_amount {
+                self.revert_pending_refund(record.id).await
+            } else {
+                log::warn!(
+                    "Recharge {} pending refund only partly applied in Stripe ({amount_refunded} of {expected} cents), manual review required",
+                    record.id
+                );
+                continue;
+            };
+            match done {
+                Ok(true) => handled += 1,
+                Ok(false) => {}
+                Err(e) => log::error!(
+                    "Failed to reconcile pending refund for recharge {}: {e:?}",
+                    record.id
+                ),
+            }
+        }
+        Ok(handled)
+    }
+
     pub async fn get_recharge_history(
         &self,
         user_id: i64,
@@ -261,6 +918,9 @@ impl RechargeService {
 
     /// 处理Stripe webhook支付成功事件
     ///
+    /// 找不到充值记录时稍等重查一次，仍找不到则按 PaymentIntent 补建记录后入账，
+    /// 因此 webhook 先于创建接口落库到达也不会丢失入账
+    ///
     /// # 参数
     ///
     /// * `payment_intent_id` - Stripe支付意图ID
@@ -275,85 +935,53 @@ impl RechargeService {
         user_id: i64,
     ) -> AppResult<()> {
         // 开始事务
-        let txn = self.pool.begin().await?;
+        let mut txn = self.pool.begin().await?;
 
-        // 获取充值记录
-        let recharge_record = rr::Entity::find()
-            .filter(rr::Column::StripePaymentIntentId.eq(payment_intent_id.to_string()))
-            .filter(rr::Column::UserId.eq(user_id))
-            .one(&txn)
-            .await?;
-        let recharge_record = if let Some(m) = recharge_record {
-            m
-        } else {
-            // 回退：按用户找到最新 Pending 记录，并把其 PI 绑定为这次 webhook 的 PI
-            if let Some(mut latest) = rr::Entity::find()
-                .filter(rr::Column::UserId.eq(user_id))
-                .filter(rr::Column::Status.eq(RechargeStatus::Pending))
-                .order_by_desc(rr::Column::CreatedAt)
-                .one(&txn)
-                .await?
-            {
-                let mut am = latest.clone().into_active_model();
-                am.stripe_payment_intent_id = Set(payment_intent_id.to_string());
-                am.update(&txn).await?;
-                latest = rr::Entity::find_by_id(latest.id).one(&txn).await?.unwrap();
-                latest
-            } else {
-                log::warn!(
-                    "Recharge record not found for payment_intent_id: {payment_intent_id} and user_id: {user_id}"
-                );
-                return Ok(());
+        // 获取充值记录；webhook 可能先于创建接口落库到达，稍等后再查一次
+        let mut recharge_record =
+            Self::find_webhook_record(&txn, payment_intent_id, user_id).await?;
+        if recharge_record.is_none() {
+            txn.rollback().await?;
+            tokio::time::sleep(WEBHOOK_RECORD_RETRY_DELAY).await;
+            txn = self.pool.begin().await?;
+            recharge_record = Self::find_webhook_record(&txn, payment_intent_id, user_id).await?;
+        }
+        let recharge_record = match recharge_record {
+            Some(m) => m,
+            None => {
+                match self
+                    .create_record_from_intent(&txn, payment_intent_id, user_id)
+                    .await?
+                {
+                    Some(m) => m,
+                    None => return Ok(()),
+                }
             }
         };
 
-        // 检查是否已经处理过
-        if recharge_record.status == RechargeStatus::Succeeded {
+        let new_balance_after = Self::credit_recharge_tx(
+            &txn,
+            &recharge_record,
+            "succeeded",
+            format!("Recharge succeeded via Stripe {payment_intent_id}"),
+        )
+        .await?;
+        if new_balance_after.is_none() {
             log::info!("Payment already processed for payment_intent_id: {payment_intent_id}");
             return Ok(());
         }
 
-        // 更新充值记录状态
-        let success_status = RechargeStatus::Succeeded;
-        if let Some(m) = rr::Entity::find_by_id(recharge_record.id).one(&txn).await? {
-            let mut am = m.into_active_model();
-            am.status = Set(success_status);
-            am.stripe_status = Set(Some("succeeded".to_string()));
-            am.update(&txn).await?;
-        }
-
-        // 更新用户余额
-        let mut new_balance_after: Option<i64> = None;
-        if let Some(u) = users::Entity::find_by_id(user_id).one(&txn).await? {
-            let cur = u.balance.unwrap_or(0);
-            let delta = recharge_record.total_amount;
-            let mut am = u.into_active_model();
-            let updated = cur + delta;
-            am.balance = Set(Some(updated));
-            am.update(&txn).await?;
-            new_balance_after = Some(updated);
-        }
+        txn.commit().await?;
 
-        // 记录 sweet_cash_transactions (Earn)
         if let Some(balance_after) = new_balance_after {
-            sct::ActiveModel {
-                user_id: Set(user_id),
-                transaction_type: Set(TransactionType::Earn),
-                amount: Set(recharge_record.total_amount),
-                balance_after: Set(balance_after),
-                related_order_id: Set(None),
-                related_discount_code_id: Set(None),
-                description: Set(Some(format!(
-                    "Recharge succeeded via Stripe {payment_intent_id}"
-                ))),
-                ..Default::default()
-            }
-            .insert(&txn)
-            .await?;
+            self.audit_credit(None, &recharge_record, balance_after);
+            self.award_recharge_spins(
+                user_id,
+                recharge_record.to_usd_cents(recharge_record.amount),
+            )
+            .await;
         }
 
-        txn.commit().await?;
-
         log::info!(
             "Successfully processed payment webhook for user {} with amount {}",
             user_id,
@@ -363,6 +991,101 @@ impl RechargeService {
         Ok(())
     }
 
+    /// 按 PI 精确查找 webhook 对应的充值记录；找不到时由 `create_record_from_intent`
+    /// 按 PaymentIntent 的实际金额与币种补建，不会把其他 Pending 记录绑定到这次的 PI
+    async fn find_webhook_record<C: ConnectionTrait>(
+        conn: &C,
+        payment_intent_id: &str,
+        user_id: i64,
+    ) -> AppResult<Option<rr::Model>> {
+        Ok(rr::Entity::find()
+            .filter(rr::Column::StripePaymentIntentId.eq(payment_intent_id.to_string()))
+            .filter(rr::Column::UserId.eq(user_id))
+            .one(conn)
+            .await?)
+    }
+
+    /// 重查后仍没有充值记录时，按 PaymentIntent 的金额与 metadata 补建 Pending 记录，
+    /// 避免入账丢失。锁定用户行后再确认一次，防止同一事件重复投递时建出两条记录。
+    /// PI 未成功或不属于该用户时返回 None
+    async fn create_record_from_intent(
+        &self,
+        txn: &DatabaseTransaction,
+        payment_intent_id: &str,
+        user_id: i64,
+    ) -> AppResult<Option<rr::Model>> {
+        let payment_intent = self
+            .stripe_service
+            .retrieve_payment_intent(payment_intent_id)
+            .await?;
+        let owner = payment_intent
+            .metadata
+            .get("user_id")
+            .and_then(|v| v.parse::<i64>().ok());
+        let category = payment_intent
+            .metadata
+            .get("category")
+            .map(String::as_str)
+            .unwrap_or("recharge");
+        if payment_intent.status != PaymentIntentStatus::Succeeded
+            || owner != Some(user_id)
+            || category != "recharge"
+        {
+            log::warn!(
+                "Recharge record not found and PaymentIntent {payment_intent_id} is not a succeeded recharge of user {user_id}"
+            );
+            return Ok(None);
+        }
+
+        users::Entity::find_by_id(user_id)
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        if let Some(existing) = rr::Entity::find()
+            .filter(rr::Column::StripePaymentIntentId.eq(payment_intent_id.to_string()))
+            .one(txn)
+            .await?
+        {
+            return Ok(Some(existing));
+        }
+
+        let currency = payment_intent.currency.to_string();
+        if !SUPPORTED_RECHARGE_CURRENCIES.contains(&currency.as_str()) {
+            log::warn!(
+                "Recharge record not found and PaymentIntent {payment_intent_id} has unsupported currency {currency}"
+            );
+            return Ok(None);
+        }
+        let exchange_rate_bps = self.exchange_rate_bps(&currency)?;
+        let amount = payment_intent.amount;
+        let bonus_amount = self
+            .load_tiers(&currency)
+            .await?
+            .iter()
+            .find(|t| t.amount_cents == amount)
+            .map(|t| t.bonus_cents)
+            .unwrap_or_else(|| bonus_for_custom(amount));
+        let record = rr::ActiveModel {
+            user_id: Set(user_id),
+            stripe_payment_intent_id: Set(payment_intent_id.to_string()),
+            amount: Set(amount),
+            bonus_amount: Set(bonus_amount),
+            total_amount: Set(amount + bonus_amount),
+            currency: Set(currency),
+            exchange_rate_bps: Set(exchange_rate_bps),
+            status: Set(RechargeStatus::Pending),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+        log::warn!(
+            "Recharge record missing for PaymentIntent {payment_intent_id}, recreated from Stripe (record {})",
+            record.id
+        );
+        Ok(Some(record))
+    }
+
     /// 处理Stripe webhook支付失败事件
     ///
     /// # 参数
@@ -458,13 +1181,118 @@ impl RechargeService {
     }
 }
 
-/// 根据充值金额计算奖励金额
-fn calculate_bonus_amount(amount: i64) -> i64 {
-    match amount {
-        500 => 50,     // $5 -> $0.5
-        1000 => 200,   // $10 -> $2
-        2000 => 400,   // $20 -> $4
-        10000 => 2500, // $100 -> $25
-        _ => 0,
+/// 由 user_id + 金额 + 货币 + 客户端 request_id 派生 Stripe 幂等 key
+fn recharge_idempotency_key(user_id: i64, amount: i64, currency: &str, request_id: &str) -> String {
+    let digest = md5::compute(format!("{user_id}:{amount}:{currency}:{request_id}"));
+    format!("recharge-{user_id}-{digest:x}")
+}
+
+/// 自定义金额充值的赠送金额：按比例向下取整到美分
+fn bonus_for_custom(amount: i64) -> i64 {
+    amount * CUSTOM_RECHARGE_BONUS_BP / 10_000
+}
+
+/// 校验并规范化充值货币（小写），未指定时为 USD
+fn normalize_currency(currency: Option<&str>) -> AppResult<String> {
+    let currency = currency
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| DEFAULT_RECHARGE_CURRENCY.to_string());
+    if !SUPPORTED_RECHARGE_CURRENCIES.contains(&currency.as_str()) {
+        return Err(AppError::ValidationError(format!(
+            "Unsupported currency: {currency}, supported: {}",
+            SUPPORTED_RECHARGE_CURRENCIES.join(", ")
+        )));
+    }
+    Ok(currency)
+}
+
+/// 按货币格式化最小单位金额，如 1050 + "eur" -> "€10.50"
+fn format_money(amount: i64, currency: &str) -> String {
+    let symbol = match currency {
+        "usd" => "$",
+        "eur" => "€",
+        "gbp" => "£",
+        _ => "",
+    };
+    format!("{symbol}{:.2}", amount as f64 / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_db;
+
+    #[test]
+    fn test_partial_refund_debit() {
+        // $10 实付 + $2 赠送，退 $5 扣回 $6
+        assert_eq!(partial_refund_debit(500, 1000, 1200), 600);
+        assert_eq!(partial_refund_debit(1000, 1000, 1200), 1200);
+        assert_eq!(partial_refund_debit(333, 1000, 1200), 399);
+        assert_eq!(partial_refund_debit(100, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_normalize_currency() {
+        assert_eq!(normalize_currency(None).unwrap(), "usd");
+        assert_eq!(normalize_currency(Some(" EUR ")).unwrap(), "eur");
+        assert_eq!(normalize_currency(Some("gbp")).unwrap(), "gbp");
+        assert!(normalize_currency(Some("jpy")).is_err());
+        assert_eq!(format_money(1050, "eur"), "€10.50");
+        assert_eq!(format_money(500, "usd"), "$5.00");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    async fn test_concurrent_confirm_credits_once() {
+        let pool = test_db::setup_db().await;
+        let user = test_db::create_user(&pool, "R").await;
+        let suffix = Utc::now().timestamp_micros().to_string();
+        let record = rr::ActiveModel {
+            user_id: Set(user.id),
+            stripe_payment_intent_id: Set(format!("pi_test_{suffix}")),
+            amount: Set(1000),
+            bonus_amount: Set(200),
+            total_amount: Set(1200),
+            status: Set(RechargeStatus::Pending),
+            ..Default::default()
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let pool = pool.clone();
+            let record = record.clone();
+            handles.push(tokio::spawn(async move {
+                let txn = pool.begin().await.unwrap();
+                let credited = RechargeService::credit_recharge_tx(
+                    &txn,
+                    &record,
+                    "Succeeded",
+                    "test".to_string(),
+                )
+                .await
+                .unwrap();
+                txn.commit().await.unwrap();
+                credited
+            }));
+        }
+        let mut credited = 0;
+        for h in handles {
+            if h.await.unwrap().is_some() {
+                credited += 1;
+            }
+        }
+        assert_eq!(credited, 1);
+
+        let balance = users::Entity::find_by_id(user.id)
+            .one(&pool)
+            .await
+            .unwrap()
+            .unwrap()
+            .balance;
+        assert_eq!(balance, Some(1200));
     }
 }
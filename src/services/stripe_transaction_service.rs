@@ -1,8 +1,33 @@
 use crate::entities::StripeTransactionCategory;
-use crate::entities::stripe_transaction_entity as stx;
+use crate::entities::{
+    payment_confirmation_entity as confirmations, processed_event_entity as processed,
+    stripe_transaction_entity as stx,
+};
 use crate::error::AppResult;
+use crate::models::{PaginatedResponse, PaginationParams, StripeTransactionResponse};
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, DatabaseConnection, Set};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+
+/// 争议交易记录的状态
+pub const DISPUTED_STATUS: &str = "disputed";
+
+/// 已认领但未完成的 webhook 事件的租约时长（分钟）
+const EVENT_LEASE_MINUTES: i64 = 10;
+
+/// webhook 事件的认领结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventClaim {
+    /// 本次请求认领成功，需要处理
+    Claimed,
+    /// 事件已处理完成
+    Completed,
+    /// 事件正由另一请求处理（租约未过期）
+    InFlight,
+}
 
 #[derive(Clone)]
 pub struct StripeTransactionService {
@@ -14,7 +39,75 @@ impl StripeTransactionService {
         Self { pool }
     }
 
-    /// 记录一条与 PaymentIntent 相关的交易
+    /// 认领 Stripe 事件（webhook 重试去重）。以唯一主键原子认领，并发到达的同一事件只有一个请求会继续处理；
+    /// 认领后未完成（处理中途崩溃）的事件在租约过期后可被重新认领
+    pub async fn claim_event(&self, event_id: &str, event_type: &str) -> AppResult<EventClaim> {
+        let now = Utc::now();
+        let inserted = processed::Entity::insert(processed::ActiveModel {
+            event_id: Set(event_id.to_string()),
+            event_type: Set(Some(event_type.to_string())),
+            processed_at: Set(Some(now)),
+            completed_at: Set(None),
+        })
+        .on_conflict(
+            OnConflict::column(processed::Column::EventId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&self.pool)
+        .await?;
+        if inserted > 0 {
+            return Ok(EventClaim::Claimed);
+        }
+
+        let lease_expired = now - chrono::Duration::minutes(EVENT_LEASE_MINUTES);
+        let reclaimed = processed::Entity::update_many()
+            .col_expr(processed::Column::ProcessedAt, Expr::value(now))
+            .filter(processed::Column::EventId.eq(event_id))
+            .filter(processed::Column::CompletedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(processed::Column::ProcessedAt.is_null())
+                    .add(processed::Column::ProcessedAt.lt(lease_expired)),
+            )
+            .exec(&self.pool)
+            .await?;
+        if reclaimed.rows_affected > 0 {
+            return Ok(EventClaim::Claimed);
+        }
+
+        let completed = processed::Entity::find_by_id(event_id.to_string())
+            .one(&self.pool)
+            .await?
+            .is_some_and(|e| e.completed_at.is_some());
+        Ok(if completed {
+            EventClaim::Completed
+        } else {
+            EventClaim::InFlight
+        })
+    }
+
+    /// 事件处理成功后标记完成，之后的重复投递直接跳过
+    pub async fn complete_event(&self, event_id: &str) -> AppResult<()> {
+        processed::Entity::update_many()
+            .col_expr(processed::Column::CompletedAt, Expr::value(Utc::now()))
+            .filter(processed::Column::EventId.eq(event_id))
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 处理失败时释放认领，允许 Stripe 重发的同一事件重新处理
+    pub async fn release_event(&self, event_id: &str) -> AppResult<()> {
+        processed::Entity::delete_by_id(event_id.to_string())
+            .filter(processed::Column::CompletedAt.is_null())
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 记录一条与 PaymentIntent 相关的交易；raw_event 为 webhook 事件对象，内部发起的记录传 None
+    #[allow(clippy::too_many_arguments)]
     pub async fn record_payment_intent(
         &self,
         user_id: i64,
@@ -24,6 +117,7 @@ impl StripeTransactionService {
         currency: Option<String>,
         status: Option<String>,
         description: Option<String>,
+        raw_event: Option<serde_json::Value>,
     ) -> AppResult<i64> {
         let model = stx::ActiveModel {
             user_id: Set(user_id),
@@ -33,6 +127,7 @@ impl StripeTransactionService {
             currency: Set(currency),
             status: Set(status),
             description: Set(description),
+            raw_event: Set(raw_event),
             created_at: Set(Some(Utc::now())),
             ..Default::default()
         };
@@ -40,7 +135,8 @@ impl StripeTransactionService {
         Ok(inserted.id)
     }
 
-    /// 记录退款
+    /// 记录退款；raw_event 为 webhook 事件对象
+    #[allow(clippy::too_many_arguments)]
     pub async fn record_refund(
         &self,
         user_id: i64,
@@ -51,6 +147,7 @@ impl StripeTransactionService {
         currency: Option<String>,
         status: Option<String>,
         description: Option<String>,
+        raw_event: Option<serde_json::Value>,
     ) -> AppResult<i64> {
         let model = stx::ActiveModel {
             user_id: Set(user_id),
@@ -61,10 +158,126 @@ impl StripeTransactionService {
             currency: Set(currency),
             status: Set(status),
             description: Set(description),
+            raw_event: Set(raw_event),
+            created_at: Set(Some(Utc::now())),
+            ..Default::default()
+        };
+        let inserted = model.insert(&self.pool).await?;
+        Ok(inserted.id)
+    }
+
+    /// 分页获取用户的支付交易记录（最新在前），可按业务类别筛选
+    pub async fn list_by_user(
+        &self,
+        user_id: i64,
+        params: &PaginationParams,
+        category: Option<StripeTransactionCategory>,
+    ) -> AppResult<PaginatedResponse<StripeTransactionResponse>> {
+        let mut cond = Condition::all().add(stx::Column::UserId.eq(user_id));
+        if let Some(category) = category {
+            cond = cond.add(stx::Column::Category.eq(category));
+        }
+        let total = stx::Entity::find()
+            .filter(cond.clone())
+            .count(&self.pool)
+            .await? as i64;
+        let items = stx::Entity::find()
+            .filter(cond)
+            .order_by_desc(stx::Column::CreatedAt)
+            .order_by_desc(stx::Column::Id)
+            .limit(params.get_limit() as u64)
+            .offset(params.get_offset() as u64)
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(StripeTransactionResponse::from)
+            .collect();
+
+        Ok(PaginatedResponse::new(
+            items,
+            params.page.unwrap_or(1),
+            params.page_size.unwrap_or(20),
+            total,
+        ))
+    }
+
+    /// 按 PaymentIntent 查找最早的一条交易记录，用于关联争议等后续事件的用户与业务类别
+    pub async fn find_by_payment_intent(
+        &self,
+        payment_intent_id: &str,
+    ) -> AppResult<Option<stx::Model>> {
+        Ok(stx::Entity::find()
+            .filter(stx::Column::PaymentIntentId.eq(payment_intent_id))
+            .order_by_asc(stx::Column::Id)
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// 记录争议（chargeback），状态固定为 disputed，供人工审核
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_dispute(
+        &self,
+        user_id: i64,
+        category: StripeTransactionCategory,
+        payment_intent_id: Option<String>,
+        charge_id: Option<String>,
+        amount: Option<i64>,
+        currency: Option<String>,
+        description: Option<String>,
+        raw_event: Option<serde_json::Value>,
+    ) -> AppResult<i64> {
+        let model = stx::ActiveModel {
+            user_id: Set(user_id),
+            category: Set(category),
+            payment_intent_id: Set(payment_intent_id),
+            charge_id: Set(charge_id),
+            amount: Set(amount),
+            currency: Set(currency),
+            status: Set(Some(DISPUTED_STATUS.to_string())),
+            description: Set(description),
+            raw_event: Set(raw_event),
             created_at: Set(Some(Utc::now())),
             ..Default::default()
         };
         let inserted = model.insert(&self.pool).await?;
         Ok(inserted.id)
     }
+
+    /// 统一确认接口已保存的首次成功结果
+    pub async fn find_confirmation(
+        &self,
+        payment_intent_id: &str,
+    ) -> AppResult<Option<confirmations::Model>> {
+        Ok(
+            confirmations::Entity::find_by_id(payment_intent_id.to_string())
+                .one(&self.pool)
+                .await?,
+        )
+    }
+
+    /// 保存统一确认接口的首次成功结果；并发重复保存时保留先写入的一条
+    pub async fn save_confirmation(
+        &self,
+        payment_intent_id: &str,
+        user_id: i64,
+        category: StripeTransactionCategory,
+        response: serde_json::Value,
+    ) -> AppResult<()> {
+        confirmations::Entity::insert(confirmations::ActiveModel {
+            payment_intent_id: Set(payment_intent_id.to_string()),
+            user_id: Set(user_id),
+            category: Set(category),
+            response: Set(response),
+            created_at: Set(Some(Utc::now())),
+        })
+        .on_conflict(
+            OnConflict::column(confirmations::Column::PaymentIntentId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(&self.pool)
+        .await?;
+        Ok(())
+    }
 }
@@ -1,49 +1,530 @@
+use crate::config::CashbackConfig;
 use crate::entities::{
     MemberType, discount_code_entity as discount_codes, lucky_draw_chance_entity as chances,
-    order_entity as orders, sweet_cash_transaction_entity as sct, user_entity as users,
+    order_entity as orders, stamp_rule_entity as stamp_rules, sweet_cash_transaction_entity as sct,
+    sync_failure_entity as sync_failures, sync_run_entity as sync_runs,
+    sync_state_entity as sync_state, user_entity as users,
 };
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
 use crate::external::*;
-use chrono::Utc;
+use crate::models::{SyncFailureResponse, SyncRunResponse};
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
-    Set, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, Set, Statement, TransactionTrait,
 };
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 订单同步游标在 sync_state 表中的 key
+const ORDERS_SYNC_KEY: &str = "orders";
+/// 首次同步（无游标）时回溯的天数
+const INITIAL_SYNC_DAYS: i64 = 30;
+/// 从游标往前多取的重叠窗口，避免边界订单漏同步（已存在订单会被跳过）
+const SYNC_OVERLAP_MINUTES: i64 = 10;
+
+/// 状态复查的游标 key：记录上次复查时间
+const ORDERS_STATUS_SWEEP_KEY: &str = "orders_status_sweep";
+/// 状态复查回看的天数：订单在创建后这段时间内仍可能被取消/退款
+const ORDER_STATUS_SWEEP_DAYS: i64 = 30;
+/// 状态复查的最小间隔（小时），复查会拉取整个窗口的订单，不随每次增量同步执行
+const ORDER_STATUS_SWEEP_INTERVAL_HOURS: i64 = 1;
+
+/// 每满该金额（美分）发放 1 次抽奖机会
+const ORDER_CENTS_PER_SPIN: i64 = 550;
+
+/// 无匹配规则时每单奖励的印花数
+const DEFAULT_STAMPS_PER_ORDER: i64 = 1;
+
+/// 会员等级对应的订单返利比例（基点）；Fan 不返利
+pub fn cashback_bps(config: &CashbackConfig, member_type: &MemberType) -> i64 {
+    match member_type {
+        MemberType::SweetShareholder => config.sweet_bps,
+        MemberType::SuperShareholder => config.super_bps,
+        MemberType::Fan => 0,
+    }
+}
+
+/// 按基点计算返利金额（分，向下取整）
+fn rebate_cents(price_cents: i64, bps: i64) -> i64 {
+    price_cents * bps / 10_000
+}
+
+/// 基点格式化为百分比文本，如 500 -> "5"、750 -> "7.5"
+fn format_bps_percent(bps: i64) -> String {
+    (bps as f64 / 100.0).to_string()
+}
+
+/// 同一订单连续处理失败达到该次数后标记为待人工处理，后续同步跳过
+const SYNC_FAILURE_MAX_ATTEMPTS: i32 = 5;
+
+/// sync_runs 中的同步类型
+pub const SYNC_RUN_KIND_ORDERS: &str = "orders";
+pub const SYNC_RUN_KIND_DISCOUNT_CODES: &str = "discount_codes";
+
+/// sync_runs 中的运行状态：进行中 / 全部成功 / 部分记录失败 / 整体失败（如七云接口不可用）
+const SYNC_RUN_RUNNING: &str = "running";
+const SYNC_RUN_SUCCEEDED: &str = "succeeded";
+const SYNC_RUN_PARTIAL: &str = "partial";
+const SYNC_RUN_FAILED: &str = "failed";
+
+/// 单次同步的处理结果
+#[derive(Debug, Default)]
+struct SyncOutcome {
+    processed: usize,
+    errors: usize,
+}
+
+impl SyncOutcome {
+    fn status(&self) -> &'static str {
+        if self.errors == 0 {
+            SYNC_RUN_SUCCEEDED
+        } else {
+            SYNC_RUN_PARTIAL
+        }
+    }
+}
+
+/// 同步运行标记的持有者：同一类同步同时只允许一个在运行（定时任务与手动触发共用），drop 时释放
+struct RunningGuard(Arc<AtomicBool>);
+
+impl RunningGuard {
+    fn acquire(flag: &Arc<AtomicBool>, kind: &str) -> AppResult<Self> {
+        if flag.swap(true, Ordering::AcqRel) {
+            return Err(AppError::Conflict(format!(
+                "{kind} sync already in progress"
+            )));
+        }
+        Ok(Self(flag.clone()))
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
 
 #[derive(Clone)]
 pub struct SyncService {
     pool: DatabaseConnection,
-    sevencloud_api: std::sync::Arc<tokio::sync::Mutex<SevenCloudAPI>>,
+    sevencloud_api: SharedPosBackend,
+    cashback: CashbackConfig,
+    orders_running: Arc<AtomicBool>,
+    discount_codes_running: Arc<AtomicBool>,
 }
 
 impl SyncService {
     pub fn new(
         pool: DatabaseConnection,
-        sevencloud_api: std::sync::Arc<tokio::sync::Mutex<SevenCloudAPI>>,
+        sevencloud_api: SharedPosBackend,
+        cashback: CashbackConfig,
     ) -> Self {
         Self {
             pool,
             sevencloud_api,
+            cashback,
+            orders_running: Arc::new(AtomicBool::new(false)),
+            discount_codes_running: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// 同步七云订单到本地
-    pub async fn sync_orders(&self, start_date: &str, end_date: &str) -> AppResult<usize> {
-        let mut api = self.sevencloud_api.lock().await;
-        let orders = api.get_orders(start_date, end_date).await?;
+    /// 同步七云订单到本地：从上次同步的游标（减去重叠窗口）开始拉取，
+    /// 无游标时回退为最近 30 天
+    pub async fn sync_orders(&self) -> AppResult<usize> {
+        let _running = RunningGuard::acquire(&self.orders_running, "Orders")?;
+        self.record_run(SYNC_RUN_KIND_ORDERS, self.sync_orders_incremental())
+            .await
+    }
 
-        let mut processed_count = 0;
+    async fn sync_orders_incremental(&self) -> AppResult<SyncOutcome> {
+        let now = Utc::now();
+        let watermark = self.load_watermark(ORDERS_SYNC_KEY).await?;
+        let start = match watermark {
+            Some(t) => t - chrono::Duration::minutes(SYNC_OVERLAP_MINUTES),
+            None => now - chrono::Duration::days(INITIAL_SYNC_DAYS),
+        };
+        let start_date = start.format("%Y-%m-%d %H:%M:%S").to_string();
+        let end_date = format!("{} 23:59:59", now.format("%Y-%m-%d"));
+        log::debug!("Start syncing orders: {start_date} ~ {end_date}");
+
+        // 拉取全部状态，以便发现已支付订单后续的取消/退款
+        let mut orders = {
+            let mut api = self.sevencloud_api.lock().await;
+            api.get_orders_with_status(&start_date, &end_date, None)
+                .await?
+        };
+        // 按创建时间升序处理，保证游标单调推进
+        orders.sort_by_key(|o| o.create_date);
+
+        let failures = self.load_failures().await?;
+        let mut outcome = SyncOutcome::default();
+        let mut advanced_to = watermark;
+        // 一旦有订单处理失败，本轮不再推进游标，下次从失败处重新拉取；
+        // 已标记待人工处理的订单不再阻塞游标
+        let mut can_advance = true;
 
         for order_record in orders {
-            if let Err(e) = self.process_order(order_record).await {
-                log::error!("Failed to process order: {e:?}");
-                continue;
+            let created_at = chrono::DateTime::from_timestamp_millis(order_record.create_date);
+            match self.process_order_tracked(order_record, &failures).await {
+                Ok(true) => outcome.processed += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!("Failed to process order: {e:?}");
+                    outcome.errors += 1;
+                    can_advance = false;
+                    continue;
+                }
             }
-            processed_count += 1;
+
+            if can_advance
+                && let Some(created_at) = created_at
+                && advanced_to.is_none_or(|t| created_at > t)
+            {
+                self.save_watermark(ORDERS_SYNC_KEY, created_at).await?;
+                advanced_to = Some(created_at);
+            }
+        }
+
+        // 增量拉取只覆盖游标附近的订单，较早订单之后的取消/退款靠状态复查发现
+        if let Err(e) = self.sweep_order_statuses(&mut outcome).await {
+            log::error!("Order status sweep failed: {e:?}");
+            outcome.errors += 1;
         }
 
-        log::debug!("Synchronization complete, processed orders: {processed_count}");
-        Ok(processed_count)
+        log::debug!(
+            "Synchronization complete, processed orders: {}",
+            outcome.processed
+        );
+        Ok(outcome)
+    }
+
+    /// 复查最近 30 天内本地已支付订单在七云的最新状态，已变为取消/退款的回收返利、印花与抽奖机会。
+    /// 距上次复查不足 1 小时时跳过
+    async fn sweep_order_statuses(&self, outcome: &mut SyncOutcome) -> AppResult<()> {
+        let now = Utc::now();
+        if let Some(last) = self.load_watermark(ORDERS_STATUS_SWEEP_KEY).await?
+            && now - last < chrono::Duration::hours(ORDER_STATUS_SWEEP_INTERVAL_HOURS)
+        {
+            return Ok(());
+        }
+
+        let start = now - chrono::Duration::days(ORDER_STATUS_SWEEP_DAYS);
+        let start_date = start.format("%Y-%m-%d %H:%M:%S").to_string();
+        let end_date = format!("{} 23:59:59", now.format("%Y-%m-%d"));
+        let remote = {
+            let mut api = self.sevencloud_api.lock().await;
+            api.get_orders_with_status(&start_date, &end_date, None)
+                .await?
+        };
+        let changed: HashMap<i64, i32> = remote
+            .into_iter()
+            .filter(|o| o.status != ORDER_STATUS_PAID)
+            .map(|o| (o.id, o.status))
+            .collect();
+
+        if !changed.is_empty() {
+            let local_paid = orders::Entity::find()
+                .filter(orders::Column::Id.is_in(changed.keys().copied()))
+                .filter(orders::Column::OrderStatus.eq(ORDER_STATUS_PAID))
+                .all(&self.pool)
+                .await?;
+            for order in local_paid {
+                let order_id = order.id;
+                match self.update_order_status(order, changed[&order_id]).await {
+                    Ok(()) => outcome.processed += 1,
+                    Err(e) => {
+                        log::error!("Failed to update status of order {order_id}: {e:?}");
+                        outcome.errors += 1;
+                    }
+                }
+            }
+        }
+
+        self.save_watermark(ORDERS_STATUS_SWEEP_KEY, now).await
+    }
+
+    /// 手动补同步指定日期范围（含首尾两天）的订单，不读写同步游标
+    pub async fn sync_orders_between(&self, start: NaiveDate, end: NaiveDate) -> AppResult<usize> {
+        if start > end {
+            return Err(AppError::ValidationError(
+                "start_date must not be after end_date".to_string(),
+            ));
+        }
+        let _running = RunningGuard::acquire(&self.orders_running, "Orders")?;
+        self.record_run(SYNC_RUN_KIND_ORDERS, self.sync_orders_range(start, end))
+            .await
+    }
+
+    async fn sync_orders_range(&self, start: NaiveDate, end: NaiveDate) -> AppResult<SyncOutcome> {
+        let start_date = format!("{start} 00:00:00");
+        let end_date = format!("{end} 23:59:59");
+        log::info!("Manual order sync: {start_date} ~ {end_date}");
+
+        let orders = {
+            let mut api = self.sevencloud_api.lock().await;
+            api.get_orders_with_status(&start_date, &end_date, None)
+                .await?
+        };
+
+        let failures = self.load_failures().await?;
+        let mut outcome = SyncOutcome::default();
+        for order_record in orders {
+            match self.process_order_tracked(order_record, &failures).await {
+                Ok(true) => outcome.processed += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!("Failed to process order: {e:?}");
+                    outcome.errors += 1;
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// 读取全部订单失败记录，按七云订单 ID 索引
+    async fn load_failures(&self) -> AppResult<HashMap<i64, sync_failures::Model>> {
+        Ok(sync_failures::Entity::find()
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|f| (f.order_id, f))
+            .collect())
+    }
+
+    /// 处理单个订单并维护失败记录：返回 Ok(false) 表示订单已标记待人工处理而被跳过。
+    /// 失败时累加尝试次数，达到上限后标记；之前失败过的订单处理成功后清除记录
+    async fn process_order_tracked(
+        &self,
+        order_record: OrderRecord,
+        failures: &HashMap<i64, sync_failures::Model>,
+    ) -> AppResult<bool> {
+        let order_id = order_record.id;
+        let order_created_at = chrono::DateTime::from_timestamp_millis(order_record.create_date);
+        let failure = failures.get(&order_id);
+        if failure.is_some_and(|f| f.flagged) {
+            log::debug!("Order flagged for manual review, skipping: {order_id}");
+            return Ok(false);
+        }
+
+        match self.process_order(order_record).await {
+            Ok(()) => {
+                if failure.is_some() {
+                    sync_failures::Entity::delete_many()
+                        .filter(sync_failures::Column::OrderId.eq(order_id))
+                        .exec(&self.pool)
+                        .await?;
+                }
+                Ok(true)
+            }
+            Err(e) => {
+                if let Err(record_err) = self
+                    .record_order_failure(order_id, order_created_at, &e)
+                    .await
+                {
+                    log::error!(
+                        "Failed to record sync failure for order {order_id}: {record_err:?}"
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 累加订单失败次数，达到上限时标记为待人工处理
+    async fn record_order_failure(
+        &self,
+        order_id: i64,
+        order_created_at: Option<DateTime<Utc>>,
+        error: &AppError,
+    ) -> AppResult<()> {
+        let row = self
+            .pool
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"INSERT INTO sync_failures (order_id, order_created_at, attempts, last_error, flagged)
+                   VALUES ($1, $4, 1, $2, 1 >= $3)
+                   ON CONFLICT (order_id) DO UPDATE SET
+                       attempts = sync_failures.attempts + 1,
+                       last_error = EXCLUDED.last_error,
+                       flagged = sync_failures.attempts + 1 >= $3,
+                       updated_at = NOW()
+                   RETURNING attempts"#,
+                [
+                    order_id.into(),
+                    error.to_string().into(),
+                    SYNC_FAILURE_MAX_ATTEMPTS.into(),
+                    order_created_at.into(),
+                ],
+            ))
+            .await?;
+        let attempts: i32 = match row {
+            Some(row) => row.try_get("", "attempts")?,
+            None => return Ok(()),
+        };
+        if attempts == SYNC_FAILURE_MAX_ATTEMPTS {
+            log::error!(
+                "Order {order_id} failed {attempts} times, flagged for manual review and skipped from now on"
+            );
+        }
+        Ok(())
+    }
+
+    /// 订单同步失败记录（最近更新在前），`flagged_only` 为 true 时只返回待人工处理的
+    pub async fn list_failures(&self, flagged_only: bool) -> AppResult<Vec<SyncFailureResponse>> {
+        let mut query = sync_failures::Entity::find();
+        if flagged_only {
+            query = query.filter(sync_failures::Column::Flagged.eq(true));
+        }
+        Ok(query
+            .order_by_desc(sync_failures::Column::UpdatedAt)
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(SyncFailureResponse::from)
+            .collect())
+    }
+
+    /// 清除订单的失败记录并补同步该订单创建当天的订单（游标可能已越过该订单），
+    /// 返回补同步处理的订单数；创建时间未知时仅清除记录，等待后续同步
+    pub async fn retry_failure(&self, order_id: i64) -> AppResult<usize> {
+        let failure = sync_failures::Entity::find()
+            .filter(sync_failures::Column::OrderId.eq(order_id))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Sync failure not found".to_string()))?;
+        sync_failures::Entity::delete_by_id(failure.id)
+            .exec(&self.pool)
+            .await?;
+        match failure.order_created_at {
+            Some(created_at) => {
+                let day = created_at.date_naive();
+                self.sync_orders_between(day, day).await
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// 执行一次同步并写入 sync_runs：开始时插入 running 记录，结束后回填结果。
+    /// 运行记录写入失败只打日志，不影响同步本身
+    async fn record_run(
+        &self,
+        kind: &str,
+        sync: impl Future<Output = AppResult<SyncOutcome>>,
+    ) -> AppResult<usize> {
+        let run = sync_runs::ActiveModel {
+            kind: Set(kind.to_string()),
+            started_at: Set(Utc::now()),
+            status: Set(SYNC_RUN_RUNNING.to_string()),
+            ..Default::default()
+        }
+        .insert(&self.pool)
+        .await
+        .inspect_err(|e| log::error!("Failed to record {kind} sync run start: {e:?}"))
+        .ok();
+
+        let result = sync.await;
+
+        if let Some(run) = run {
+            let mut am = run.into_active_model();
+            am.finished_at = Set(Some(Utc::now()));
+            match &result {
+                Ok(outcome) => {
+                    am.processed = Set(outcome.processed as i64);
+                    am.errors = Set(outcome.errors as i64);
+                    am.status = Set(outcome.status().to_string());
+                }
+                Err(e) => {
+                    am.status = Set(SYNC_RUN_FAILED.to_string());
+                    am.error_message = Set(Some(e.to_string()));
+                }
+            }
+            if let Err(e) = am.update(&self.pool).await {
+                log::error!("Failed to record {kind} sync run result: {e:?}");
+            }
+        }
+
+        result.map(|outcome| outcome.processed)
+    }
+
+    /// 最近的同步运行记录（最新在前），可按类型过滤
+    pub async fn list_runs(
+        &self,
+        kind: Option<&str>,
+        limit: u64,
+    ) -> AppResult<Vec<SyncRunResponse>> {
+        let mut query = sync_runs::Entity::find();
+        if let Some(kind) = kind {
+            query = query.filter(sync_runs::Column::Kind.eq(kind));
+        }
+        Ok(query
+            .order_by_desc(sync_runs::Column::Id)
+            .limit(limit)
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(SyncRunResponse::from)
+            .collect())
+    }
+
+    /// 读取同步游标
+    async fn load_watermark(&self, key: &str) -> AppResult<Option<chrono::DateTime<Utc>>> {
+        Ok(sync_state::Entity::find_by_id(key.to_string())
+            .one(&self.pool)
+            .await?
+            .and_then(|s| s.last_synced_at))
+    }
+
+    /// 写入同步游标
+    async fn save_watermark(&self, key: &str, at: chrono::DateTime<Utc>) -> AppResult<()> {
+        match sync_state::Entity::find_by_id(key.to_string())
+            .one(&self.pool)
+            .await?
+        {
+            Some(state) => {
+                let mut am = state.into_active_model();
+                am.last_synced_at = Set(Some(at));
+                am.updated_at = Set(Some(Utc::now()));
+                am.update(&self.pool).await?;
+            }
+            None => {
+                sync_state::ActiveModel {
+                    key: Set(key.to_string()),
+                    last_synced_at: Set(Some(at)),
+                    updated_at: Set(Some(Utc::now())),
+                }
+                .insert(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 计算订单应得印花数：优先匹配 product_no 单品规则，其次按价格档（取 min_price_cents
+    /// 不超过订单金额的最高档），都不匹配时默认 1 个
+    pub async fn stamps_for(&self, order_record: &OrderRecord) -> AppResult<i64> {
+        if let Some(product_no) = &order_record.product_no
+            && let Some(rule) = stamp_rules::Entity::find()
+                .filter(stamp_rules::Column::ProductNo.eq(product_no.clone()))
+                .filter(stamp_rules::Column::Active.eq(true))
+                .one(&self.pool)
+                .await?
+        {
+            return Ok(rule.stamps.max(0));
+        }
+
+        let price_cents = (order_record.price.unwrap_or(0.0) * 100.0) as i64;
+        let tier = stamp_rules::Entity::find()
+            .filter(stamp_rules::Column::ProductNo.is_null())
+            .filter(stamp_rules::Column::Active.eq(true))
+            .filter(stamp_rules::Column::MinPriceCents.lte(price_cents))
+            .order_by_desc(stamp_rules::Column::MinPriceCents)
+            .one(&self.pool)
+            .await?;
+
+        Ok(tier.map_or(DEFAULT_STAMPS_PER_ORDER, |r| r.stamps.max(0)))
     }
 
     /// 处理七云订单
@@ -52,8 +533,23 @@ impl SyncService {
         let existing = orders::Entity::find_by_id(order_record.id)
             .one(&self.pool)
             .await?;
-        if existing.is_some() {
-            log::debug!("Order already exists, skipping: {}", order_record.id);
+        if let Some(existing) = existing {
+            if existing.order_status != order_record.status {
+                self.update_order_status(existing, order_record.status)
+                    .await?;
+            } else {
+                log::debug!("Order already exists, skipping: {}", order_record.id);
+            }
+            return Ok(());
+        }
+
+        // 新订单仅处理已支付状态
+        if order_record.status != ORDER_STATUS_PAID {
+            log::debug!(
+                "Order not paid, skipping: {}, status: {}",
+                order_record.id,
+                order_record.status
+            );
             return Ok(());
         }
 
@@ -68,6 +564,7 @@ impl SyncService {
         };
 
         if let Some(user_model) = user_opt {
+            let stamps_awarded = self.stamps_for(&order_record).await?;
             let user_id_db: i64 = user_model.id;
             let referrer_id_opt: Option<i64> = user_model.referrer_id;
             // 开始事务
@@ -79,7 +576,7 @@ impl SyncService {
             let price_cents: i64 = (order_record.price.unwrap_or(0.0) * 100.0) as i64;
             // 每满 $5.5 美元获得 1 次抽奖机会（按向下取整计算）
             let spins_awarded: i64 = if price_cents > 0 {
-                price_cents / 550
+                price_cents / ORDER_CENTS_PER_SPIN
             } else {
                 0
             };
@@ -93,17 +590,18 @@ impl SyncService {
                 product_no: Set(order_record.product_no.clone()),
                 order_status: Set(order_record.status),
                 pay_type: Set(Some(order_record.pay_type.unwrap_or_default())),
-                stamps_earned: Set(Some(1)),
+                stamps_earned: Set(Some(stamps_awarded)),
+                spins_earned: Set(Some(spins_awarded)),
                 external_created_at: Set(created_at),
                 ..Default::default()
             }
             .insert(&txn)
             .await?;
 
-            // 新订单 +1 个 stamp
+            // 按印花规则发放 stamp
             if let Some(user_model_in_txn) = users::Entity::find_by_id(user_id_db).one(&txn).await?
             {
-                let new_stamps = user_model_in_txn.stamps.unwrap_or(0) + 1;
+                let new_stamps = user_model_in_txn.stamps.unwrap_or(0) + stamps_awarded;
                 let mut user_active = user_model_in_txn.into_active_model();
                 user_active.stamps = Set(Some(new_stamps));
                 user_active.update(&txn).await?;
@@ -127,12 +625,8 @@ impl SyncService {
 
                     // 买家返利
                     if is_active_paid(&buyer) {
-                        let buyer_member_type = buyer.member_type.clone();
-                        let buyer_rebate = match buyer_member_type {
-                            MemberType::SweetShareholder => (price_cents * 5) / 100,
-                            MemberType::SuperShareholder => price_cents / 10,
-                            MemberType::Fan => 0,
-                        };
+                        let buyer_bps = cashback_bps(&self.cashback, &buyer.member_type);
+                        let buyer_rebate = rebate_cents(price_cents, buyer_bps);
                         if buyer_rebate > 0 {
                             let buyer_new_balance = buyer.balance.unwrap_or(0) + buyer_rebate;
                             let mut buyer_am = buyer.into_active_model();
@@ -147,11 +641,7 @@ impl SyncService {
                                 related_order_id: Set(Some(order_record.id)),
                                 description: Set(Some(format!(
                                     "Order cashback {}% for order {}",
-                                    match buyer_member_type {
-                                        MemberType::SweetShareholder => 5,
-                                        MemberType::SuperShareholder => 10,
-                                        MemberType::Fan => 0,
-                                    },
+                                    format_bps_percent(buyer_bps),
                                     order_record.id
                                 ))),
                                 ..Default::default()
@@ -167,12 +657,8 @@ impl SyncService {
                             users::Entity::find_by_id(referrer_id).one(&txn).await?
                         {
                             if is_active_paid(&referrer) {
-                                let ref_member_type = referrer.member_type.clone();
-                                let ref_rebate = match ref_member_type {
-                                    MemberType::SweetShareholder => (price_cents * 5) / 100,
-                                    MemberType::SuperShareholder => price_cents / 10,
-                                    MemberType::Fan => 0,
-                                };
+                                let ref_bps = cashback_bps(&self.cashback, &referrer.member_type);
+                                let ref_rebate = rebate_cents(price_cents, ref_bps);
                                 if ref_rebate > 0 {
                                     let ref_new_balance =
                                         referrer.balance.unwrap_or(0) + ref_rebate;
@@ -188,11 +674,7 @@ impl SyncService {
                                         related_order_id: Set(Some(order_record.id)),
                                         description: Set(Some(format!(
                                             "Referral cashback {}% from user {} order {}",
-                                            match ref_member_type {
-                                                MemberType::SweetShareholder => 5,
-                                                MemberType::SuperShareholder => 10,
-                                                MemberType::Fan => 0,
-                                            },
+                                            format_bps_percent(ref_bps),
                                             user_id_db,
                                             order_record.id
                                         ))),
@@ -243,7 +725,7 @@ impl SyncService {
                 "Successfully processed order: {}, User: {}, Stamps reward: {}, Spins awarded: {}",
                 order_record.id,
                 user_id_db,
-                1,
+                stamps_awarded,
                 spins_awarded
             );
         } else {
@@ -256,23 +738,170 @@ impl SyncService {
         Ok(())
     }
 
+    /// 外部订单状态变化时更新本地订单；从已支付变为取消/退款时回收返利与印花
+    async fn update_order_status(&self, order: orders::Model, new_status: i32) -> AppResult<()> {
+        let txn = self.pool.begin().await?;
+
+        // 条件更新保证同一状态变化只处理一次
+        let res = orders::Entity::update_many()
+            .col_expr(orders::Column::OrderStatus, Expr::value(new_status))
+            .col_expr(orders::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(orders::Column::Id.eq(order.id))
+            .filter(orders::Column::OrderStatus.eq(order.order_status))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
+            txn.rollback().await?;
+            return Ok(());
+        }
+
+        if order.order_status == ORDER_STATUS_PAID && new_status != ORDER_STATUS_PAID {
+            self.reverse_order_effects(&txn, &order).await?;
+        }
+
+        txn.commit().await?;
+        log::info!(
+            "Order status updated: {}, {} -> {}",
+            order.id,
+            order.order_status,
+            new_status
+        );
+        Ok(())
+    }
+
+    /// 回收订单已发放的返利（买家与推荐人）、印花和抽奖机会。
+    /// 已存在该订单的扣回流水或印花/抽奖次数已清零时不会重复扣减；
+    /// 已经用掉的抽奖机会无法回收，最多收回到 total_used
+    async fn reverse_order_effects(
+        &self,
+        txn: &sea_orm::DatabaseTransaction,
+        order: &orders::Model,
+    ) -> AppResult<()> {
+        let already_reversed = sct::Entity::find()
+            .filter(sct::Column::RelatedOrderId.eq(order.id))
+            .filter(sct::Column::TransactionType.eq(sct::TransactionType::Redeem))
+            .one(txn)
+            .await?
+            .is_some();
+
+        if !already_reversed {
+            let rebates = sct::Entity::find()
+                .filter(sct::Column::RelatedOrderId.eq(order.id))
+                .filter(sct::Column::TransactionType.eq(sct::TransactionType::Earn))
+                .all(txn)
+                .await?;
+            for rebate in rebates {
+                let Some(user) = users::Entity::find_by_id(rebate.user_id).one(txn).await? else {
+                    log::warn!(
+                        "User {} not found when reversing cashback of order {}",
+                        rebate.user_id,
+                        order.id
+                    );
+                    continue;
+                };
+                // 余额已被使用时最多扣至 0
+                let balance = user.balance.unwrap_or(0);
+                let debit = rebate.amount.min(balance);
+                if debit < rebate.amount {
+                    log::warn!(
+                        "Insufficient balance to fully reverse cashback: user {}, order {}, owed {}, debited {}",
+                        rebate.user_id,
+                        order.id,
+                        rebate.amount,
+                        debit
+                    );
+                }
+                let new_balance = balance - debit;
+                let mut am = user.into_active_model();
+                am.balance = Set(Some(new_balance));
+                am.update(txn).await?;
+
+                sct::ActiveModel {
+                    user_id: Set(rebate.user_id),
+                    transaction_type: Set(sct::TransactionType::Redeem),
+                    amount: Set(debit),
+                    balance_after: Set(new_balance),
+                    related_order_id: Set(Some(order.id)),
+                    description: Set(Some(format!(
+                        "Cashback reversed for canceled order {}",
+                        order.id
+                    ))),
+                    ..Default::default()
+                }
+                .insert(txn)
+                .await?;
+            }
+        }
+
+        let stamps = order.stamps_earned.unwrap_or(0);
+        if stamps > 0 {
+            if let Some(user) = users::Entity::find_by_id(order.user_id).one(txn).await? {
+                let new_stamps = (user.stamps.unwrap_or(0) - stamps).max(0);
+                let mut am = user.into_active_model();
+                am.stamps = Set(Some(new_stamps));
+                am.update(txn).await?;
+            }
+            orders::Entity::update_many()
+                .col_expr(orders::Column::StampsEarned, Expr::value(0i64))
+                .filter(orders::Column::Id.eq(order.id))
+                .exec(txn)
+                .await?;
+        }
+
+        // 早于 spins_earned 列的订单按金额推算发放的次数
+        let spins = order
+            .spins_earned
+            .unwrap_or_else(|| order.price.max(0) / ORDER_CENTS_PER_SPIN);
+        if spins > 0 {
+            chances::Entity::update_many()
+                .col_expr(
+                    chances::Column::TotalAwarded,
+                    Expr::cust_with_values("GREATEST(total_used, total_awarded - $1)", [spins]),
+                )
+                .col_expr(chances::Column::UpdatedAt, Expr::value(Utc::now()))
+                .filter(chances::Column::UserId.eq(order.user_id))
+                .exec(txn)
+                .await?;
+        }
+        orders::Entity::update_many()
+            .col_expr(orders::Column::SpinsEarned, Expr::value(0i64))
+            .filter(orders::Column::Id.eq(order.id))
+            .exec(txn)
+            .await?;
+
+        Ok(())
+    }
+
     /// 同步七云优惠码
     pub async fn sync_discount_codes(&self) -> AppResult<usize> {
+        let _running = RunningGuard::acquire(&self.discount_codes_running, "Discount codes")?;
+        self.record_run(
+            SYNC_RUN_KIND_DISCOUNT_CODES,
+            self.sync_discount_codes_once(),
+        )
+        .await
+    }
+
+    async fn sync_discount_codes_once(&self) -> AppResult<SyncOutcome> {
         let mut api = self.sevencloud_api.lock().await;
         let coupons = api.get_discount_codes(None).await?;
 
-        let mut processed_count = 0;
+        let mut outcome = SyncOutcome::default();
 
         for coupon_record in coupons {
             if let Err(e) = self.process_discount_code(coupon_record).await {
                 log::error!("Failed to process discount code: {e:?}");
+                outcome.errors += 1;
                 continue;
             }
-            processed_count += 1;
+            outcome.processed += 1;
         }
 
-        log::debug!("Synchronization complete, processed discount codes: {processed_count}");
-        Ok(processed_count)
+        log::debug!(
+            "Synchronization complete, processed discount codes: {}",
+            outcome.processed
+        );
+        Ok(outcome)
     }
 
     /// 处理七云优惠码
@@ -348,3 +977,38 @@ impl SyncService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_guard_rejects_overlap() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let guard = RunningGuard::acquire(&flag, "Orders").unwrap();
+        assert!(matches!(
+            RunningGuard::acquire(&flag, "Orders"),
+            Err(AppError::Conflict(_))
+        ));
+        drop(guard);
+        assert!(RunningGuard::acquire(&flag, "Orders").is_ok());
+    }
+
+    #[test]
+    fn test_cashback_per_tier() {
+        let config = CashbackConfig::default();
+        let price_cents = 1999;
+
+        let fan = cashback_bps(&config, &MemberType::Fan);
+        let sweet = cashback_bps(&config, &MemberType::SweetShareholder);
+        let sup = cashback_bps(&config, &MemberType::SuperShareholder);
+
+        assert_eq!(rebate_cents(price_cents, fan), 0);
+        // 5% 向下取整：99.95 -> 99
+        assert_eq!(rebate_cents(price_cents, sweet), 99);
+        // 10% 向下取整：199.9 -> 199
+        assert_eq!(rebate_cents(price_cents, sup), 199);
+        assert_eq!(format_bps_percent(sweet), "5");
+        assert_eq!(format_bps_percent(750), "7.5");
+    }
+}
@@ -3,21 +3,52 @@ use crate::entities::{
     order_entity as orders, sweet_cash_transaction_entity as sct, user_entity as users,
 };
 use crate::error::{AppError, AppResult};
+use crate::external::TwilioService;
 use crate::models::*;
+use crate::services::{
+    LuckyDrawService, MonthlyCardService, load_stamp_redemption_tiers, next_stamp_reward,
+};
+use crate::utils::{extract_member_code_from_phone, normalize_us_phone, validate_us_phone};
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
 use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
-    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
+use std::collections::{HashMap, HashSet};
+
+/// 推荐树最大展开层级
+const MAX_REFERRAL_DEPTH: u32 = 3;
+
+/// 单笔余额转赠最小金额（美分）
+const WALLET_TRANSFER_MIN_CENTS: i64 = 100;
+/// 每人每天（UTC）累计转出上限（美分）
+const WALLET_TRANSFER_DAILY_CAP_CENTS: i64 = 20_000;
+/// 转赠流水描述前缀，用于区分流水类型与统计当日转出
+const TRANSFER_OUT_PREFIX: &str = "Transfer to member";
+const TRANSFER_IN_PREFIX: &str = "Transfer from member";
 
 #[derive(Clone)]
 pub struct UserService {
     pool: DatabaseConnection,
+    twilio_service: TwilioService,
+    monthly_card_service: MonthlyCardService,
+    lucky_draw_service: LuckyDrawService,
 }
 
 impl UserService {
-    pub fn new(pool: DatabaseConnection) -> Self {
-        Self { pool }
+    pub fn new(
+        pool: DatabaseConnection,
+        twilio_service: TwilioService,
+        monthly_card_service: MonthlyCardService,
+        lucky_draw_service: LuckyDrawService,
+    ) -> Self {
+        Self {
+            pool,
+            twilio_service,
+            monthly_card_service,
+            lucky_draw_service,
+        }
     }
 
     /// 获取用户个人资料和统计信息
@@ -35,7 +66,9 @@ impl UserService {
             .await? as i64;
 
         // 获取用户统计信息
-        let statistics = self.get_user_statistics(user_id).await?;
+        let statistics = self
+            .get_user_statistics(user_id, user.stamps.unwrap_or(0))
+            .await?;
 
         let mut user_response = UserResponse::from(user);
         user_response.total_referrals = total_referrals;
@@ -54,6 +87,36 @@ impl UserService {
         Ok((user_response, statistics))
     }
 
+    /// 当前用户的资料、统计、生效月卡与抽奖次数，合并为一次请求
+    pub async fn get_me(&self, user_id: i64) -> AppResult<MeResponse> {
+        let (user, statistics) = self.get_user_profile(user_id).await?;
+        let monthly_card = match self.monthly_card_service.get_current(user_id).await {
+            Ok(card) => Some(card),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let lucky_draw_chances = self.lucky_draw_service.get_user_chances(user_id).await?;
+        Ok(MeResponse {
+            user,
+            statistics,
+            monthly_card,
+            lucky_draw_chances,
+        })
+    }
+
+    /// 按会员码查询用户资料和统计信息（管理端）
+    pub async fn get_by_member_code(
+        &self,
+        member_code: &str,
+    ) -> AppResult<(UserResponse, UserStatistics)> {
+        let user = users::Entity::find()
+            .filter(users::Column::MemberCode.eq(member_code.trim()))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        self.get_user_profile(user.id).await
+    }
+
     /// 更新用户Profile
     pub async fn update_user_profile(
         &self,
@@ -106,6 +169,82 @@ impl UserService {
         Ok(user_response)
     }
 
+    /// 更换手机号：校验新号码的验证码后同时更新 phone 与由其派生的 member_code
+    pub async fn change_phone(
+        &self,
+        user_id: i64,
+        new_phone: &str,
+        verification_code: &str,
+    ) -> AppResult<UserResponse> {
+        let new_phone = normalize_us_phone(new_phone);
+        validate_us_phone(&new_phone)?;
+        let member_code = extract_member_code_from_phone(&new_phone)?;
+
+        let user = users::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        if user.phone == new_phone {
+            return Err(AppError::ValidationError(
+                "The new phone number is the same as the current one".to_string(),
+            ));
+        }
+
+        // 新号码已注册或其会员号已被占用时拒绝（先于验证码校验，避免消耗有效验证码）
+        self.ensure_phone_available(user_id, &new_phone, &member_code)
+            .await?;
+
+        let approved = self
+            .twilio_service
+            .check_verification_code(&new_phone, verification_code)
+            .await?;
+        if !approved {
+            return Err(AppError::ValidationError(
+                "The verification code is incorrect or expired".to_string(),
+            ));
+        }
+
+        let mut model = user.into_active_model();
+        model.phone = Set(new_phone.clone());
+        model.member_code = Set(member_code.clone());
+        model.updated_at = Set(Some(Utc::now()));
+        if let Err(e) = model.update(&self.pool).await {
+            // 并发下被他人抢先占用时，唯一约束冲突转为业务错误
+            self.ensure_phone_available(user_id, &new_phone, &member_code)
+                .await?;
+            return Err(e.into());
+        }
+
+        let (user_response, _) = self.get_user_profile(user_id).await?;
+        Ok(user_response)
+    }
+
+    async fn ensure_phone_available(
+        &self,
+        user_id: i64,
+        phone: &str,
+        member_code: &str,
+    ) -> AppResult<()> {
+        let taken = users::Entity::find()
+            .filter(users::Column::Id.ne(user_id))
+            .filter(
+                Condition::any()
+                    .add(users::Column::Phone.eq(phone))
+                    .add(users::Column::MemberCode.eq(member_code)),
+            )
+            .one(&self.pool)
+            .await?;
+        match taken {
+            Some(u) if u.phone == phone => Err(AppError::ValidationError(
+                "The mobile phone number is registered".to_string(),
+            )),
+            Some(_) => Err(AppError::ValidationError(
+                "The member code corresponding to this phone number already exists".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
     /// 获取用户推荐列表
     pub async fn get_user_referrals(
         &self,
@@ -139,8 +278,98 @@ impl UserService {
         ))
     }
 
+    /// 管理端搜索用户：用户名不区分大小写前缀匹配，纯数字时同时按手机号后缀匹配
+    pub async fn search(
+        &self,
+        query: &str,
+        params: &PaginationParams,
+    ) -> AppResult<PaginatedResponse<UserResponse>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(AppError::ValidationError(
+                "Search query cannot be empty".to_string(),
+            ));
+        }
+
+        // 与 idx_users_username_lower / idx_users_phone_reverse 表达式保持一致
+        let mut condition = Condition::any().add(Expr::cust_with_values(
+            "LOWER(username) LIKE $1",
+            [format!("{}%", escape_like(&query.to_lowercase()))],
+        ));
+        let digits = query.strip_prefix('+').unwrap_or(query);
+        if digits.chars().all(|c| c.is_ascii_digit()) {
+            let reversed: String = digits.chars().rev().collect();
+            condition = condition.add(Expr::cust_with_values(
+                "REVERSE(phone) LIKE $1",
+                [format!("{reversed}%")],
+            ));
+        }
+
+        let total = users::Entity::find()
+            .filter(condition.clone())
+            .count(&self.pool)
+            .await? as i64;
+        let models = users::Entity::find()
+            .filter(condition)
+            .order_by_desc(users::Column::Id)
+            .limit(params.get_limit() as u64)
+            .offset(params.get_offset() as u64)
+            .all(&self.pool)
+            .await?;
+        let items: Vec<UserResponse> = models.into_iter().map(UserResponse::from).collect();
+
+        Ok(PaginatedResponse::new(
+            items,
+            params.page.unwrap_or(1),
+            params.page_size.unwrap_or(20),
+            total,
+        ))
+    }
+
+    /// 获取多级推荐树（逐层查询，最多 3 层），并统计下线总数
+    pub async fn get_referral_tree(
+        &self,
+        user_id: i64,
+        depth: u32,
+    ) -> AppResult<ReferralTreeResponse> {
+        let depth = depth.clamp(1, MAX_REFERRAL_DEPTH);
+        let mut visited: HashSet<i64> = HashSet::from([user_id]);
+        let mut children: HashMap<i64, Vec<users::Model>> = HashMap::new();
+        let mut frontier = vec![user_id];
+        let mut total_downstream = 0i64;
+
+        for _ in 0..depth {
+            if frontier.is_empty() {
+                break;
+            }
+            let level = users::Entity::find()
+                .filter(users::Column::ReferrerId.is_in(frontier.clone()))
+                .order_by_desc(users::Column::CreatedAt)
+                .all(&self.pool)
+                .await?;
+            frontier.clear();
+            for u in level {
+                // 防御性处理：推荐关系理论上不会成环
+                if !visited.insert(u.id) {
+                    continue;
+                }
+                frontier.push(u.id);
+                total_downstream += 1;
+                if let Some(parent) = u.referrer_id {
+                    children.entry(parent).or_default().push(u);
+                }
+            }
+        }
+
+        Ok(ReferralTreeResponse {
+            depth,
+            total_downstream,
+            referrals: build_referral_nodes(user_id, &mut children),
+        })
+    }
+
     /// 获取用户统计信息
-    async fn get_user_statistics(&self, user_id: i64) -> AppResult<UserStatistics> {
+    async fn get_user_statistics(&self, user_id: i64, stamps: i64) -> AppResult<UserStatistics> {
         // 获取订单统计
         #[derive(Debug, sea_orm::FromQueryResult)]
         struct OrderStatsRow {
@@ -166,10 +395,14 @@ impl UserService {
         let available_codes = discount_codes::Entity::find()
             .filter(discount_codes::Column::UserId.eq(user_id))
             .filter(discount_codes::Column::IsUsed.eq(false))
+            .filter(discount_codes::Column::Status.eq(crate::entities::DiscountCodeStatus::Active))
             .filter(discount_codes::Column::ExpiresAt.gt(chrono::Utc::now()))
             .count(&self.pool)
             .await? as i64;
 
+        let tiers = load_stamp_redemption_tiers(&self.pool).await?;
+        let (stamps_to_next_reward, next_reward_value_cents) = next_stamp_reward(&tiers, stamps);
+
         Ok(UserStatistics {
             total_orders: order_stats_row
                 .as_ref()
@@ -184,6 +417,173 @@ impl UserService {
                 .and_then(|r| r.total_earned_stamps)
                 .unwrap_or(0),
             available_discount_codes: available_codes,
+            stamps_to_next_reward,
+            next_reward_value_cents,
+        })
+    }
+
+    /// 按月统计最近 N 个月的订单消费，无订单的月份补 0
+    pub async fn get_spending_by_month(
+        &self,
+        user_id: i64,
+        months: u32,
+    ) -> AppResult<Vec<MonthlySpending>> {
+        let months = months.clamp(1, 36);
+        let series = month_series(Utc::now().date_naive(), months);
+        let start = NaiveDate::parse_from_str(&format!("{}-01", series[0]), "%Y-%m-%d")
+            .map_err(|e| AppError::InternalError(e.to_string()))?;
+        let start_dt = Utc.from_utc_datetime(&start.and_hms_opt(0, 0, 0).unwrap());
+
+        const MONTH_EXPR: &str = "to_char(external_created_at AT TIME ZONE 'UTC', 'YYYY-MM')";
+        let rows: Vec<(String, Option<i64>, i64)> = orders::Entity::find()
+            .filter(orders::Column::UserId.eq(user_id))
+            .filter(orders::Column::ExternalCreatedAt.gte(start_dt))
+            .select_only()
+            .column_as(Expr::cust(MONTH_EXPR), "month")
+            .column_as(Expr::cust("SUM(price)::BIGINT"), "total_spent")
+            .column_as(Expr::val(1).count(), "order_count")
+            .group_by(Expr::cust(MONTH_EXPR))
+            .into_tuple()
+            .all(&self.pool)
+            .await?;
+        let by_month: HashMap<String, (i64, i64)> = rows
+            .into_iter()
+            .map(|(m, spent, count)| (m, (spent.unwrap_or(0), count)))
+            .collect();
+
+        Ok(series
+            .into_iter()
+            .map(|month| {
+                let (total_spent, order_count) = by_month.get(&month).copied().unwrap_or((0, 0));
+                MonthlySpending {
+                    month,
+                    total_spent,
+                    order_count,
+                }
+            })
+            .collect())
+    }
+
+    /// 将余额转赠给其他用户（按会员号）：同一事务内锁定双方用户行，扣减转出方、
+    /// 增加受赠方，并写入两条互相引用的 sweet_cash_transactions 流水
+    pub async fn transfer_balance(
+        &self,
+        from_id: i64,
+        to_member_code: &str,
+        amount: i64,
+    ) -> AppResult<WalletTransferResponse> {
+        if amount < WALLET_TRANSFER_MIN_CENTS {
+            return Err(AppError::ValidationError(format!(
+                "Transfer amount must be at least {WALLET_TRANSFER_MIN_CENTS} cents"
+            )));
+        }
+        let recipient = users::Entity::find()
+            .filter(users::Column::MemberCode.eq(to_member_code))
+            .one(&self.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Recipient not found".to_string()))?;
+        if recipient.id == from_id {
+            return Err(AppError::ValidationError(
+                "Cannot transfer balance to yourself".to_string(),
+            ));
+        }
+
+        let txn = self.pool.begin().await?;
+
+        // 按 id 顺序加锁，避免双向转赠时死锁
+        let locked = users::Entity::find()
+            .filter(users::Column::Id.is_in([from_id, recipient.id]))
+            .order_by_asc(users::Column::Id)
+            .lock_exclusive()
+            .all(&txn)
+            .await?;
+        let sender = locked
+            .iter()
+            .find(|u| u.id == from_id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let recipient = locked
+            .into_iter()
+            .find(|u| u.id == recipient.id)
+            .ok_or_else(|| AppError::NotFound("Recipient not found".to_string()))?;
+
+        // 转出方行锁保证当日累计统计不被并发转赠绕过
+        let today_start =
+            Utc.from_utc_datetime(&Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap());
+        let sent_today: Option<i64> = sct::Entity::find()
+            .select_only()
+            .column_as(sct::Column::Amount.sum(), "total")
+            .filter(sct::Column::UserId.eq(from_id))
+            .filter(sct::Column::TransactionType.eq(sct::TransactionType::Redeem))
+            .filter(sct::Column::Description.starts_with(TRANSFER_OUT_PREFIX))
+            .filter(sct::Column::CreatedAt.gte(today_start))
+            .into_tuple()
+            .one(&txn)
+            .await?
+            .flatten();
+        if sent_today.unwrap_or(0) + amount > WALLET_TRANSFER_DAILY_CAP_CENTS {
+            return Err(AppError::ValidationError(format!(
+                "Daily transfer limit of {WALLET_TRANSFER_DAILY_CAP_CENTS} cents exceeded"
+            )));
+        }
+
+        let sender_balance = sender.balance.unwrap_or(0);
+        if sender_balance < amount {
+            return Err(AppError::ValidationError(
+                "Insufficient balance".to_string(),
+            ));
+        }
+        let sender_after = sender_balance - amount;
+        let recipient_after = recipient.balance.unwrap_or(0) + amount;
+        let now = Utc::now();
+
+        let sender_code = sender.member_code.clone();
+        let recipient_id = recipient.id;
+        let mut am = sender.into_active_model();
+        am.balance = Set(Some(sender_after));
+        am.updated_at = Set(Some(now));
+        am.update(&txn).await?;
+        let mut am = recipient.into_active_model();
+        am.balance = Set(Some(recipient_after));
+        am.updated_at = Set(Some(now));
+        am.update(&txn).await?;
+
+        let spend = sct::ActiveModel {
+            user_id: Set(from_id),
+            transaction_type: Set(sct::TransactionType::Redeem),
+            amount: Set(amount),
+            balance_after: Set(sender_after),
+            description: Set(Some(format!("{TRANSFER_OUT_PREFIX} {to_member_code}"))),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        let earn = sct::ActiveModel {
+            user_id: Set(recipient_id),
+            transaction_type: Set(sct::TransactionType::Earn),
+            amount: Set(amount),
+            balance_after: Set(recipient_after),
+            description: Set(Some(format!(
+                "{TRANSFER_IN_PREFIX} {sender_code} (tx #{})",
+                spend.id
+            ))),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        let spend_description = format!("{TRANSFER_OUT_PREFIX} {to_member_code} (tx #{})", earn.id);
+        let mut am = spend.into_active_model();
+        am.description = Set(Some(spend_description));
+        am.update(&txn).await?;
+
+        txn.commit().await?;
+
+        log::info!("User {from_id} transferred {amount} cents to user {recipient_id}");
+
+        Ok(WalletTransferResponse {
+            to_member_code: to_member_code.to_string(),
+            amount,
+            balance_after: sender_after,
         })
     }
 
@@ -191,31 +591,84 @@ impl UserService {
     pub async fn get_user_wallet_transactions(
         &self,
         user_id: i64,
-        params: &PaginationParams,
+        query: &WalletTransactionQuery,
     ) -> AppResult<PaginatedResponse<WalletTransactionResponse>> {
+        let params = PaginationParams {
+            page: query.page,
+            page_size: query.page_size,
+            after_id: query.after_id,
+        };
         let offset = params.get_offset();
         let limit = params.get_limit();
 
-        // 统计总数（所有钱包流水均来自 sweet_cash_transactions）
-        let total = sct::Entity::find()
-            .filter(sct::Column::UserId.eq(user_id))
-            .count(&self.pool)
-            .await? as i64;
+        let mut cond = Condition::all().add(sct::Column::UserId.eq(user_id));
+        if let Some(kind) = query.kind.as_deref().filter(|k| !k.is_empty()) {
+            let tx_type = match kind.to_ascii_lowercase().as_str() {
+                "earn" => sct::TransactionType::Earn,
+                "spend" | "redeem" => sct::TransactionType::Redeem,
+                other => {
+                    return Err(AppError::ValidationError(format!(
+                        "无效的流水类型: {other}"
+                    )));
+                }
+            };
+            cond = cond.add(sct::Column::TransactionType.eq(tx_type));
+        }
+        if let (Some(from), Some(to)) = (query.from, query.to)
+            && from > to
+        {
+            return Err(AppError::ValidationError("from 不能晚于 to".to_string()));
+        }
+        if let Some(from) = query.from {
+            let from_dt = Utc.from_utc_datetime(&from.and_hms_opt(0, 0, 0).unwrap());
+            cond = cond.add(sct::Column::CreatedAt.gte(from_dt));
+        }
+        if let Some(to) = query.to {
+            let to_dt = Utc.from_utc_datetime(&to.and_hms_opt(23, 59, 59).unwrap());
+            cond = cond.add(sct::Column::CreatedAt.lte(to_dt));
+        }
 
-        // 拉取当前页数据
-        let rows = sct::Entity::find()
-            .filter(sct::Column::UserId.eq(user_id))
-            .order_by_desc(sct::Column::CreatedAt)
-            .limit(limit as u64)
-            .offset(offset as u64)
-            .all(&self.pool)
-            .await?;
+        // 流水只追加不修改，id 顺序即入账顺序：两种分页方式统一按 id 倒序，
+        // 游标模式不统计总数（所有钱包流水均来自 sweet_cash_transactions）
+        let select = sct::Entity::find()
+            .filter(cond.clone())
+            .order_by_desc(sct::Column::Id)
+            .limit(limit as u64);
+        let (rows, total) = match params.after_id {
+            Some(after_id) => {
+                let rows = select
+                    .filter(sct::Column::Id.lt(after_id))
+                    .all(&self.pool)
+                    .await?;
+                (rows, None)
+            }
+            None => {
+                let total = sct::Entity::find().filter(cond).count(&self.pool).await? as i64;
+                let rows = select.offset(offset as u64).all(&self.pool).await?;
+                (rows, Some(total))
+            }
+        };
+        // 满页时返回最后一条的 id 作为下一页游标（首页同样返回，便于切换到游标分页）
+        let next_cursor = if rows.len() as i64 == limit {
+            rows.last().map(|t| t.id)
+        } else {
+            None
+        };
 
         let items: Vec<WalletTransactionResponse> = rows
             .into_iter()
             .map(|t| {
+                let description = t.description.as_deref().unwrap_or_default();
                 let kind = match t.transaction_type {
+                    sct::TransactionType::Redeem
+                        if description.starts_with(TRANSFER_OUT_PREFIX) =>
+                    {
+                        WalletTransactionKind::TransferOut
+                    }
                     sct::TransactionType::Redeem => WalletTransactionKind::Redeem,
+                    sct::TransactionType::Earn if description.starts_with(TRANSFER_IN_PREFIX) => {
+                        WalletTransactionKind::TransferIn
+                    }
                     sct::TransactionType::Earn => {
                         let is_birthday = t
                             .description
@@ -240,11 +693,81 @@ impl UserService {
             })
             .collect();
 
-        Ok(PaginatedResponse::new(
-            items,
-            params.page.unwrap_or(1),
-            params.page_size.unwrap_or(20),
-            total,
-        ))
+        let page = match total {
+            Some(total) => PaginatedResponse::new(
+                items,
+                params.page.unwrap_or(1),
+                params.page_size.unwrap_or(20),
+                total,
+            ),
+            None => PaginatedResponse::by_cursor(items, limit),
+        };
+        Ok(page.with_next_cursor(next_cursor))
+    }
+}
+
+/// 由 parent -> children 映射递归组装推荐树节点
+fn build_referral_nodes(
+    parent_id: i64,
+    children: &mut HashMap<i64, Vec<users::Model>>,
+) -> Vec<ReferralTreeNode> {
+    children
+        .remove(&parent_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|u| ReferralTreeNode {
+            children: build_referral_nodes(u.id, children),
+            id: u.id,
+            member_code: u.member_code,
+            username: u.username,
+            member_type: u.member_type,
+            created_at: u.created_at.unwrap_or_else(Utc::now),
+        })
+        .collect()
+}
+
+/// 生成以 today 所在月份结尾、共 months 个月的 YYYY-MM 序列（升序）
+fn month_series(today: NaiveDate, months: u32) -> Vec<String> {
+    let end = today.year() * 12 + today.month0() as i32;
+    (0..months as i32)
+        .rev()
+        .map(|i| {
+            let idx = end - i;
+            format!("{:04}-{:02}", idx.div_euclid(12), idx.rem_euclid(12) + 1)
+        })
+        .collect()
+}
+
+/// 转义 LIKE 模式中的通配符
+fn escape_like(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_like, month_series};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_month_series_crosses_year() {
+        let today = NaiveDate::from_ymd_opt(2025, 2, 15).unwrap();
+        assert_eq!(
+            month_series(today, 4),
+            vec!["2024-11", "2024-12", "2025-01", "2025-02"]
+        );
+        assert_eq!(month_series(today, 1), vec!["2025-02"]);
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("ab"), "ab");
+        assert_eq!(escape_like("a_b%c\\"), "a\\_b\\%c\\\\");
     }
 }
@@ -7,8 +7,9 @@ use utoipa::{
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::entities::{
-    CodeType, MemberType, MonthlyCardPlanType, MonthlyCardStatus, RechargeStatus,
+    CodeType, MemberType, MonthlyCardPlanType, MonthlyCardStatus, RechargeStatus, UserRole,
 };
+use crate::external::VerificationChannel;
 use crate::handlers;
 use crate::handlers::recharge::UnifiedConfirmRequest;
 use crate::models::*;
@@ -33,39 +34,85 @@ impl Modify for SecurityAddon {
         handlers::auth::login,
         handlers::auth::refresh,
         handlers::auth::reset_password,
+        handlers::user::get_me,
         handlers::user::get_profile,
         handlers::user::update_profile,
+        handlers::user::change_phone,
+        handlers::user::get_notifications,
+        handlers::user::mark_notification_read,
+        handlers::user::get_notification_prefs,
+        handlers::user::update_notification_prefs,
         handlers::user::get_referrals,
         handlers::user::get_wallet_transactions,
+        handlers::user::transfer_balance,
+        handlers::user::get_birthday_reward_preview,
+        handlers::user::get_spending,
+        handlers::user::get_referral_tree,
         handlers::order::get_orders,
+        handlers::order::export_orders,
+        handlers::order::get_order,
         handlers::discount_code::get_discount_codes,
         handlers::discount_code::redeem_discount_code,
         handlers::discount_code::redeem_balance_discount_code,
+        handlers::discount_code::transfer_discount_code,
         handlers::recharge::create_payment_intent,
         handlers::recharge::confirm_recharge,
         handlers::recharge::get_history,
         handlers::recharge::create_membership_payment_intent,
         handlers::recharge::confirm_membership,
+        handlers::recharge::get_membership_benefits,
+        handlers::recharge::create_gift_membership_payment_intent,
+        handlers::recharge::schedule_membership_downgrade,
+        handlers::recharge::set_membership_auto_renew,
         handlers::recharge::create_monthly_card_payment_intent,
         handlers::recharge::confirm_monthly_card,
+        handlers::recharge::get_current_monthly_card,
+        handlers::recharge::get_monthly_card_history,
         handlers::recharge::confirm_unified,
+        handlers::recharge::list_transactions,
         handlers::lucky_draw::get_chances,
         handlers::lucky_draw::get_prizes,
         handlers::lucky_draw::get_records,
         handlers::lucky_draw::spin,
+        handlers::admin::list_recharge_tiers,
+        handlers::admin::list_stamp_redemption_tiers,
+        handlers::admin::bulk_generate_discount_codes,
+        handlers::admin::validate_discount_code,
+        handlers::admin::list_lucky_draw_prizes,
+        handlers::admin::upsert_lucky_draw_prize,
+        handlers::admin::refill_lucky_draw_prize,
+        handlers::admin::get_lucky_draw_stats,
+        handlers::admin::get_user_by_member_code,
+        handlers::admin::get_user_discount_code,
+        handlers::admin::search_users,
+        handlers::admin::sync_orders,
+        handlers::admin::sync_discount_codes,
+        handlers::admin::list_sync_runs,
+        handlers::admin::list_sync_failures,
+        handlers::admin::retry_sync_failure,
+        handlers::admin::refund_recharge,
     ),
     components(
         schemas(
             UserResponse,
             UserStatistics,
+            MeResponse,
             CreateUserRequest,
             LoginRequest,
             UpdateUserRequest,
+            ChangePhoneRequest,
+            UserSearchQuery,
+            NotificationKind,
+            NotificationResponse,
+            NotificationPrefs,
+            BirthdayRewardPreview,
             AuthResponse,
             SendCodeRequest,
+            VerificationChannel,
             SendCodeResponse,
             ResetPasswordRequest,
             MemberType,
+            UserRole,
             OrderResponse,
             OrderQuery,
             DiscountCodeResponse,
@@ -74,21 +121,38 @@ impl Modify for SecurityAddon {
             RedeemDiscountCodeResponse,
             RedeemBalanceDiscountCodeRequest,
             RedeemBalanceDiscountCodeResponse,
+            DiscountCodeValidation,
+            TransferDiscountCodeRequest,
+            TransferDiscountCodeResponse,
             CodeType,
             RechargeRecordResponse,
+            StripeTransactionQuery,
+            StripeTransactionResponse,
             CreatePaymentIntentRequest,
             CreatePaymentIntentResponse,
             ConfirmRechargeRequest,
             ConfirmRechargeResponse,
+            RefundRechargeRequest,
+            RefundRechargeResponse,
             RechargeQuery,
             RechargeStatus,
             WalletTransactionKind,
             WalletTransactionResponse,
+            WalletTransactionQuery,
+            WalletTransferRequest,
+            WalletTransferResponse,
             MembershipPurchaseRecordResponse,
             CreateMembershipIntentRequest,
             CreateMembershipIntentResponse,
+            CreateGiftMembershipIntentRequest,
             ConfirmMembershipRequest,
             ConfirmMembershipResponse,
+            MembershipBenefits,
+            MembershipBenefitsResponse,
+            ScheduleDowngradeRequest,
+            ScheduleDowngradeResponse,
+            AutoRenewRequest,
+            AutoRenewResponse,
             ApiError,
             MonthlyCardPlanType,
             MonthlyCardStatus,
@@ -96,17 +160,40 @@ impl Modify for SecurityAddon {
             CreateMonthlyCardIntentResponse,
             ConfirmMonthlyCardRequest,
             ConfirmMonthlyCardResponse,
+            CurrentMonthlyCardResponse,
             UnifiedConfirmRequest,
             PaginatedOrderResponse,
             AuthApiResponse,
             SendCodeApiResponse,
             UserApiResponse,
             OrderListApiResponse,
+            OrderDetailResponse,
+            SpendingQuery,
+            ReferralTreeQuery,
+            ReferralTreeNode,
+            ReferralTreeResponse,
+            MonthlySpending,
+            OrderCashbackTransaction,
             LuckyDrawChancesResponse,
             LuckyDrawPrizeResponse,
             LuckyDrawRecordResponse,
             LuckyDrawRecordQuery,
             LuckyDrawSpinResponse,
+            RechargeTierResponse,
+            StampRedemptionTierResponse,
+            BulkGenerateDiscountCodesRequest,
+            BulkGenerateDiscountCodesResponse,
+            UpsertLuckyDrawPrizeRequest,
+            RefillLuckyDrawPrizeRequest,
+            LuckyDrawStatsQuery,
+            LuckyDrawPrizeStat,
+            LuckyDrawStatsResponse,
+            ManualSyncOrdersRequest,
+            ManualSyncResponse,
+            SyncRunQuery,
+            SyncRunResponse,
+            SyncFailureQuery,
+            SyncFailureResponse,
         )
     ),
     modifiers(&SecurityAddon),
@@ -120,6 +207,7 @@ impl Modify for SecurityAddon {
     (name = "monthly_card", description = "Monthly card API"),
     (name = "payments", description = "Unified payments API"),
     (name = "lucky_draw", description = "Lucky draw wheel API"),
+    (name = "admin", description = "Admin API"),
     ),
     info(
         title = "KKSS Backend API",
@@ -1,47 +1,63 @@
 //! Background scheduled tasks for the application.
 //!
 //! This module centralizes all recurring background jobs (syncing orders/discount codes,
-//! membership expiration checks, birthday rewards, and monthly card coupons).
+//! membership expiration checks, auto-renewal and upgrade reward retries, discount code
+//! expiry and reconciliation, pending recharge refund reconciliation, daily free lucky draw
+//! spins, birthday rewards, monthly card expiry and coupons).
 //! Call `spawn_all` once during startup to launch them.
 
-use crate::services::{BirthdayRewardService, MembershipService, MonthlyCardService, SyncService};
+use crate::config::TasksConfig;
+use crate::error::AppError;
+use crate::services::{
+    BirthdayRewardService, DiscountCodeService, LuckyDrawService, MembershipService,
+    MonthlyCardService, RechargeService, SyncService,
+};
 
 /// Spawn all background tasks.
 ///
 /// Notes
 /// - Each task is idempotent as implemented in its service and runs on its own schedule.
 /// - This function detaches tasks via `tokio::spawn`; it does not block.
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_all(
     sync_service: SyncService,
     membership_service: MembershipService,
     birthday_reward_service: BirthdayRewardService,
     monthly_card_service: MonthlyCardService,
+    discount_code_service: DiscountCodeService,
+    lucky_draw_service: LuckyDrawService,
+    recharge_service: RechargeService,
+    tasks: TasksConfig,
 ) {
-    // 每分钟同步最近一月订单与优惠码
-    {
+    for name in &tasks.disabled {
+        log::info!("Background task disabled by config: {name}");
+    }
+
+    // 增量同步订单（基于游标，首次回溯 30 天）与优惠码（默认每分钟）
+    if tasks.is_enabled("orders_sync") {
+        let interval = std::time::Duration::from_secs(tasks.orders_sync_secs);
         let sync_service_clone = sync_service.clone();
         tokio::spawn(async move {
-            use chrono::{Duration, Utc};
             loop {
-                let now = Utc::now();
-                let start = now - Duration::days(30);
-                let start_date = start.format("%Y-%m-%d %H:%M:%S").to_string();
-                let end_date = format!("{} 23:59:59", now.format("%Y-%m-%d"));
-
-                log::debug!("Start syncing orders and discount codes: {start_date} ~ {end_date}");
-                if let Err(e) = sync_service_clone.sync_orders(&start_date, &end_date).await {
-                    log::error!("Failed to sync orders: {e:?}");
+                // 管理端手动同步正在运行时跳过本轮
+                match sync_service_clone.sync_orders().await {
+                    Ok(_) => {}
+                    Err(AppError::Conflict(msg)) => log::info!("Skip scheduled sync: {msg}"),
+                    Err(e) => log::error!("Failed to sync orders: {e:?}"),
                 }
-                if let Err(e) = sync_service_clone.sync_discount_codes().await {
-                    log::error!("Failed to sync discount codes: {e:?}");
+                match sync_service_clone.sync_discount_codes().await {
+                    Ok(_) => {}
+                    Err(AppError::Conflict(msg)) => log::info!("Skip scheduled sync: {msg}"),
+                    Err(e) => log::error!("Failed to sync discount codes: {e:?}"),
                 }
-                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                tokio::time::sleep(interval).await;
             }
         });
     }
 
-    // 会员过期检查（每 6 小时）
-    {
+    // 会员过期检查（默认每 6 小时）
+    if tasks.is_enabled("membership_expiry") {
+        let interval = std::time::Duration::from_secs(tasks.membership_expiry_secs);
         let svc = membership_service.clone();
         tokio::spawn(async move {
             loop {
@@ -50,13 +66,110 @@ pub fn spawn_all(
                     Ok(_) => {}
                     Err(e) => log::error!("Failed to expire memberships: {e:?}"),
                 }
-                tokio::time::sleep(std::time::Duration::from_secs(6 * 3600)).await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 会员自动续费（默认每天，处理 24 小时内到期的会员）
+    if tasks.is_enabled("membership_renewal") {
+        let interval = std::time::Duration::from_secs(tasks.membership_renewal_secs);
+        let svc = membership_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.attempt_renewals().await {
+                    Ok(n) if n > 0 => log::info!("Memberships auto-renewed: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to auto-renew memberships: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 会员升级奖励补发（默认每 10 分钟）
+    if tasks.is_enabled("membership_reward_retry") {
+        let interval = std::time::Duration::from_secs(tasks.membership_reward_retry_secs);
+        let svc = membership_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.retry_reward_grants().await {
+                    Ok(n) if n > 0 => log::info!("Membership upgrade reward codes re-granted: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to retry membership upgrade rewards: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 优惠码过期标记（默认每小时）
+    if tasks.is_enabled("discount_code_expiry") {
+        let interval = std::time::Duration::from_secs(tasks.discount_code_expiry_secs);
+        let svc = discount_code_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.expire_codes().await {
+                    Ok(n) if n > 0 => log::info!("Expired discount codes marked: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to expire discount codes: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // pending 优惠码对账（默认每 10 分钟）
+    if tasks.is_enabled("discount_code_reconcile") {
+        let interval = std::time::Duration::from_secs(tasks.discount_code_reconcile_secs);
+        let svc = discount_code_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.reconcile_pending_codes().await {
+                    Ok(n) if n > 0 => log::info!("Pending discount codes reconciled: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to reconcile pending discount codes: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 停留在 refund_pending 的充值退款对账（默认每 15 分钟）
+    if tasks.is_enabled("recharge_refund_reconcile") {
+        let interval = std::time::Duration::from_secs(tasks.recharge_refund_reconcile_secs);
+        let svc = recharge_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.reconcile_pending_refunds().await {
+                    Ok(n) if n > 0 => log::info!("Pending recharge refunds reconciled: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to reconcile pending recharge refunds: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 每日免费抽奖次数发放（默认每小时检查，同一天只发一次）
+    if tasks.is_enabled("free_spin") {
+        let interval = std::time::Duration::from_secs(tasks.free_spin_secs);
+        let svc = lucky_draw_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.grant_daily_free_spins().await {
+                    Ok(n) if n > 0 => log::info!("Daily free spins granted: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to grant daily free spins: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
             }
         });
     }
 
-    // 生日福利发放（每小时）
-    {
+    // 生日福利发放（默认每小时）
+    if tasks.is_enabled("birthday_reward") {
+        let interval = std::time::Duration::from_secs(tasks.birthday_reward_secs);
         let svc = birthday_reward_service.clone();
         tokio::spawn(async move {
             loop {
@@ -65,13 +178,30 @@ pub fn spawn_all(
                     Ok(_) => {}
                     Err(e) => log::error!("Failed to grant birthday rewards: {e:?}"),
                 }
-                tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 月卡过期检查（默认每 6 小时）
+    if tasks.is_enabled("monthly_card_expiry") {
+        let interval = std::time::Duration::from_secs(tasks.monthly_card_expiry_secs);
+        let svc = monthly_card_service.clone();
+        tokio::spawn(async move {
+            loop {
+                match svc.expire_cards().await {
+                    Ok(n) if n > 0 => log::info!("Expired monthly cards processed: {n}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Failed to expire monthly cards: {e:?}"),
+                }
+                tokio::time::sleep(interval).await;
             }
         });
     }
 
-    // 月卡每日优惠券发放（每天一次）
-    {
+    // 月卡每日优惠券发放（默认每天一次）
+    if tasks.is_enabled("monthly_card_coupon") {
+        let interval = std::time::Duration::from_secs(tasks.monthly_card_coupon_secs);
         let svc = monthly_card_service.clone();
         tokio::spawn(async move {
             loop {
@@ -80,7 +210,7 @@ pub fn spawn_all(
                     Ok(_) => {}
                     Err(e) => log::error!("Failed to grant monthly card daily coupons: {e:?}"),
                 }
-                tokio::time::sleep(std::time::Duration::from_secs(24 * 3600)).await;
+                tokio::time::sleep(interval).await;
             }
         });
     }
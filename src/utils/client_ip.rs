@@ -0,0 +1,152 @@
+use actix_web::{HttpRequest, web};
+use std::net::IpAddr;
+
+/// 受信任的反向代理（单个 IP 或 CIDR），只有来自这些地址的连接才采信转发头
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl TrustedProxies {
+    /// 解析 `server.trusted_proxies`，任一项格式错误时返回该项
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        entries
+            .iter()
+            .map(|entry| parse_cidr(entry).ok_or_else(|| entry.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0
+            .iter()
+            .any(|&(net, prefix)| in_network(ip, net, prefix))
+    }
+}
+
+fn parse_cidr(entry: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (entry, None),
+    };
+    let ip: IpAddr = addr.trim().parse().ok()?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(p) => p.trim().parse::<u8>().ok().filter(|p| *p <= max)?,
+        None => max,
+    };
+    Some((ip, prefix))
+}
+
+fn in_network(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (ip.to_canonical(), net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+/// 提取客户端 IP：默认取连接对端地址；仅当对端是受信任代理时，
+/// 才依次采信 CF-Connecting-IP 与 X-Forwarded-For（从右向左跳过受信任代理）
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip().to_canonical();
+    let Some(trusted) = req
+        .app_data::<web::Data<TrustedProxies>>()
+        .filter(|t| t.contains(peer))
+    else {
+        return Some(peer.to_string());
+    };
+
+    let headers = req.headers();
+    if let Some(ip) = headers
+        .get("CF-Connecting-IP")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.trim().parse::<IpAddr>().ok())
+    {
+        return Some(ip.to_string());
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("X-Forwarded-For")
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|s| s.split(','))
+        .filter_map(|s| s.trim().parse::<IpAddr>().ok())
+        .collect();
+    let ip = forwarded
+        .iter()
+        .rev()
+        .find(|ip| !trusted.contains(**ip))
+        .or(forwarded.first())
+        .copied()
+        .unwrap_or(peer);
+    Some(ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn request(peer: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        let trusted =
+            TrustedProxies::parse(&["10.0.0.0/8".to_string(), "::1".to_string()]).unwrap();
+        let mut req = TestRequest::default()
+            .peer_addr(peer.parse().unwrap())
+            .app_data(web::Data::new(trusted));
+        for &(name, value) in headers {
+            req = req.insert_header((name, value));
+        }
+        req.to_http_request()
+    }
+
+    #[test]
+    fn test_parse_trusted_proxies() {
+        assert!(TrustedProxies::parse(&["203.0.113.0/24".to_string()]).is_ok());
+        assert_eq!(
+            TrustedProxies::parse(&["10.0.0.0/33".to_string()]).unwrap_err(),
+            "10.0.0.0/33"
+        );
+        assert!(TrustedProxies::parse(&["proxy".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_forwarded_headers() {
+        let req = request(
+            "198.51.100.7:4000",
+            &[
+                ("CF-Connecting-IP", "1.2.3.4"),
+                ("X-Forwarded-For", "1.2.3.4"),
+            ],
+        );
+        assert_eq!(client_ip(&req).as_deref(), Some("198.51.100.7"));
+    }
+
+    #[test]
+    fn test_trusted_proxy_headers() {
+        let req = request("10.1.2.3:4000", &[("CF-Connecting-IP", "1.2.3.4")]);
+        assert_eq!(client_ip(&req).as_deref(), Some("1.2.3.4"));
+
+        // 客户端自带的 X-Forwarded-For 前缀不被采信，取最右侧的非代理地址
+        let req = request(
+            "10.1.2.3:4000",
+            &[("X-Forwarded-For", "9.9.9.9, 5.6.7.8, 10.0.0.2")],
+        );
+        assert_eq!(client_ip(&req).as_deref(), Some("5.6.7.8"));
+
+        let req = request("[::1]:4000", &[]);
+        assert_eq!(client_ip(&req).as_deref(), Some("::1"));
+    }
+
+    #[test]
+    fn test_without_trusted_proxies_uses_peer() {
+        let req = TestRequest::default()
+            .peer_addr("10.1.2.3:4000".parse().unwrap())
+            .insert_header(("CF-Connecting-IP", "1.2.3.4"))
+            .to_http_request();
+        assert_eq!(client_ip(&req).as_deref(), Some("10.1.2.3"));
+    }
+}
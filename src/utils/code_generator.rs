@@ -1,11 +1,23 @@
-use rand::Rng;
+use rand::rngs::OsRng;
+use rand::{Rng, TryRngCore};
 
 /// 生成6位数字代码（可用于验证码、优惠码等）
+///
+/// 使用操作系统 CSPRNG (`OsRng`)，避免优惠码被预测
 pub fn generate_six_digit_code() -> String {
-    let mut rng = rand::rng();
+    let mut rng = OsRng.unwrap_err();
     format!("{:06}", rng.random_range(100000..=999999))
 }
 
+/// 一次生成 `count` 个互不相同的 6 位数字代码
+pub fn generate_six_digit_codes(count: usize) -> Vec<String> {
+    let mut codes = std::collections::HashSet::with_capacity(count);
+    while codes.len() < count {
+        codes.insert(generate_six_digit_code());
+    }
+    codes.into_iter().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -18,7 +30,16 @@ mod tests {
 
         // 确保代码在有效范围内
         let code_num: u32 = code.parse().unwrap();
-        assert!(code_num >= 100000 && code_num <= 999999);
+        assert!((100000..=999999).contains(&code_num));
+    }
+
+    #[test]
+    fn test_generate_six_digit_codes_distinct() {
+        let codes = generate_six_digit_codes(20);
+        assert_eq!(codes.len(), 20);
+        let unique: std::collections::HashSet<_> = codes.iter().collect();
+        assert_eq!(unique.len(), 20);
+        assert!(codes.iter().all(|c| c.len() == 6));
     }
 
     #[test]
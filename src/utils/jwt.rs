@@ -1,3 +1,4 @@
+use crate::entities::{MemberType, UserRole};
 use crate::error::{AppError, AppResult};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
@@ -10,29 +11,78 @@ pub struct Claims {
     pub exp: i64,
     pub iat: i64,
     pub token_type: String, // "access" or "refresh"
+    #[serde(default)]
+    pub role: String, // "user" or "admin"；旧令牌缺省为空，按普通用户处理
+    /// 刷新令牌版本，需与 `users.token_version` 一致；访问令牌恒为 0
+    #[serde(default)]
+    pub ver: i32,
+}
+
+/// 访问令牌与刷新令牌的有效期（秒）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenLifetimes {
+    pub access: i64,
+    pub refresh: i64,
 }
 
 #[derive(Clone)]
 pub struct JwtService {
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
-    access_token_expires_in: i64,
-    refresh_token_expires_in: i64,
+    /// Fan 及未单独配置的等级使用的有效期
+    default_lifetimes: TokenLifetimes,
+    sweet_shareholder_lifetimes: TokenLifetimes,
+    super_shareholder_lifetimes: TokenLifetimes,
 }
 
 impl JwtService {
     pub fn new(secret: &str, access_expires_in: i64, refresh_expires_in: i64) -> Self {
+        let default_lifetimes = TokenLifetimes {
+            access: access_expires_in,
+            refresh: refresh_expires_in,
+        };
         Self {
             encoding_key: EncodingKey::from_secret(secret.as_bytes()),
             decoding_key: DecodingKey::from_secret(secret.as_bytes()),
-            access_token_expires_in: access_expires_in,
-            refresh_token_expires_in: refresh_expires_in,
+            default_lifetimes,
+            sweet_shareholder_lifetimes: default_lifetimes,
+            super_shareholder_lifetimes: default_lifetimes,
+        }
+    }
+
+    /// 为指定会员等级设置令牌有效期（Fan 始终使用默认值）
+    pub fn with_tier_lifetimes(
+        mut self,
+        member_type: &MemberType,
+        lifetimes: TokenLifetimes,
+    ) -> Self {
+        match member_type {
+            MemberType::Fan => {}
+            MemberType::SweetShareholder => self.sweet_shareholder_lifetimes = lifetimes,
+            MemberType::SuperShareholder => self.super_shareholder_lifetimes = lifetimes,
         }
+        self
     }
 
-    pub fn generate_access_token(&self, user_id: i64, member_code: &str) -> AppResult<String> {
+    /// 按会员等级获取令牌有效期
+    pub fn lifetimes_for(&self, member_type: &MemberType) -> TokenLifetimes {
+        match member_type {
+            MemberType::Fan => self.default_lifetimes,
+            MemberType::SweetShareholder => self.sweet_shareholder_lifetimes,
+            MemberType::SuperShareholder => self.super_shareholder_lifetimes,
+        }
+    }
+
+    /// 生成访问令牌，`expires_in` 为有效期（秒），通常取自 `lifetimes_for`
+    pub fn generate_access_token(
+        &self,
+        user_id: i64,
+        member_code: &str,
+        role: &UserRole,
+        expires_in: i64,
+    ) -> AppResult<String> {
         let now = Utc::now();
-        let exp = now + Duration::seconds(self.access_token_expires_in);
+        let exp = now + Duration::seconds(expires_in);
 
         let claims = Claims {
             sub: user_id.to_string(),
@@ -40,14 +90,24 @@ impl JwtService {
             exp: exp.timestamp(),
             iat: now.timestamp(),
             token_type: "access".to_string(),
+            role: role.to_string(),
+            ver: 0,
         };
 
         encode(&Header::default(), &claims, &self.encoding_key).map_err(AppError::JwtError)
     }
 
-    pub fn generate_refresh_token(&self, user_id: i64, member_code: &str) -> AppResult<String> {
+    /// 生成刷新令牌，`expires_in` 为有效期（秒），`token_version` 取自 `users.token_version`
+    pub fn generate_refresh_token(
+        &self,
+        user_id: i64,
+        member_code: &str,
+        role: &UserRole,
+        expires_in: i64,
+        token_version: i32,
+    ) -> AppResult<String> {
         let now = Utc::now();
-        let exp = now + Duration::seconds(self.refresh_token_expires_in);
+        let exp = now + Duration::seconds(expires_in);
 
         let claims = Claims {
             sub: user_id.to_string(),
@@ -55,6 +115,8 @@ impl JwtService {
             exp: exp.timestamp(),
             iat: now.timestamp(),
             token_type: "refresh".to_string(),
+            role: role.to_string(),
+            ver: token_version,
         };
 
         encode(&Header::default(), &claims, &self.encoding_key).map_err(AppError::JwtError)
@@ -89,7 +151,48 @@ impl JwtService {
         Ok(claims)
     }
 
-    pub fn get_access_token_expires_in(&self) -> i64 {
-        self.access_token_expires_in
+    pub fn is_admin(claims: &Claims) -> bool {
+        claims.role == UserRole::Admin.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> JwtService {
+        JwtService::new("test-secret", 7200, 2_592_000).with_tier_lifetimes(
+            &MemberType::SuperShareholder,
+            TokenLifetimes {
+                access: 86_400,
+                refresh: 7_776_000,
+            },
+        )
+    }
+
+    #[test]
+    fn test_token_exp_matches_tier_lifetime() {
+        let jwt = service();
+        for (member_type, access, refresh) in [
+            (MemberType::Fan, 7200, 2_592_000),
+            (MemberType::SweetShareholder, 7200, 2_592_000),
+            (MemberType::SuperShareholder, 86_400, 7_776_000),
+        ] {
+            let lifetimes = jwt.lifetimes_for(&member_type);
+            assert_eq!(lifetimes, TokenLifetimes { access, refresh });
+
+            let token = jwt
+                .generate_access_token(1, "2345678901", &UserRole::User, lifetimes.access)
+                .unwrap();
+            let claims = jwt.verify_access_token(&token).unwrap();
+            assert_eq!(claims.exp - claims.iat, access);
+
+            let token = jwt
+                .generate_refresh_token(1, "2345678901", &UserRole::User, lifetimes.refresh, 3)
+                .unwrap();
+            let claims = jwt.verify_refresh_token(&token).unwrap();
+            assert_eq!(claims.exp - claims.iat, refresh);
+            assert_eq!(claims.ver, 3);
+        }
     }
 }
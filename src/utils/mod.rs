@@ -1,10 +1,12 @@
+pub mod client_ip;
 pub mod code_generator;
 pub mod jwt;
 pub mod member_code;
 pub mod password;
 pub mod phone;
 
-pub use code_generator::generate_six_digit_code;
+pub use client_ip::{TrustedProxies, client_ip};
+pub use code_generator::{generate_six_digit_code, generate_six_digit_codes};
 pub use jwt::*;
 pub use member_code::generate_unique_referral_code;
 pub use password::*;
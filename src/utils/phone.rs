@@ -14,19 +14,28 @@ pub fn validate_us_phone(phone: &str) -> AppResult<()> {
     Ok(())
 }
 
-/// 格式化手机号，确保以+1开头
-pub fn format_us_phone(phone: &str) -> String {
-    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+/// 将常见格式的美国手机号规范为 E.164 (+1XXXXXXXXXX)
+///
+/// 支持 "(415) 555-1234"、"415.555.1234"、"1 415 555 1234"、"+1 415-555-1234" 等写法；
+/// 无法识别时返回去除首尾空白的原始输入，交由 `validate_us_phone` 拒绝
+pub fn normalize_us_phone(input: &str) -> String {
+    let trimmed = input.trim();
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
 
     if digits.len() == 11 && digits.starts_with('1') {
         format!("+{digits}")
-    } else if digits.len() == 10 {
+    } else if digits.len() == 10 && !trimmed.starts_with('+') {
         format!("+1{digits}")
     } else {
-        phone.to_string()
+        trimmed.to_string()
     }
 }
 
+/// 格式化手机号，确保以+1开头
+pub fn format_us_phone(phone: &str) -> String {
+    normalize_us_phone(phone)
+}
+
 /// 从美国手机号中提取十位数字作为member_code
 pub fn extract_member_code_from_phone(phone: &str) -> AppResult<String> {
     let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
@@ -64,6 +73,27 @@ mod tests {
         assert_eq!(format_us_phone("(234) 567-8901"), "+12345678901");
     }
 
+    #[test]
+    fn test_normalize_us_phone() {
+        for input in [
+            "+14155551234",
+            "14155551234",
+            "4155551234",
+            "(415) 555-1234",
+            "415-555-1234",
+            "415.555.1234",
+            "1 415 555 1234",
+            "+1 (415) 555-1234",
+            "  +1-415-555-1234 ",
+        ] {
+            assert_eq!(normalize_us_phone(input), "+14155551234", "input: {input}");
+        }
+        // 非美国号码或位数不对时保持原样，由校验拒绝
+        assert_eq!(normalize_us_phone("+44 20 7946 0958"), "+44 20 7946 0958");
+        assert_eq!(normalize_us_phone("555-1234"), "555-1234");
+        assert!(validate_us_phone(&normalize_us_phone("555-1234")).is_err());
+    }
+
     #[test]
     fn test_extract_member_code_from_phone() {
         assert_eq!(
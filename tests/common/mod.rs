@@ -0,0 +1,249 @@
+//! 集成测试公共工具：建库与创建测试用户复用库内的 `database::test_db`，
+//! 另提供 POS 后端与 Stripe 网关的测试实现。
+//!
+//! 本地可用一次性容器运行：
+//! docker run --rm -d -p 55432:5432 -e POSTGRES_HOST_AUTH_METHOD=trust postgres:16
+//! TEST_DATABASE_URL=postgres://postgres@localhost:55432/postgres cargo test -- --ignored
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use kkss_backend::external::{CheckoutInit, MockPosBackend, SharedPosBackend, StripeGateway};
+use kkss_backend::{AppError, AppResult};
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::sync::Arc;
+use stripe::{Currency, Event, PaymentIntent, PaymentIntentStatus};
+use tokio::sync::Mutex;
+
+pub use kkss_backend::database::test_db::{create_user, setup_db};
+
+/// 测试用 POS 后端：生成优惠码总是成功
+pub fn pos_backend() -> SharedPosBackend {
+    Arc::new(Mutex::new(MockPosBackend::default()))
+}
+
+/// 测试用支付网关：retrieve_payment_intent 返回指定状态与金额的 PaymentIntent，
+/// create_offsession_payment_intent 按幂等 key 返回指定状态的 PaymentIntent，
+/// 充值下单（PaymentIntent / Checkout Session）按幂等 key 返回固定的 id 与地址，
+/// create_refund 仅在 `refunds` 为 true 时成功，refunded_amount 返回 `refunded`，其余调用直接报错
+pub struct FakeStripe {
+    pub status: PaymentIntentStatus,
+    pub amount: i64,
+    pub currency: Currency,
+    pub metadata: HashMap<String, String>,
+    pub refunds: bool,
+    /// refunded_amount 返回的 Stripe 累计退款金额
+    pub refunded: i64,
+}
+
+impl FakeStripe {
+    pub fn succeeded(amount: i64) -> Arc<dyn StripeGateway> {
+        Arc::new(Self {
+            status: PaymentIntentStatus::Succeeded,
+            amount,
+            currency: Currency::USD,
+            metadata: HashMap::new(),
+            refunds: false,
+            refunded: 0,
+        })
+    }
+
+    /// 扣款被拒（需要更换支付方式）的 PaymentIntent
+    pub fn declined() -> Arc<dyn StripeGateway> {
+        Arc::new(Self {
+            status: PaymentIntentStatus::RequiresPaymentMethod,
+            amount: 0,
+            currency: Currency::USD,
+            metadata: HashMap::new(),
+            refunds: false,
+            refunded: 0,
+        })
+    }
+
+    /// Stripe 上该笔支付已累计退款 `refunded` 美分
+    pub fn refunded(amount: i64, refunded: i64) -> Arc<dyn StripeGateway> {
+        Arc::new(Self {
+            status: PaymentIntentStatus::Succeeded,
+            amount,
+            currency: Currency::USD,
+            metadata: HashMap::new(),
+            refunds: false,
+            refunded,
+        })
+    }
+
+    /// 以指定货币支付成功的 PaymentIntent
+    pub fn succeeded_in(amount: i64, currency: Currency) -> Arc<dyn StripeGateway> {
+        Arc::new(Self {
+            status: PaymentIntentStatus::Succeeded,
+            amount,
+            currency,
+            metadata: HashMap::new(),
+            refunds: false,
+            refunded: 0,
+        })
+    }
+
+    /// 退款调用会成功的网关
+    pub fn refundable(amount: i64) -> Arc<dyn StripeGateway> {
+        Arc::new(Self {
+            status: PaymentIntentStatus::Succeeded,
+            amount,
+            currency: Currency::USD,
+            metadata: HashMap::new(),
+            refunds: true,
+            refunded: 0,
+        })
+    }
+
+    /// 带 user_id / category metadata 的成功 PaymentIntent，模拟 webhook 场景
+    pub fn succeeded_for(amount: i64, user_id: i64, category: &str) -> Arc<dyn StripeGateway> {
+        Arc::new(Self {
+            status: PaymentIntentStatus::Succeeded,
+            amount,
+            currency: Currency::USD,
+            metadata: HashMap::from([
+                ("user_id".to_string(), user_id.to_string()),
+                ("category".to_string(), category.to_string()),
+            ]),
+            refunds: false,
+            refunded: 0,
+        })
+    }
+}
+
+/// 按幂等 key 派生固定的对象 id，没有 key 时每次都不同
+fn stable_id(idempotency_key: Option<String>) -> String {
+    idempotency_key
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+        .replace('-', "_")
+}
+
+fn unsupported<T>() -> AppResult<T> {
+    Err(AppError::ExternalApiError(
+        "not supported by FakeStripe".to_string(),
+    ))
+}
+
+#[async_trait]
+impl StripeGateway for FakeStripe {
+    async fn create_payment_intent_with_category(
+        &self,
+        amount: i64,
+        _user_id: i64,
+        _category: &str,
+        _currency: Option<String>,
+        description: Option<String>,
+        _extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+        _customer_id: Option<String>,
+    ) -> AppResult<PaymentIntent> {
+        let id = format!("pi_{}", stable_id(idempotency_key));
+        Ok(PaymentIntent {
+            client_secret: Some(format!("{id}_secret")),
+            id: id.parse().expect("valid payment intent id"),
+            status: PaymentIntentStatus::RequiresPaymentMethod,
+            amount,
+            currency: self.currency,
+            description,
+            ..Default::default()
+        })
+    }
+
+    async fn create_checkout_session_for_amount(
+        &self,
+        _amount: i64,
+        _currency: Option<String>,
+        _user_id: i64,
+        _category: &str,
+        _description: Option<String>,
+        _extra_metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<String>,
+    ) -> AppResult<CheckoutInit> {
+        Ok(CheckoutInit {
+            url: format!(
+                "https://checkout.stripe.test/{}",
+                stable_id(idempotency_key)
+            ),
+            payment_intent_id: None,
+            client_secret: None,
+        })
+    }
+
+    async fn create_offsession_payment_intent(
+        &self,
+        _customer_id: &str,
+        amount: i64,
+        metadata: HashMap<String, String>,
+        description: String,
+        idempotency_key: String,
+    ) -> AppResult<PaymentIntent> {
+        Ok(PaymentIntent {
+            id: format!("pi_{}", idempotency_key.replace('-', "_"))
+                .parse()
+                .expect("valid payment intent id"),
+            status: self.status,
+            amount,
+            currency: Currency::USD,
+            metadata,
+            description: Some(description),
+            ..Default::default()
+        })
+    }
+
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> AppResult<PaymentIntent> {
+        Ok(PaymentIntent {
+            client_secret: Some(format!("{payment_intent_id}_secret")),
+            id: payment_intent_id.parse().expect("valid payment intent id"),
+            status: self.status,
+            amount: self.amount,
+            currency: self.currency,
+            metadata: self.metadata.clone(),
+            ..Default::default()
+        })
+    }
+
+    async fn create_refund(
+        &self,
+        payment_intent_id: &str,
+        _amount_cents: Option<i64>,
+    ) -> AppResult<String> {
+        if !self.refunds {
+            return unsupported();
+        }
+        Ok(format!("re_{payment_intent_id}"))
+    }
+
+    async fn refunded_amount(&self, _payment_intent_id: &str) -> AppResult<i64> {
+        Ok(self.refunded)
+    }
+
+    async fn get_price_unit_amount(&self, _price_id: &str) -> AppResult<i64> {
+        unsupported()
+    }
+
+    async fn get_or_create_customer(
+        &self,
+        _db: &DatabaseConnection,
+        user_id: i64,
+    ) -> AppResult<String> {
+        Ok(format!("cus_{user_id}"))
+    }
+
+    fn monthly_card_ids(&self) -> (Option<String>, Option<String>, Option<String>) {
+        (None, None, None)
+    }
+
+    fn monthly_card_fallback_amount_cents(&self) -> i64 {
+        0
+    }
+
+    fn verify_webhook_signature(
+        &self,
+        _payload: &str,
+        _signature: &str,
+        _now: i64,
+    ) -> AppResult<Event> {
+        unsupported()
+    }
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use kkss_backend::config::LuckyDrawConfig;
+use kkss_backend::entities::lucky_draw_record_entity as records;
+use kkss_backend::error::AppError;
+use kkss_backend::external::{MockPosBackend, SharedPosBackend};
+use kkss_backend::models::UpsertLuckyDrawPrizeRequest;
+use kkss_backend::services::{DiscountCodeService, LuckyDrawService};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+fn lucky_draw_service(
+    pool: &sea_orm::DatabaseConnection,
+    pos: SharedPosBackend,
+) -> LuckyDrawService {
+    let discount_code_service = DiscountCodeService::new(pool.clone(), pos);
+    LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service,
+        LuckyDrawConfig::default(),
+    )
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_spin_without_chances_is_rejected() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "LD").await;
+    let service = lucky_draw_service(&pool, common::pos_backend());
+
+    assert!(service.spin(user.id).await.is_err());
+    let chances = service.get_user_chances(user.id).await.unwrap();
+    assert_eq!(chances.total_used, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_spin_consumes_one_chance() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "LD").await;
+    let service = lucky_draw_service(&pool, common::pos_backend());
+    service.award_chances(user.id, 1).await.unwrap();
+
+    let spin = service.spin(user.id).await.unwrap();
+    assert_eq!(spin.remaining_chances, 0);
+    let chances = service.get_user_chances(user.id).await.unwrap();
+    assert_eq!(chances.total_used, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_spin_keeps_chance_when_award_fails() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "LD").await;
+    let pos = MockPosBackend {
+        fail_generate: true,
+        ..Default::default()
+    };
+    let service = lucky_draw_service(&pool, Arc::new(Mutex::new(pos)));
+    service.award_chances(user.id, 1).await.unwrap();
+
+    // 抽中优惠券类奖品时七云注册失败，撤销本次抽奖（次数保留、记录删除）；抽中 Thank You 则正常消耗
+    let result = service.spin(user.id).await;
+    let chances = service.get_user_chances(user.id).await.unwrap();
+    let spins = records::Entity::find()
+        .filter(records::Column::UserId.eq(user.id))
+        .count(&pool)
+        .await
+        .unwrap();
+    match result {
+        Ok(_) => {
+            assert_eq!(chances.total_used, 1);
+            assert_eq!(spins, 1);
+        }
+        Err(_) => {
+            assert_eq!(chances.total_used, 0);
+            assert_eq!(spins, 0);
+        }
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_upsert_prize_rejects_unsupported_name() {
+    let pool = common::setup_db().await;
+    let service = lucky_draw_service(&pool, common::pos_backend());
+
+    let err = service
+        .upsert_prize(UpsertLuckyDrawPrizeRequest {
+            id: None,
+            name_en: "Free Waffle Coupon".to_string(),
+            value_cents: 300,
+            probability_bp: 0,
+            stock_limit: None,
+            is_active: false,
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::ValidationError(_)), "{err:?}");
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use chrono::{Datelike, Duration, Utc};
+use kkss_backend::config::{BirthdayRewardConfig, CashbackConfig, MonthlyCardConfig};
+use kkss_backend::entities::{
+    CodeType, MemberType, StripeTransactionCategory, discount_code_entity as dc,
+    notification_entity as notifications, stripe_transaction_entity as stx, user_entity as users,
+};
+use kkss_backend::error::AppError;
+use kkss_backend::external::StripeGateway;
+use kkss_backend::services::{BirthdayRewardService, DiscountCodeService, MembershipService};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use std::sync::Arc;
+
+async fn make_member(
+    pool: &sea_orm::DatabaseConnection,
+    member_type: MemberType,
+    pending: Option<MemberType>,
+    expires_in_days: i64,
+) -> users::Model {
+    let user = common::create_user(pool, "EX").await;
+    let mut am = user.into_active_model();
+    am.member_type = Set(member_type);
+    am.pending_member_type = Set(pending);
+    am.membership_expires_at = Set(Some(Utc::now() + Duration::days(expires_in_days)));
+    am.update(pool).await.unwrap()
+}
+
+/// 12 小时后到期、开启自动续费且已保存 Stripe Customer 的会员
+async fn make_renewal_candidate(
+    pool: &sea_orm::DatabaseConnection,
+    member_type: MemberType,
+    pending: Option<MemberType>,
+) -> users::Model {
+    let user = common::create_user(pool, "AR").await;
+    let mut am = user.into_active_model();
+    am.member_type = Set(member_type);
+    am.pending_member_type = Set(pending);
+    am.membership_expires_at = Set(Some(Utc::now() + Duration::hours(12)));
+    am.membership_auto_renew = Set(true);
+    am.stripe_customer_id = Set(Some("cus_test".to_string()));
+    am.update(pool).await.unwrap()
+}
+
+fn membership_service(
+    pool: &sea_orm::DatabaseConnection,
+    stripe: Arc<dyn StripeGateway>,
+) -> MembershipService {
+    MembershipService::new(
+        pool.clone(),
+        stripe,
+        DiscountCodeService::new(pool.clone(), common::pos_backend()),
+        CashbackConfig::default(),
+        MonthlyCardConfig::default(),
+    )
+}
+
+async fn notification_kinds(pool: &sea_orm::DatabaseConnection, user_id: i64) -> Vec<String> {
+    notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(user_id))
+        .all(pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|n| n.kind)
+        .collect()
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_attempt_renewals_charges_and_extends() {
+    let pool = common::setup_db().await;
+    let find = |id| users::Entity::find_by_id(id).one(&pool);
+
+    // 扣款成功：顺延一个周期；预约了降级的按新等级扣款并清除预约
+    let sweet = make_renewal_candidate(&pool, MemberType::SweetShareholder, None).await;
+    let downgrading = make_renewal_candidate(
+        &pool,
+        MemberType::SuperShareholder,
+        Some(MemberType::SweetShareholder),
+    )
+    .await;
+    let service = membership_service(&pool, common::FakeStripe::succeeded(0));
+    assert!(service.attempt_renewals().await.unwrap() >= 2);
+
+    for before in [&sweet, &downgrading] {
+        let after = find(before.id).await.unwrap().unwrap();
+        assert_eq!(after.member_type, MemberType::SweetShareholder);
+        assert_eq!(after.pending_member_type, None);
+        assert_eq!(
+            after.membership_expires_at.unwrap(),
+            before.membership_expires_at.unwrap() + Duration::days(365)
+        );
+        let txs = stx::Entity::find()
+            .filter(stx::Column::UserId.eq(before.id))
+            .all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].category, StripeTransactionCategory::Membership);
+        assert_eq!(txs[0].amount, Some(800));
+        assert_eq!(
+            notification_kinds(&pool, before.id).await,
+            vec!["membership_renewed".to_string()]
+        );
+    }
+
+    // 扣款被拒：会员保持不变并通知续费失败，交由正常过期流程处理
+    let declined = make_renewal_candidate(&pool, MemberType::SuperShareholder, None).await;
+    let service = membership_service(&pool, common::FakeStripe::declined());
+    service.attempt_renewals().await.unwrap();
+    let after = find(declined.id).await.unwrap().unwrap();
+    assert_eq!(after.member_type, MemberType::SuperShareholder);
+    assert_eq!(after.membership_expires_at, declined.membership_expires_at);
+    assert_eq!(
+        notification_kinds(&pool, declined.id).await,
+        vec!["membership_renewal_failed".to_string()]
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_expire_memberships_bulk_update() {
+    let pool = common::setup_db().await;
+    let service = membership_service(&pool, common::FakeStripe::succeeded(0));
+    // 先处理库中已有的到期会员，确保下面的计数只包含本测试的数据
+    service.expire_memberships().await.unwrap();
+
+    let expired = make_member(&pool, MemberType::SweetShareholder, None, -1).await;
+    let downgraded = make_member(
+        &pool,
+        MemberType::SuperShareholder,
+        Some(MemberType::SweetShareholder),
+        -1,
+    )
+    .await;
+    let active = make_member(&pool, MemberType::SweetShareholder, None, 10).await;
+
+    assert_eq!(service.expire_memberships().await.unwrap(), 2);
+
+    let find = |id| users::Entity::find_by_id(id).one(&pool);
+    let expired = find(expired.id).await.unwrap().unwrap();
+    assert_eq!(expired.member_type, MemberType::Fan);
+
+    // 预约等级只通过付费续费生效，到期未续费一律降为 Fan，不顺延周期
+    let before = downgraded.membership_expires_at.unwrap();
+    let downgraded = find(downgraded.id).await.unwrap().unwrap();
+    assert_eq!(downgraded.member_type, MemberType::Fan);
+    assert_eq!(downgraded.pending_member_type, None);
+    assert_eq!(downgraded.membership_expires_at.unwrap(), before);
+
+    let active = find(active.id).await.unwrap().unwrap();
+    assert_eq!(active.member_type, MemberType::SweetShareholder);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_schedule_downgrade_requires_auto_renew() {
+    let pool = common::setup_db().await;
+    let service = membership_service(&pool, common::FakeStripe::succeeded(0));
+
+    // 未开启自动续费：到期直接回到 Fan，预约降级不会生效
+    let manual = make_member(&pool, MemberType::SuperShareholder, None, 30).await;
+    let err = service
+        .schedule_downgrade(manual.id, MemberType::SweetShareholder)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::ValidationError(_)), "{err:?}");
+
+    let renewing = make_renewal_candidate(&pool, MemberType::SuperShareholder, None).await;
+    let resp = service
+        .schedule_downgrade(renewing.id, MemberType::SweetShareholder)
+        .await
+        .unwrap();
+    assert_eq!(resp.pending_member_type, MemberType::SweetShareholder);
+    assert_eq!(resp.effective_at, renewing.membership_expires_at);
+
+    // 关闭自动续费时取消预约
+    service.set_auto_renew(renewing.id, false).await.unwrap();
+    let user = users::Entity::find_by_id(renewing.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.pending_member_type, None);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_birthday_reward_code_defaults_to_birthday_type() {
+    let pool = common::setup_db().await;
+    let today = Utc::now().date_naive();
+    let user = common::create_user(&pool, "BD").await;
+    let mut am = user.into_active_model();
+    am.birthday_month = Set(today.month() as i16);
+    am.birthday_day = Set(today.day() as i16);
+    let user = am.update(&pool).await.unwrap();
+
+    let service = BirthdayRewardService::new(
+        pool.clone(),
+        DiscountCodeService::new(pool.clone(), common::pos_backend()),
+        BirthdayRewardConfig {
+            issue_code: true,
+            ..Default::default()
+        },
+    );
+    service.grant_today_birthdays().await.unwrap();
+
+    let codes = dc::Entity::find()
+        .filter(dc::Column::UserId.eq(user.id))
+        .all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(codes.len(), 1);
+    assert_eq!(codes[0].code_type, CodeType::BirthdayReward);
+    assert_eq!(
+        codes[0].discount_amount,
+        BirthdayRewardConfig::default().fan_cents
+    );
+}
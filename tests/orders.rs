@@ -0,0 +1,66 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use kkss_backend::entities::order_entity as orders;
+use kkss_backend::models::OrderQuery;
+use kkss_backend::services::OrderService;
+use sea_orm::{ActiveModelTrait, Set};
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_order_cursor_pagination_follows_listing_order() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "OC").await;
+
+    // id 顺序与下单时间顺序不一致（补同步的订单 id 更大但时间更早）
+    let base = Utc::now().timestamp_micros();
+    let now = Utc::now();
+    for (id, days_ago) in [(base + 1, 1), (base + 2, 3), (base + 3, 2)] {
+        orders::ActiveModel {
+            id: Set(id),
+            user_id: Set(user.id),
+            member_code: Set(Some(user.member_code.clone())),
+            price: Set(500),
+            product_name: Set("Test".to_string()),
+            order_status: Set(1),
+            external_created_at: Set(now - Duration::days(days_ago)),
+            ..Default::default()
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+    }
+
+    let service = OrderService::new(pool.clone());
+    let first = service
+        .get_user_orders(
+            user.id,
+            &OrderQuery {
+                per_page: Some(2),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let ids: Vec<i64> = first.data.iter().map(|o| o.id).collect();
+    assert_eq!(ids, vec![base + 1, base + 3]);
+    assert_eq!(first.total, Some(3));
+    assert_eq!(first.next_cursor, Some(base + 3));
+
+    // 用首页的游标继续，顺序与 offset 分页一致且不统计总数
+    let second = service
+        .get_user_orders(
+            user.id,
+            &OrderQuery {
+                per_page: Some(2),
+                after_id: first.next_cursor,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let ids: Vec<i64> = second.data.iter().map(|o| o.id).collect();
+    assert_eq!(ids, vec![base + 2]);
+    assert_eq!(second.total, None);
+    assert_eq!(second.next_cursor, None);
+}
@@ -0,0 +1,907 @@
+mod common;
+
+use chrono::Utc;
+use kkss_backend::config::{CashbackConfig, LuckyDrawConfig, MonthlyCardConfig, RechargeConfig};
+use kkss_backend::entities::{
+    DiscountCodeStatus, MemberType, MembershipPurchaseStatus, RechargeStatus,
+    discount_code_entity as dc, membership_purchase_entity as mp,
+    membership_reward_grant_entity as reward_grants, processed_event_entity,
+    recharge_record_entity as rr, referral_reward_entity as referral_rewards, user_entity as users,
+};
+use kkss_backend::external::{MockPosBackend, SharedPosBackend};
+use kkss_backend::models::{
+    ConfirmMembershipRequest, ConfirmRechargeRequest, CreatePaymentIntentRequest,
+};
+use kkss_backend::services::{
+    DiscountCodeService, EventClaim, LuckyDrawService, MembershipService, RechargeService,
+    StripeTransactionService,
+};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, Set,
+};
+use std::sync::Arc;
+use stripe::Currency;
+use tokio::sync::Mutex;
+
+fn discount_code_service(pool: &sea_orm::DatabaseConnection) -> DiscountCodeService {
+    DiscountCodeService::new(pool.clone(), common::pos_backend())
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_confirm_recharge_credits_once() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RC").await;
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        amount: Set(1000),
+        bonus_amount: Set(200),
+        total_amount: Set(1200),
+        status: Set(RechargeStatus::Pending),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let service = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(1000),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+
+    // 前端确认与重试各调用一次，只应入账一次
+    for _ in 0..2 {
+        let resp = service
+            .confirm_recharge(
+                user.id,
+                ConfirmRechargeRequest {
+                    payment_intent_id: pi_id.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.new_balance, 1200);
+    }
+    let balance = users::Entity::find_by_id(user.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance;
+    assert_eq!(balance, Some(1200));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_webhook_without_record_recreates_and_credits_once() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "WH").await;
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    // 用户另一笔未支付的充值，不应被绑定到这次 webhook 的 PI 上
+    let other = rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(format!("{pi_id}_other")),
+        amount: Set(5000),
+        bonus_amount: Set(0),
+        total_amount: Set(5000),
+        status: Set(RechargeStatus::Pending),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let service = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded_for(1000, user.id, "recharge"),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+
+    // webhook 先于创建接口落库到达，且 Stripe 重复投递
+    for _ in 0..2 {
+        service
+            .handle_payment_success_webhook(&pi_id, user.id)
+            .await
+            .unwrap();
+    }
+    let records = rr::Entity::find()
+        .filter(rr::Column::StripePaymentIntentId.eq(pi_id.clone()))
+        .all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].status, RechargeStatus::Succeeded);
+    assert_eq!(records[0].amount, 1000);
+    let other = rr::Entity::find_by_id(other.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(other.status, RechargeStatus::Pending);
+    assert_eq!(other.stripe_payment_intent_id, format!("{pi_id}_other"));
+    let balance = users::Entity::find_by_id(user.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance;
+    assert_eq!(balance, Some(records[0].total_amount));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_eur_recharge_credits_and_claws_back_in_usd() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RE").await;
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    // €10 + €2 赠送，创建时汇率 1.08
+    rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        amount: Set(1000),
+        bonus_amount: Set(200),
+        total_amount: Set(1200),
+        currency: Set("eur".to_string()),
+        exchange_rate_bps: Set(10800),
+        status: Set(RechargeStatus::Pending),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let service = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded_in(1000, Currency::EUR),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+    let resp = service
+        .confirm_recharge(
+            user.id,
+            ConfirmRechargeRequest {
+                payment_intent_id: pi_id.clone(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.new_balance, 1296);
+    assert_eq!(resp.recharge_record.credited_amount, 1296);
+
+    // 部分退款 €5 按折算后的入账余额扣回一半
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let refunding = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::refundable(1000),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+    let resp = refunding
+        .refund_recharge(None, &pi_id, Some(500))
+        .await
+        .unwrap();
+    assert_eq!(resp.balance_debited, 648);
+    assert_eq!(resp.new_balance, 648);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_refund_recharge_reverts_on_stripe_failure() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RF").await;
+    let mut am: users::ActiveModel = user.clone().into();
+    am.balance = Set(Some(1200));
+    am.update(&pool).await.unwrap();
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        amount: Set(1000),
+        bonus_amount: Set(200),
+        total_amount: Set(1200),
+        status: Set(RechargeStatus::Succeeded),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let service_with = |stripe| {
+        let lucky_draw_service = LuckyDrawService::new(
+            pool.clone(),
+            discount_code_service(&pool),
+            LuckyDrawConfig::default(),
+        );
+        RechargeService::new(
+            pool.clone(),
+            stripe,
+            lucky_draw_service,
+            RechargeConfig::default(),
+        )
+    };
+    let load = || async {
+        let record = rr::Entity::find()
+            .filter(rr::Column::StripePaymentIntentId.eq(pi_id.clone()))
+            .one(&pool)
+            .await
+            .unwrap()
+            .unwrap();
+        let balance = users::Entity::find_by_id(user.id)
+            .one(&pool)
+            .await
+            .unwrap()
+            .unwrap()
+            .balance;
+        (record, balance)
+    };
+
+    // Stripe 退款失败：余额退回，记录恢复 Succeeded
+    let failing = service_with(common::FakeStripe::succeeded(1000));
+    assert!(failing.refund_recharge(None, &pi_id, None).await.is_err());
+    let (record, balance) = load().await;
+    assert_eq!(record.status, RechargeStatus::Succeeded);
+    assert_eq!(record.pending_refund_debit, None);
+    assert_eq!(balance, Some(1200));
+
+    // Stripe 退款成功：扣回余额并置为 Refunded
+    let refunding = service_with(common::FakeStripe::refundable(1000));
+    let resp = refunding.refund_recharge(None, &pi_id, None).await.unwrap();
+    assert_eq!(resp.balance_debited, 1200);
+    let (record, balance) = load().await;
+    assert_eq!(record.status, RechargeStatus::Refunded);
+    assert_eq!(record.refunded_amount, 1000);
+    assert_eq!(balance, Some(0));
+    assert!(refunding.refund_recharge(None, &pi_id, None).await.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_refund_webhook_finalizes_pending_refund() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RW").await;
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    // 进程在 Stripe 退款后、本地完成前退出
+    rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        amount: Set(1000),
+        bonus_amount: Set(200),
+        total_amount: Set(1200),
+        status: Set(RechargeStatus::RefundPending),
+        pending_refund_amount: Set(Some(1000)),
+        pending_refund_debit: Set(Some(1200)),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let service = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(1000),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+    let load = || async {
+        rr::Entity::find()
+            .filter(rr::Column::StripePaymentIntentId.eq(pi_id.clone()))
+            .one(&pool)
+            .await
+            .unwrap()
+            .unwrap()
+    };
+
+    // Stripe 累计退款尚未覆盖进行中的退款（迟到的旧事件）：不完成
+    service.handle_refund_webhook(&pi_id, 0).await.unwrap();
+    assert_eq!(load().await.status, RechargeStatus::RefundPending);
+
+    service.handle_refund_webhook(&pi_id, 1000).await.unwrap();
+    let record = load().await;
+    assert_eq!(record.status, RechargeStatus::Refunded);
+    assert_eq!(record.refunded_amount, 1000);
+    assert_eq!(record.pending_refund_amount, None);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_reconcile_pending_refunds_checks_stripe() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RQ").await;
+    // 发起退款后进程退出，且一直没有收到 webhook
+    let stale_pending = |pi_id: String| rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id),
+        amount: Set(1000),
+        bonus_amount: Set(200),
+        total_amount: Set(1200),
+        status: Set(RechargeStatus::RefundPending),
+        pending_refund_amount: Set(Some(1000)),
+        pending_refund_debit: Set(Some(1200)),
+        updated_at: Set(Some(Utc::now() - chrono::Duration::hours(1))),
+        ..Default::default()
+    };
+    let service = |stripe| {
+        RechargeService::new(
+            pool.clone(),
+            stripe,
+            LuckyDrawService::new(
+                pool.clone(),
+                discount_code_service(&pool),
+                LuckyDrawConfig::default(),
+            ),
+            RechargeConfig::default(),
+        )
+    };
+    let db = &pool;
+    let load = |id| async move { rr::Entity::find_by_id(id).one(db).await.unwrap().unwrap() };
+
+    // Stripe 上退款已生效：完成本地退款
+    let refunded = stale_pending(format!("pi_test_{}", Utc::now().timestamp_micros()))
+        .insert(&pool)
+        .await
+        .unwrap();
+    service(common::FakeStripe::refunded(1000, 1000))
+        .reconcile_pending_refunds()
+        .await
+        .unwrap();
+    let record = load(refunded.id).await;
+    assert_eq!(record.status, RechargeStatus::Refunded);
+    assert_eq!(record.refunded_amount, 1000);
+
+    // Stripe 上没有退款：退回扣掉的余额并恢复为 Succeeded
+    let reverted = stale_pending(format!("pi_test_{}", Utc::now().timestamp_micros()))
+        .insert(&pool)
+        .await
+        .unwrap();
+    service(common::FakeStripe::refunded(1000, 0))
+        .reconcile_pending_refunds()
+        .await
+        .unwrap();
+    let record = load(reverted.id).await;
+    assert_eq!(record.status, RechargeStatus::Succeeded);
+    assert_eq!(record.refunded_amount, 0);
+    assert_eq!(record.pending_refund_amount, None);
+    let balance = users::Entity::find_by_id(user.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance;
+    assert_eq!(balance, Some(1200));
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_partial_refunds_claw_back_proportionally() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RP").await;
+    let mut am: users::ActiveModel = user.clone().into();
+    am.balance = Set(Some(1200));
+    am.update(&pool).await.unwrap();
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    rr::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        amount: Set(1000),
+        bonus_amount: Set(200),
+        total_amount: Set(1200),
+        status: Set(RechargeStatus::Succeeded),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let service = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::refundable(1000),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+
+    let resp = service
+        .refund_recharge(None, &pi_id, Some(300))
+        .await
+        .unwrap();
+    assert_eq!(resp.balance_debited, 360);
+    assert_eq!(
+        resp.recharge_record.status,
+        RechargeStatus::PartiallyRefunded
+    );
+    // 超过剩余可退金额
+    assert!(
+        service
+            .refund_recharge(None, &pi_id, Some(800))
+            .await
+            .is_err()
+    );
+
+    let resp = service
+        .refund_recharge(None, &pi_id, Some(333))
+        .await
+        .unwrap();
+    // 累计 633 应扣回 759，此前已扣 360
+    assert_eq!(resp.balance_debited, 399);
+
+    // 全额退款只退剩余部分，累计扣回不超过入账总额
+    let resp = service.refund_recharge(None, &pi_id, None).await.unwrap();
+    assert_eq!(resp.refunded_amount, 367);
+    assert_eq!(resp.balance_debited, 441);
+    assert_eq!(resp.new_balance, 0);
+
+    let record = rr::Entity::find()
+        .filter(rr::Column::StripePaymentIntentId.eq(pi_id.clone()))
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.status, RechargeStatus::Refunded);
+    assert_eq!(record.refunded_amount, 1000);
+    assert!(service.refund_recharge(None, &pi_id, None).await.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_confirm_membership_upgrades_once() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "MB").await;
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    mp::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        target_member_type: Set(MemberType::SweetShareholder),
+        amount: Set(5000),
+        status: Set(MembershipPurchaseStatus::Pending),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let service = MembershipService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(5000),
+        discount_code_service(&pool),
+        CashbackConfig::default(),
+        MonthlyCardConfig::default(),
+    );
+
+    for _ in 0..2 {
+        let resp = service
+            .confirm_membership(
+                user.id,
+                ConfirmMembershipRequest {
+                    payment_intent_id: pi_id.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.new_member_type, MemberType::SweetShareholder);
+    }
+    let purchase = mp::Entity::find()
+        .filter(mp::Column::StripePaymentIntentId.eq(pi_id))
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(purchase.status, MembershipPurchaseStatus::Succeeded);
+    let user = users::Entity::find_by_id(user.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.member_type, MemberType::SweetShareholder);
+    assert!(user.membership_expires_at.is_some());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_referral_reward_committed_before_registration() {
+    let pool = common::setup_db().await;
+    let referrer = common::create_user(&pool, "RR").await;
+    let mut am = referrer.clone().into_active_model();
+    am.member_type = Set(MemberType::SweetShareholder);
+    am.membership_expires_at = Set(Some(Utc::now() + chrono::Duration::days(30)));
+    am.update(&pool).await.unwrap();
+
+    for (fail_generate, expected) in [
+        (false, DiscountCodeStatus::Active),
+        (true, DiscountCodeStatus::Failed),
+    ] {
+        let referee = common::create_user(&pool, "RE").await;
+        let mut am = referee.clone().into_active_model();
+        am.referrer_id = Set(Some(referrer.id));
+        am.update(&pool).await.unwrap();
+        let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+        mp::ActiveModel {
+            user_id: Set(referee.id),
+            stripe_payment_intent_id: Set(pi_id.clone()),
+            target_member_type: Set(MemberType::SweetShareholder),
+            amount: Set(5000),
+            status: Set(MembershipPurchaseStatus::Pending),
+            ..Default::default()
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+
+        let pos: SharedPosBackend = Arc::new(Mutex::new(MockPosBackend {
+            fail_generate,
+            ..Default::default()
+        }));
+        let service = MembershipService::new(
+            pool.clone(),
+            common::FakeStripe::succeeded(5000),
+            DiscountCodeService::new(pool.clone(), pos),
+            CashbackConfig::default(),
+            MonthlyCardConfig::default(),
+        );
+        // 七云注册失败不影响升级，推荐记录与奖励码已先提交
+        service
+            .confirm_membership(
+                referee.id,
+                ConfirmMembershipRequest {
+                    payment_intent_id: pi_id,
+                },
+            )
+            .await
+            .unwrap();
+
+        let reward = referral_rewards::Entity::find()
+            .filter(referral_rewards::Column::RefereeId.eq(referee.id))
+            .one(&pool)
+            .await
+            .unwrap()
+            .expect("referral reward row");
+        assert_eq!(reward.referrer_id, referrer.id);
+        let code = dc::Entity::find_by_id(reward.discount_code_id.expect("reward code"))
+            .one(&pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(code.user_id, Some(referrer.id));
+        assert_eq!(code.status, expected);
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_confirm_gift_extends_higher_recipient() {
+    let pool = common::setup_db().await;
+    let buyer = common::create_user(&pool, "GB").await;
+    let recipient = common::create_user(&pool, "GR").await;
+    let expires_at = Utc::now() + chrono::Duration::days(30);
+    let mut am: users::ActiveModel = recipient.clone().into();
+    am.member_type = Set(MemberType::SuperShareholder);
+    am.membership_expires_at = Set(Some(expires_at));
+    let recipient = am.update(&pool).await.unwrap();
+
+    // 赠送下单时受赠人还是 Fan，付款前已升级为 Super
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    mp::ActiveModel {
+        user_id: Set(buyer.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        target_member_type: Set(MemberType::SweetShareholder),
+        amount: Set(800),
+        status: Set(MembershipPurchaseStatus::Pending),
+        recipient_user_id: Set(Some(recipient.id)),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let service = MembershipService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(800),
+        discount_code_service(&pool),
+        CashbackConfig::default(),
+        MonthlyCardConfig::default(),
+    );
+    let resp = service
+        .confirm_membership(
+            buyer.id,
+            ConfirmMembershipRequest {
+                payment_intent_id: pi_id,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.new_member_type, MemberType::SuperShareholder);
+
+    // 不降级，按 $8 / $30 折算顺延 97 天
+    let updated = users::Entity::find_by_id(recipient.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.member_type, MemberType::SuperShareholder);
+    assert_eq!(
+        updated.membership_expires_at.unwrap() - recipient.membership_expires_at.unwrap(),
+        chrono::Duration::days(97)
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_claim_event_once_until_released() {
+    let pool = common::setup_db().await;
+    let stx_service = StripeTransactionService::new(pool.clone());
+    let event_id = format!("evt_test_{}", Utc::now().timestamp_micros());
+
+    let (a, b) = tokio::join!(
+        stx_service.claim_event(&event_id, "payment_intent.succeeded"),
+        stx_service.claim_event(&event_id, "payment_intent.succeeded"),
+    );
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert!(
+        (a == EventClaim::Claimed) ^ (b == EventClaim::Claimed),
+        "exactly one claim must win"
+    );
+    assert!(matches!(a, EventClaim::Claimed | EventClaim::InFlight));
+    assert!(matches!(b, EventClaim::Claimed | EventClaim::InFlight));
+
+    stx_service.release_event(&event_id).await.unwrap();
+    assert_eq!(
+        stx_service
+            .claim_event(&event_id, "payment_intent.succeeded")
+            .await
+            .unwrap(),
+        EventClaim::Claimed
+    );
+
+    stx_service.complete_event(&event_id).await.unwrap();
+    stx_service.release_event(&event_id).await.unwrap();
+    assert_eq!(
+        stx_service
+            .claim_event(&event_id, "payment_intent.succeeded")
+            .await
+            .unwrap(),
+        EventClaim::Completed
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_claim_event_reclaims_after_lease_expires() {
+    let pool = common::setup_db().await;
+    let stx_service = StripeTransactionService::new(pool.clone());
+    let event_id = format!("evt_test_{}", Utc::now().timestamp_micros());
+
+    assert_eq!(
+        stx_service
+            .claim_event(&event_id, "payment_intent.succeeded")
+            .await
+            .unwrap(),
+        EventClaim::Claimed
+    );
+    // 模拟认领后进程崩溃：租约过期前重复投递视为处理中，过期后可重新认领
+    processed_event_entity::Entity::update_many()
+        .col_expr(
+            processed_event_entity::Column::ProcessedAt,
+            Expr::value(Utc::now() - chrono::Duration::hours(1)),
+        )
+        .filter(processed_event_entity::Column::EventId.eq(event_id.clone()))
+        .exec(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        stx_service
+            .claim_event(&event_id, "payment_intent.succeeded")
+            .await
+            .unwrap(),
+        EventClaim::Claimed
+    );
+    assert_eq!(
+        stx_service
+            .claim_event(&event_id, "payment_intent.succeeded")
+            .await
+            .unwrap(),
+        EventClaim::InFlight
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_retry_reward_grants_completes_interrupted_grant() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RG").await;
+    let purchase = mp::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(format!("pi_test_{}", Utc::now().timestamp_micros())),
+        target_member_type: Set(MemberType::SweetShareholder),
+        amount: Set(5000),
+        status: Set(MembershipPurchaseStatus::Succeeded),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+    // 模拟确认后进程中断：记录已认领但租约早已过期
+    let now = Utc::now();
+    let grant = reward_grants::ActiveModel {
+        purchase_id: Set(purchase.id),
+        user_id: Set(user.id),
+        member_type: Set(MemberType::SweetShareholder),
+        codes_total: Set(1),
+        codes_granted: Set(0),
+        granted: Set(false),
+        claimed_at: Set(Some(now - chrono::Duration::hours(1))),
+        last_error: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let service = MembershipService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(5000),
+        discount_code_service(&pool),
+        CashbackConfig::default(),
+        MonthlyCardConfig::default(),
+    );
+    service.retry_reward_grants().await.unwrap();
+    // 已发放完的记录不会再次发放
+    assert_eq!(service.grant_upgrade_reward(grant.id).await.unwrap(), 0);
+
+    let grant = reward_grants::Entity::find_by_id(grant.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(grant.granted);
+    assert_eq!(grant.codes_granted, 1);
+    assert_eq!(grant.claimed_at, None);
+    let codes = dc::Entity::find()
+        .filter(dc::Column::UserId.eq(user.id))
+        .count(&pool)
+        .await
+        .unwrap();
+    assert_eq!(codes, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_confirm_membership_renews_expired_member_as_upgrade() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "EX").await;
+    // 已过期但尚未被定时任务降级的 sweet 会员
+    let mut am: users::ActiveModel = user.clone().into();
+    am.member_type = Set(MemberType::SweetShareholder);
+    am.membership_expires_at = Set(Some(Utc::now() - chrono::Duration::days(3)));
+    am.update(&pool).await.unwrap();
+    let pi_id = format!("pi_test_{}", Utc::now().timestamp_micros());
+    let purchase = mp::ActiveModel {
+        user_id: Set(user.id),
+        stripe_payment_intent_id: Set(pi_id.clone()),
+        target_member_type: Set(MemberType::SweetShareholder),
+        amount: Set(5000),
+        status: Set(MembershipPurchaseStatus::Pending),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    let service = MembershipService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(5000),
+        discount_code_service(&pool),
+        CashbackConfig::default(),
+        MonthlyCardConfig::default(),
+    );
+    let resp = service
+        .confirm_membership(
+            user.id,
+            ConfirmMembershipRequest {
+                payment_intent_id: pi_id,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.new_member_type, MemberType::SweetShareholder);
+
+    // 按 fan 升级处理：有效期从现在起算一年，并发放升级奖励
+    let user = users::Entity::find_by_id(user.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    let days = (user.membership_expires_at.unwrap() - Utc::now()).num_days();
+    assert!((364..=365).contains(&days));
+    let grant = reward_grants::Entity::find()
+        .filter(reward_grants::Column::PurchaseId.eq(purchase.id))
+        .one(&pool)
+        .await
+        .unwrap();
+    assert!(grant.is_some());
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_repeated_recharge_request_returns_existing_record() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "RQ").await;
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service(&pool),
+        LuckyDrawConfig::default(),
+    );
+    let service = RechargeService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(1234),
+        lucky_draw_service,
+        RechargeConfig::default(),
+    );
+    let request = |currency: &str| CreatePaymentIntentRequest {
+        amount: 1234,
+        custom: true,
+        request_id: Some("req-1".to_string()),
+        currency: Some(currency.to_string()),
+    };
+
+    let first = service
+        .create_payment_intent(user.id, request("usd"))
+        .await
+        .unwrap();
+    let retry = service
+        .create_payment_intent(user.id, request("usd"))
+        .await
+        .unwrap();
+    assert_eq!(retry.payment_intent_id, first.payment_intent_id);
+    assert_eq!(retry.checkout_url, first.checkout_url);
+    assert_eq!(retry.client_secret, first.client_secret);
+
+    // 相同 request_id 换货币视为新的充值
+    let eur = service
+        .create_payment_intent(user.id, request("eur"))
+        .await
+        .unwrap();
+    assert_ne!(eur.payment_intent_id, first.payment_intent_id);
+    assert_eq!(eur.currency, "eur");
+
+    let records = rr::Entity::find()
+        .filter(rr::Column::UserId.eq(user.id))
+        .count(&pool)
+        .await
+        .unwrap();
+    assert_eq!(records, 2);
+}
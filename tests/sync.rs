@@ -0,0 +1,153 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{App, test, web};
+use chrono::{Duration, Utc};
+use kkss_backend::config::CashbackConfig;
+use kkss_backend::entities::{
+    UserRole, lucky_draw_chance_entity as chances, order_entity as orders, user_entity as users,
+};
+use kkss_backend::external::{MockPosBackend, OrderRecord};
+use kkss_backend::handlers;
+use kkss_backend::middlewares::AuthMiddleware;
+use kkss_backend::services::{SYNC_RUN_KIND_DISCOUNT_CODES, SyncService};
+use kkss_backend::utils::JwtService;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_sync_run_is_recorded() {
+    let pool = common::setup_db().await;
+    let service = SyncService::new(
+        pool.clone(),
+        common::pos_backend(),
+        CashbackConfig::default(),
+    );
+
+    let processed = service.sync_discount_codes().await.unwrap();
+    assert_eq!(processed, 0);
+
+    let runs = service
+        .list_runs(Some(SYNC_RUN_KIND_DISCOUNT_CODES), 1)
+        .await
+        .unwrap();
+    let run = runs.first().expect("sync run recorded");
+    assert_eq!(run.status, "succeeded");
+    assert_eq!(run.processed, 0);
+    assert!(run.finished_at.is_some());
+}
+
+#[actix_web::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_admin_lists_sync_runs() {
+    let pool = common::setup_db().await;
+    let service = SyncService::new(
+        pool.clone(),
+        common::pos_backend(),
+        CashbackConfig::default(),
+    );
+    service.sync_discount_codes().await.unwrap();
+
+    let jwt = JwtService::new("test-secret", 7200, 2_592_000);
+    let app = test::init_service(
+        App::new()
+            .wrap(AuthMiddleware::new(jwt.clone()))
+            .app_data(web::Data::new(service))
+            .service(web::scope("/api/v1").configure(handlers::admin_config)),
+    )
+    .await;
+    let token = jwt
+        .generate_access_token(1, "2345678901", &UserRole::Admin, 7200)
+        .unwrap();
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/sync/runs?kind=discount_codes&limit=1")
+        .insert_header(("Authorization", format!("Bearer {token}")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let runs = body["data"].as_array().expect("runs array");
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0]["kind"], SYNC_RUN_KIND_DISCOUNT_CODES);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_canceled_order_reverses_stamps_and_spins() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "SC").await;
+    let mut am = user.clone().into_active_model();
+    am.stamps = Set(Some(1));
+    am.update(&pool).await.unwrap();
+    chances::ActiveModel {
+        user_id: Set(user.id),
+        total_awarded: Set(2),
+        total_used: Set(1),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+
+    // 几天前已同步的 $11 订单（1 印花、2 次抽奖），之后在七云被取消
+    let order_id = Utc::now().timestamp_micros();
+    let created_at = Utc::now() - Duration::days(5);
+    orders::ActiveModel {
+        id: Set(order_id),
+        user_id: Set(user.id),
+        member_code: Set(Some(user.member_code.clone())),
+        price: Set(1100),
+        product_name: Set("Test".to_string()),
+        order_status: Set(1),
+        stamps_earned: Set(Some(1)),
+        spins_earned: Set(Some(2)),
+        external_created_at: Set(created_at),
+        ..Default::default()
+    }
+    .insert(&pool)
+    .await
+    .unwrap();
+    let backend = MockPosBackend {
+        orders: vec![OrderRecord {
+            id: order_id,
+            create_date: created_at.timestamp_millis(),
+            member_code: Some(user.member_code.clone()),
+            price: Some(11.0),
+            product_name: "Test".to_string(),
+            product_no: None,
+            status: 2,
+            pay_type: None,
+        }],
+        ..Default::default()
+    };
+    let service = SyncService::new(
+        pool.clone(),
+        Arc::new(Mutex::new(backend)),
+        CashbackConfig::default(),
+    );
+    service.sync_orders().await.unwrap();
+
+    let order = orders::Entity::find_by_id(order_id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(order.order_status, 2);
+    assert_eq!(order.spins_earned, Some(0));
+    let user = users::Entity::find_by_id(user.id)
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.stamps, Some(0));
+    // 已用掉的 1 次无法收回
+    let chance = chances::Entity::find()
+        .filter(chances::Column::UserId.eq(user.id))
+        .one(&pool)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(chance.total_awarded, 1);
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use kkss_backend::config::{LuckyDrawConfig, MonthlyCardConfig, TwilioConfig};
+use kkss_backend::entities::user_entity as users;
+use kkss_backend::external::TwilioService;
+use kkss_backend::services::{
+    DiscountCodeService, LuckyDrawService, MonthlyCardService, UserService,
+};
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Set};
+
+fn user_service(pool: &sea_orm::DatabaseConnection) -> UserService {
+    let twilio = TwilioService::new(
+        TwilioConfig {
+            account_sid: String::new(),
+            auth_token: String::new(),
+            from_phone: String::new(),
+            verify_service_sid: String::new(),
+        },
+        pool.clone(),
+    );
+    let discount_code_service = DiscountCodeService::new(pool.clone(), common::pos_backend());
+    let monthly_card_service = MonthlyCardService::new(
+        pool.clone(),
+        common::FakeStripe::succeeded(0),
+        discount_code_service.clone(),
+        MonthlyCardConfig::default(),
+    );
+    let lucky_draw_service = LuckyDrawService::new(
+        pool.clone(),
+        discount_code_service,
+        LuckyDrawConfig::default(),
+    );
+    UserService::new(
+        pool.clone(),
+        twilio,
+        monthly_card_service,
+        lucky_draw_service,
+    )
+}
+
+async fn set_balance(pool: &sea_orm::DatabaseConnection, user: users::Model, balance: i64) {
+    let mut am = user.into_active_model();
+    am.balance = Set(Some(balance));
+    am.update(pool).await.unwrap();
+}
+
+async fn balance_of(pool: &sea_orm::DatabaseConnection, user_id: i64) -> i64 {
+    users::Entity::find_by_id(user_id)
+        .one(pool)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_transfer_moves_balance_and_enforces_limits() {
+    let pool = common::setup_db().await;
+    let sender = common::create_user(&pool, "ws").await;
+    let recipient = common::create_user(&pool, "wr").await;
+    let sender_id = sender.id;
+    set_balance(&pool, sender, 30_000).await;
+    let service = user_service(&pool);
+
+    let resp = service
+        .transfer_balance(sender_id, &recipient.member_code, 15_000)
+        .await
+        .unwrap();
+    assert_eq!(resp.balance_after, 15_000);
+    assert_eq!(balance_of(&pool, recipient.id).await, 15_000);
+
+    // 低于最小金额
+    assert!(
+        service
+            .transfer_balance(sender_id, &recipient.member_code, 50)
+            .await
+            .is_err()
+    );
+    // 超出每日上限：余额足够但当日累计超过 $200
+    assert!(
+        service
+            .transfer_balance(sender_id, &recipient.member_code, 10_000)
+            .await
+            .is_err()
+    );
+    assert_eq!(balance_of(&pool, sender_id).await, 15_000);
+    assert_eq!(balance_of(&pool, recipient.id).await, 15_000);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_transfer_rejects_insufficient_balance() {
+    let pool = common::setup_db().await;
+    let sender = common::create_user(&pool, "ws").await;
+    let recipient = common::create_user(&pool, "wr").await;
+    let sender_id = sender.id;
+    set_balance(&pool, sender, 500).await;
+
+    let result = user_service(&pool)
+        .transfer_balance(sender_id, &recipient.member_code, 1_000)
+        .await;
+    assert!(result.is_err());
+    assert_eq!(balance_of(&pool, sender_id).await, 500);
+    assert_eq!(balance_of(&pool, recipient.id).await, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires TEST_DATABASE_URL"]
+async fn test_get_me_aggregates_context() {
+    let pool = common::setup_db().await;
+    let user = common::create_user(&pool, "ME").await;
+
+    let me = user_service(&pool).get_me(user.id).await.unwrap();
+    assert_eq!(me.user.id, user.id);
+    assert_eq!(me.statistics.total_orders, 0);
+    assert!(me.monthly_card.is_none());
+    assert_eq!(me.lucky_draw_chances.total_used, 0);
+}